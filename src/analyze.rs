@@ -0,0 +1,213 @@
+//! Heuristic consistency check between SPEC.md and the implementation plan.
+//!
+//! After archives and hand edits a plan can keep tasks for features the spec
+//! no longer describes, and the loop will build them anyway. Each open task's
+//! significant terms (words over four characters, minus common task verbs and
+//! filler) are looked up in the spec; a task sharing none is reported. This is
+//! a hint, not proof: synonyms and terse specs produce false positives.
+
+use crate::parser::Task;
+use std::collections::BTreeSet;
+
+/// Most unrelated tasks reported at once.
+pub const MAX_REPORTED: usize = 10;
+
+/// Shortest word, in characters, that counts as a term.
+const MIN_TERM_CHARS: usize = 5;
+
+/// Words too common in plans to say anything about the spec.
+const STOPWORDS: &[&str] = &[
+    "about",
+    "above",
+    "action",
+    "added",
+    "adding",
+    "after",
+    "again",
+    "against",
+    "allow",
+    "basic",
+    "before",
+    "being",
+    "below",
+    "between",
+    "build",
+    "change",
+    "changes",
+    "check",
+    "could",
+    "create",
+    "delete",
+    "during",
+    "ensure",
+    "every",
+    "feature",
+    "features",
+    "first",
+    "further",
+    "handle",
+    "implement",
+    "improve",
+    "initial",
+    "other",
+    "refactor",
+    "remove",
+    "should",
+    "simple",
+    "support",
+    "testing",
+    "tests",
+    "their",
+    "there",
+    "these",
+    "thing",
+    "things",
+    "those",
+    "through",
+    "under",
+    "until",
+    "update",
+    "using",
+    "verify",
+    "where",
+    "which",
+    "while",
+    "within",
+    "without",
+    "would",
+    "write",
+];
+
+/// Significant terms in `text`: lowercased words over four characters that
+/// are not stopwords.
+///
+/// Words are runs of alphanumeric characters in any script, so `stripe_api`
+/// yields `stripe` and accented words keep their accents.
+pub fn significant_terms(text: &str) -> BTreeSet<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.chars().count() >= MIN_TERM_CHARS)
+        .map(str::to_lowercase)
+        .filter(|word| !STOPWORDS.contains(&word.as_str()))
+        .collect()
+}
+
+/// Whether `term` appears in the lowercased spec, also trying it without a
+/// plural `s` so `webhooks` matches a spec that says `webhook`.
+fn spec_mentions(spec: &str, term: &str) -> bool {
+    spec.contains(term)
+        || term
+            .strip_suffix('s')
+            .is_some_and(|stem| spec.contains(stem))
+}
+
+/// Open tasks none of whose significant terms appear in `spec`.
+///
+/// Completed tasks are skipped, as are tasks without any significant terms,
+/// since there is nothing to compare.
+pub fn unrelated_tasks<'a>(spec: &str, tasks: &'a [Task]) -> Vec<&'a Task> {
+    let spec = spec.to_lowercase();
+    tasks
+        .iter()
+        .filter(|task| !task.completed)
+        .filter(|task| {
+            let terms = significant_terms(&task.text);
+            !terms.is_empty() && !terms.iter().any(|term| spec_mentions(&spec, term))
+        })
+        .collect()
+}
+
+/// Format: `task 'Implement Stripe webhooks' shares no terms with SPEC.md`
+pub fn format_unrelated_task(task: &Task, spec_name: &str) -> String {
+    format!("task '{}' shares no terms with {}", task.text, spec_name)
+}
+
+/// Warning lines for up to `MAX_REPORTED` unrelated tasks, plus a count of
+/// the rest.
+pub fn format_consistency_warnings(unrelated: &[&Task], spec_name: &str) -> Vec<String> {
+    let mut lines: Vec<String> = unrelated
+        .iter()
+        .take(MAX_REPORTED)
+        .map(|task| format_unrelated_task(task, spec_name))
+        .collect();
+    if unrelated.len() > MAX_REPORTED {
+        lines.push(format!(
+            "... and {} more tasks sharing no terms with {}",
+            unrelated.len() - MAX_REPORTED,
+            spec_name
+        ));
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_tasks;
+
+    const SPEC: &str = "# Billing\n\nCustomers pay by invoice. Send a reminder email \
+                        when an invoice is overdue.\n";
+
+    fn texts<'a>(tasks: &[&'a Task]) -> Vec<&'a str> {
+        tasks.iter().map(|task| task.text.as_str()).collect()
+    }
+
+    #[test]
+    fn test_significant_terms_drop_short_words_and_stopwords() {
+        let terms = significant_terms("Implement the Stripe webhook_handler (v2) for users");
+        assert_eq!(
+            terms.into_iter().collect::<Vec<_>>(),
+            vec!["handler", "stripe", "users", "webhook"]
+        );
+    }
+
+    #[test]
+    fn test_unrelated_tasks_flags_tasks_missing_from_spec() {
+        let plan = "- [ ] Generate invoices nightly\n\
+                    - [ ] Implement Stripe webhooks\n\
+                    - [ ] Send overdue reminders\n";
+        let tasks = parse_tasks(plan);
+        assert_eq!(
+            texts(&unrelated_tasks(SPEC, &tasks)),
+            vec!["Implement Stripe webhooks"]
+        );
+    }
+
+    #[test]
+    fn test_unrelated_tasks_skips_completed_and_termless_tasks() {
+        let plan = "- [x] Implement Stripe webhooks\n- [ ] Fix it\n- [ ] Add tests\n";
+        let tasks = parse_tasks(plan);
+        assert!(unrelated_tasks(SPEC, &tasks).is_empty());
+    }
+
+    #[test]
+    fn test_unrelated_tasks_matches_unicode_case_insensitively() {
+        let spec = "# Zahlungen\n\nKunden zahlen per ÜBERWEISUNG. 決済ウェブフックを受け取る。\n";
+        let plan = "- [ ] Überweisung prüfen\n\
+                    - [ ] 決済ウェブフックを受け取る\n\
+                    - [ ] Kreditkarten akzeptieren\n";
+        let tasks = parse_tasks(plan);
+        assert_eq!(
+            texts(&unrelated_tasks(spec, &tasks)),
+            vec!["Kreditkarten akzeptieren"]
+        );
+    }
+
+    #[test]
+    fn test_format_consistency_warnings_caps_the_list() {
+        let plan: String = (0..12)
+            .map(|i| format!("- [ ] Stripe webhook number{}\n", i))
+            .collect();
+        let tasks = parse_tasks(&plan);
+        let unrelated = unrelated_tasks(SPEC, &tasks);
+        let lines = format_consistency_warnings(&unrelated, "SPEC.md");
+        assert_eq!(lines.len(), MAX_REPORTED + 1);
+        assert_eq!(
+            lines[0],
+            "task 'Stripe webhook number0' shares no terms with SPEC.md"
+        );
+        assert_eq!(
+            lines[MAX_REPORTED],
+            "... and 2 more tasks sharing no terms with SPEC.md"
+        );
+    }
+}
@@ -0,0 +1,301 @@
+//! Plan checkpoints in `.ralphctl/checkpoints/`.
+//!
+//! Before each iteration, `run` copies the plan and spec into
+//! `<run-id>/iter-<N>/` so an iteration that mangles the plan can be undone
+//! with `ralphctl rollback`. Each run keeps its own checkpoints, so starting
+//! a new run never destroys the ones a failed run left behind. Only the
+//! newest few checkpoints of a run, and the newest few runs, are kept.
+
+use crate::files;
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Checkpoints kept per run by default (`--keep-checkpoints`).
+pub const DEFAULT_KEEP: usize = 10;
+
+/// Runs whose checkpoints are kept; older runs' go when a run starts.
+pub const KEEP_RUNS: usize = 5;
+
+/// Prefix of checkpoint directory names (`iter-3`).
+const DIR_PREFIX: &str = "iter-";
+
+/// Directory of the checkpoint taken before `iteration`.
+pub fn checkpoint_dir(base: &Path, iteration: u32) -> PathBuf {
+    base.join(format!("{}{}", DIR_PREFIX, iteration))
+}
+
+/// Iteration number of a checkpoint directory name, if it is one.
+fn checkpoint_iteration(name: &str) -> Option<u32> {
+    name.strip_prefix(DIR_PREFIX)?.parse().ok()
+}
+
+/// Checkpoints under `base` as `(iteration, dir)`, oldest first.
+pub fn list(base: &Path) -> Vec<(u32, PathBuf)> {
+    let Ok(entries) = fs::read_dir(base) else {
+        return Vec::new();
+    };
+    let mut checkpoints: Vec<(u32, PathBuf)> = entries
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().is_dir())
+        .filter_map(|e| Some((checkpoint_iteration(e.file_name().to_str()?)?, e.path())))
+        .collect();
+    checkpoints.sort_by_key(|(n, _)| *n);
+    checkpoints
+}
+
+/// Remove every checkpoint under `base`.
+pub fn clear(base: &Path) -> Result<()> {
+    match fs::remove_dir_all(base) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            Err(e).with_context(|| format!("failed to remove {}", base.display()))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Directory holding the checkpoints of run `run_id`.
+pub fn run_dir(base: &Path, run_id: &str) -> PathBuf {
+    base.join(run_id)
+}
+
+/// Run directories under `base`, least recently written first.
+pub fn runs(base: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(base) else {
+        return Vec::new();
+    };
+    let mut runs: Vec<(SystemTime, PathBuf)> = entries
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().is_dir())
+        .filter(|e| {
+            e.file_name()
+                .to_str()
+                .is_some_and(|name| checkpoint_iteration(name).is_none())
+        })
+        .filter_map(|e| Some((e.metadata().ok()?.modified().ok()?, e.path())))
+        .collect();
+    runs.sort();
+    runs.into_iter().map(|(_, dir)| dir).collect()
+}
+
+/// Prepare the checkpoints of run `run_id` under `base`.
+///
+/// Its directory starts empty, since a reused `--tag` must not mix runs,
+/// and only the newest `keep_runs` runs, this one included, are kept.
+/// Returns the run's directory.
+pub fn start_run(base: &Path, run_id: &str, keep_runs: usize) -> Result<PathBuf> {
+    let dir = run_dir(base, run_id);
+    clear(&dir)?;
+    fs::create_dir_all(&dir).with_context(|| format!("failed to create {}", dir.display()))?;
+    let others: Vec<PathBuf> = runs(base).into_iter().filter(|run| *run != dir).collect();
+    let excess = others.len().saturating_sub(keep_runs.saturating_sub(1));
+    for old in others.into_iter().take(excess) {
+        clear(&old)?;
+    }
+    Ok(dir)
+}
+
+/// Copy `plan` and `spec` into the checkpoint for `iteration`, then keep
+/// only the newest `keep` checkpoints.
+///
+/// Files are stored under their default names; a missing spec is skipped.
+pub fn save(base: &Path, iteration: u32, plan: &Path, spec: &Path, keep: usize) -> Result<()> {
+    let dir = checkpoint_dir(base, iteration);
+    if dir.exists() {
+        fs::remove_dir_all(&dir).with_context(|| format!("failed to clear {}", dir.display()))?;
+    }
+    fs::create_dir_all(&dir).with_context(|| format!("failed to create {}", dir.display()))?;
+    for (source, name) in [
+        (plan, files::IMPLEMENTATION_PLAN_FILE),
+        (spec, files::SPEC_FILE),
+    ] {
+        if source.is_file() {
+            fs::copy(source, dir.join(name)).with_context(|| {
+                format!(
+                    "failed to checkpoint {} into {}",
+                    source.display(),
+                    dir.display()
+                )
+            })?;
+        }
+    }
+
+    let checkpoints = list(base);
+    let excess = checkpoints.len().saturating_sub(keep);
+    for (_, old) in checkpoints.into_iter().take(excess) {
+        fs::remove_dir_all(&old).with_context(|| format!("failed to remove {}", old.display()))?;
+    }
+    Ok(())
+}
+
+/// Find the checkpoint to roll back to: iteration `to`, or the newest.
+pub fn find(base: &Path, to: Option<u32>) -> Option<(u32, PathBuf)> {
+    let mut checkpoints = list(base);
+    match to {
+        Some(n) => checkpoints.into_iter().find(|(i, _)| *i == n),
+        None => checkpoints.pop(),
+    }
+}
+
+/// Copy the checkpointed plan in `dir` to `plan`, and the spec to `spec`
+/// when given.
+///
+/// Fails without touching anything if a requested file is not in the
+/// checkpoint.
+pub fn restore(dir: &Path, plan: &Path, spec: Option<&Path>) -> Result<()> {
+    let mut copies = vec![(dir.join(files::IMPLEMENTATION_PLAN_FILE), plan)];
+    if let Some(spec) = spec {
+        copies.push((dir.join(files::SPEC_FILE), spec));
+    }
+    if let Some((missing, _)) = copies.iter().find(|(source, _)| !source.is_file()) {
+        anyhow::bail!("{} not found", missing.display());
+    }
+    for (source, target) in copies {
+        fs::copy(&source, target)
+            .with_context(|| format!("failed to restore {}", target.display()))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_files(dir: &Path, plan: &str, spec: &str) -> (PathBuf, PathBuf) {
+        let (plan_path, spec_path) = (dir.join("plan.md"), dir.join("spec.md"));
+        fs::write(&plan_path, plan).unwrap();
+        fs::write(&spec_path, spec).unwrap();
+        (plan_path, spec_path)
+    }
+
+    #[test]
+    fn test_save_copies_files_and_prunes_oldest() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = dir.path().join("checkpoints");
+        let (plan, spec) = write_files(dir.path(), "- [ ] A\n", "# Spec\n");
+
+        for iteration in 1..=4 {
+            save(&base, iteration, &plan, &spec, 3).unwrap();
+        }
+
+        let kept: Vec<u32> = list(&base).into_iter().map(|(n, _)| n).collect();
+        assert_eq!(kept, vec![2, 3, 4]);
+        let newest = checkpoint_dir(&base, 4);
+        assert_eq!(
+            fs::read_to_string(newest.join(files::IMPLEMENTATION_PLAN_FILE)).unwrap(),
+            "- [ ] A\n"
+        );
+        assert_eq!(
+            fs::read_to_string(newest.join(files::SPEC_FILE)).unwrap(),
+            "# Spec\n"
+        );
+    }
+
+    #[test]
+    fn test_list_orders_numerically_and_skips_other_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["iter-10", "iter-2", "iter-x", "notes"] {
+            fs::create_dir_all(dir.path().join(name)).unwrap();
+        }
+        fs::write(dir.path().join("iter-3"), "a file").unwrap();
+
+        let found: Vec<u32> = list(dir.path()).into_iter().map(|(n, _)| n).collect();
+        assert_eq!(found, vec![2, 10]);
+        assert!(list(&dir.path().join("missing")).is_empty());
+    }
+
+    #[test]
+    fn test_find_newest_or_requested() {
+        let dir = tempfile::tempdir().unwrap();
+        for n in [1, 2, 3] {
+            fs::create_dir_all(checkpoint_dir(dir.path(), n)).unwrap();
+        }
+        assert_eq!(find(dir.path(), None).unwrap().0, 3);
+        assert_eq!(find(dir.path(), Some(2)).unwrap().0, 2);
+        assert!(find(dir.path(), Some(7)).is_none());
+    }
+
+    #[test]
+    fn test_restore_plan_and_optionally_spec() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = dir.path().join("checkpoints");
+        let (plan, spec) = write_files(dir.path(), "- [ ] A\n- [ ] B\n", "# Spec\n");
+        save(&base, 1, &plan, &spec, DEFAULT_KEEP).unwrap();
+        fs::write(&plan, "mangled").unwrap();
+        fs::write(&spec, "mangled").unwrap();
+
+        restore(&checkpoint_dir(&base, 1), &plan, None).unwrap();
+        assert_eq!(fs::read_to_string(&plan).unwrap(), "- [ ] A\n- [ ] B\n");
+        assert_eq!(fs::read_to_string(&spec).unwrap(), "mangled");
+
+        restore(&checkpoint_dir(&base, 1), &plan, Some(&spec)).unwrap();
+        assert_eq!(fs::read_to_string(&spec).unwrap(), "# Spec\n");
+    }
+
+    #[test]
+    fn test_restore_missing_spec_changes_nothing() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = dir.path().join("checkpoints");
+        let plan = dir.path().join("plan.md");
+        fs::write(&plan, "- [ ] A\n").unwrap();
+        save(
+            &base,
+            1,
+            &plan,
+            &dir.path().join("missing.md"),
+            DEFAULT_KEEP,
+        )
+        .unwrap();
+        fs::write(&plan, "mangled").unwrap();
+
+        let spec = dir.path().join("spec.md");
+        assert!(restore(&checkpoint_dir(&base, 1), &plan, Some(&spec)).is_err());
+        assert_eq!(fs::read_to_string(&plan).unwrap(), "mangled");
+    }
+
+    /// Create a run directory last written `age` seconds ago.
+    fn old_run(base: &Path, run_id: &str, age: u64) -> PathBuf {
+        let dir = run_dir(base, run_id);
+        fs::create_dir_all(checkpoint_dir(&dir, 1)).unwrap();
+        let written = SystemTime::now() - std::time::Duration::from_secs(age);
+        fs::File::open(&dir).unwrap().set_modified(written).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_runs_orders_by_age_and_skips_flat_checkpoints() {
+        let dir = tempfile::tempdir().unwrap();
+        let newer = old_run(dir.path(), "bbbb", 10);
+        let older = old_run(dir.path(), "aaaa", 20);
+        fs::create_dir_all(checkpoint_dir(dir.path(), 3)).unwrap();
+        fs::write(dir.path().join("notes"), "a file").unwrap();
+
+        assert_eq!(runs(dir.path()), vec![older, newer]);
+        assert!(runs(&dir.path().join("missing")).is_empty());
+    }
+
+    #[test]
+    fn test_start_run_keeps_newest_runs_and_empties_its_own() {
+        let dir = tempfile::tempdir().unwrap();
+        let oldest = old_run(dir.path(), "run-1", 30);
+        let middle = old_run(dir.path(), "run-2", 20);
+        let reused = old_run(dir.path(), "run-3", 10);
+
+        let current = start_run(dir.path(), "run-3", 2).unwrap();
+        assert_eq!(current, reused);
+        assert!(list(&current).is_empty());
+        assert!(!oldest.exists());
+        assert_eq!(runs(dir.path()), vec![middle, current]);
+    }
+
+    #[test]
+    fn test_clear_tolerates_missing_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = dir.path().join("checkpoints");
+        clear(&base).unwrap();
+        fs::create_dir_all(checkpoint_dir(&base, 1)).unwrap();
+        clear(&base).unwrap();
+        assert!(!base.exists());
+    }
+}
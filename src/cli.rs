@@ -1,10 +1,78 @@
 //! CLI utility detection for ralphctl.
 //!
-//! Provides functions for detecting external CLI dependencies.
+//! Provides functions for detecting external CLI dependencies, the
+//! terminal ralphctl is writing to, and y/N confirmations.
 
 #![allow(dead_code)] // Utilities for init command
 
+use std::io::{self, BufRead, IsTerminal, Write};
 use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Set by the global `--yes` flag; prompts answer themselves when true.
+static ASSUME_YES: AtomicBool = AtomicBool::new(false);
+
+/// Answer every interactive prompt affirmatively from now on (`--yes`).
+pub fn set_assume_yes(yes: bool) {
+    ASSUME_YES.store(yes, Ordering::SeqCst);
+}
+
+/// Whether `--yes` was given.
+pub fn assume_yes() -> bool {
+    ASSUME_YES.load(Ordering::SeqCst)
+}
+
+/// When to color output (the global `--color` flag).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ColorChoice {
+    /// Never emit color codes
+    Never,
+    /// Color when stdout is a terminal and `NO_COLOR` is unset
+    #[default]
+    Auto,
+    /// Always emit color codes, even into pipes
+    Always,
+}
+
+/// Resolved `--color`, set once in `main`.
+static USE_COLOR: AtomicBool = AtomicBool::new(false);
+
+/// Resolve `--color` into whether to color output.
+///
+/// `no_color` is the value of `NO_COLOR`; like other tools, an empty value
+/// counts as unset.
+pub fn resolve_color(
+    choice: ColorChoice,
+    stdout_is_terminal: bool,
+    no_color: Option<&str>,
+) -> bool {
+    match choice {
+        ColorChoice::Never => false,
+        ColorChoice::Always => true,
+        ColorChoice::Auto => stdout_is_terminal && no_color.is_none_or(str::is_empty),
+    }
+}
+
+/// Resolve `--color` against the environment and apply it to all output.
+pub fn init_color(choice: ColorChoice) {
+    let no_color = std::env::var("NO_COLOR").ok();
+    let use_color = resolve_color(choice, io::stdout().is_terminal(), no_color.as_deref());
+    USE_COLOR.store(use_color, Ordering::SeqCst);
+}
+
+/// Whether output should be colored.
+pub fn use_color() -> bool {
+    USE_COLOR.load(Ordering::SeqCst)
+}
+
+/// Dim `text` when color is on.
+pub fn dim(text: &str) -> String {
+    if use_color() {
+        format!("\x1b[2m{}\x1b[0m", text)
+    } else {
+        text.to_string()
+    }
+}
 
 /// Check if the `claude` CLI is available in PATH.
 ///
@@ -17,10 +85,95 @@ pub fn claude_exists() -> bool {
         .unwrap_or(false)
 }
 
+/// Whether someone could answer a prompt on stdin.
+///
+/// False under `--yes` and when stdin is a character device that is not a
+/// terminal, such as `/dev/null` in CI. Pipes and files still count: scripts
+/// answer prompts that way.
+pub fn stdin_can_prompt() -> bool {
+    use nix::sys::stat::{fstat, SFlag};
+
+    if assume_yes() {
+        return false;
+    }
+    let stdin = io::stdin();
+    if stdin.is_terminal() {
+        return true;
+    }
+    match fstat(&stdin) {
+        Ok(stat) => SFlag::from_bits_truncate(stat.st_mode) & SFlag::S_IFMT != SFlag::S_IFCHR,
+        Err(_) => false,
+    }
+}
+
+/// Width of the terminal stdout is attached to, in columns.
+///
+/// Returns `None` when stdout is not a terminal (piped, CI logs) or the
+/// width cannot be determined.
+pub fn terminal_width() -> Option<usize> {
+    if !io::stdout().is_terminal() {
+        return None;
+    }
+    ratatui::crossterm::terminal::size()
+        .ok()
+        .map(|(cols, _)| cols as usize)
+        .filter(|&cols| cols > 0)
+}
+
+/// Answer to a y/N confirmation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Confirmation {
+    /// `y` or `yes`
+    Yes,
+    /// Anything else, including an empty line
+    No,
+    /// Input ended before a line was read (stdin closed or not interactive)
+    NoInput,
+}
+
+/// Print `question` with a `[y/N]` suffix to stderr and read the answer.
+///
+/// Only `y` or `yes` (any case) confirms. End of input is reported as
+/// `NoInput` rather than `No`, so callers can explain that nobody could
+/// answer instead of treating it as a decline. Under `--yes` the question is
+/// printed with its answer and nothing is read.
+pub fn confirm<R: BufRead>(question: &str, input: &mut R) -> io::Result<Confirmation> {
+    if assume_yes() {
+        eprintln!("{} [y/N] y (--yes)", question);
+        return Ok(Confirmation::Yes);
+    }
+    eprint!("{} [y/N] ", question);
+    io::stderr().flush()?;
+
+    let mut line = String::new();
+    if input.read_line(&mut line)? == 0 {
+        eprintln!();
+        return Ok(Confirmation::NoInput);
+    }
+    let answer = line.trim().to_lowercase();
+    Ok(if answer == "y" || answer == "yes" {
+        Confirmation::Yes
+    } else {
+        Confirmation::No
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_resolve_color() {
+        use ColorChoice::*;
+
+        assert!(resolve_color(Always, false, Some("1")));
+        assert!(!resolve_color(Never, true, None));
+        assert!(resolve_color(Auto, true, None));
+        assert!(resolve_color(Auto, true, Some("")));
+        assert!(!resolve_color(Auto, true, Some("1")));
+        assert!(!resolve_color(Auto, false, None));
+    }
+
     #[test]
     fn test_claude_exists_returns_bool() {
         // We can't assert the specific value since it depends on the environment,
@@ -28,6 +181,37 @@ mod tests {
         let _ = claude_exists();
     }
 
+    fn answer(input: &str) -> Confirmation {
+        confirm("Proceed?", &mut io::Cursor::new(input)).unwrap()
+    }
+
+    #[test]
+    fn test_confirm_answers() {
+        assert_eq!(answer("y\n"), Confirmation::Yes);
+        assert_eq!(answer("YES\n"), Confirmation::Yes);
+        assert_eq!(answer("  yes  \n"), Confirmation::Yes);
+        assert_eq!(answer("y"), Confirmation::Yes);
+        assert_eq!(answer("n\n"), Confirmation::No);
+        assert_eq!(answer("maybe\n"), Confirmation::No);
+        assert_eq!(answer("\n"), Confirmation::No);
+    }
+
+    #[test]
+    fn test_confirm_end_of_input() {
+        assert_eq!(answer(""), Confirmation::NoInput);
+    }
+
+    #[test]
+    fn test_confirm_reads_one_line() {
+        let mut input = io::Cursor::new("n\ny\n");
+        assert_eq!(confirm("First?", &mut input).unwrap(), Confirmation::No);
+        assert_eq!(confirm("Second?", &mut input).unwrap(), Confirmation::Yes);
+        assert_eq!(
+            confirm("Third?", &mut input).unwrap(),
+            Confirmation::NoInput
+        );
+    }
+
     #[test]
     fn test_which_nonexistent_command() {
         // Test that which returns false for a command that definitely doesn't exist
@@ -0,0 +1,259 @@
+//! Effective configuration resolution for `--dump-config`.
+//!
+//! Collects the settings that shape a command (model, iteration limit, agent
+//! binary, template source, markers, ...) together with where each value came
+//! from, so "why did it use sonnet?" has a one-command answer.
+
+use crate::{signals, templates};
+use std::fmt;
+
+/// Where a setting's value came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    /// Built-in default
+    Default,
+    /// Derived from project state (e.g. open plan tasks)
+    Auto,
+    /// Command-line flag
+    Flag(&'static str),
+    /// Environment variable
+    Env(&'static str),
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Default => write!(f, "default"),
+            Source::Auto => write!(f, "auto"),
+            Source::Flag(flag) => write!(f, "flag {}", flag),
+            Source::Env(var) => write!(f, "env {}", var),
+        }
+    }
+}
+
+/// One resolved setting.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Setting {
+    /// Setting name as printed (e.g. `model`)
+    pub name: &'static str,
+    /// Resolved value
+    pub value: String,
+    /// Where the value came from
+    pub source: Source,
+}
+
+impl Setting {
+    fn new(name: &'static str, value: impl Into<String>, source: Source) -> Self {
+        Self {
+            name,
+            value: value.into(),
+            source,
+        }
+    }
+}
+
+/// Loop settings taken from `run`/`reverse` flags.
+#[derive(Debug, Clone)]
+pub struct LoopFlags {
+    /// `--model`, if given
+    pub model: Option<String>,
+    /// Effective `--max-iterations`
+    pub max_iterations: u32,
+    /// Where `max_iterations` came from
+    pub max_iterations_source: Source,
+    /// Reverse mode (selects the reverse markers)
+    pub reverse: bool,
+}
+
+/// Resolve the effective configuration.
+///
+/// `loop_flags` is `None` for commands that do not run a loop. `env` looks up
+/// environment variables, so tests can supply a fixed environment.
+pub fn resolve(
+    loop_flags: Option<&LoopFlags>,
+    env: impl Fn(&str) -> Option<String>,
+) -> Vec<Setting> {
+    let env = |var: &str| env(var).filter(|v| !v.is_empty());
+    let mut settings = Vec::new();
+
+    if let Some(flags) = loop_flags {
+        settings.push(match &flags.model {
+            Some(model) => Setting::new("model", model, Source::Flag("--model")),
+            None => Setting::new("model", "(claude default)", Source::Default),
+        });
+        settings.push(Setting::new(
+            "max-iterations",
+            flags.max_iterations.to_string(),
+            flags.max_iterations_source.clone(),
+        ));
+    }
+
+    settings.push(Setting::new("agent", "claude", Source::Default));
+
+    let cache_dir = templates::cache_dir_from(env)
+        .map(|dir| dir.display().to_string())
+        .unwrap_or_else(|_| "(unavailable)".to_string());
+    let cache_source = if env(templates::CACHE_DIR_ENV).is_some() {
+        Source::Env(templates::CACHE_DIR_ENV)
+    } else if env("XDG_CACHE_HOME").is_some() {
+        Source::Env("XDG_CACHE_HOME")
+    } else {
+        Source::Default
+    };
+    settings.push(Setting::new("cache-dir", cache_dir, cache_source));
+
+    settings.push(match env(templates::TEMPLATE_URL_ENV) {
+        Some(url) => Setting::new(
+            "template-base",
+            url,
+            Source::Env(templates::TEMPLATE_URL_ENV),
+        ),
+        None => Setting::new(
+            "template-base",
+            templates::TEMPLATE_BASE_URL,
+            Source::Default,
+        ),
+    });
+
+    // Never print the token itself
+    settings.push(
+        match templates::TOKEN_ENV_VARS
+            .iter()
+            .find(|var| env(var).is_some())
+        {
+            Some(var) => Setting::new("template-token", "set", Source::Env(var)),
+            None => Setting::new("template-token", "(none)", Source::Default),
+        },
+    );
+
+    let command = if loop_flags.is_some_and(|f| f.reverse) {
+        "reverse"
+    } else {
+        "run"
+    };
+    let markers = signals::for_command(command)
+        .into_iter()
+        .map(|s| s.marker)
+        .collect::<Vec<_>>()
+        .join(", ");
+    settings.push(Setting::new("markers", markers, Source::Default));
+
+    settings
+}
+
+/// Format settings as aligned `name  value  (source)` lines.
+pub fn format_config(settings: &[Setting]) -> String {
+    settings
+        .iter()
+        .map(|s| format!("{:<16}{}  ({})\n", s.name, s.value, s.source))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn no_env(_: &str) -> Option<String> {
+        None
+    }
+
+    fn find<'a>(settings: &'a [Setting], name: &str) -> &'a Setting {
+        settings.iter().find(|s| s.name == name).unwrap()
+    }
+
+    #[test]
+    fn test_resolve_defaults_without_loop() {
+        let settings = resolve(None, no_env);
+        assert!(settings.iter().all(|s| s.name != "model"));
+        assert_eq!(find(&settings, "agent").value, "claude");
+        assert_eq!(
+            find(&settings, "template-base").value,
+            templates::TEMPLATE_BASE_URL
+        );
+        assert_eq!(find(&settings, "template-token").value, "(none)");
+        assert!(find(&settings, "markers").value.contains("[[RALPH:DONE]]"));
+    }
+
+    #[test]
+    fn test_resolve_loop_flags_sources() {
+        let flags = LoopFlags {
+            model: Some("opus".to_string()),
+            max_iterations: 50,
+            max_iterations_source: Source::Default,
+            reverse: false,
+        };
+        let settings = resolve(Some(&flags), no_env);
+        assert_eq!(
+            find(&settings, "model"),
+            &Setting::new("model", "opus", Source::Flag("--model"))
+        );
+        assert_eq!(find(&settings, "max-iterations").source, Source::Default);
+
+        let flags = LoopFlags {
+            model: None,
+            max_iterations_source: Source::Flag("--max-iterations"),
+            ..flags
+        };
+        let settings = resolve(Some(&flags), no_env);
+        assert_eq!(find(&settings, "model").source, Source::Default);
+        assert_eq!(
+            find(&settings, "max-iterations").source,
+            Source::Flag("--max-iterations")
+        );
+    }
+
+    #[test]
+    fn test_resolve_env_sources_hide_token() {
+        let env = |var: &str| match var {
+            "RALPHCTL_TEMPLATE_URL" => Some("file:///tmp/tpl".to_string()),
+            "GITHUB_TOKEN" => Some("secret".to_string()),
+            "RALPHCTL_CACHE_DIR" => Some("/tmp/cache".to_string()),
+            "XDG_CACHE_HOME" => Some("/tmp/xdg".to_string()),
+            "RALPHCTL_TOKEN" => Some(String::new()),
+            _ => None,
+        };
+        let settings = resolve(None, env);
+        assert_eq!(
+            find(&settings, "template-base"),
+            &Setting::new(
+                "template-base",
+                "file:///tmp/tpl",
+                Source::Env("RALPHCTL_TEMPLATE_URL")
+            )
+        );
+        assert_eq!(
+            find(&settings, "cache-dir").source,
+            Source::Env("RALPHCTL_CACHE_DIR")
+        );
+        let token = find(&settings, "template-token");
+        assert_eq!(token.value, "set");
+        assert_eq!(token.source, Source::Env("GITHUB_TOKEN"));
+        assert!(!format_config(&settings).contains("secret"));
+    }
+
+    #[test]
+    fn test_resolve_reverse_markers() {
+        let flags = LoopFlags {
+            model: None,
+            max_iterations: 100,
+            max_iterations_source: Source::Default,
+            reverse: true,
+        };
+        let settings = resolve(Some(&flags), no_env);
+        let markers = &find(&settings, "markers").value;
+        assert!(markers.contains("[[RALPH:FOUND:"));
+        assert!(!markers.contains("[[RALPH:DONE]]"));
+    }
+
+    #[test]
+    fn test_format_config() {
+        let settings = vec![
+            Setting::new("agent", "claude", Source::Default),
+            Setting::new("model", "opus", Source::Flag("--model")),
+        ];
+        assert_eq!(
+            format_config(&settings),
+            "agent           claude  (default)\nmodel           opus  (flag --model)\n"
+        );
+    }
+}
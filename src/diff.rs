@@ -0,0 +1,211 @@
+//! Line-based diffing for previewing file updates.
+//!
+//! A small LCS diff, enough to show what `fetch-latest-prompt` would change
+//! in PROMPT.md before overwriting it. Quadratic in the number of lines,
+//! which is fine for prompt-sized files.
+
+/// Lines of unchanged context shown around each change.
+const CONTEXT: usize = 3;
+
+/// One line of a diff.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffLine<'a> {
+    /// Present in both old and new
+    Same(&'a str),
+    /// Only in old
+    Removed(&'a str),
+    /// Only in new
+    Added(&'a str),
+}
+
+/// Compute a line diff between `old` and `new` via longest common subsequence.
+///
+/// Removals are listed before additions where a block of lines is replaced.
+pub fn diff_lines<'a>(old: &'a str, new: &'a str) -> Vec<DiffLine<'a>> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    // lcs[i][j] = LCS length of old[i..] and new[j..]
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut lines = Vec::with_capacity(old.len().max(new.len()));
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            lines.push(DiffLine::Same(old[i]));
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            lines.push(DiffLine::Removed(old[i]));
+            i += 1;
+        } else {
+            lines.push(DiffLine::Added(new[j]));
+            j += 1;
+        }
+    }
+    lines.extend(old[i..].iter().map(|l| DiffLine::Removed(l)));
+    lines.extend(new[j..].iter().map(|l| DiffLine::Added(l)));
+    lines
+}
+
+/// Format a unified-style diff of `old` → `new` with `@@` hunk headers.
+///
+/// Returns an empty string when the inputs have the same lines.
+pub fn unified_diff(old_label: &str, new_label: &str, old: &str, new: &str) -> String {
+    let lines = diff_lines(old, new);
+    let changes: Vec<usize> = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, l)| !matches!(l, DiffLine::Same(_)))
+        .map(|(idx, _)| idx)
+        .collect();
+    if changes.is_empty() {
+        return String::new();
+    }
+
+    // Line numbers (0-based) in old and new before each diff line
+    let mut positions = Vec::with_capacity(lines.len());
+    let (mut old_pos, mut new_pos) = (0, 0);
+    for line in &lines {
+        positions.push((old_pos, new_pos));
+        match line {
+            DiffLine::Same(_) => {
+                old_pos += 1;
+                new_pos += 1;
+            }
+            DiffLine::Removed(_) => old_pos += 1,
+            DiffLine::Added(_) => new_pos += 1,
+        }
+    }
+
+    // Group changes whose gap fits within the shared context
+    let mut groups: Vec<(usize, usize)> = Vec::new();
+    for &idx in &changes {
+        match groups.last_mut() {
+            Some((_, last)) if idx - *last <= 2 * CONTEXT + 1 => *last = idx,
+            _ => groups.push((idx, idx)),
+        }
+    }
+
+    let mut out = format!("--- {}\n+++ {}\n", old_label, new_label);
+    for (first, last) in groups {
+        let start = first.saturating_sub(CONTEXT);
+        let end = (last + CONTEXT + 1).min(lines.len());
+        let hunk = &lines[start..end];
+        let old_count = hunk
+            .iter()
+            .filter(|l| !matches!(l, DiffLine::Added(_)))
+            .count();
+        let new_count = hunk
+            .iter()
+            .filter(|l| !matches!(l, DiffLine::Removed(_)))
+            .count();
+        let (old_start, new_start) = positions[start];
+        out.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            hunk_range(old_start, old_count),
+            hunk_range(new_start, new_count)
+        ));
+        for line in hunk {
+            let (prefix, text) = match line {
+                DiffLine::Same(t) => (' ', t),
+                DiffLine::Removed(t) => ('-', t),
+                DiffLine::Added(t) => ('+', t),
+            };
+            out.push(prefix);
+            out.push_str(text);
+            out.push('\n');
+        }
+    }
+    out
+}
+
+/// Format a hunk range as `start,count` (1-based; start is 0 for empty ranges).
+fn hunk_range(start: usize, count: usize) -> String {
+    if count == 0 {
+        format!("{},0", start)
+    } else {
+        format!("{},{}", start + 1, count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use DiffLine::*;
+
+    #[test]
+    fn test_diff_identical() {
+        assert_eq!(diff_lines("a\nb", "a\nb"), vec![Same("a"), Same("b")]);
+        assert_eq!(unified_diff("old", "new", "a\nb\n", "a\nb\n"), "");
+    }
+
+    #[test]
+    fn test_diff_insert() {
+        assert_eq!(
+            diff_lines("a\nc", "a\nb\nc"),
+            vec![Same("a"), Added("b"), Same("c")]
+        );
+        assert_eq!(diff_lines("", "a"), vec![Added("a")]);
+    }
+
+    #[test]
+    fn test_diff_delete() {
+        assert_eq!(
+            diff_lines("a\nb\nc", "a\nc"),
+            vec![Same("a"), Removed("b"), Same("c")]
+        );
+        assert_eq!(diff_lines("a", ""), vec![Removed("a")]);
+    }
+
+    #[test]
+    fn test_diff_replace() {
+        assert_eq!(
+            diff_lines("a\nb\nc", "a\nx\nc"),
+            vec![Same("a"), Removed("b"), Added("x"), Same("c")]
+        );
+    }
+
+    #[test]
+    fn test_unified_diff_format() {
+        let diff = unified_diff("PROMPT.md", "PROMPT.md (latest)", "a\nb\nc\n", "a\nx\nc\n");
+        assert_eq!(
+            diff,
+            "--- PROMPT.md\n+++ PROMPT.md (latest)\n@@ -1,3 +1,3 @@\n a\n-b\n+x\n c\n"
+        );
+    }
+
+    #[test]
+    fn test_unified_diff_splits_distant_hunks() {
+        let old: String = (1..=20).map(|n| format!("{}\n", n)).collect();
+        let new: String = (1..=20)
+            .map(|n| match n {
+                2 => "two\n".to_string(),
+                19 => "nineteen\n".to_string(),
+                _ => format!("{}\n", n),
+            })
+            .collect();
+        let diff = unified_diff("a", "b", &old, &new);
+
+        assert_eq!(diff.matches("@@ -").count(), 2);
+        assert!(diff.contains("@@ -1,5 +1,5 @@\n 1\n-2\n+two\n 3\n"));
+        assert!(diff.contains("@@ -16,5 +16,5 @@\n 16\n 17\n 18\n-19\n+nineteen\n 20\n"));
+    }
+
+    #[test]
+    fn test_unified_diff_empty_old() {
+        assert_eq!(
+            unified_diff("a", "b", "", "x\n"),
+            "--- a\n+++ b\n@@ -0,0 +1,1 @@\n+x\n"
+        );
+    }
+}
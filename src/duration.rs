@@ -0,0 +1,191 @@
+//! Duration strings for time-valued flags.
+//!
+//! Every flag that takes a length of time accepts the same forms: a bare
+//! number of seconds (`90`) or unit-suffixed parts in hours, minutes, and
+//! seconds (`90s`, `5m`, `1h30m`, `2h`). Parsing is case-insensitive and
+//! ignores whitespace, so `format_duration` output (`1h 02m 03s`) parses back
+//! to the same value.
+
+use anyhow::{bail, Result};
+use std::time::Duration;
+
+/// Parse a duration string such as `90`, `90s`, `5m`, or `1h30m`.
+///
+/// Units must appear at most once each, in h, m, s order. Zero is allowed;
+/// flags that need a positive value use `parse_positive_duration`.
+pub fn parse_duration(input: &str) -> Result<Duration> {
+    let s: String = input
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<String>()
+        .to_ascii_lowercase();
+    if s.is_empty() {
+        bail!("empty duration (expected e.g. 90, 90s, 5m, 1h30m)");
+    }
+    if s.starts_with('-') {
+        bail!("duration '{}' must not be negative", input.trim());
+    }
+    if s.chars().all(|c| c.is_ascii_digit()) {
+        return Ok(Duration::from_secs(parse_number(&s, input)?));
+    }
+
+    let mut total: u64 = 0;
+    let mut last_unit: Option<usize> = None;
+    let mut rest = s.as_str();
+    while !rest.is_empty() {
+        let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        if digits == 0 {
+            bail!(
+                "invalid duration '{}': expected a number before '{}'",
+                input.trim(),
+                &rest[..rest.chars().next().map_or(0, char::len_utf8)]
+            );
+        }
+        let value = parse_number(&rest[..digits], input)?;
+        rest = &rest[digits..];
+
+        let unit = rest.chars().next();
+        let (index, scale) = match unit {
+            Some('h') => (0, 3600),
+            Some('m') => (1, 60),
+            Some('s') => (2, 1),
+            Some(c) => bail!(
+                "invalid duration '{}': unknown unit '{}' (use h, m, or s)",
+                input.trim(),
+                c
+            ),
+            None => bail!(
+                "invalid duration '{}': missing unit after {} (use h, m, or s)",
+                input.trim(),
+                value
+            ),
+        };
+        if last_unit.is_some_and(|last| index <= last) {
+            bail!(
+                "invalid duration '{}': units must appear once each, in h, m, s order",
+                input.trim()
+            );
+        }
+        last_unit = Some(index);
+        rest = &rest[1..];
+
+        total = value
+            .checked_mul(scale)
+            .and_then(|secs| total.checked_add(secs))
+            .ok_or_else(|| anyhow::anyhow!("duration '{}' is too large", input.trim()))?;
+    }
+    Ok(Duration::from_secs(total))
+}
+
+/// Parse a run of ASCII digits, rejecting values that overflow `u64`.
+fn parse_number(digits: &str, input: &str) -> Result<u64> {
+    digits
+        .parse()
+        .map_err(|_| anyhow::anyhow!("duration '{}' is too large", input.trim()))
+}
+
+/// clap value parser for flags that need a positive duration.
+///
+/// clap prefixes the message with the flag name and the rejected value.
+pub fn parse_positive_duration(input: &str) -> Result<Duration, String> {
+    match parse_duration(input) {
+        Ok(d) if d.is_zero() => Err("must be greater than zero".to_string()),
+        Ok(d) => Ok(d),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Format a duration as a compact wall-clock string, whole seconds only.
+///
+/// Format: `45s`, `3m 07s`, `1h 02m 03s`
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let (h, m, s) = (secs / 3600, (secs % 3600) / 60, secs % 60);
+    if h > 0 {
+        format!("{}h {:02}m {:02}s", h, m, s)
+    } else if m > 0 {
+        format!("{}m {:02}s", m, s)
+    } else {
+        format!("{}s", s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secs(n: u64) -> Duration {
+        Duration::from_secs(n)
+    }
+
+    #[test]
+    fn test_parse_duration_forms() {
+        assert_eq!(parse_duration("90").unwrap(), secs(90));
+        assert_eq!(parse_duration("90s").unwrap(), secs(90));
+        assert_eq!(parse_duration("5m").unwrap(), secs(300));
+        assert_eq!(parse_duration("2h").unwrap(), secs(7200));
+        assert_eq!(parse_duration("1h30m").unwrap(), secs(5400));
+        assert_eq!(parse_duration("1h0m5s").unwrap(), secs(3605));
+        assert_eq!(parse_duration("0").unwrap(), secs(0));
+        assert_eq!(parse_duration("0s").unwrap(), secs(0));
+    }
+
+    #[test]
+    fn test_parse_duration_case_and_whitespace() {
+        assert_eq!(parse_duration("1H30M").unwrap(), secs(5400));
+        assert_eq!(parse_duration("  1h 30m\t").unwrap(), secs(5400));
+        assert_eq!(parse_duration("3m 07s").unwrap(), secs(187));
+        assert_eq!(parse_duration(" 45 ").unwrap(), secs(45));
+    }
+
+    #[test]
+    fn test_parse_duration_malformed() {
+        for input in [
+            "", "   ", "h", "5x", "1h-5m", "-5", "-5m", "5m1h", "1m1m", "1.5h", "m5", "5 h x",
+        ] {
+            assert!(parse_duration(input).is_err(), "accepted {:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_parse_duration_messages() {
+        let err = |s: &str| parse_duration(s).unwrap_err().to_string();
+        assert!(err("").contains("empty duration"));
+        assert!(err("h").contains("expected a number before 'h'"));
+        assert!(err("5x").contains("unknown unit 'x'"));
+        assert!(err("1h-5m").contains("expected a number before '-'"));
+        assert!(err("-5m").contains("must not be negative"));
+        assert!(err("1h5").contains("missing unit after 5"));
+        assert!(err("5m1h").contains("in h, m, s order"));
+        assert!(err("99999999999999999999").contains("too large"));
+        assert!(err("9999999999999999999h").contains("too large"));
+    }
+
+    #[test]
+    fn test_parse_positive_duration() {
+        assert_eq!(parse_positive_duration("2m"), Ok(secs(120)));
+        assert_eq!(
+            parse_positive_duration("0s"),
+            Err("must be greater than zero".to_string())
+        );
+        assert!(parse_positive_duration("5x")
+            .unwrap_err()
+            .contains("unknown unit"));
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(secs(0)), "0s");
+        assert_eq!(format_duration(secs(45)), "45s");
+        assert_eq!(format_duration(secs(187)), "3m 07s");
+        assert_eq!(format_duration(secs(3723)), "1h 02m 03s");
+        assert_eq!(format_duration(Duration::from_millis(1999)), "1s");
+    }
+
+    #[test]
+    fn test_format_parse_round_trip() {
+        for n in [0, 1, 59, 60, 61, 599, 3600, 3661, 86_399, 360_000] {
+            assert_eq!(parse_duration(&format_duration(secs(n))).unwrap(), secs(n));
+        }
+    }
+}
@@ -10,6 +10,8 @@ use std::process;
 pub mod exit {
     /// Successful completion
     pub const SUCCESS: i32 = 0;
+    /// Question answered (reverse mode); the same code as `SUCCESS`
+    pub const FOUND: i32 = SUCCESS;
     /// General error (missing files, invalid input)
     pub const ERROR: i32 = 1;
     /// Max iterations reached without completion
@@ -18,6 +20,14 @@ pub mod exit {
     pub const BLOCKED: i32 = 3;
     /// Investigation inconclusive (reverse mode only)
     pub const INCONCLUSIVE: i32 = 4;
+    /// Iteration ended without a signal under `--strict-signals`
+    pub const NO_SIGNAL: i32 = 5;
+    /// Iteration killed for exceeding `--max-output-bytes`
+    pub const OUTPUT_LIMIT: i32 = 6;
+    /// Confirmation needed but stdin was closed (pass `--force`)
+    pub const NOT_INTERACTIVE: i32 = 7;
+    /// Too many consecutive blank iterations under `--max-blank-iterations`
+    pub const BLANK_OUTPUT: i32 = 8;
     /// Interrupted by signal (Ctrl+C)
     pub const INTERRUPTED: i32 = 130;
 }
@@ -58,6 +68,22 @@ impl<T> ResultExt<T> for Option<T> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_exit_code_values() {
+        // Scripts depend on these; the help texts and README list them too
+        assert_eq!(exit::SUCCESS, 0);
+        assert_eq!(exit::FOUND, 0);
+        assert_eq!(exit::ERROR, 1);
+        assert_eq!(exit::MAX_ITERATIONS, 2);
+        assert_eq!(exit::BLOCKED, 3);
+        assert_eq!(exit::INCONCLUSIVE, 4);
+        assert_eq!(exit::NO_SIGNAL, 5);
+        assert_eq!(exit::OUTPUT_LIMIT, 6);
+        assert_eq!(exit::NOT_INTERACTIVE, 7);
+        assert_eq!(exit::BLANK_OUTPUT, 8);
+        assert_eq!(exit::INTERRUPTED, 130);
+    }
+
     #[test]
     fn test_result_ext_ok() {
         let result: Result<i32, std::io::Error> = Ok(42);
@@ -47,12 +47,36 @@ pub const ALL_ARCHIVABLE_FILES: &[&str] = &[
     FINDINGS_FILE,
 ];
 
+/// Prompt files `archive --include-prompt` copies (but never resets).
+pub const ARCHIVE_PROMPT_FILES: &[&str] = &[PROMPT_FILE, REVERSE_PROMPT_FILE];
+
 /// The ralphctl directory for storing archives and other data.
 pub const RALPHCTL_DIR: &str = ".ralphctl";
 
 /// The archive subdirectory within .ralphctl.
 pub const ARCHIVE_DIR: &str = "archive";
 
+/// Scratch subdirectory within .ralphctl for per-iteration temp files.
+pub const SCRATCH_DIR: &str = "scratch";
+
+/// Checkpoints subdirectory within .ralphctl for pre-iteration plan copies.
+pub const CHECKPOINTS_DIR: &str = "checkpoints";
+
+/// Logs subdirectory within .ralphctl for per-iteration logs (--split-logs).
+pub const LOGS_DIR: &str = "logs";
+
+/// Lock file within .ralphctl marking an active loop.
+pub const LOCK_FILE: &str = "run.lock";
+
+/// Persistent project state within .ralphctl (last run ID, ...).
+pub const STATE_FILE: &str = "state.json";
+
+/// Run statistics within .ralphctl, kept across runs.
+pub const STATS_FILE: &str = "stats.toml";
+
+/// Manifest written into each archive directory.
+pub const MANIFEST_FILE: &str = "manifest.json";
+
 /// All ralph files (forward mode + reverse mode) that can be cleaned.
 pub const ALL_RALPH_FILES: &[&str] = &[
     // Forward mode
@@ -110,6 +134,25 @@ pub fn find_archivable_files(dir: &Path) -> Vec<PathBuf> {
         .collect()
 }
 
+/// Find the files `archive --include-log` and `--include-prompt` add.
+///
+/// These are copied into the archive alongside the archivable files but left
+/// in place afterwards. Only files that exist are returned.
+pub fn find_archive_extras(dir: &Path, include_log: bool, include_prompt: bool) -> Vec<PathBuf> {
+    let log: &[&str] = if include_log { &[LOG_FILE] } else { &[] };
+    let prompts: &[&str] = if include_prompt {
+        ARCHIVE_PROMPT_FILES
+    } else {
+        &[]
+    };
+    prompts
+        .iter()
+        .chain(log)
+        .map(|name| dir.join(name))
+        .filter(|path| path.exists())
+        .collect()
+}
+
 /// Find archivable reverse mode files that exist in the given directory.
 ///
 /// Returns a list of paths to existing archivable reverse mode files.
@@ -127,6 +170,51 @@ pub fn archive_base_dir(dir: &Path) -> PathBuf {
     dir.join(RALPHCTL_DIR).join(ARCHIVE_DIR)
 }
 
+/// Names of the archives in .ralphctl/archive (their timestamps), oldest first.
+pub fn list_archives(dir: &Path) -> Vec<String> {
+    let mut names: Vec<String> = std::fs::read_dir(archive_base_dir(dir))
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.path().is_dir())
+                .map(|entry| entry.file_name().to_string_lossy().into_owned())
+                .collect()
+        })
+        .unwrap_or_default();
+    names.sort();
+    names
+}
+
+/// Get the base scratch directory path (.ralphctl/scratch).
+pub fn scratch_base_dir(dir: &Path) -> PathBuf {
+    dir.join(RALPHCTL_DIR).join(SCRATCH_DIR)
+}
+
+/// Get the plan checkpoints directory path (.ralphctl/checkpoints).
+pub fn checkpoints_dir(dir: &Path) -> PathBuf {
+    dir.join(RALPHCTL_DIR).join(CHECKPOINTS_DIR)
+}
+
+/// Get the per-iteration logs directory path (.ralphctl/logs).
+pub fn logs_dir(dir: &Path) -> PathBuf {
+    dir.join(RALPHCTL_DIR).join(LOGS_DIR)
+}
+
+/// Get the project state file path (.ralphctl/state.json).
+pub fn state_file_path(dir: &Path) -> PathBuf {
+    dir.join(RALPHCTL_DIR).join(STATE_FILE)
+}
+
+/// Get the run statistics file path (.ralphctl/stats.toml).
+pub fn stats_file_path(dir: &Path) -> PathBuf {
+    dir.join(RALPHCTL_DIR).join(STATS_FILE)
+}
+
+/// Get the run lock file path (.ralphctl/run.lock).
+pub fn lock_file_path(dir: &Path) -> PathBuf {
+    dir.join(RALPHCTL_DIR).join(LOCK_FILE)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -262,6 +350,24 @@ mod tests {
         assert!(!found.iter().any(|p| p.ends_with(PROMPT_FILE)));
     }
 
+    #[test]
+    fn test_find_archive_extras() {
+        let dir = create_temp_dir();
+        fs::write(dir.path().join(PROMPT_FILE), "# Prompt").unwrap();
+        fs::write(dir.path().join(LOG_FILE), "log").unwrap();
+
+        assert!(find_archive_extras(dir.path(), false, false).is_empty());
+        assert_eq!(
+            find_archive_extras(dir.path(), true, false),
+            vec![dir.path().join(LOG_FILE)]
+        );
+        // REVERSE_PROMPT.md is skipped because it doesn't exist
+        assert_eq!(
+            find_archive_extras(dir.path(), true, true),
+            vec![dir.path().join(PROMPT_FILE), dir.path().join(LOG_FILE)]
+        );
+    }
+
     #[test]
     fn test_find_archivable_files_includes_reverse() {
         let dir = create_temp_dir();
@@ -309,6 +415,21 @@ mod tests {
         assert!(archive_dir.ends_with(".ralphctl/archive"));
     }
 
+    #[test]
+    fn test_list_archives() {
+        let dir = create_temp_dir();
+        assert!(list_archives(dir.path()).is_empty());
+
+        let base = archive_base_dir(dir.path());
+        fs::create_dir_all(base.join("2026-02-01T09-00-00")).unwrap();
+        fs::create_dir_all(base.join("2026-01-15T18-30-00")).unwrap();
+        fs::write(base.join("stray.txt"), "").unwrap();
+        assert_eq!(
+            list_archives(dir.path()),
+            vec!["2026-01-15T18-30-00", "2026-02-01T09-00-00"]
+        );
+    }
+
     // Reverse mode file tests
 
     #[test]
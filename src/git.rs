@@ -0,0 +1,259 @@
+//! Per-iteration change summaries via git.
+//!
+//! `run --show-diff` snapshots the worktree before each iteration and, once
+//! claude exits, lists the files that changed with their line counts. The
+//! snapshot is a `git stash create` commit, so nothing in the worktree,
+//! index, or stash list is touched. Outside a git repo every function is a
+//! no-op.
+
+use anyhow::{Context, Result};
+use std::collections::BTreeSet;
+use std::path::Path;
+use std::process::Command;
+
+/// Hash of git's empty tree, the base for a repository without commits.
+const EMPTY_TREE: &str = "4b825dc642cb6eb9a060e54bf8d69288fbee4904";
+
+/// Worktree state captured before an iteration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    /// Commit (or tree) holding the tracked files as they were
+    base: String,
+    /// Untracked, non-ignored files that already existed
+    untracked: BTreeSet<String>,
+}
+
+/// One changed file with its line counts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileChange {
+    /// Path relative to the repository root
+    pub path: String,
+    /// Lines added, or `None` for binary files
+    pub added: Option<u64>,
+    /// Lines removed, or `None` for binary files
+    pub removed: Option<u64>,
+}
+
+/// Run git in `dir`, returning trimmed stdout, or `None` if it failed.
+fn git(dir: &Path, args: &[&str]) -> Option<String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .ok()?;
+    output.status.success().then(|| {
+        String::from_utf8_lossy(&output.stdout)
+            .trim_end()
+            .to_string()
+    })
+}
+
+/// Capture the worktree state of the repository containing `dir`.
+///
+/// Returns `None` outside a git repository or when git is not installed.
+pub fn snapshot(dir: &Path) -> Option<Snapshot> {
+    git(dir, &["rev-parse", "--is-inside-work-tree"]).filter(|out| out == "true")?;
+
+    // `stash create` prints nothing when the worktree is clean, and fails
+    // before the first commit. The identity only labels the dangling commit,
+    // so a missing user.email cannot make it fail.
+    let stash = [
+        "-c",
+        "user.name=ralphctl",
+        "-c",
+        "user.email=ralphctl@localhost",
+        "stash",
+        "create",
+    ];
+    let base = git(dir, &stash)
+        .filter(|hash| !hash.is_empty())
+        .or_else(|| git(dir, &["rev-parse", "--verify", "HEAD"]))
+        .unwrap_or_else(|| EMPTY_TREE.to_string());
+    let untracked = untracked_files(dir).unwrap_or_default();
+    Some(Snapshot { base, untracked })
+}
+
+/// List untracked, non-ignored files relative to the repository root.
+fn untracked_files(dir: &Path) -> Option<BTreeSet<String>> {
+    let out = git(
+        dir,
+        &[
+            "ls-files",
+            "--others",
+            "--exclude-standard",
+            "--full-name",
+            "-z",
+        ],
+    )?;
+    Some(
+        out.split('\0')
+            .filter(|p| !p.is_empty())
+            .map(str::to_string)
+            .collect(),
+    )
+}
+
+/// List files changed since `snapshot` was taken.
+///
+/// Tracked files are compared with `git diff --numstat`; untracked files
+/// that did not exist before are reported with all their lines added.
+pub fn changes_since(dir: &Path, snapshot: &Snapshot) -> Result<Vec<FileChange>> {
+    let root = git(dir, &["rev-parse", "--show-toplevel"])
+        .context("failed to find the git repository root")?;
+    let numstat = git(Path::new(&root), &["diff", "--numstat", &snapshot.base])
+        .with_context(|| format!("git diff against {} failed", snapshot.base))?;
+    let mut changes = parse_numstat(&numstat);
+
+    for path in untracked_files(Path::new(&root)).unwrap_or_default() {
+        if snapshot.untracked.contains(&path) {
+            continue;
+        }
+        let added = std::fs::read(Path::new(&root).join(&path))
+            .ok()
+            .and_then(|bytes| String::from_utf8(bytes).ok())
+            .map(|text| text.lines().count() as u64);
+        changes.push(FileChange {
+            path,
+            added,
+            removed: added.map(|_| 0),
+        });
+    }
+    Ok(changes)
+}
+
+/// Parse `git diff --numstat` output.
+///
+/// Each line is `<added>\t<removed>\t<path>`; binary files show `-` for both
+/// counts.
+pub fn parse_numstat(output: &str) -> Vec<FileChange> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(3, '\t');
+            let added = fields.next()?;
+            let removed = fields.next()?;
+            let path = fields.next()?;
+            Some(FileChange {
+                path: path.to_string(),
+                added: added.parse().ok(),
+                removed: removed.parse().ok(),
+            })
+        })
+        .collect()
+}
+
+/// Format changes as an indented `+added -removed  path` list.
+pub fn format_changes(changes: &[FileChange]) -> String {
+    if changes.is_empty() {
+        return "No files changed.\n".to_string();
+    }
+    let count = |n: Option<u64>, sign: char| match n {
+        Some(n) => format!("{}{}", sign, n),
+        None => "bin".to_string(),
+    };
+    let mut out = format!(
+        "Changed {} file{}:\n",
+        changes.len(),
+        if changes.len() == 1 { "" } else { "s" }
+    );
+    for change in changes {
+        out.push_str(&format!(
+            "  {:>6} {:>6}  {}\n",
+            count(change.added, '+'),
+            count(change.removed, '-'),
+            change.path
+        ));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn change(path: &str, added: Option<u64>, removed: Option<u64>) -> FileChange {
+        FileChange {
+            path: path.to_string(),
+            added,
+            removed,
+        }
+    }
+
+    #[test]
+    fn test_parse_numstat() {
+        let output =
+            "12\t3\tsrc/main.rs\n-\t-\tlogo.png\n0\t4\tdir with space/a b.txt\n\ngarbage\n";
+        assert_eq!(
+            parse_numstat(output),
+            vec![
+                change("src/main.rs", Some(12), Some(3)),
+                change("logo.png", None, None),
+                change("dir with space/a b.txt", Some(0), Some(4)),
+            ]
+        );
+        assert!(parse_numstat("").is_empty());
+    }
+
+    #[test]
+    fn test_format_changes() {
+        assert_eq!(format_changes(&[]), "No files changed.\n");
+        assert_eq!(
+            format_changes(&[
+                change("src/main.rs", Some(12), Some(3)),
+                change("logo.png", None, None),
+            ]),
+            "Changed 2 files:\n     +12     -3  src/main.rs\n     bin    bin  logo.png\n"
+        );
+        assert!(format_changes(&[change("a", Some(1), Some(0))]).starts_with("Changed 1 file:\n"));
+    }
+
+    #[test]
+    fn test_snapshot_outside_git_repo() {
+        let dir = tempfile::tempdir().unwrap();
+        // Skip if the temp dir happens to live inside a repository
+        if git(dir.path(), &["rev-parse", "--git-dir"]).is_none() {
+            assert_eq!(snapshot(dir.path()), None);
+        }
+    }
+
+    #[test]
+    fn test_changes_since_snapshot() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path();
+        if git(path, &["init", "-q"]).is_none() {
+            return; // git not installed
+        }
+        fs::write(path.join("tracked.txt"), "one\ntwo\n").unwrap();
+        fs::write(path.join("old-untracked.txt"), "x\n").unwrap();
+        git(path, &["add", "tracked.txt"]).unwrap();
+        git(
+            path,
+            &[
+                "-c",
+                "user.name=t",
+                "-c",
+                "user.email=t@example.com",
+                "commit",
+                "-qm",
+                "init",
+            ],
+        )
+        .unwrap();
+        // Uncommitted edits before the snapshot are not reported
+        fs::write(path.join("tracked.txt"), "one\ntwo\nthree\n").unwrap();
+
+        let snap = snapshot(path).unwrap();
+        assert_eq!(changes_since(path, &snap).unwrap(), vec![]);
+
+        fs::write(path.join("tracked.txt"), "one\nthree\nfour\n").unwrap();
+        fs::write(path.join("new.txt"), "a\nb\n").unwrap();
+        assert_eq!(
+            changes_since(path, &snap).unwrap(),
+            vec![
+                change("tracked.txt", Some(1), Some(1)),
+                change("new.txt", Some(2), Some(0)),
+            ]
+        );
+    }
+}
@@ -0,0 +1,219 @@
+//! Run identifiers for ralphctl.
+//!
+//! Each `run` or `reverse` invocation gets a short random ID (or the label
+//! given with `--tag`) that ties together its ralph.log entries, lock file,
+//! summary, and any archive made afterwards. The most recent ID is kept in
+//! `.ralphctl/state.json`.
+
+use crate::files;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+use std::collections::hash_map::RandomState;
+use std::fs;
+use std::hash::{BuildHasher, Hasher};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Environment variable exposing the run ID to the claude child.
+pub const RUN_ID_ENV: &str = "RALPH_RUN_ID";
+
+/// Generate a random run ID of 8 lowercase hex characters.
+///
+/// Uses the standard library's randomly seeded hasher mixed with the PID
+/// and current time, which is plenty for telling runs apart.
+pub fn generate_run_id() -> String {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u32(std::process::id());
+    if let Ok(now) = SystemTime::now().duration_since(UNIX_EPOCH) {
+        hasher.write_u128(now.as_nanos());
+    }
+    format_run_id(hasher.finish())
+}
+
+/// Format the low 32 bits of `value` as an 8-character hex run ID.
+pub fn format_run_id(value: u64) -> String {
+    format!("{:08x}", value as u32)
+}
+
+/// Parse a `--tag` label used in place of a generated run ID.
+///
+/// Tags appear in log headers, so they are limited to letters, digits,
+/// `-`, `_`, and `.`.
+pub fn parse_tag(s: &str) -> Result<String, String> {
+    if s.is_empty() {
+        return Err("tag must not be empty".to_string());
+    }
+    if let Some(c) = s
+        .chars()
+        .find(|c| !(c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')))
+    {
+        return Err(format!(
+            "invalid character {:?} in tag '{}' (use letters, digits, '-', '_', '.')",
+            c, s
+        ));
+    }
+    Ok(s.to_string())
+}
+
+/// Persistent project state in `.ralphctl/state.json`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct State {
+    /// ID of the most recent run or reverse invocation
+    #[serde(default)]
+    pub last_run_id: Option<String>,
+    /// Project name of that run, if one was resolved
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+    /// Number of the last ralph.log iteration entry
+    #[serde(default)]
+    pub log_seq: u64,
+}
+
+/// Load `.ralphctl/state.json` from `dir`, or the default if missing or invalid.
+pub fn load_state(dir: &Path) -> State {
+    fs::read_to_string(files::state_file_path(dir))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// Record `run_id` and its project name as the last run in
+/// `.ralphctl/state.json`.
+pub fn save_last_run_id(dir: &Path, run_id: &str, project: Option<&str>) -> Result<()> {
+    let mut state = load_state(dir);
+    state.last_run_id = Some(run_id.to_string());
+    state.project = project.map(str::to_string);
+    save_state(dir, &state)
+}
+
+/// Numbers ralph.log entries for one run.
+///
+/// Numbers start at 1 and keep increasing across runs, including after
+/// `--fresh` truncates the log. They are counted in memory; `.ralphctl/state.json`
+/// is read when the run starts and written once by `save` when it ends.
+#[derive(Debug)]
+pub struct LogSeq {
+    last: Cell<u64>,
+}
+
+impl LogSeq {
+    /// Continue after the larger of the saved number and `logged`, the
+    /// highest entry number already in ralph.log, so entries from a run that
+    /// died before saving are not numbered twice.
+    pub fn resume(dir: &Path, logged: u64) -> Self {
+        Self {
+            last: Cell::new(load_state(dir).log_seq.max(logged)),
+        }
+    }
+
+    /// Claim the next entry number.
+    pub fn next(&self) -> u64 {
+        let seq = self.last.get() + 1;
+        self.last.set(seq);
+        seq
+    }
+
+    /// Record the last claimed number in `.ralphctl/state.json`.
+    pub fn save(&self, dir: &Path) -> Result<()> {
+        let mut state = load_state(dir);
+        if state.log_seq == self.last.get() {
+            return Ok(());
+        }
+        state.log_seq = self.last.get();
+        save_state(dir, &state)
+    }
+}
+
+/// Write `state` to `.ralphctl/state.json`, creating the directory if needed.
+fn save_state(dir: &Path, state: &State) -> Result<()> {
+    let path = files::state_file_path(dir);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("failed to create {}", parent.display()))?;
+    }
+    fs::write(&path, serde_json::to_string_pretty(state)? + "\n")
+        .with_context(|| format!("failed to write {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_run_id() {
+        assert_eq!(format_run_id(0), "00000000");
+        assert_eq!(format_run_id(0xdead_beef), "deadbeef");
+        assert_eq!(format_run_id(0x1234_5678_9abc_def0), "9abcdef0");
+    }
+
+    #[test]
+    fn test_generate_run_id_shape() {
+        let id = generate_run_id();
+        assert_eq!(id.len(), 8);
+        assert!(id
+            .chars()
+            .all(|c| c.is_ascii_hexdigit() && !c.is_ascii_uppercase()));
+    }
+
+    #[test]
+    fn test_generate_run_id_differs() {
+        assert_ne!(generate_run_id(), generate_run_id());
+    }
+
+    #[test]
+    fn test_parse_tag() {
+        assert_eq!(parse_tag("nightly-2.1_a").unwrap(), "nightly-2.1_a");
+        assert!(parse_tag("").is_err());
+        assert!(parse_tag("two words").unwrap_err().contains("' '"));
+        assert!(parse_tag("a]b").is_err());
+    }
+
+    #[test]
+    fn test_state_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(load_state(dir.path()), State::default());
+
+        save_last_run_id(dir.path(), "0badc0de", Some("my-app")).unwrap();
+        let state = load_state(dir.path());
+        assert_eq!(state.last_run_id.as_deref(), Some("0badc0de"));
+        assert_eq!(state.project.as_deref(), Some("my-app"));
+
+        save_last_run_id(dir.path(), "feedf00d", None).unwrap();
+        let state = load_state(dir.path());
+        assert_eq!(state.last_run_id.as_deref(), Some("feedf00d"));
+        assert_eq!(state.project, None);
+    }
+
+    #[test]
+    fn test_log_seq_counts_in_memory_and_saves_once() {
+        let dir = tempfile::tempdir().unwrap();
+        save_last_run_id(dir.path(), "0badc0de", None).unwrap();
+        let seq = LogSeq::resume(dir.path(), 0);
+        assert_eq!(seq.next(), 1);
+        assert_eq!(seq.next(), 2);
+        assert_eq!(load_state(dir.path()).log_seq, 0);
+        seq.save(dir.path()).unwrap();
+
+        let state = load_state(dir.path());
+        assert_eq!(state.log_seq, 2);
+        assert_eq!(state.last_run_id.as_deref(), Some("0badc0de"));
+        assert_eq!(LogSeq::resume(dir.path(), 0).next(), 3);
+    }
+
+    #[test]
+    fn test_log_seq_resumes_after_unsaved_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        // A run that died after logging entry 7 never saved it
+        assert_eq!(LogSeq::resume(dir.path(), 7).next(), 8);
+    }
+
+    #[test]
+    fn test_load_state_invalid_json() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = files::state_file_path(dir.path());
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, "not json").unwrap();
+        assert_eq!(load_state(dir.path()), State::default());
+    }
+}
@@ -0,0 +1,73 @@
+//! Signal parsing for Ralph Loop orchestrators.
+//!
+//! The `ralphctl` binary is built on this crate. The items re-exported at the
+//! crate root are the stable API: they detect the `[[RALPH:...]]` markers an
+//! agent prints and count checkbox progress in an implementation plan, so
+//! another orchestrator can follow the same protocol without shelling out.
+//!
+//! ```
+//! use ralphctl::{detect_signal, LoopSignal};
+//!
+//! let output = "Finished the last task.\n[[RALPH:DONE]]\n";
+//! assert_eq!(detect_signal(output), LoopSignal::Done);
+//!
+//! // Markers only count when alone on their line
+//! assert_eq!(
+//!     detect_signal("I will print [[RALPH:DONE]] when finished"),
+//!     LoopSignal::NoSignal
+//! );
+//! ```
+//!
+//! The modules below back the binary. They are public only so `main.rs` can
+//! reach them and may change in any release.
+
+pub use parser::{count_checkboxes, TaskCount};
+pub use reverse::{detect_reverse_signal, ReverseSignal};
+pub use run::{detect_blocked_signal, detect_signal, LoopSignal};
+
+#[doc(hidden)]
+pub mod analyze;
+#[doc(hidden)]
+pub mod checkpoint;
+#[doc(hidden)]
+pub mod cli;
+#[doc(hidden)]
+pub mod config;
+#[doc(hidden)]
+pub mod diff;
+#[doc(hidden)]
+pub mod duration;
+#[doc(hidden)]
+pub mod error;
+#[doc(hidden)]
+pub mod files;
+#[doc(hidden)]
+pub mod git;
+#[doc(hidden)]
+pub mod ids;
+#[doc(hidden)]
+pub mod lock;
+#[doc(hidden)]
+pub mod parser;
+#[doc(hidden)]
+pub mod porcelain;
+#[doc(hidden)]
+pub mod project;
+#[doc(hidden)]
+pub mod reverse;
+#[doc(hidden)]
+pub mod run;
+#[doc(hidden)]
+pub mod selftest;
+#[doc(hidden)]
+pub mod signals;
+#[doc(hidden)]
+pub mod stats;
+#[doc(hidden)]
+pub mod stream;
+#[doc(hidden)]
+pub mod templates;
+#[doc(hidden)]
+pub mod tools;
+#[doc(hidden)]
+pub mod tui;
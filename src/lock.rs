@@ -0,0 +1,195 @@
+//! Run lock for detecting an active ralph loop.
+//!
+//! `run` and `reverse` record their PID and current iteration in
+//! `.ralphctl/run.lock` while looping. Destructive commands (clean, archive)
+//! read it to avoid deleting files out from under a live loop.
+
+use crate::files;
+use anyhow::{Context, Result};
+use nix::errno::Errno;
+use nix::sys::signal::kill;
+use nix::unistd::Pid;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Contents of the run lock file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockInfo {
+    /// PID of the `ralphctl` process running the loop
+    pub pid: u32,
+    /// Iteration currently in progress (0 before the first starts)
+    pub iteration: u32,
+    /// ID of the run holding the lock
+    #[serde(default)]
+    pub run_id: String,
+    /// Project the run belongs to, if a name could be derived
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+}
+
+/// Held by a running loop; the lock file is removed on release or drop.
+#[derive(Debug)]
+pub struct RunLock {
+    path: PathBuf,
+    run_id: String,
+    project: Option<String>,
+}
+
+impl RunLock {
+    /// Write a lock file for the current process in `dir`.
+    pub fn acquire(dir: &Path, run_id: &str, project: Option<&str>) -> Result<Self> {
+        let lock = Self {
+            path: files::lock_file_path(dir),
+            run_id: run_id.to_string(),
+            project: project.map(str::to_string),
+        };
+        if let Some(parent) = lock.path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("failed to create {}", parent.display()))?;
+        }
+        lock.set_iteration(0)?;
+        Ok(lock)
+    }
+
+    /// Record the iteration now in progress.
+    pub fn set_iteration(&self, iteration: u32) -> Result<()> {
+        let info = LockInfo {
+            pid: std::process::id(),
+            iteration,
+            run_id: self.run_id.clone(),
+            project: self.project.clone(),
+        };
+        fs::write(&self.path, serde_json::to_string(&info)?)
+            .with_context(|| format!("failed to write {}", self.path.display()))
+    }
+
+    /// Remove the lock file.
+    ///
+    /// Called explicitly on paths that end in `process::exit`, where `Drop`
+    /// never runs. Removing an already-removed lock is a no-op.
+    pub fn release(&self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+impl Drop for RunLock {
+    fn drop(&mut self) {
+        self.release();
+    }
+}
+
+/// Read the lock file in `dir`, if present and well-formed.
+pub fn read_lock(dir: &Path) -> Option<LockInfo> {
+    let content = fs::read_to_string(files::lock_file_path(dir)).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+/// Check whether a process with `pid` exists, via `kill(pid, 0)`.
+///
+/// A permission error still means the process exists.
+pub fn is_process_alive(pid: u32) -> bool {
+    let Ok(raw) = i32::try_from(pid) else {
+        return false;
+    };
+    if raw <= 0 {
+        return false;
+    }
+    matches!(kill(Pid::from_raw(raw), None), Ok(()) | Err(Errno::EPERM))
+}
+
+/// Return the lock info if a loop in `dir` appears to be running.
+///
+/// A lock whose PID no longer exists is stale (e.g. the loop crashed) and
+/// is ignored.
+pub fn active_run(dir: &Path) -> Option<LockInfo> {
+    read_lock(dir).filter(|info| is_process_alive(info.pid))
+}
+
+/// Exit with an error if a loop is active in `dir`, unless `ignore_active`.
+pub fn refuse_if_active(dir: &Path, ignore_active: bool) {
+    if ignore_active {
+        return;
+    }
+    if let Some(info) = active_run(dir) {
+        crate::error::die(&format!(
+            "a ralph loop appears to be running (pid {}, iteration {}); \
+             stop it first or pass --ignore-active",
+            info.pid, info.iteration
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_acquire_writes_current_pid() {
+        let dir = tempfile::tempdir().unwrap();
+        let lock = RunLock::acquire(dir.path(), "0badc0de", None).unwrap();
+        let info = read_lock(dir.path()).unwrap();
+        assert_eq!(info.pid, std::process::id());
+        assert_eq!(info.iteration, 0);
+        assert_eq!(info.run_id, "0badc0de");
+        drop(lock);
+    }
+
+    #[test]
+    fn test_set_iteration_updates_lock() {
+        let dir = tempfile::tempdir().unwrap();
+        let lock = RunLock::acquire(dir.path(), "0badc0de", None).unwrap();
+        lock.set_iteration(7).unwrap();
+        assert_eq!(read_lock(dir.path()).unwrap().iteration, 7);
+    }
+
+    #[test]
+    fn test_drop_removes_lock() {
+        let dir = tempfile::tempdir().unwrap();
+        {
+            let _lock = RunLock::acquire(dir.path(), "0badc0de", None).unwrap();
+            assert!(files::lock_file_path(dir.path()).exists());
+        }
+        assert!(!files::lock_file_path(dir.path()).exists());
+    }
+
+    #[test]
+    fn test_release_is_idempotent() {
+        let dir = tempfile::tempdir().unwrap();
+        let lock = RunLock::acquire(dir.path(), "0badc0de", None).unwrap();
+        lock.release();
+        lock.release();
+        assert!(read_lock(dir.path()).is_none());
+    }
+
+    #[test]
+    fn test_is_process_alive() {
+        assert!(is_process_alive(std::process::id()));
+        assert!(!is_process_alive(0));
+        assert!(!is_process_alive(i32::MAX as u32));
+        assert!(!is_process_alive(u32::MAX));
+    }
+
+    #[test]
+    fn test_active_run_ignores_stale_lock() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = files::lock_file_path(dir.path());
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+
+        fs::write(&path, r#"{"pid":2147483647,"iteration":3}"#).unwrap();
+        assert!(active_run(dir.path()).is_none());
+
+        let live = format!(r#"{{"pid":{},"iteration":3}}"#, std::process::id());
+        fs::write(&path, live).unwrap();
+        assert_eq!(active_run(dir.path()).unwrap().iteration, 3);
+    }
+
+    #[test]
+    fn test_read_lock_malformed() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = files::lock_file_path(dir.path());
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, "garbage").unwrap();
+        assert!(read_lock(dir.path()).is_none());
+    }
+}
@@ -1,16 +1,14 @@
-mod cli;
-mod error;
-mod files;
-mod parser;
-mod reverse;
-mod run;
-mod templates;
-
-use anyhow::Result;
-use clap::{Parser, Subcommand};
+use ralphctl::{
+    analyze, checkpoint, cli, config, diff, duration, error, files, git, ids, lock, parser,
+    porcelain, project, reverse, run, selftest, signals, stats, stream, templates, tools, tui,
+};
+
+use anyhow::{Context, Result};
+use clap::parser::ValueSource;
+use clap::{ArgMatches, Args, CommandFactory, FromArgMatches, Parser, Subcommand};
 use std::fs;
-use std::io::{self, Write};
-use std::path::Path;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
 
 /// Files that init creates (excludes ralph.log which is only created by run)
 const INIT_FILES: &[&str] = &[
@@ -47,6 +45,18 @@ EXAMPLES:
 struct Cli {
     #[command(subcommand)]
     command: Command,
+
+    /// Print the effective configuration and its sources, then exit
+    #[arg(long, global = true)]
+    dump_config: bool,
+
+    /// Answer yes to every confirmation and continue at every loop prompt
+    #[arg(short = 'y', long, global = true)]
+    yes: bool,
+
+    /// When to color output; auto colors only when stdout is a terminal and NO_COLOR is unset
+    #[arg(long, global = true, value_enum, value_name = "WHEN", default_value_t = cli::ColorChoice::Auto)]
+    color: cli::ColorChoice,
 }
 
 #[derive(Subcommand)]
@@ -56,12 +66,33 @@ enum Command {
         long_about = "Fetch template files from GitHub and create them in the current directory.\n\n\
                       Creates: SPEC.md, IMPLEMENTATION_PLAN.md, PROMPT.md\n\n\
                       Templates are cached locally for offline use. Requires the claude CLI to be installed.",
-        after_help = "EXAMPLES:\n  ralphctl init           # Create files (fails if they exist)\n  ralphctl init --force   # Overwrite existing files"
+        after_help = "EXAMPLES:\n  \
+                      ralphctl init                                # Create files (fails if they exist)\n  \
+                      ralphctl init --force                        # Overwrite existing files\n  \
+                      ralphctl init --from-spec docs/spec.md       # Import an existing spec\n  \
+                      ralphctl init --from-spec spec.md --derive-plan  # Let claude draft the plan"
     )]
     Init {
         /// Overwrite existing files without prompting
         #[arg(long)]
         force: bool,
+
+        /// Copy an existing spec to SPEC.md instead of using the template
+        #[arg(long, value_name = "PATH")]
+        from_spec: Option<PathBuf>,
+
+        /// Ask claude to derive IMPLEMENTATION_PLAN.md tasks from the imported spec
+        #[arg(long, requires = "from_spec")]
+        derive_plan: bool,
+
+        /// Start from a specialized plan template (IMPLEMENTATION_PLAN-<NAME>.md)
+        #[arg(
+            long,
+            value_name = "NAME",
+            conflicts_with = "from_spec",
+            value_parser = templates::parse_plan_template_name
+        )]
+        plan_template: Option<String>,
     },
 
     /// AI-guided interview to create SPEC.md and IMPLEMENTATION_PLAN.md
@@ -73,47 +104,127 @@ enum Command {
     )]
     Interview {
         /// Claude model to use (e.g., 'sonnet', 'opus', or full model name)
-        #[arg(long, value_name = "MODEL")]
+        #[arg(short, long, value_name = "MODEL")]
         model: Option<String>,
+
+        /// Have claude structure the plan after a specialized plan template
+        #[arg(
+            long,
+            value_name = "NAME",
+            value_parser = templates::parse_plan_template_name
+        )]
+        plan_template: Option<String>,
+
+        /// Replace the built-in interview system prompt with this file ({cwd} and {spec} are still substituted)
+        #[arg(long, value_name = "PATH")]
+        system_prompt_file: Option<PathBuf>,
+
+        /// Have claude write the spec here instead of SPEC.md
+        #[arg(long, value_name = "PATH", default_value = files::SPEC_FILE)]
+        spec_file: PathBuf,
     },
 
     /// Execute the ralph loop until done or blocked
     #[command(
         long_about = "Run the autonomous development loop by piping PROMPT.md to claude.\n\n\
                       Each iteration: Claude reads state files, implements one task, marks it complete.\n\
-                      Loop ends when [[RALPH:DONE]] or [[RALPH:BLOCKED:<reason>]] is detected.",
+                      Loop ends when [[RALPH:DONE]] or [[RALPH:BLOCKED:<reason>]] is detected.\n\n\
+                      Each iteration gets an empty scratch directory, .ralphctl/scratch/iter-<N>/, for\n\
+                      temporary files. Its absolute path is exported as RALPH_SCRATCH_DIR and replaces\n\
+                      {{SCRATCH_DIR}} in PROMPT.md, so the prompt can tell claude to keep notes there\n\
+                      instead of in the repository. Scratch directories from earlier runs are removed\n\
+                      when a run starts.\n\n\
+                      Claude also sees where it is in the run's budget, so PROMPT.md can ask it to wrap\n\
+                      up when few iterations remain:\n  \
+                      RALPH_ITERATION        Current iteration (1-based)\n  \
+                      RALPH_MAX_ITERATIONS   Iteration limit for this run\n  \
+                      RALPH_TASKS_COMPLETED  Checked tasks in the plan when the iteration starts\n  \
+                      RALPH_TASKS_TOTAL      Total tasks in the plan\n  \
+                      RALPH_RUN_ID           Run ID (or --tag label)",
         after_help = "EXIT CODES:\n  \
                       0   Success (RALPH:DONE detected)\n  \
-                      1   Error or RALPH:BLOCKED detected\n  \
+                      1   Error\n  \
                       2   Max iterations reached\n  \
+                      3   Blocked (RALPH:BLOCKED detected)\n  \
+                      5   No signal from an iteration (--strict-signals, non-interactive)\n  \
+                      6   Iteration output exceeded --max-output-bytes\n  \
+                      8   Blank output for --max-blank-iterations iterations in a row\n  \
                       130 Interrupted (Ctrl+C)\n\n\
                       EXAMPLES:\n  \
                       ralphctl run                      # Run up to 50 iterations\n  \
                       ralphctl run --max-iterations 10  # Limit to 10 iterations\n  \
                       ralphctl run --pause              # Confirm before each iteration\n  \
-                      ralphctl run --model opus         # Use a specific model"
+                      ralphctl run --pause-every 5      # Confirm every 5th iteration\n  \
+                      ralphctl run --model opus         # Use a specific model\n  \
+                      ralphctl run --stop-file STOP     # `touch STOP` to stop after the current iteration"
     )]
-    Run {
-        /// Maximum iterations before stopping
-        #[arg(long, default_value = "50", value_name = "N")]
-        max_iterations: u32,
+    Run(Box<RunArgs>),
 
-        /// Prompt for confirmation before each iteration
+    /// Show ralph loop progress from IMPLEMENTATION_PLAN.md
+    #[command(
+        long_about = "Parse IMPLEMENTATION_PLAN.md and display a progress bar showing task completion.\n\n\
+                      Counts all checkboxes (- [ ] and - [x]) to calculate percentage complete.\n\
+                      With --plan-format org, counts org-mode TODO and DONE headlines instead.",
+        after_help = "OUTPUT FORMAT:\n  [████████░░░░] 60% (12/20 tasks)\n\n\
+                      EXAMPLES:\n  \
+                      ralphctl status                                        # Read IMPLEMENTATION_PLAN.md\n  \
+                      ralphctl status IMPLEMENTATION_PLAN.backend.md         # Read a custom plan\n  \
+                      ralphctl status --glob 'IMPLEMENTATION_PLAN.*.md'      # Sum several plans\n  \
+                      ralphctl status --plan-file PLAN.org --plan-format org # Count TODO/DONE headlines\n  \
+                      ralphctl status --badge > badge.json                   # shields.io endpoint JSON"
+    )]
+    Status {
+        /// Implementation plan to read (same as --plan-file)
+        #[arg(value_name = "PLAN_FILE", conflicts_with_all = ["plan_file", "plan_glob"])]
+        plan: Option<PathBuf>,
+
+        /// Implementation plan to read instead of IMPLEMENTATION_PLAN.md
+        #[arg(long, value_name = "PATH", default_value = files::IMPLEMENTATION_PLAN_FILE)]
+        plan_file: PathBuf,
+
+        /// Sum the progress of every plan matching PATTERN (e.g. 'plans/*.md')
+        #[arg(
+            long,
+            visible_aliases = ["plan-file-glob", "glob"],
+            value_name = "PATTERN",
+            conflicts_with = "plan_file"
+        )]
+        plan_glob: Option<String>,
+
+        /// How tasks are written: markdown `- [ ]` checkboxes or org-mode `* TODO`/`* DONE` headlines
+        #[arg(long, value_enum, default_value = "markdown", value_name = "FORMAT")]
+        plan_format: parser::PlanFormat,
+
+        /// Print a shields.io endpoint badge JSON instead of the progress bar
         #[arg(long)]
-        pause: bool,
-
-        /// Claude model to use (e.g., 'sonnet', 'opus', or full model name)
-        #[arg(long, value_name = "MODEL")]
-        model: Option<String>,
+        badge: bool,
     },
 
-    /// Show ralph loop progress from IMPLEMENTATION_PLAN.md
+    /// Show run statistics for this project
     #[command(
-        long_about = "Parse IMPLEMENTATION_PLAN.md and display a progress bar showing task completion.\n\n\
-                      Counts all checkboxes (- [ ] and - [x]) to calculate percentage complete.",
-        after_help = "OUTPUT FORMAT:\n  [████████░░░░] 60% (12/20 tasks)"
+        long_about = "Print the totals kept in .ralphctl/stats.toml: runs, iterations, wall-clock\n\
+                      time, and how each run ended. Every run and reverse invocation adds to them\n\
+                      when it exits. The file never leaves the machine.",
+        after_help = "EXAMPLES:\n  \
+                      ralphctl stats                  # Print the totals\n  \
+                      ralphctl stats --reset          # Zero them after confirmation\n  \
+                      ralphctl stats --reset --force  # Zero them without prompting"
     )]
-    Status,
+    Stats {
+        /// Zero the totals
+        #[arg(long)]
+        reset: bool,
+
+        /// Reset without confirmation prompt
+        #[arg(long, requires = "reset")]
+        force: bool,
+    },
+
+    /// Tidy up the implementation plan
+    Plan {
+        #[command(subcommand)]
+        command: PlanCommand,
+    },
 
     /// Remove ralph loop files
     #[command(
@@ -125,6 +236,56 @@ enum Command {
         /// Delete files without confirmation prompt
         #[arg(long)]
         force: bool,
+
+        /// Proceed even if a ralph loop appears to be running
+        #[arg(long)]
+        ignore_active: bool,
+
+        /// Also remove the .ralphctl/scratch and .ralphctl/checkpoints trees
+        #[arg(long)]
+        all: bool,
+    },
+
+    /// Restore the plan from a checkpoint taken before an iteration
+    #[command(
+        long_about = "Restore IMPLEMENTATION_PLAN.md from .ralphctl/checkpoints/, where run copies\n\
+                      the plan and SPEC.md before each iteration, one directory per run. Uses the\n\
+                      latest run unless --run names another. Prints the checkbox counts of the\n\
+                      current plan and the checkpoint, then asks for confirmation.",
+        after_help = "EXAMPLES:\n  \
+                      ralphctl rollback                # Restore the most recent checkpoint\n  \
+                      ralphctl rollback --to 3         # Restore the plan as it was before iteration 3\n  \
+                      ralphctl rollback --run a1b2c3d4 # Use the checkpoints of an earlier run\n  \
+                      ralphctl rollback --spec --force # Also restore SPEC.md, without prompting"
+    )]
+    Rollback {
+        /// Run ID or --tag whose checkpoints to use (default: the latest run)
+        #[arg(long, value_name = "ID")]
+        run: Option<String>,
+
+        /// Iteration whose checkpoint to restore (default: the most recent)
+        #[arg(long, value_name = "N")]
+        to: Option<u32>,
+
+        /// Also restore the spec
+        #[arg(long)]
+        spec: bool,
+
+        /// Implementation plan to restore instead of IMPLEMENTATION_PLAN.md
+        #[arg(long, value_name = "PATH", default_value = files::IMPLEMENTATION_PLAN_FILE)]
+        plan_file: PathBuf,
+
+        /// Spec to restore with --spec instead of SPEC.md
+        #[arg(long, value_name = "PATH", default_value = files::SPEC_FILE, requires = "spec")]
+        spec_file: PathBuf,
+
+        /// How tasks are written: markdown `- [ ]` checkboxes or org-mode `* TODO`/`* DONE` headlines
+        #[arg(long, value_enum, default_value = "markdown", value_name = "FORMAT")]
+        plan_format: parser::PlanFormat,
+
+        /// Restore without confirmation prompt
+        #[arg(long)]
+        force: bool,
     },
 
     /// Archive SPEC.md and IMPLEMENTATION_PLAN.md, then reset to blank
@@ -132,12 +293,25 @@ enum Command {
         long_about = "Save the current SPEC.md and IMPLEMENTATION_PLAN.md to a timestamped archive\n\
                       directory (.ralphctl/archive/<timestamp>/), then reset them to blank templates.\n\n\
                       Useful for starting a new project while preserving completed work.",
-        after_help = "EXAMPLES:\n  ralphctl archive          # Prompt for confirmation\n  ralphctl archive --force  # Archive without prompting"
+        after_help = "EXAMPLES:\n  ralphctl archive          # Prompt for confirmation\n  ralphctl archive --force  # Archive without prompting\n  \
+                      ralphctl archive --include-log --include-prompt  # Full session snapshot"
     )]
     Archive {
         /// Archive files without confirmation prompt
         #[arg(long)]
         force: bool,
+
+        /// Proceed even if a ralph loop appears to be running
+        #[arg(long)]
+        ignore_active: bool,
+
+        /// Also copy ralph.log into the archive (it is left in place)
+        #[arg(long)]
+        include_log: bool,
+
+        /// Also copy PROMPT.md and REVERSE_PROMPT.md into the archive (they are left in place)
+        #[arg(long)]
+        include_prompt: bool,
     },
 
     /// Update ralphctl to the latest version from GitHub
@@ -157,9 +331,30 @@ enum Command {
                       magic control signals like [[RALPH:DONE]] and [[RALPH:BLOCKED:<reason>]]. When\n\
                       ralphctl is updated with new signals or improved prompting, running this command\n\
                       ensures your local prompt stays current.\n\n\
-                      EXAMPLES:\n  ralphctl fetch-latest-prompt    # Download latest PROMPT.md"
+                      If PROMPT.md already exists and differs, a diff is shown and you are asked to\n\
+                      confirm before it is overwritten.\n\n\
+                      EXAMPLES:\n  ralphctl fetch-latest-prompt            # Download latest PROMPT.md\n  \
+                      ralphctl fetch-latest-prompt --backup   # Keep the old file as PROMPT.md.bak\n  \
+                      ralphctl fetch-latest-prompt --reverse  # Update REVERSE_PROMPT.md instead\n  \
+                      ralphctl fetch-latest-prompt --diff     # Preview changes without writing"
     )]
-    FetchLatestPrompt,
+    FetchLatestPrompt {
+        /// Overwrite a locally modified PROMPT.md without confirmation
+        #[arg(long)]
+        force: bool,
+
+        /// Save the previous PROMPT.md as PROMPT.md.bak before overwriting
+        #[arg(long)]
+        backup: bool,
+
+        /// Update REVERSE_PROMPT.md (the reverse mode prompt) instead of PROMPT.md
+        #[arg(long)]
+        reverse: bool,
+
+        /// Only print the diff against the latest version; write nothing
+        #[arg(long, conflicts_with_all = ["force", "backup"])]
+        diff: bool,
+    },
 
     /// Investigate a codebase to answer a question
     #[command(
@@ -171,79 +366,755 @@ enum Command {
                       ralphctl reverse \"Why does auth fail?\"      # Provide question directly\n  \
                       ralphctl reverse                             # Use existing QUESTION.md\n  \
                       ralphctl reverse --model opus \"How?\"        # Use specific model\n  \
-                      ralphctl reverse --pause                     # Confirm each iteration\n\n\
+                      ralphctl reverse --pause                     # Confirm each iteration\n  \
+                      ralphctl reverse --resume                    # Continue after an interruption\n\n\
                       EXIT CODES:\n  \
                       0   Found (question answered)\n  \
                       1   Error\n  \
                       2   Max iterations reached\n  \
                       3   Blocked\n  \
-                      4   Inconclusive\n  \
+                      4   Inconclusive (or --max-time ran out)\n  \
                       130 Interrupted"
     )]
-    Reverse {
-        /// The investigation question (reads from QUESTION.md if omitted)
-        question: Option<String>,
+    Reverse(ReverseArgs),
+
+    /// Print a shell completion script
+    #[command(
+        long_about = "Print a completion script covering every ralphctl subcommand and flag.\n\n\
+                      Write it where your shell loads completions, or source it directly.",
+        after_help = "EXAMPLES:\n  \
+                      ralphctl completions bash > ~/.local/share/bash-completion/completions/ralphctl\n  \
+                      ralphctl completions zsh > ~/.zfunc/_ralphctl\n  \
+                      ralphctl completions fish > ~/.config/fish/completions/ralphctl.fish"
+    )]
+    Completions {
+        /// Shell to generate completions for
+        #[arg(value_enum)]
+        shell: clap_complete::Shell,
+    },
+
+    /// List every [[RALPH:...]] signal marker
+    #[command(
+        long_about = "List every [[RALPH:...]] marker ralphctl recognizes, the commands that act\n\
+                      on it, and what it means. Markers only count when alone on their own line.",
+        after_help = "EXAMPLES:\n  \
+                      ralphctl signals                    # Marker, commands, description\n  \
+                      ralphctl signals | grep reverse     # Markers reverse mode understands"
+    )]
+    Signals,
+
+    /// Check the loop end to end against a built-in mock agent
+    #[command(
+        long_about = "Run the real loop in a scratch directory against a built-in mock agent that\n\
+                      answers CONTINUE, edits the plan, then says DONE. Signal detection, logging,\n\
+                      plan parsing, and exit codes are checked in turn, so a failure here points at\n\
+                      ralphctl rather than claude. Needs no network and no claude binary.",
+        after_help = "EXIT CODES:\n  \
+                      0 All checks passed\n  \
+                      1 A check failed (the error names it)"
+    )]
+    SelfTest,
+
+    /// Print the man page
+    #[command(
+        long_about = "Render the ralphctl man page as roff on stdout. With --output, write a page\n\
+                      for ralphctl and one per subcommand (ralphctl-run.1, ...) into a directory.",
+        after_help = "EXAMPLES:\n  \
+                      ralphctl man | man -l -             # Read it now\n  \
+                      ralphctl man --output share/man/man1  # Pages for packaging"
+    )]
+    Man {
+        /// Write ralphctl.1 and per-subcommand pages into DIR instead of printing
+        #[arg(long, value_name = "DIR")]
+        output: Option<PathBuf>,
+    },
+}
+
+/// Arguments for the `run` command.
+#[derive(Args)]
+struct RunArgs {
+    /// Maximum iterations before stopping [default: auto (≤50)]
+    #[arg(long, value_name = "N")]
+    max_iterations: Option<u32>,
+
+    /// Prompt for confirmation before each iteration
+    #[arg(long)]
+    pause: bool,
+
+    /// Prompt for confirmation every N iterations (overridden by --pause)
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    pause_every: Option<u32>,
+
+    /// Claude model to use (e.g., 'sonnet', 'opus', or full model name)
+    #[arg(short, long, value_name = "MODEL")]
+    model: Option<String>,
+
+    /// Use MODEL while the first incomplete plan section's heading contains HEADING (repeatable)
+    #[arg(long, value_name = "HEADING=MODEL", value_parser = run::parse_phase_model)]
+    model_per_phase: Vec<run::PhaseModel>,
+
+    /// Truncate ralph.log before the first iteration
+    #[arg(long)]
+    fresh: bool,
+
+    /// Rename an existing ralph.log to ralph.log.<timestamp> before the first iteration
+    #[arg(long, conflicts_with = "fresh")]
+    fresh_log: bool,
+
+    /// Print a "still running" line at this interval during an iteration [accepts 90, 90s, 5m, 1h30m]
+    #[arg(long, value_name = "DURATION", value_parser = duration::parse_positive_duration)]
+    heartbeat: Option<std::time::Duration>,
+
+    /// Re-read the plan during each iteration and print progress when tasks are checked off
+    #[arg(long, conflicts_with = "tui")]
+    live_progress: bool,
+
+    /// Implementation plan to use instead of IMPLEMENTATION_PLAN.md
+    #[arg(long, value_name = "PATH", default_value = files::IMPLEMENTATION_PLAN_FILE)]
+    plan_file: PathBuf,
+
+    /// How tasks are written in the plan: markdown `- [ ]` checkboxes or org-mode `* TODO`/`* DONE` headlines
+    #[arg(long, value_enum, default_value = "markdown", value_name = "FORMAT")]
+    plan_format: parser::PlanFormat,
+
+    /// Spec to use instead of SPEC.md
+    #[arg(long, value_name = "PATH", default_value = files::SPEC_FILE)]
+    spec_file: PathBuf,
+
+    /// Write a JSON report of per-iteration stats to PATH when the run ends
+    #[arg(long, value_name = "PATH")]
+    report: Option<PathBuf>,
+
+    /// Run even if the plan has no checkbox tasks
+    #[arg(long)]
+    allow_empty_plan: bool,
+
+    /// Warn at start about open plan tasks that share no terms with the spec
+    #[arg(long)]
+    check_consistency: bool,
+
+    /// Refuse to start if any open plan task shares no terms with the spec (implies --check-consistency)
+    #[arg(long)]
+    strict_consistency: bool,
+
+    /// Check that claude is installed and logged in before the first iteration
+    #[arg(long)]
+    preflight: bool,
+
+    /// Stop gracefully before the next iteration if PATH exists (the file is deleted)
+    #[arg(long, value_name = "PATH")]
+    stop_file: Option<PathBuf>,
+
+    /// Run this shell command before each iteration; the loop stops if it fails
+    #[arg(long, value_name = "CMD")]
+    pre_cmd: Option<String>,
+
+    /// On BLOCKED, run this shell command and keep looping if it exits 0
+    #[arg(long, value_name = "CMD")]
+    on_blocked_retry: Option<String>,
+
+    /// Most times --on-blocked-retry may resume the loop in one run
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 1,
+        requires = "on_blocked_retry"
+    )]
+    max_blocked_retries: u32,
+
+    /// Checkpoint the plan and spec before each iteration, keeping the last K (0 disables)
+    #[arg(long, value_name = "K", default_value_t = checkpoint::DEFAULT_KEEP)]
+    keep_checkpoints: usize,
+
+    /// Keep only the last K iteration scratch directories
+    #[arg(long, value_name = "K", value_parser = clap::value_parser!(u64).range(1..))]
+    keep_scratch: Option<u64>,
+
+    /// Also write each iteration's output to .ralphctl/logs/iteration-<N>.log
+    #[arg(long)]
+    split_logs: bool,
+
+    /// Log how similar each iteration's output is to the previous one (spots a spinning agent)
+    #[arg(long)]
+    log_similarity: bool,
+
+    /// Fsync ralph.log after every entry so it survives a crash or power loss
+    #[arg(long)]
+    durable_log: bool,
+
+    /// Print (and log to ralph.log) the exact claude command line before each iteration
+    #[arg(long)]
+    trace: bool,
+
+    /// Start claude with only PATH, HOME, TERM, LANG (plus names in RALPHCTL_ENV_ALLOW) from this environment
+    #[arg(long)]
+    isolate_env: bool,
+
+    /// Stop claude as soon as it prints DONE or BLOCKED instead of waiting for it to exit
+    #[arg(long)]
+    early_stop: bool,
+
+    /// Name shown in headers, the summary, and the lock file (default: SPEC.md heading or directory name)
+    #[arg(long, value_name = "NAME")]
+    project_name: Option<String>,
+
+    /// Don't echo claude's output; print only iteration headers and the result (still logged)
+    #[arg(long, conflicts_with = "tui")]
+    summary_only: bool,
+
+    /// Ask whether an inline (not alone on a line) DONE/CONTINUE marker counts
+    #[arg(long)]
+    interactive_signals: bool,
+
+    /// POST a JSON run summary to URL when the loop ends, waiting up to 5s for a reply
+    #[arg(long, value_name = "URL")]
+    webhook: Option<String>,
+
+    /// Label this run in logs, state, and summary (default: random 8-hex ID)
+    #[arg(long, visible_alias = "session-id", value_name = "LABEL", value_parser = ids::parse_tag)]
+    tag: Option<String>,
+
+    /// Print the claude command, prompt source, and files, then exit without running
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Without a terminal, exit 5 when an iteration prints 64+ bytes but no signal instead of continuing
+    #[arg(long)]
+    strict_signals: bool,
+
+    /// Download the prompt from an http(s) URL instead of reading PROMPT.md (not cached)
+    #[arg(long, value_name = "URL")]
+    prompt_from_url: Option<String>,
+
+    /// Keep looping when an iteration edits PROMPT.md (normally the run stops)
+    #[arg(long, conflicts_with = "prompt_from_url")]
+    allow_prompt_edits: bool,
+
+    /// Write the exact prompt sent to claude for the first iteration to PATH
+    #[arg(long, value_name = "PATH")]
+    save_prompt: Option<PathBuf>,
+
+    /// Refuse (or truncate) a composed prompt longer than N bytes before sending it
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    prompt_max_bytes: Option<u64>,
+
+    /// What to do with a prompt over --prompt-max-bytes
+    #[arg(
+        long,
+        value_enum,
+        value_name = "MODE",
+        default_value_t,
+        requires = "prompt_max_bytes"
+    )]
+    prompt_overflow: run::PromptOverflow,
+
+    /// Re-read PROMPT.md before every iteration instead of only at start
+    #[arg(long, conflicts_with = "prompt_from_url")]
+    reload_prompt: bool,
+
+    /// Stop as soon as one more plan task is checked off, even on CONTINUE
+    #[arg(long)]
+    once_per_task: bool,
+
+    /// Kill an iteration once its output exceeds N bytes and stop the run
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    max_output_bytes: Option<u64>,
+
+    /// Echo only the first and last N/2 lines of each iteration's output (ralph.log keeps it all)
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    max_output_lines: Option<u64>,
+
+    /// Stop after N consecutive iterations with blank output (0 disables)
+    #[arg(long, value_name = "N", default_value_t = 3)]
+    max_blank_iterations: u32,
+
+    /// In a git repo, list the files each iteration changed (also logged to ralph.log)
+    #[arg(long)]
+    show_diff: bool,
+
+    /// Show a live dashboard while claude runs (falls back to plain output without a TTY)
+    #[arg(long, conflicts_with = "heartbeat")]
+    tui: bool,
+
+    /// Print one machine-readable record per state change on stdout; everything else goes to stderr
+    #[arg(long, conflicts_with_all = ["tui", "dry_run"])]
+    porcelain: bool,
 
-        /// Maximum iterations before stopping
-        #[arg(long, default_value = "100", value_name = "N")]
-        max_iterations: u32,
+    /// On BLOCKED, ask whether to retry the iteration, open a shell, or abort instead of exiting
+    #[arg(long)]
+    interactive_blocked: bool,
 
-        /// Prompt for confirmation before each iteration
+    /// After each iteration, append the plan's progress bar to ralph.log
+    #[arg(long)]
+    plan_progress_in_log: bool,
+
+    /// With --plan-progress-in-log, also append each progress line to PATH (e.g. PROGRESS.md)
+    #[arg(long, value_name = "PATH", requires = "plan_progress_in_log")]
+    progress_file: Option<PathBuf>,
+}
+
+/// Subcommands of `plan`.
+#[derive(Subcommand)]
+enum PlanCommand {
+    /// Reorder tasks within each section of the plan
+    #[command(
+        long_about = "Rewrite the plan so incomplete tasks come before completed ones within each\n\
+                      section. Tasks keep their relative order, indented lines move with their task,\n\
+                      and prose and blank lines stay where they are. An already ordered plan is left\n\
+                      untouched.",
+        after_help = "EXAMPLES:\n  \
+                      ralphctl plan reorder --pending-first                           # Reorder IMPLEMENTATION_PLAN.md\n  \
+                      ralphctl plan reorder --pending-first --plan-file plans/auth.md # Reorder a custom plan"
+    )]
+    Reorder {
+        /// Move `- [ ]` tasks above `- [x]` tasks
+        #[arg(long, required = true)]
+        pending_first: bool,
+
+        /// Implementation plan to reorder instead of IMPLEMENTATION_PLAN.md
+        #[arg(long, value_name = "PATH", default_value = files::IMPLEMENTATION_PLAN_FILE)]
+        plan_file: PathBuf,
+    },
+
+    /// Report lines that look like checkboxes but won't be counted
+    #[command(
+        long_about = "Scan the plan for checkbox-like lines that status and run silently skip, such as\n\
+                      `- []` (no space), `- [y]`, or `* [ ]`, and print each with its line number.\n\
+                      Exits 1 if any are found.\n\n\
+                      Also warns about open tasks whose significant words never appear in SPEC.md,\n\
+                      a sign the plan has drifted from the spec. This is a heuristic and does not\n\
+                      change the exit code unless --strict-consistency is given.",
+        after_help = "EXAMPLES:\n  \
+                      ralphctl plan validate                           # Check IMPLEMENTATION_PLAN.md\n  \
+                      ralphctl plan validate --plan-file plans/auth.md # Check a custom plan\n  \
+                      ralphctl plan validate --strict-consistency      # Also fail on spec drift"
+    )]
+    Validate {
+        /// Implementation plan to check instead of IMPLEMENTATION_PLAN.md
+        #[arg(long, value_name = "PATH", default_value = files::IMPLEMENTATION_PLAN_FILE)]
+        plan_file: PathBuf,
+
+        /// Spec to compare the plan's tasks against instead of SPEC.md
+        #[arg(long, value_name = "PATH", default_value = files::SPEC_FILE)]
+        spec_file: PathBuf,
+
+        /// Exit 1 if any open task shares no terms with the spec
         #[arg(long)]
-        pause: bool,
+        strict_consistency: bool,
+    },
 
-        /// Claude model to use (e.g., 'sonnet', 'opus', or full model name)
-        #[arg(long, value_name = "MODEL")]
-        model: Option<String>,
+    /// Show the task the loop should pick up next
+    #[command(
+        long_about = "Print the first unchecked task in the plan. A task ending in\n\
+                      `(after: <text>)` is skipped until a completed task containing <text> exists.\n\
+                      An annotation that matches no task, or that forms a cycle, is ignored with a\n\
+                      warning.",
+        after_help = "EXAMPLES:\n  \
+                      ralphctl plan next                           # Next task in IMPLEMENTATION_PLAN.md\n  \
+                      ralphctl plan next --plan-file plans/auth.md # Next task in a custom plan"
+    )]
+    Next {
+        /// Implementation plan to read instead of IMPLEMENTATION_PLAN.md
+        #[arg(long, value_name = "PATH", default_value = files::IMPLEMENTATION_PLAN_FILE)]
+        plan_file: PathBuf,
     },
+
+    /// Compare the plan's tasks with an archived plan
+    #[command(
+        long_about = "Load IMPLEMENTATION_PLAN.md from .ralphctl/archive/<TIMESTAMP>/ and compare its\n\
+                      tasks with the current plan: which were completed, reopened, added, or removed\n\
+                      since the archive. Tasks are matched by their text.",
+        after_help = "EXAMPLES:\n  \
+                      ralphctl plan diff 2026-01-15T18-30-00                           # Changes since that archive\n  \
+                      ralphctl plan diff 2026-01-15T18-30-00 --plan-file plans/auth.md # Compare a custom plan"
+    )]
+    Diff {
+        /// Archive to compare with (a directory name under .ralphctl/archive)
+        #[arg(value_name = "TIMESTAMP")]
+        timestamp: String,
+
+        /// Current plan to compare instead of IMPLEMENTATION_PLAN.md
+        #[arg(long, value_name = "PATH", default_value = files::IMPLEMENTATION_PLAN_FILE)]
+        plan_file: PathBuf,
+    },
+}
+
+/// Output format for `reverse`'s final outcome.
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum OutputFormat {
+    /// Human-readable lines
+    Text,
+    /// A single JSON object on stdout
+    Json,
+}
+
+/// Arguments for the `reverse` command.
+#[derive(Args)]
+struct ReverseArgs {
+    /// The investigation question (reads from QUESTION.md if omitted)
+    question: Option<String>,
+
+    /// Maximum iterations before stopping
+    #[arg(long, default_value = "100", value_name = "N")]
+    max_iterations: u32,
+
+    /// Stop as inconclusive once the investigation has run this long [accepts 90, 90s, 5m, 1h30m]
+    #[arg(
+        long,
+        visible_alias = "timeout",
+        value_name = "DURATION",
+        value_parser = duration::parse_positive_duration
+    )]
+    max_time: Option<std::time::Duration>,
+
+    /// Prompt for confirmation before each iteration
+    #[arg(long)]
+    pause: bool,
+
+    /// Claude model to use (e.g., 'sonnet', 'opus', or full model name)
+    #[arg(short, long, value_name = "MODEL")]
+    model: Option<String>,
+
+    /// Truncate ralph.log before the first iteration
+    #[arg(long, conflicts_with = "resume")]
+    fresh: bool,
+
+    /// Continue an existing investigation from QUESTION.md and INVESTIGATION.md
+    #[arg(long, conflicts_with = "question")]
+    resume: bool,
+
+    /// POST a JSON run summary to URL when the loop ends, waiting up to 5s for a reply
+    #[arg(long, value_name = "URL")]
+    webhook: Option<String>,
+
+    /// Also write each iteration's output to .ralphctl/logs/iteration-<N>.log
+    #[arg(long)]
+    split_logs: bool,
+
+    /// Fsync ralph.log after every entry so it survives a crash or power loss
+    #[arg(long)]
+    durable_log: bool,
+
+    /// Print (and log to ralph.log) the exact claude command line before each iteration
+    #[arg(long)]
+    trace: bool,
+
+    /// Start claude with only PATH, HOME, TERM, LANG (plus names in RALPHCTL_ENV_ALLOW) from this environment
+    #[arg(long)]
+    isolate_env: bool,
+
+    /// Stop claude as soon as it prints FOUND, INCONCLUSIVE, or BLOCKED instead of waiting for it to exit
+    #[arg(long)]
+    early_stop: bool,
+
+    /// How to report the final outcome; json prints one object on stdout and nothing else
+    #[arg(long, value_enum, default_value = "text")]
+    format: OutputFormat,
+
+    /// Name shown in headers, the summary, and the lock file (default: SPEC.md heading or directory name)
+    #[arg(long, value_name = "NAME")]
+    project_name: Option<String>,
+
+    /// Don't echo claude's output; print only iteration headers and the result (still logged)
+    #[arg(long)]
+    summary_only: bool,
+
+    /// Label this run in logs, state, and summary (default: random 8-hex ID)
+    #[arg(long, visible_alias = "session-id", value_name = "LABEL", value_parser = ids::parse_tag)]
+    tag: Option<String>,
+
+    /// Print the claude command, question source, and files, then exit without running
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Also write the investigation prompt to REVERSE_PROMPT.md for reference
+    #[arg(long)]
+    write_prompt: bool,
+
+    /// Ignore a local REVERSE_PROMPT.md and use the built-in prompt
+    #[arg(long)]
+    refresh_prompt: bool,
+
+    /// Investigate the codebase at PATH; claude runs there while the investigation files stay here
+    #[arg(long, value_name = "PATH")]
+    target: Option<PathBuf>,
+
+    /// Use the investigation prompt in PATH instead of REVERSE_PROMPT.md or the built-in one
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["refresh_prompt", "write_prompt"])]
+    reverse_prompt_file: Option<PathBuf>,
+
+    /// Append a one-line summary of each iteration to INVESTIGATION.md
+    #[arg(long)]
+    write_investigation: bool,
+
+    /// Truncate a longer question argument to its first BYTES bytes
+    #[arg(
+        long,
+        value_name = "BYTES",
+        default_value_t = reverse::DEFAULT_MAX_QUESTION_BYTES as u64,
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    max_question_bytes: u64,
+
+    /// Print one machine-readable record per state change on stdout; everything else goes to stderr
+    #[arg(long, conflicts_with_all = ["format", "dry_run"])]
+    porcelain: bool,
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let cli = Cli::parse();
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    cli::set_assume_yes(cli.yes);
+    cli::init_color(cli.color);
+
+    if cli.dump_config {
+        dump_config(&cli.command, &matches);
+        return Ok(());
+    }
 
     match cli.command {
-        Command::Init { force } => {
-            init_cmd(force).await?;
-        }
-        Command::Interview { model } => {
-            interview_cmd(model.as_deref())?;
+        Command::Init {
+            force,
+            from_spec,
+            derive_plan,
+            plan_template,
+        } => {
+            init_cmd(
+                force,
+                from_spec.as_deref(),
+                derive_plan,
+                plan_template.as_deref(),
+            )
+            .await?;
         }
-        Command::Run {
-            max_iterations,
-            pause,
+        Command::Interview {
             model,
+            plan_template,
+            system_prompt_file,
+            spec_file,
+        } => {
+            let plan = match plan_template {
+                Some(name) => Some(get_plan_template(&name).await?),
+                None => None,
+            };
+            interview_cmd(
+                model.as_deref(),
+                plan.as_deref(),
+                system_prompt_file.as_deref(),
+                &spec_file,
+            )?;
+        }
+        Command::Run(args) => {
+            run_cmd(&args)?;
+        }
+        Command::Status {
+            plan,
+            plan_file,
+            plan_glob,
+            plan_format,
+            badge,
+        } => match plan_glob {
+            Some(pattern) => status_glob_cmd(&pattern, plan_format, badge)?,
+            None => status_cmd(plan.as_deref().unwrap_or(&plan_file), plan_format, badge)?,
+        },
+        Command::Stats { reset, force } => {
+            stats_cmd(reset, force)?;
+        }
+        Command::Plan {
+            command: PlanCommand::Reorder { plan_file, .. },
+        } => {
+            plan_reorder_cmd(&plan_file)?;
+        }
+        Command::Plan {
+            command:
+                PlanCommand::Validate {
+                    plan_file,
+                    spec_file,
+                    strict_consistency,
+                },
+        } => {
+            plan_validate_cmd(&plan_file, &spec_file, strict_consistency)?;
+        }
+        Command::Plan {
+            command: PlanCommand::Next { plan_file },
+        } => {
+            plan_next_cmd(&plan_file)?;
+        }
+        Command::Plan {
+            command:
+                PlanCommand::Diff {
+                    timestamp,
+                    plan_file,
+                },
         } => {
-            run_cmd(max_iterations, pause, model.as_deref())?;
+            plan_diff_cmd(&timestamp, &plan_file)?;
         }
-        Command::Status => {
-            status_cmd()?;
+        Command::Clean {
+            force,
+            ignore_active,
+            all,
+        } => {
+            clean_cmd(force, ignore_active, all)?;
         }
-        Command::Clean { force } => {
-            clean_cmd(force)?;
+        Command::Rollback {
+            run,
+            to,
+            spec,
+            plan_file,
+            spec_file,
+            plan_format,
+            force,
+        } => {
+            rollback_cmd(
+                run.as_deref(),
+                to,
+                &plan_file,
+                spec.then_some(spec_file.as_path()),
+                plan_format,
+                force,
+            )?;
         }
-        Command::Archive { force } => {
-            archive_cmd(force)?;
+        Command::Archive {
+            force,
+            ignore_active,
+            include_log,
+            include_prompt,
+        } => {
+            archive_cmd(force, ignore_active, include_log, include_prompt)?;
         }
         Command::Update => {
             update_cmd()?;
         }
-        Command::FetchLatestPrompt => {
-            fetch_latest_prompt_cmd().await?;
-        }
-        Command::Reverse {
-            question,
-            max_iterations,
-            pause,
-            model,
+        Command::FetchLatestPrompt {
+            force,
+            backup,
+            reverse,
+            diff,
         } => {
-            reverse_cmd(question, max_iterations, pause, model.as_deref()).await?;
+            fetch_latest_prompt_cmd(force, backup, reverse, diff).await?;
+        }
+        Command::Reverse(args) => {
+            reverse_cmd(&args).await?;
+        }
+        Command::Completions { shell } => {
+            completions_cmd(shell)?;
+        }
+        Command::SelfTest => {
+            if let Err(e) = selftest::run() {
+                error::die(&format!("self-test failed: {:#}", e));
+            }
+            println!("self-test passed");
+        }
+        Command::Signals => {
+            print!("{}", signals::format_signals(&signals::all()));
+        }
+        Command::Man { output } => {
+            man_cmd(output.as_deref())?;
         }
     }
 
     Ok(())
 }
 
+/// Print the completion script for `shell` to stdout.
+///
+/// A closed pipe (e.g. `| head`) is not an error.
+fn completions_cmd(shell: clap_complete::Shell) -> Result<()> {
+    let mut command = Cli::command();
+    let name = command.get_name().to_string();
+    let mut script = Vec::new();
+    clap_complete::generate(shell, &mut command, name, &mut script);
+    match io::stdout().write_all(&script) {
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        result => result.context("failed to write completions"),
+    }
+}
+
+/// Man page for `command`, titled in upper case as man pages are. Subcommand
+/// pages carry the ralphctl version too.
+fn man_page(command: clap::Command) -> clap_mangen::Man {
+    let title = command
+        .get_display_name()
+        .unwrap_or_else(|| command.get_name())
+        .to_uppercase();
+    clap_mangen::Man::new(command)
+        .title(title)
+        .source(format!("ralphctl {}", env!("CARGO_PKG_VERSION")))
+}
+
+fn man_cmd(output: Option<&Path>) -> Result<()> {
+    let mut command = Cli::command().disable_help_subcommand(true);
+    command.build();
+
+    let Some(dir) = output else {
+        let mut page = Vec::new();
+        man_page(command).render(&mut page)?;
+        return match io::stdout().write_all(&page) {
+            Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+            result => result.context("failed to write man page"),
+        };
+    };
+
+    fs::create_dir_all(dir).with_context(|| format!("failed to create {}", dir.display()))?;
+    // Depth-first, so `plan reorder` gets ralphctl-plan-reorder.1
+    let mut pending = vec![command];
+    let mut written = 0;
+    while let Some(command) = pending.pop() {
+        pending.extend(
+            command
+                .get_subcommands()
+                .filter(|sub| !sub.is_hide_set())
+                .cloned(),
+        );
+        man_page(command)
+            .generate_to(dir)
+            .with_context(|| format!("failed to write man pages to {}", dir.display()))?;
+        written += 1;
+    }
+    println!("Wrote {} man pages to {}.", written, dir.display());
+    Ok(())
+}
+
+/// Print the effective configuration for `command` (`--dump-config`).
+fn dump_config(command: &Command, matches: &ArgMatches) {
+    let from_flag = |id: &str| {
+        matches
+            .subcommand()
+            .and_then(|(_, sub)| sub.value_source(id))
+            .is_some_and(|source| source == ValueSource::CommandLine)
+    };
+    let loop_flags = match command {
+        Command::Run(args) => Some(config::LoopFlags {
+            model: args.model.clone(),
+            max_iterations: args.max_iterations.unwrap_or_else(|| {
+                run::auto_max_iterations(
+                    run::plan_task_count(&args.plan_file, args.plan_format).as_ref(),
+                )
+            }),
+            max_iterations_source: if args.max_iterations.is_some() {
+                config::Source::Flag("--max-iterations")
+            } else {
+                config::Source::Auto
+            },
+            reverse: false,
+        }),
+        Command::Reverse(args) => Some(config::LoopFlags {
+            model: args.model.clone(),
+            max_iterations: args.max_iterations,
+            max_iterations_source: if from_flag("max_iterations") {
+                config::Source::Flag("--max-iterations")
+            } else {
+                config::Source::Default
+            },
+            reverse: true,
+        }),
+        _ => None,
+    };
+    let settings = config::resolve(loop_flags.as_ref(), |var| std::env::var(var).ok());
+    print!("{}", config::format_config(&settings));
+}
+
 fn update_cmd() -> Result<()> {
     use std::process::Command;
 
@@ -263,25 +1134,290 @@ fn update_cmd() -> Result<()> {
     Ok(())
 }
 
-fn status_cmd() -> Result<()> {
-    let path = Path::new(files::IMPLEMENTATION_PLAN_FILE);
+fn status_cmd(path: &Path, format: parser::PlanFormat, badge: bool) -> Result<()> {
     if !path.exists() {
-        error::die(&format!("{} not found", files::IMPLEMENTATION_PLAN_FILE));
+        error::die(&format!("{} not found", path.display()));
+    }
+
+    let content = fs::read_to_string(path)?;
+    let count = format.count_tasks(&content);
+
+    if badge {
+        println!("{}", count.render_badge_json());
+    } else {
+        println!("{}", count.render_progress_bar(cli::terminal_width()));
     }
 
+    Ok(())
+}
+
+/// Print one combined progress bar for every plan matching `pattern`.
+///
+/// With `badge`, print the combined count as a badge and skip the breakdown.
+fn status_glob_cmd(pattern: &str, format: parser::PlanFormat, badge: bool) -> Result<()> {
+    let mut paths = glob::glob(pattern)
+        .with_context(|| format!("invalid glob pattern '{}'", pattern))?
+        .filter_map(|entry| entry.ok())
+        .filter(|path| path.is_file())
+        .collect::<Vec<_>>();
+    paths.sort();
+    if paths.is_empty() {
+        error::die(&format!("no plan files match '{}'", pattern));
+    }
+
+    let mut plans = Vec::new();
+    for path in paths {
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        plans.push((path.display().to_string(), format.count_tasks(&content)));
+    }
+    if badge {
+        println!("{}", parser::total_task_count(&plans).render_badge_json());
+        return Ok(());
+    }
+    print!(
+        "{}",
+        parser::format_plan_progress(&plans, cli::terminal_width())
+    );
+    Ok(())
+}
+
+fn plan_reorder_cmd(path: &Path) -> Result<()> {
+    if !path.exists() {
+        error::die(&format!("{} not found", path.display()));
+    }
+
+    let content = fs::read_to_string(path)?;
+    let reordered = parser::reorder_pending_first(&content);
+    if reordered == content {
+        println!("{} is already ordered.", path.display());
+        return Ok(());
+    }
+
+    fs::write(path, reordered).with_context(|| format!("failed to write {}", path.display()))?;
+    println!("Reordered {}: pending tasks first.", path.display());
+    Ok(())
+}
+
+fn plan_validate_cmd(path: &Path, spec_file: &Path, strict_consistency: bool) -> Result<()> {
+    if !path.exists() {
+        error::die(&format!("{} not found", path.display()));
+    }
+
+    let content = fs::read_to_string(path)?;
+    let unrelated = warn_unrelated_tasks(spec_file, &content)?;
+    let malformed = parser::find_malformed_checkboxes(&content);
+    if malformed.is_empty() {
+        if strict_consistency && unrelated > 0 {
+            die_inconsistent(unrelated, spec_file);
+        }
+        println!("{}: no malformed checkboxes.", path.display());
+        return Ok(());
+    }
+
+    for found in &malformed {
+        eprintln!("warning: {}", parser::format_malformed_checkbox(found));
+    }
+    error::die(&format!(
+        "{} has {} malformed checkbox{}",
+        path.display(),
+        malformed.len(),
+        if malformed.len() == 1 { "" } else { "es" }
+    ));
+}
+
+/// Warn about open plan tasks that share no terms with the spec.
+///
+/// Returns how many were found. Without a spec there is nothing to compare,
+/// so the check is skipped.
+fn warn_unrelated_tasks(spec_file: &Path, plan_content: &str) -> Result<usize> {
+    if !spec_file.exists() {
+        return Ok(0);
+    }
+    let spec = fs::read_to_string(spec_file)
+        .with_context(|| format!("failed to read {}", spec_file.display()))?;
+    let tasks = parser::parse_tasks(plan_content);
+    let unrelated = analyze::unrelated_tasks(&spec, &tasks);
+    for line in analyze::format_consistency_warnings(&unrelated, &spec_file.display().to_string()) {
+        eprintln!("warning: {}", line);
+    }
+    Ok(unrelated.len())
+}
+
+/// Exit for `--strict-consistency` once unrelated tasks were reported.
+fn die_inconsistent(unrelated: usize, spec_file: &Path) -> ! {
+    error::die(&format!(
+        "{} plan task{} share{} no terms with {} (--strict-consistency)",
+        unrelated,
+        if unrelated == 1 { "" } else { "s" },
+        if unrelated == 1 { "s" } else { "" },
+        spec_file.display()
+    ));
+}
+
+fn plan_next_cmd(path: &Path) -> Result<()> {
+    if !path.exists() {
+        error::die(&format!("{} not found", path.display()));
+    }
     let content = fs::read_to_string(path)?;
-    let count = parser::count_checkboxes(&content);
+    let tasks = parser::parse_tasks(&content);
+    let next = parser::next_task(&tasks);
+    for warning in &next.warnings {
+        eprintln!("warning: {}", warning);
+    }
+    match next.task {
+        Some(task) => println!("{}", task.text),
+        None if tasks.iter().all(|task| task.completed) => println!("No open tasks."),
+        None => println!("Every open task is waiting on another."),
+    }
+    Ok(())
+}
+
+fn plan_diff_cmd(timestamp: &str, path: &Path) -> Result<()> {
+    let cwd = Path::new(".");
+    let archives = files::list_archives(cwd);
+    if !archives.iter().any(|name| name == timestamp) {
+        let available = if archives.is_empty() {
+            "no archives yet (create one with 'ralphctl archive')".to_string()
+        } else {
+            format!("available: {}", archives.join(", "))
+        };
+        error::die(&format!("no archive '{}'; {}", timestamp, available));
+    }
+    let archived = files::archive_base_dir(cwd)
+        .join(timestamp)
+        .join(files::IMPLEMENTATION_PLAN_FILE);
+    if !archived.exists() {
+        error::die(&format!(
+            "archive '{}' has no {}",
+            timestamp,
+            files::IMPLEMENTATION_PLAN_FILE
+        ));
+    }
+    if !path.exists() {
+        error::die(&format!("{} not found", path.display()));
+    }
 
-    println!("{}", count.render_progress_bar());
+    let old = fs::read_to_string(&archived)
+        .with_context(|| format!("failed to read {}", archived.display()))?;
+    let new = fs::read_to_string(path)?;
+    let diff = parser::diff_tasks(&parser::parse_tasks(&old), &parser::parse_tasks(&new));
 
+    let (before, now) = (
+        parser::count_checkboxes(&old),
+        parser::count_checkboxes(&new),
+    );
+    println!(
+        "{} since archive {}: {}/{} → {}/{} tasks",
+        path.display(),
+        timestamp,
+        before.completed,
+        before.total,
+        now.completed,
+        now.total
+    );
+    if diff.is_empty() {
+        println!("No task changes.");
+    } else {
+        print!("{}", parser::format_plan_diff(&diff));
+    }
     Ok(())
 }
 
-fn clean_cmd(force: bool) -> Result<()> {
+/// Ask a y/N question on stdin, exiting unless the answer is yes.
+///
+/// A decline exits 1. Closed stdin exits `NOT_INTERACTIVE` with a hint to
+/// pass `--force`, since nobody could have answered.
+fn confirm_or_exit(question: &str) -> Result<()> {
+    match cli::confirm(question, &mut io::stdin().lock())? {
+        cli::Confirmation::Yes => Ok(()),
+        cli::Confirmation::No => std::process::exit(error::exit::ERROR),
+        cli::Confirmation::NoInput => error::die_with_code(
+            "stdin is not interactive; pass --force to proceed",
+            error::exit::NOT_INTERACTIVE,
+        ),
+    }
+}
+
+fn stats_cmd(reset: bool, force: bool) -> Result<()> {
     let cwd = Path::new(".");
-    let existing_files = files::find_existing_ralph_files(cwd);
+    if !reset {
+        print!("{}", stats::format_stats(&stats::load(cwd)?));
+        return Ok(());
+    }
+    if !files::stats_file_path(cwd).exists() {
+        println!("No run statistics to reset.");
+        return Ok(());
+    }
+    if !force {
+        confirm_or_exit("Reset run statistics?")?;
+    }
+    stats::save(cwd, &stats::Stats::default())?;
+    println!("Reset run statistics.");
+    Ok(())
+}
+
+/// Resolve the project name from `--project-name`, `RALPHCTL_PROJECT_NAME`,
+/// the spec at `spec`, or the directory name.
+fn project_name(flag: Option<&str>, spec: &Path, dir: &Path) -> Option<String> {
+    project::project_name(
+        flag,
+        std::env::var(project::PROJECT_NAME_ENV).ok(),
+        spec,
+        dir,
+    )
+}
+
+/// The project name for headers, the run banner, and heartbeat lines: only
+/// `--project-name` or `RALPHCTL_PROJECT_NAME`, never a derived one.
+fn configured_project_name(flag: Option<&str>) -> Option<String> {
+    project::configured_name(
+        flag,
+        std::env::var(project::PROJECT_NAME_ENV).ok().as_deref(),
+    )
+}
+
+/// Resolve the `--isolate-env` allowlist and report what it drops.
+fn isolated_env(isolate: bool) -> Option<Vec<String>> {
+    if !isolate {
+        return None;
+    }
+    let allowlist = run::isolated_env_allowlist(std::env::var(run::ENV_ALLOW_ENV).ok().as_deref());
+    run::print_dropped_env(&allowlist);
+    Some(allowlist)
+}
+
+/// Add a finished run to `.ralphctl/stats.toml`, warning on failure.
+fn record_stats(
+    dir: &Path,
+    outcome: &run::RunOutcome,
+    iterations: u32,
+    elapsed: std::time::Duration,
+) {
+    match stats::record_run(dir, outcome.kind(), iterations, elapsed) {
+        Ok(Some(backup)) => eprintln!(
+            "warning: {} was unreadable; moved it to {} and started over",
+            files::stats_file_path(dir).display(),
+            backup.display()
+        ),
+        Ok(None) => {}
+        Err(e) => eprintln!("warning: {:#}", e),
+    }
+}
 
-    if existing_files.is_empty() {
+fn clean_cmd(force: bool, ignore_active: bool, all: bool) -> Result<()> {
+    let cwd = Path::new(".");
+    lock::refuse_if_active(cwd, ignore_active);
+    let existing_files = files::find_existing_ralph_files(cwd);
+    let dirs: Vec<(PathBuf, &str)> = [
+        (files::scratch_base_dir(cwd), files::SCRATCH_DIR),
+        (files::checkpoints_dir(cwd), files::CHECKPOINTS_DIR),
+    ]
+    .into_iter()
+    .filter(|(dir, _)| all && dir.exists())
+    .collect();
+
+    if existing_files.is_empty() && dirs.is_empty() {
         println!("No ralph files found.");
         return Ok(());
     }
@@ -289,16 +1425,20 @@ fn clean_cmd(force: bool) -> Result<()> {
     let file_count = existing_files.len();
 
     if !force {
-        eprint!("Delete {} ralph files? [y/N] ", file_count);
-        io::stderr().flush()?;
-
-        let mut input = String::new();
-        io::stdin().read_line(&mut input)?;
-
-        let answer = input.trim().to_lowercase();
-        if answer != "y" && answer != "yes" {
-            std::process::exit(error::exit::ERROR);
-        }
+        let question = if dirs.is_empty() {
+            format!("Delete {} ralph files?", file_count)
+        } else {
+            let names: Vec<String> = dirs
+                .iter()
+                .map(|(_, name)| format!("{}/{}", files::RALPHCTL_DIR, name))
+                .collect();
+            format!(
+                "Delete {} ralph files and {}?",
+                file_count,
+                names.join(", ")
+            )
+        };
+        confirm_or_exit(&question)?;
     }
 
     for path in &existing_files {
@@ -311,11 +1451,78 @@ fn clean_cmd(force: bool) -> Result<()> {
         if file_count == 1 { "" } else { "s" }
     );
 
+    for (dir, name) in dirs {
+        fs::remove_dir_all(&dir)?;
+        println!("Removed {}/{}.", files::RALPHCTL_DIR, name);
+    }
+
     Ok(())
 }
 
-fn archive_cmd(force: bool) -> Result<()> {
+fn rollback_cmd(
+    run_id: Option<&str>,
+    to: Option<u32>,
+    plan_file: &Path,
+    spec_file: Option<&Path>,
+    plan_format: parser::PlanFormat,
+    force: bool,
+) -> Result<()> {
+    let base = files::checkpoints_dir(Path::new("."));
+    let run_dir = match run_id {
+        Some(run_id) => checkpoint::run_dir(&base, run_id),
+        None => checkpoint::runs(&base)
+            .pop()
+            .unwrap_or_else(|| error::die(&format!("no checkpoints found in {}", base.display()))),
+    };
+    let run_name = run_dir
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let Some((iteration, dir)) = checkpoint::find(&run_dir, to) else {
+        match to {
+            Some(n) => error::die(&format!(
+                "no checkpoint for iteration {} in run {}",
+                n, run_name
+            )),
+            None => error::die(&format!("no checkpoints found for run {}", run_name)),
+        }
+    };
+
+    let checkpointed = dir.join(files::IMPLEMENTATION_PLAN_FILE);
+    let describe = |path: &Path| {
+        run::plan_task_count(path, plan_format)
+            .map(|count| count.render_progress_bar(cli::terminal_width()))
+            .unwrap_or_else(|| "missing".to_string())
+    };
+    println!("Checkpoint before iteration {}:", iteration);
+    println!("  Run:        {}", run_name);
+    println!("  Current:    {}", describe(plan_file));
+    println!("  Checkpoint: {}", describe(&checkpointed));
+
+    if !force {
+        confirm_or_exit(&format!(
+            "Restore {}{} from this checkpoint?",
+            plan_file.display(),
+            spec_file
+                .map(|spec| format!(" and {}", spec.display()))
+                .unwrap_or_default()
+        ))?;
+    }
+    if let Err(e) = checkpoint::restore(&dir, plan_file, spec_file) {
+        error::die(&format!("{:#}", e));
+    }
+    println!("Restored checkpoint from before iteration {}.", iteration);
+    Ok(())
+}
+
+fn archive_cmd(
+    force: bool,
+    ignore_active: bool,
+    include_log: bool,
+    include_prompt: bool,
+) -> Result<()> {
     let cwd = Path::new(".");
+    lock::refuse_if_active(cwd, ignore_active);
     let archivable_files = files::find_archivable_files(cwd);
 
     if archivable_files.is_empty() {
@@ -323,23 +1530,17 @@ fn archive_cmd(force: bool) -> Result<()> {
         return Ok(());
     }
 
-    let file_count = archivable_files.len();
+    // Copied for a complete snapshot, but never reset
+    let extra_files = files::find_archive_extras(cwd, include_log, include_prompt);
+    let copied_files: Vec<&PathBuf> = archivable_files.iter().chain(&extra_files).collect();
+    let file_count = copied_files.len();
 
     if !force {
-        eprint!(
-            "Archive {} file{}? [y/N] ",
+        confirm_or_exit(&format!(
+            "Archive {} file{}?",
             file_count,
             if file_count == 1 { "" } else { "s" }
-        );
-        io::stderr().flush()?;
-
-        let mut input = String::new();
-        io::stdin().read_line(&mut input)?;
-
-        let answer = input.trim().to_lowercase();
-        if answer != "y" && answer != "yes" {
-            std::process::exit(error::exit::ERROR);
-        }
+        ))?;
     }
 
     // Ensure .ralphctl is in .gitignore
@@ -351,12 +1552,28 @@ fn archive_cmd(force: bool) -> Result<()> {
     fs::create_dir_all(&archive_dir)?;
 
     // Copy files to archive
-    for path in &archivable_files {
+    for path in &copied_files {
         let filename = path.file_name().unwrap();
         let dest = archive_dir.join(filename);
         fs::copy(path, dest)?;
     }
 
+    // Record which run produced the archived files
+    let manifest = serde_json::json!({
+        "archived_at": timestamp,
+        "run_id": ids::load_state(cwd).last_run_id,
+        "project": project_name(None, &cwd.join(files::SPEC_FILE), cwd),
+        "files": copied_files
+            .iter()
+            .filter_map(|p| p.file_name())
+            .map(|n| n.to_string_lossy().into_owned())
+            .collect::<Vec<_>>(),
+    });
+    fs::write(
+        archive_dir.join(files::MANIFEST_FILE),
+        serde_json::to_string_pretty(&manifest)? + "\n",
+    )?;
+
     // Reset original files to blank templates (or delete if no reset template)
     for path in &archivable_files {
         if let Some(blank) = generate_blank_content(path) {
@@ -424,18 +1641,101 @@ fn update_gitignore(dir: &Path) -> Result<()> {
         fs::write(&gitignore_path, format!("{}\n", entry))?;
     }
 
-    Ok(())
-}
-
-fn run_cmd(max_iterations: u32, pause: bool, model: Option<&str>) -> Result<()> {
-    use std::sync::atomic::{AtomicBool, Ordering};
-    use std::sync::Arc;
+    Ok(())
+}
+
+fn run_cmd(args: &RunArgs) -> Result<()> {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    let model = args.model.as_deref();
+
+    if args.porcelain {
+        porcelain::enable()?;
+    }
+
+    // Step 1: Validate required files exist
+    let plan_file = args.plan_file.as_path();
+    run::validate_required_files(&args.spec_file, plan_file, args.prompt_from_url.is_none())?;
+    run::validate_plan_has_tasks(plan_file, args.plan_format, args.allow_empty_plan);
+    if args.check_consistency || args.strict_consistency {
+        let content = fs::read_to_string(plan_file)
+            .with_context(|| format!("failed to read {}", plan_file.display()))?;
+        let unrelated = warn_unrelated_tasks(&args.spec_file, &content)?;
+        if args.strict_consistency && unrelated > 0 {
+            die_inconsistent(unrelated, &args.spec_file);
+        }
+    }
+
+    // An explicit --max-iterations wins; otherwise derive one from open tasks
+    let max_iterations = match args.max_iterations {
+        Some(n) => n,
+        None => {
+            let n = run::auto_max_iterations(
+                run::plan_task_count(plan_file, args.plan_format).as_ref(),
+            );
+            println!("Max iterations: {} (auto-derived from open tasks)", n);
+            n
+        }
+    };
+
+    // Step 2: Read PROMPT.md, or download the prompt with --prompt-from-url
+    let mut prompt = match &args.prompt_from_url {
+        Some(url) => match run::fetch_prompt_from_url(url) {
+            Ok(prompt) => prompt,
+            Err(e) => error::die(&format!("{:#}", e)),
+        },
+        None => run::read_prompt()?,
+    };
+
+    if args.dry_run {
+        // The first iteration's model, as --model-per-phase would pick it
+        let phase_model = fs::read_to_string(plan_file)
+            .ok()
+            .and_then(|content| run::resolve_phase_model(&content, &args.model_per_phase));
+        let argv = run::claude_argv(&run::SpawnOptions {
+            model: phase_model.map(|(_, m)| m).or(model),
+            ..Default::default()
+        });
+        let prompt_source = args
+            .prompt_from_url
+            .as_deref()
+            .unwrap_or(files::PROMPT_FILE);
+        let mut checked = vec![
+            args.spec_file.display().to_string(),
+            plan_file.display().to_string(),
+        ];
+        if args.prompt_from_url.is_none() {
+            checked.insert(0, files::PROMPT_FILE.to_string());
+        }
+        let checked: Vec<(String, &str)> = checked.into_iter().map(|f| (f, "present")).collect();
+        print!(
+            "{}",
+            run::format_dry_run(&argv, prompt_source, &prompt, max_iterations, &checked)
+        );
+        return Ok(());
+    }
+
+    // Step 2b: With --preflight, probe claude before committing to the loop
+    if args.preflight {
+        if let Err(e) = run::preflight_agent("claude") {
+            error::die(&format!("{:#}", e));
+        }
+    }
 
-    // Step 1: Validate required files exist
-    run::validate_required_files()?;
+    // Entry numbers carry on from the old log even when it is reset
+    let log_seq = run::resume_log_seq(Path::new("."));
 
-    // Step 2: Read PROMPT.md
-    let prompt = run::read_prompt()?;
+    // Step 2c: With --fresh, reset the log before the first iteration;
+    // with --fresh-log, keep the old one under a timestamped name
+    if args.fresh {
+        run::reset_log()?;
+    }
+    if args.fresh_log {
+        if let Some(rotated) = run::rotate_log(&generate_timestamp())? {
+            println!("Rotated {} to {}.", files::LOG_FILE, rotated);
+        }
+    }
 
     // Step 3: Set up Ctrl+C handler
     let interrupt_flag = Arc::new(AtomicBool::new(false));
@@ -446,79 +1746,568 @@ fn run_cmd(max_iterations: u32, pause: bool, model: Option<&str>) -> Result<()>
     })
     .expect("error setting Ctrl+C handler");
 
+    let run_id = args.tag.clone().unwrap_or_else(ids::generate_run_id);
+    let project = project_name(
+        args.project_name.as_deref(),
+        &args.spec_file,
+        Path::new("."),
+    );
+    let header_project = configured_project_name(args.project_name.as_deref());
+
+    let spawn_opts = run::SpawnOptions {
+        model,
+        interrupt_flag: Some(interrupt_flag.clone()),
+        heartbeat: args.heartbeat,
+        project: header_project.as_deref(),
+        max_output_bytes: args.max_output_bytes,
+        max_output_lines: args.max_output_lines.map(|n| n as usize),
+        suppress_echo: args.summary_only,
+        env_allowlist: isolated_env(args.isolate_env),
+        stop_on_line: args
+            .early_stop
+            .then_some(run::is_terminal_signal_line as fn(&str) -> bool),
+        live_progress: (args.live_progress && !args.summary_only).then(|| args.plan_file.clone()),
+        plan_format: args.plan_format,
+        ..Default::default()
+    };
+
+    // Scratch directories from earlier runs are stale; start clean
+    let scratch_base = files::scratch_base_dir(Path::new("."));
+    run::prune_scratch_dirs(&scratch_base, 0)?;
+
+    // Refuse an oversized prompt before anything runs; under --reload-prompt
+    // each iteration checks its own copy again
+    if let Some(max_bytes) = args.prompt_max_bytes {
+        let scratch = std::path::absolute(run::scratch_dir(&scratch_base, 1))?;
+        let composed = prompt.replace(run::SCRATCH_DIR_PLACEHOLDER, &scratch.display().to_string());
+        run::limit_prompt(&composed, max_bytes, args.prompt_overflow)?;
+    }
+
     // Step 4: Run iteration loop
+    ids::save_last_run_id(Path::new("."), &run_id, project.as_deref())?;
+    run::warn_on_log_error(run::log_run_banner(
+        &run_id,
+        "run",
+        header_project.as_deref(),
+        args.durable_log,
+    ));
+    let run_lock = lock::RunLock::acquire(Path::new("."), &run_id, project.as_deref())?;
+    let logs_dir = files::logs_dir(Path::new("."));
+    let started = std::time::Instant::now();
+    let summarize = |outcome: run::RunOutcome,
+                     iterations: u32,
+                     records: &[run::IterationRecord]| {
+        if let Some(path) = &args.report {
+            if let Err(e) = run::write_report(path, records) {
+                eprintln!("warning: {:#}", e);
+            }
+        }
+        if let Err(e) = log_seq.save(Path::new(".")) {
+            eprintln!("warning: {:#}", e);
+        }
+        run_lock.release();
+        porcelain::emit(&porcelain::format_outcome(
+            &outcome,
+            iterations,
+            run::plan_task_count(plan_file, args.plan_format).as_ref(),
+        ));
+        record_stats(Path::new("."), &outcome, iterations, started.elapsed());
+        let stats = run::forward_stats(plan_file, args.plan_format, iterations, started.elapsed())
+            .with_field("Run ID", run_id.as_str())
+            .with_optional_field("Project", project.as_deref());
+        run::print_run_summary(&outcome, &stats);
+        if let Some(url) = &args.webhook {
+            if let Err(e) = run::post_webhook(url, &run::summary_json(&outcome, &stats)) {
+                eprintln!("warning: {:#}", e);
+            }
+        }
+    };
+    // Summarize, then exit with the outcome's code
+    let finish =
+        |outcome: run::RunOutcome, iterations: u32, records: &[run::IterationRecord]| -> ! {
+            let code = outcome.exit_code();
+            summarize(outcome, iterations, records);
+            std::process::exit(code)
+        };
     let mut iterations_completed = 0u32;
+    let tasks_at_start = run::plan_task_count(plan_file, args.plan_format);
+    let print_session_delta = || {
+        if let (Some(start), Some(end)) = (
+            &tasks_at_start,
+            run::plan_task_count(plan_file, args.plan_format),
+        ) {
+            println!("{}", run::format_session_delta(start, &end));
+        }
+    };
+    let mut records: Vec<run::IterationRecord> = Vec::new();
+    // Last iteration's output, kept only for --log-similarity
+    let mut previous_output: Option<String> = None;
+    // Consecutive iterations with blank output, for --max-blank-iterations
+    let mut blank_iterations: u32 = 0;
+    // BLOCKED signals resolved by --on-blocked-retry so far
+    let mut blocked_retries: u32 = 0;
+    // An iteration rewriting its own instructions stops the run
+    let prompt_fingerprint = (args.prompt_from_url.is_none() && !args.allow_prompt_edits)
+        .then(run::prompt_fingerprint)
+        .flatten();
+    // Each run checkpoints into its own directory; failures only warn
+    let checkpoint_base = files::checkpoints_dir(Path::new("."));
+    let checkpoints = checkpoint::run_dir(&checkpoint_base, &run_id);
+    if args.keep_checkpoints > 0 {
+        if let Err(e) = checkpoint::start_run(&checkpoint_base, &run_id, checkpoint::KEEP_RUNS) {
+            eprintln!("warning: {:#}", e);
+        }
+    }
 
-    for iteration in 1..=max_iterations {
-        run::print_iteration_header(iteration);
+    // --tui draws on the alternate screen only while claude runs; everything
+    // else (progress, prompts, summary) stays on the normal screen
+    let dashboard = if args.tui && io::stdout().is_terminal() {
+        let state = tui::DashboardState::new(
+            max_iterations,
+            tasks_at_start.clone(),
+            std::time::Instant::now(),
+        );
+        Some(tui::Dashboard::start(state)?)
+    } else {
+        None
+    };
 
-        let result = run::spawn_claude(&prompt, model, Some(interrupt_flag.clone()))?;
+    let interactive_blocked = args.interactive_blocked && cli::stdin_can_prompt();
+    if args.interactive_blocked && !interactive_blocked {
+        eprintln!("warning: --interactive-blocked ignored: nobody can answer the prompt on stdin");
+    }
+    // The loop cannot go on; `LoopEnded` reports the reason
+    let failed = |e: anyhow::Error| stream::Step::Stop(run::RunOutcome::Failed(format!("{:#}", e)));
+    // Iteration the current events belong to
+    let mut iteration = 0u32;
+    let mut snapshot = None;
+    let mut tasks_before = None;
+    // Stdout of the iteration that just finished
+    let mut last_stdout = String::new();
+
+    let outcome = stream::drive_loop(max_iterations, |event| match event {
+        stream::LoopEvent::IterationStarted(n) => {
+            iteration = n;
+            // --stop-file is only checked between iterations, never mid-iteration
+            if let Some(stop_file) = args.stop_file.as_deref().filter(|p| p.exists()) {
+                let _ = fs::remove_file(stop_file);
+                println!("Stopped via stop-file ({}).", stop_file.display());
+                return stream::Step::Stop(run::RunOutcome::Stopped);
+            }
 
-        // Log iteration output to ralph.log
-        run::log_iteration(iteration, &result.stdout)?;
+            run::print_iteration_header(iteration, &run_id, header_project.as_deref());
+            if let Err(e) = run_lock.set_iteration(iteration) {
+                return failed(e);
+            }
+            porcelain::emit(&porcelain::format_iteration_start(iteration));
+
+            if let Some(cmd) = &args.pre_cmd {
+                let (code, output) = match run::run_hook("--pre-cmd", cmd) {
+                    Ok(hook) => hook,
+                    Err(e) => return failed(e),
+                };
+                run::warn_on_log_error(run::log_hook(
+                    &format!("pre-cmd before iteration {}", iteration),
+                    cmd,
+                    code,
+                    &output,
+                    args.durable_log,
+                ));
+                if code != Some(0) {
+                    eprint!("{}", output);
+                    return stream::Step::Stop(run::RunOutcome::Failed(format!(
+                        "--pre-cmd failed before iteration {} ({}); claude was not started",
+                        iteration,
+                        run::format_exit_status(code)
+                    )));
+                }
+            }
+            if args.keep_checkpoints > 0 {
+                if let Err(e) = checkpoint::save(
+                    &checkpoints,
+                    iteration,
+                    plan_file,
+                    &args.spec_file,
+                    args.keep_checkpoints,
+                ) {
+                    eprintln!("warning: failed to checkpoint the plan: {:#}", e);
+                }
+            }
 
-        // Print progress status
-        run::print_progress();
+            let iteration_started = std::time::Instant::now();
+            // --model-per-phase overrides --model while a mapped phase is active
+            let phase_model = fs::read_to_string(plan_file)
+                .ok()
+                .and_then(|content| run::resolve_phase_model(&content, &args.model_per_phase));
+            if let Some((phase, phase_model)) = &phase_model {
+                println!("Using model {} for phase: {}", phase_model, phase);
+            }
+            if args.reload_prompt && iteration > 1 {
+                prompt = match run::read_prompt() {
+                    Ok(prompt) => prompt,
+                    Err(e) => return failed(e),
+                };
+            }
+            // Per-iteration scratch space, passed via env var and prompt placeholder
+            let scratch = match run::prepare_scratch_dir(&scratch_base, iteration) {
+                Ok(scratch) => scratch,
+                Err(e) => return failed(e),
+            };
+            if let Some(keep) = args.keep_scratch {
+                if let Err(e) = run::prune_scratch_dirs(&scratch_base, keep as usize) {
+                    return failed(e);
+                }
+            }
+            let scratch = scratch.display().to_string();
+            let mut iteration_prompt = prompt.replace(run::SCRATCH_DIR_PLACEHOLDER, &scratch);
+            if let Some(max_bytes) = args.prompt_max_bytes {
+                let (limited, truncated_from) =
+                    match run::limit_prompt(&iteration_prompt, max_bytes, args.prompt_overflow) {
+                        Ok(limited) => limited,
+                        Err(e) => return failed(e),
+                    };
+                if let Some(len) = truncated_from {
+                    eprintln!(
+                        "warning: prompt is {} bytes; sending only the first {} (--prompt-max-bytes)",
+                        len,
+                        limited.len()
+                    );
+                    iteration_prompt = limited.to_string();
+                }
+            }
+            if let Some(path) = args.save_prompt.as_deref().filter(|_| iteration == 1) {
+                if let Err(e) = fs::write(path, &iteration_prompt)
+                    .with_context(|| format!("failed to write prompt to {}", path.display()))
+                {
+                    return failed(e);
+                }
+            }
 
-        // Check if we were interrupted
-        if result.was_interrupted {
-            run::print_interrupt_summary(iterations_completed);
-            std::process::exit(error::exit::INTERRUPTED);
+            let iteration_opts = run::SpawnOptions {
+                model: phase_model.map(|(_, m)| m).or(model),
+                env: [
+                    (run::SCRATCH_DIR_ENV, scratch),
+                    (ids::RUN_ID_ENV, run_id.clone()),
+                ]
+                .into_iter()
+                .chain(run::budget_env(
+                    iteration,
+                    max_iterations,
+                    run::plan_task_count(plan_file, args.plan_format).as_ref(),
+                ))
+                .collect(),
+                hide_stdout: dashboard.is_some(),
+                ..spawn_opts.clone()
+            };
+
+            snapshot = args
+                .show_diff
+                .then(|| git::snapshot(Path::new(".")))
+                .flatten();
+            tasks_before = args.once_per_task.then(|| run::plan_tasks(plan_file));
+            // Before the dashboard takes over the screen
+            if args.trace {
+                run::trace_command(&run::claude_argv(&iteration_opts), args.durable_log);
+            }
+            if let Some(dashboard) = &dashboard {
+                dashboard.update(|state| state.start_iteration(iteration, iteration_started));
+                dashboard.resume();
+            }
+            stream::Step::Spawn(iteration_prompt, Box::new(iteration_opts))
+        }
+        stream::LoopEvent::OutputChunk(line) => {
+            if let Some(dashboard) = &dashboard {
+                dashboard.update(|state| state.push_output(&line));
+            }
+            stream::Step::Proceed
         }
+        stream::LoopEvent::IterationFinished(finished) => {
+            if let Some(dashboard) = &dashboard {
+                dashboard.suspend();
+            }
+            let result = finished.result;
+            // Before ralph.log is written, so the log itself is not reported
+            let changes = snapshot
+                .take()
+                .map(|snapshot| git::changes_since(Path::new("."), &snapshot));
+            records.push(finished.record);
+
+            // Log iteration output to ralph.log
+            run::warn_on_log_error(run::log_iteration(
+                iteration,
+                log_seq.next(),
+                &run_id,
+                header_project.as_deref(),
+                &result.stdout,
+                args.durable_log,
+            ));
+            if args.log_similarity {
+                if let Some(previous) = &previous_output {
+                    let similarity = run::output_similarity(previous, &result.stdout);
+                    run::warn_on_log_error(run::log_similarity(similarity, args.durable_log));
+                }
+                previous_output = Some(result.stdout.clone());
+            }
+            if args.split_logs {
+                if let Err(e) = run::write_iteration_log(&logs_dir, iteration, &result.stdout) {
+                    return failed(e);
+                }
+            }
+            if let Some(summary) = records.last().and_then(|r| r.tools.as_ref()) {
+                if !args.summary_only {
+                    println!("{}", tools::format_tool_line(summary));
+                }
+                run::warn_on_log_error(run::log_tool_summary(iteration, summary, args.durable_log));
+            }
+            match changes {
+                Some(Ok(changes)) => {
+                    let summary = git::format_changes(&changes);
+                    if !args.summary_only {
+                        print!("{}", summary);
+                    }
+                    run::warn_on_log_error(run::log_changed_files(
+                        iteration,
+                        &summary,
+                        args.durable_log,
+                    ));
+                }
+                Some(Err(e)) => eprintln!("warning: {:#}", e),
+                None => {}
+            }
+            if args.plan_progress_in_log {
+                if let Some(count) = run::plan_task_count(plan_file, args.plan_format) {
+                    run::warn_on_log_error(run::log_plan_progress(
+                        iteration,
+                        &count,
+                        args.durable_log,
+                    ));
+                    if let Some(path) = &args.progress_file {
+                        if let Err(e) = run::append_progress_file(path, iteration, &count) {
+                            eprintln!("warning: {:#}", e);
+                        }
+                    }
+                }
+            }
 
-        iterations_completed = iteration;
+            if let Some(dashboard) = &dashboard {
+                let signal = records.last().map_or("", |r| r.signal);
+                let tasks = run::plan_task_count(plan_file, args.plan_format);
+                dashboard.update(|state| state.finish_iteration(signal, tasks));
+            }
 
-        if !result.success {
-            error::die(&format!(
-                "claude exited with code {}",
-                result.exit_code.unwrap_or(-1)
-            ));
-        }
+            // Print progress status
+            if !args.summary_only {
+                run::print_progress(plan_file, args.plan_format);
+                if let Some(percent) = run::detect_progress(&result.stdout) {
+                    println!("{}", parser::render_reported_progress(percent));
+                }
+            }
 
-        // Check for blocked signal first (takes priority)
-        if let Some(reason) = run::detect_blocked_signal(&result.stdout) {
-            eprintln!("blocked: {}", reason);
-            std::process::exit(error::exit::BLOCKED);
+            // An interrupted iteration does not count as completed
+            if !result.was_interrupted {
+                iterations_completed = iteration;
+            }
+            last_stdout = result.stdout;
+            stream::Step::Proceed
         }
+        stream::LoopEvent::SignalDetected(mut signal) => {
+            if prompt_fingerprint.is_some_and(|hash| run::prompt_fingerprint() != Some(hash)) {
+                run::warn_on_log_error(run::log_prompt_modified(iteration, args.durable_log));
+                eprintln!("error: {}", run::format_prompt_modified(iteration));
+                return stream::Step::Stop(run::RunOutcome::PromptModified(iteration));
+            }
+
+            if run::is_blank_output(&last_stdout) {
+                blank_iterations += 1;
+                if args.max_blank_iterations > 0 && blank_iterations >= args.max_blank_iterations {
+                    eprintln!(
+                        "error: claude produced no output for {} consecutive iteration{} \
+                         (--max-blank-iterations); check that it is logged in and working",
+                        blank_iterations,
+                        if blank_iterations == 1 { "" } else { "s" }
+                    );
+                    return stream::Step::Stop(run::RunOutcome::BlankOutput);
+                }
+            } else {
+                blank_iterations = 0;
+            }
 
-        // Check for completion/continue signals in stdout
-        match run::detect_signal(&result.stdout) {
-            run::LoopSignal::Done => {
-                println!("=== Loop complete ===");
-                return Ok(());
+            // --pause prompts every iteration; --pause-every only on every Nth
+            let pause_now = args.pause
+                || args
+                    .pause_every
+                    .is_some_and(|n| iteration.is_multiple_of(n));
+
+            // The loop found BLOCKED, else the first DONE/CONTINUE, in one pass
+            if signal == run::LoopSignal::NoSignal
+                && args.interactive_signals
+                && io::stdin().is_terminal()
+            {
+                if let Some(inline) = run::detect_inline_signal(&last_stdout) {
+                    match run::prompt_inline_signal(&inline, &interrupt_flag) {
+                        Ok(Some(true)) => signal = inline,
+                        Ok(Some(false)) => {}
+                        Ok(None) => return stream::Step::Stop(run::RunOutcome::Interrupted),
+                        Err(e) => return failed(e),
+                    }
+                }
+            }
+            porcelain::emit(&porcelain::format_signal(&signal));
+            // --once-per-task: one newly checked box ends the run, whatever the signal
+            if let Some(before) = tasks_before
+                .take()
+                .filter(|_| !matches!(signal, run::LoopSignal::Blocked(_)))
+            {
+                let completed = run::newly_completed_tasks(&before, &run::plan_tasks(plan_file));
+                if let Some(task) = completed.first() {
+                    println!("Task completed: {}", task);
+                    println!("Stopping after one task (--once-per-task).");
+                    print_session_delta();
+                    return stream::Step::Stop(run::RunOutcome::TaskCompleted(task.clone()));
+                }
             }
-            run::LoopSignal::Continue => {
+
+            match signal {
+                run::LoopSignal::Blocked(reason) => {
+                    eprintln!("{}", run::format_blocked(&reason));
+                    if interactive_blocked {
+                        match run::prompt_blocked(&interrupt_flag) {
+                            Ok(run::BlockedAction::Retry) => {
+                                // Same iteration number; the budget is not consumed
+                                println!("Retrying iteration {}.", iteration);
+                                return stream::Step::Retry;
+                            }
+                            Ok(run::BlockedAction::Interrupted) => {
+                                return stream::Step::Stop(run::RunOutcome::Interrupted)
+                            }
+                            Ok(run::BlockedAction::Abort | run::BlockedAction::Shell) => {}
+                            Err(e) => return failed(e),
+                        }
+                    }
+                    if let Some(cmd) = &args.on_blocked_retry {
+                        if blocked_retries < args.max_blocked_retries {
+                            blocked_retries += 1;
+                            println!(
+                                "Running --on-blocked-retry ({}/{}): {}",
+                                blocked_retries, args.max_blocked_retries, cmd
+                            );
+                            let (code, output) = match run::run_hook("--on-blocked-retry", cmd) {
+                                Ok(hook) => hook,
+                                Err(e) => return failed(e),
+                            };
+                            print!("{}", output);
+                            run::warn_on_log_error(run::log_hook(
+                                &format!("on-blocked-retry after iteration {}", iteration),
+                                cmd,
+                                code,
+                                &output,
+                                args.durable_log,
+                            ));
+                            if code == Some(0) {
+                                println!("Retry command succeeded; resuming the loop.");
+                                return stream::Step::Continue;
+                            }
+                            eprintln!(
+                                "warning: --on-blocked-retry failed ({}); stopping",
+                                run::format_exit_status(code)
+                            );
+                        } else {
+                            eprintln!(
+                                "warning: --max-blocked-retries ({}) reached; stopping",
+                                args.max_blocked_retries
+                            );
+                        }
+                    }
+                    stream::Step::Stop(run::RunOutcome::Blocked(reason))
+                }
+                run::LoopSignal::Done => stream::Step::Stop(run::RunOutcome::Done),
+                run::LoopSignal::NoSignal
+                    if args.strict_signals
+                        && !io::stdin().is_terminal()
+                        && run::is_substantial_output(&last_stdout) =>
+                {
+                    // Nobody can answer the prompt; fail instead of guessing
+                    eprintln!(
+                        "error: iteration {} produced {} bytes of output but no [[RALPH:DONE]], \
+                         [[RALPH:CONTINUE]], or [[RALPH:BLOCKED:...]] signal (--strict-signals)",
+                        iteration,
+                        last_stdout.len()
+                    );
+                    stream::Step::Stop(run::RunOutcome::NoSignal)
+                }
+                run::LoopSignal::Continue | run::LoopSignal::NoSignal if pause_now => {
+                    // Pausing this iteration: one prompt handles continuation
+                    match run::prompt_continue(&interrupt_flag) {
+                        Ok(run::PauseAction::Continue) => stream::Step::Continue,
+                        Ok(run::PauseAction::Stop) => {
+                            println!("Stopped by user.");
+                            stream::Step::Stop(run::RunOutcome::Stopped)
+                        }
+                        Ok(run::PauseAction::Interrupted) => {
+                            stream::Step::Stop(run::RunOutcome::Interrupted)
+                        }
+                        Err(e) => failed(e),
+                    }
+                }
                 // Task completed, continue to next iteration
-                // If --pause is set, prompt user before continuing
-                if pause && run::prompt_continue()? == run::PauseAction::Stop {
-                    println!("Stopped by user.");
-                    return Ok(());
+                run::LoopSignal::Continue => stream::Step::Continue,
+                run::LoopSignal::NoSignal => {
+                    // No signal detected, prompt user for action
+                    match run::prompt_no_signal(&interrupt_flag) {
+                        Ok(run::NoSignalAction::Continue) => stream::Step::Continue,
+                        Ok(run::NoSignalAction::Stop) => {
+                            println!("Stopped by user.");
+                            stream::Step::Stop(run::RunOutcome::Stopped)
+                        }
+                        Ok(run::NoSignalAction::Interrupted) => {
+                            stream::Step::Stop(run::RunOutcome::Interrupted)
+                        }
+                        Err(e) => failed(e),
+                    }
                 }
             }
-            run::LoopSignal::NoSignal => {
-                // No signal detected, prompt user for action
-                if !pause && run::prompt_no_signal()? == run::NoSignalAction::Stop {
-                    println!("Stopped by user.");
-                    return Ok(());
+        }
+        stream::LoopEvent::LoopEnded(outcome) => {
+            // A spawn error ends the loop with the dashboard still up
+            if let Some(dashboard) = &dashboard {
+                dashboard.suspend();
+            }
+            match &outcome {
+                run::RunOutcome::Interrupted => {
+                    run::print_interrupt_summary(iterations_completed, plan_file, args.plan_format)
                 }
-                // If --pause is set, that prompt handles continuation
-                if pause && run::prompt_continue()? == run::PauseAction::Stop {
-                    println!("Stopped by user.");
-                    return Ok(());
+                run::RunOutcome::OutputLimit => eprintln!(
+                    "error: iteration {} exceeded --max-output-bytes ({} bytes); claude was stopped",
+                    iteration,
+                    args.max_output_bytes.unwrap_or_default()
+                ),
+                run::RunOutcome::Failed(reason) => eprintln!("error: {}", reason),
+                run::RunOutcome::Done => {
+                    println!("=== Loop complete ===");
+                    print_session_delta();
                 }
+                run::RunOutcome::MaxIterations => {
+                    // Reached max iterations without completion
+                    eprintln!(
+                        "warning: reached max iterations ({}) without [[RALPH:DONE]]",
+                        max_iterations
+                    );
+                    print_session_delta();
+                }
+                _ => {}
             }
+            stream::Step::Proceed
         }
-    }
-
-    // Reached max iterations without completion
-    eprintln!(
-        "warning: reached max iterations ({}) without [[RALPH:DONE]]",
-        max_iterations
-    );
-    std::process::exit(error::exit::MAX_ITERATIONS);
+    });
+    finish(outcome, iterations_completed, &records)
 }
 
-fn interview_cmd(model: Option<&str>) -> Result<()> {
+fn interview_cmd(
+    model: Option<&str>,
+    plan_template: Option<&str>,
+    system_prompt_file: Option<&Path>,
+    spec_file: &Path,
+) -> Result<()> {
     use std::process::Command;
 
     if !cli::claude_exists() {
@@ -528,147 +2317,34 @@ fn interview_cmd(model: Option<&str>) -> Result<()> {
     let cwd = std::env::current_dir()
         .map(|p| p.display().to_string())
         .unwrap_or_else(|_| ".".to_string());
-
-    let system_prompt = format!(
-        r#"# Ralph Loop System Context
-
-You are setting up a Ralph Loop—an autonomous development workflow where an AI agent iteratively builds software by reading local state files and executing tasks until completion.
-
-## How the Ralph Loop Works
-
-The `ralphctl run` command executes this loop:
-
-1. Read PROMPT.md (orchestration instructions) and pipe it to `claude -p`
-2. Claude reads SPEC.md and IMPLEMENTATION_PLAN.md to understand the project and find the next unchecked task
-3. Claude implements the task, runs tests, and checks off the completed item in IMPLEMENTATION_PLAN.md
-4. When done, Claude outputs `[[RALPH:DONE]]` (all tasks complete) or `[[RALPH:BLOCKED:<reason>]]` (cannot proceed)
-5. If no stop signal, repeat from step 1
-
-## Why This Architecture is Effective
-
-**Fresh context each iteration**: Each `claude -p` invocation starts with clean context. This eliminates "context rot"—the degradation of AI performance as conversation history accumulates with stale information, abandoned approaches, and confusion.
-
-**Local state as memory**: IMPLEMENTATION_PLAN.md checkboxes persist progress across iterations. The agent doesn't need to remember what it did—it reads the current state and determines what's next. This is more reliable than conversation-based memory.
-
-**Atomic task execution**: Each iteration focuses on one task. Smaller, focused work produces better results than sprawling multi-task sessions.
-
-**Stop conditions prevent waste**: `[[RALPH:DONE]]` stops the loop when all work is complete, avoiding unnecessary LLM invocations. `[[RALPH:BLOCKED:<reason>]]` stops when human intervention is needed.
-
-## What Makes a Great SPEC.md
-
-A spec that enables autonomous development must be:
-
-- **Unambiguous**: No room for interpretation. "Fast" is vague; "responds within 200ms" is testable.
-- **Complete**: Covers all features, edge cases, error handling, and acceptance criteria.
-- **Scoped**: Clearly defines what's in and out of scope. Prevents scope creep during development.
-- **Testable**: Every requirement maps to a verification method.
-- **Architecturally sound**: Describes the high-level design, key components, and their interactions.
-
-Structure:
-```markdown
-# Project Name
-
-## Overview
-One paragraph describing what this is and why it exists.
-
-## Requirements
-### Functional Requirements
-- Specific, testable requirements
-
-### Non-Functional Requirements
-- Performance, security, reliability constraints
-
-## Architecture
-- Key components and their responsibilities
-- Data flow and interactions
-- Technology choices with rationale
-
-## Build & Test
-- Exact commands to build, test, lint, and format the project
-- The agent uses these commands every iteration — they must be correct
-
-## Out of Scope
-- Explicit list of what this project does NOT do
-```
-
-## What Makes a Great IMPLEMENTATION_PLAN.md
-
-The implementation plan is the agent's task queue. Each checkbox is one unit of work.
-
-**Task qualities:**
-- **Atomic**: Completable in one focused session (15-60 minutes of work)
-- **Ordered**: Dependencies flow top-to-bottom; earlier tasks don't depend on later ones
-- **Testable**: Each task has clear "done" criteria
-- **Specific**: "Add user authentication" is too broad; "Implement JWT token generation in auth.rs" is specific
-
-**Structure:**
-```markdown
-# Implementation Plan
-
-## Phase 1: Foundation
-- [ ] Set up project structure with Cargo.toml and module layout
-- [ ] Implement core data types in src/types.rs
-- [ ] Implement core logic in src/lib.rs
-- [ ] Add unit tests for data types and core logic
-- [ ] Verify: build passes, all tests pass, no lint warnings
-
-## Phase 2: Core Features
-- [ ] Implement feature X in src/feature_x.rs
-- [ ] Implement feature Y in src/feature_y.rs
-- [ ] Add tests for features X and Y
-- [ ] Verify: build passes, all tests pass, no lint warnings
-
-## Phase 3: Integration & Polish
-- [ ] Add integration tests covering end-to-end workflows
-- [ ] Write user documentation
-```
-
-**Phasing**: Group related tasks into phases. Complete one phase before starting the next. This provides natural checkpoints and reduces context needed per iteration.
-
-**Task ordering within phases**: Front-load implementation tasks — write the code first, then write tests, then verify everything builds and passes. This lets the agent focus on code generation while context is fresh, and handle verification as a separate step where the build/test output drives the work.
-
-## Interview Guidelines
-
-Your job is to extract enough detail to write these files.
-
-**IMPORTANT**: Always use the `AskUserQuestion` tool to ask questions. Do NOT ask questions as free-form text in your response—the user cannot reply to text responses. Every question must go through the AskUserQuestion tool so the user can provide structured answers.
-
-Topics to cover:
-
-1. **Core purpose**: What problem does this solve? Who is it for?
-2. **Features**: What must it do? What's nice-to-have vs essential?
-3. **Technical constraints**: Language, framework, dependencies, environment?
-4. **Build & test commands**: Exact commands to build, run tests, lint, and format. The agent runs these every iteration — they must be right. Always populate the "Build & Test" section of SPEC.md with these commands.
-5. **Interfaces**: CLI args? API endpoints? File formats? UI?
-6. **Edge cases**: What happens when things go wrong? Invalid input? Network failures?
-7. **Success criteria**: How do we know it's done? What tests prove it works?
-8. **Scope boundaries**: What does this explicitly NOT do?
-
-Don't accept vague answers. "It should be fast" → "What's the latency budget? 100ms? 1s?" Push for specifics.
-
-## After Writing the Files
-
-When you have enough detail:
-
-1. Write `./SPEC.md` with the complete project specification
-2. Write `./IMPLEMENTATION_PLAN.md` with the phased task list
-3. Summarize what you created (brief overview of the spec and number of tasks)
-4. Tell the user to run `ralphctl run` to start the autonomous development loop
-5. Remind them they can check progress anytime with `ralphctl status`
-
-## Working Directory
-
-You are working in: `{cwd}`
-
-When writing files, use this exact path as the base. For example:
-- SPEC.md → `{cwd}/SPEC.md`
-- IMPLEMENTATION_PLAN.md → `{cwd}/IMPLEMENTATION_PLAN.md`
-
-NEVER use paths from other context (like ~/.claude/CLAUDE.md). The path above is the ONLY correct location for project files."#,
-        cwd = cwd
-    );
-
-    const INITIAL_PROMPT: &str = r#"You are an assistant helping me set up a Ralph Loop. Interview me to create SPEC.md and IMPLEMENTATION_PLAN.md for my project. Tell me how to get started—I might paste a detailed project idea, describe something simple, or just have a rough concept."#;
+    let spec = Path::new(&cwd).join(spec_file).display().to_string();
+
+    let system_prompt = match system_prompt_file {
+        Some(path) => {
+            let template = fs::read_to_string(path)
+                .with_context(|| format!("failed to read system prompt file {}", path.display()))?;
+            if template.trim().is_empty() {
+                error::die(&format!("{} is empty", path.display()));
+            }
+            templates::render_interview_system_prompt(&template, &cwd, &spec)
+        }
+        None => templates::render_interview_system_prompt(
+            templates::INTERVIEW_SYSTEM_PROMPT,
+            &cwd,
+            &spec,
+        ),
+    };
+    let system_prompt = match plan_template {
+        Some(plan) => format!(
+            "{}\n\n## Plan Template\n\nThe user chose a starting plan for this kind of project. Structure IMPLEMENTATION_PLAN.md after it, replacing its placeholder tasks with ones from the interview:\n\n```markdown\n{}\n```",
+            system_prompt,
+            plan.trim_end()
+        ),
+        None => system_prompt,
+    };
+
+    let initial_prompt =
+        templates::render_interview_initial_prompt(&spec_file.display().to_string());
 
     // Launch claude in interactive mode with the interview prompt
     let mut cmd = Command::new("claude");
@@ -681,7 +2357,7 @@ NEVER use paths from other context (like ~/.claude/CLAUDE.md). The path above is
         cmd.arg("--model").arg(m);
     }
 
-    let status = cmd.arg(INITIAL_PROMPT).status().inspect_err(|e| {
+    let status = cmd.arg(&initial_prompt).status().inspect_err(|e| {
         if e.kind() == std::io::ErrorKind::NotFound {
             error::die("claude not found in PATH");
         }
@@ -700,7 +2376,47 @@ NEVER use paths from other context (like ~/.claude/CLAUDE.md). The path above is
     Ok(())
 }
 
-async fn init_cmd(force: bool) -> Result<()> {
+/// Stub plan written by `init --from-spec` when tasks are not derived by claude.
+const STUB_PLAN: &str = "# Implementation Plan
+
+<!-- Break SPEC.md down into tasks, one `- [ ]` checkbox per unit of work. -->
+";
+
+/// Prompt used by `init --from-spec --derive-plan` to draft the task list.
+const DERIVE_PLAN_PROMPT: &str = r#"Read ./SPEC.md and write ./IMPLEMENTATION_PLAN.md for it.
+
+The plan is the task queue for an autonomous Ralph Loop. Group tasks into phases
+(`## Phase N: <name>`), one `- [ ] <task>` checkbox per atomic unit of work, ordered so
+that dependencies flow top-to-bottom. End each phase with a verification task.
+
+Only write IMPLEMENTATION_PLAN.md. Do not modify SPEC.md or any other file."#;
+
+/// Get the specialized plan template `name` (`--plan-template`).
+///
+/// Falls back to the default IMPLEMENTATION_PLAN.md with a warning when the
+/// template does not exist upstream or in the cache.
+async fn get_plan_template(name: &str) -> Result<String> {
+    let filename = templates::plan_template_filename(name);
+    match templates::get_template(&filename).await {
+        Ok(content) => Ok(content),
+        Err(e) => {
+            eprintln!(
+                "warning: unknown plan template '{}' ({:#}); using the default {}",
+                name,
+                e,
+                files::IMPLEMENTATION_PLAN_FILE
+            );
+            templates::get_template(files::IMPLEMENTATION_PLAN_FILE).await
+        }
+    }
+}
+
+async fn init_cmd(
+    force: bool,
+    from_spec: Option<&Path>,
+    derive_plan: bool,
+    plan_template: Option<&str>,
+) -> Result<()> {
     // Step 1: Verify claude CLI is in PATH
     if !cli::claude_exists() {
         error::die("claude not found in PATH");
@@ -723,15 +2439,55 @@ async fn init_cmd(force: bool) -> Result<()> {
         ));
     }
 
-    // Step 3: Fetch templates from GitHub (with cache fallback)
-    let templates = templates::get_all_templates().await?;
+    // Step 3: Fetch templates from GitHub (with cache fallback).
+    // With --from-spec, only PROMPT.md comes from the templates.
+    let templates = match from_spec {
+        Some(spec_path) => {
+            let spec = fs::read_to_string(spec_path).unwrap_or_else(|_| {
+                error::die(&format!("cannot read spec: {}", spec_path.display()))
+            });
+            let prompt = templates::get_template(files::PROMPT_FILE).await?;
+            vec![
+                (files::SPEC_FILE, spec),
+                (files::IMPLEMENTATION_PLAN_FILE, STUB_PLAN.to_string()),
+                (files::PROMPT_FILE, prompt),
+            ]
+        }
+        None => {
+            let mut templates = templates::get_all_templates().await?;
+            if let Some(name) = plan_template {
+                let plan = get_plan_template(name).await?;
+                for (filename, content) in templates.iter_mut() {
+                    if *filename == files::IMPLEMENTATION_PLAN_FILE {
+                        *content = plan.clone();
+                    }
+                }
+            }
+            templates
+        }
+    };
 
     // Step 4: Write files to current directory
     for (filename, content) in templates {
         fs::write(filename, content)?;
     }
 
-    println!("Initialized ralph loop files.");
+    // Step 5: Optionally let claude replace the stub plan
+    if derive_plan {
+        let result = run::spawn_claude(DERIVE_PLAN_PROMPT, &run::SpawnOptions::default())?;
+        if !result.success {
+            error::die(&format!(
+                "claude exited with code {}",
+                result.exit_code.unwrap_or(-1)
+            ));
+        }
+    }
+
+    if let Some(spec_path) = from_spec {
+        println!("Initialized ralph loop files from {}.", spec_path.display());
+    } else {
+        println!("Initialized ralph loop files.");
+    }
     println!();
     println!("Next steps:");
     println!("  1. Run 'ralphctl interview' to define your project interactively, or");
@@ -741,30 +2497,190 @@ async fn init_cmd(force: bool) -> Result<()> {
     Ok(())
 }
 
-async fn fetch_latest_prompt_cmd() -> Result<()> {
-    let content = templates::get_template("PROMPT.md").await?;
-    fs::write("PROMPT.md", content)?;
-    println!("Updated PROMPT.md to latest version.");
+async fn fetch_latest_prompt_cmd(
+    force: bool,
+    backup: bool,
+    reverse: bool,
+    preview: bool,
+) -> Result<()> {
+    // The reverse prompt ships with the binary, so it needs no fetch
+    let (file, content) = if reverse {
+        (
+            files::REVERSE_PROMPT_FILE,
+            templates::get_reverse_template(),
+        )
+    } else {
+        (
+            files::PROMPT_FILE,
+            templates::get_template(files::PROMPT_FILE).await?,
+        )
+    };
+
+    let existing = fs::read_to_string(file).ok();
+    if preview && existing.is_none() {
+        println!("{} does not exist; it would be created.", file);
+        return Ok(());
+    }
+
+    if let Some(existing) = existing {
+        if existing == content {
+            println!("{} is already up to date.", file);
+            return Ok(());
+        }
+
+        print!(
+            "{}",
+            diff::unified_diff(file, &format!("{} (latest)", file), &existing, &content)
+        );
+
+        // --diff is a preview only
+        if preview {
+            return Ok(());
+        }
+
+        if !force {
+            // -y answers the prompt below without reading stdin
+            if !io::stdin().is_terminal() && !cli::assume_yes() {
+                error::die(&format!(
+                    "{} differs from the latest version; pass --force to overwrite",
+                    file
+                ));
+            }
+
+            confirm_or_exit(&format!("Overwrite {}?", file))?;
+        }
+
+        if backup {
+            let backup_path = format!("{}.bak", file);
+            fs::write(&backup_path, &existing)?;
+            println!("Saved previous {} to {}.", file, backup_path);
+        }
+    }
+
+    fs::write(file, content)?;
+    println!("Updated {} to latest version.", file);
     Ok(())
 }
 
-async fn reverse_cmd(
-    question: Option<String>,
-    max_iterations: u32,
-    pause: bool,
-    model: Option<&str>,
-) -> Result<()> {
+/// Print what `reverse` would do (`--dry-run`) without touching any file.
+fn reverse_dry_run(args: &ReverseArgs, cwd: &Path, target: Option<&Path>) -> Result<()> {
+    let prompt_file = args.reverse_prompt_file.as_deref();
+    let (prompt, source) = reverse::resolve_prompt(cwd, args.refresh_prompt, prompt_file)
+        .unwrap_or_else(|e| error::die(&format!("{:#}", e)));
+    let prompt = match target {
+        Some(target) => reverse::with_target_section(&prompt, target, &std::env::current_dir()?),
+        None => prompt,
+    };
+    let prompt_source = match (source, prompt_file) {
+        (reverse::PromptSource::File, Some(path)) => path.display().to_string(),
+        (reverse::PromptSource::Local, _) => "local REVERSE_PROMPT.md".to_string(),
+        _ => "embedded REVERSE_PROMPT.md".to_string(),
+    };
+    let argv = run::claude_argv(&run::SpawnOptions {
+        model: args.model.as_deref(),
+        ..Default::default()
+    });
+    let exists = |f: &str| {
+        if cwd.join(f).exists() {
+            "present"
+        } else {
+            "missing"
+        }
+    };
+    let question_state = match &args.question {
+        Some(_) => "written from the question argument",
+        None => exists(files::QUESTION_FILE),
+    };
+    let checked = [
+        (files::QUESTION_FILE.to_string(), question_state),
+        (
+            files::INVESTIGATION_FILE.to_string(),
+            exists(files::INVESTIGATION_FILE),
+        ),
+        (
+            files::FINDINGS_FILE.to_string(),
+            exists(files::FINDINGS_FILE),
+        ),
+    ];
+    print!(
+        "{}",
+        run::format_dry_run(
+            &argv,
+            &prompt_source,
+            &prompt,
+            args.max_iterations,
+            &checked
+        )
+    );
+    if let Some(target) = target {
+        println!(
+            "Target:         {} (claude's working directory)",
+            target.display()
+        );
+    }
+    Ok(())
+}
+
+async fn reverse_cmd(args: &ReverseArgs) -> Result<()> {
     use std::sync::atomic::{AtomicBool, Ordering};
     use std::sync::Arc;
 
+    let max_iterations = args.max_iterations;
+    let pause = args.pause;
+    let model = args.model.as_deref();
+
     let cwd = Path::new(".");
 
+    if args.porcelain {
+        porcelain::enable()?;
+    }
+
+    // --target: claude works in another directory; the ralph files stay here
+    let target = args
+        .target
+        .as_deref()
+        .map(|t| reverse::resolve_target(t).unwrap_or_else(|e| error::die(&format!("{:#}", e))));
+
+    if args.dry_run {
+        reverse_dry_run(args, cwd, target.as_deref())?;
+        return Ok(());
+    }
+
+    // With --format json, stdout carries only the final JSON object; notes
+    // that would normally go there are sent to stderr
+    let json = args.format == OutputFormat::Json;
+    let note = |message: String| {
+        if json {
+            eprintln!("{}", message);
+        } else {
+            println!("{}", message);
+        }
+    };
+
     // Step 1: Handle question setup
+    // - With --resume: require QUESTION.md and INVESTIGATION.md, touch neither
     // - If argument provided: write to QUESTION.md
     // - If no argument and QUESTION.md exists: use existing file
     // - If no argument and no QUESTION.md: create template, print instructions, exit
-    if let Some(q) = question {
-        reverse::write_question(cwd, &q)?;
+    if args.resume {
+        let missing: Vec<&str> = [files::QUESTION_FILE, files::INVESTIGATION_FILE]
+            .into_iter()
+            .filter(|f| !cwd.join(f).exists())
+            .collect();
+        if !missing.is_empty() {
+            error::die(&format!(
+                "nothing to resume: missing {}",
+                missing.join(", ")
+            ));
+        }
+        note(format!(
+            "Resuming investigation from {}.",
+            files::INVESTIGATION_FILE
+        ));
+    } else if let Some(q) = &args.question {
+        if let Err(e) = reverse::write_question(cwd, q, args.max_question_bytes as usize) {
+            error::die(&format!("{:#}", e));
+        }
     } else if !cwd.join(files::QUESTION_FILE).exists() {
         reverse::create_question_template(cwd)?;
         eprintln!(
@@ -779,11 +2695,52 @@ async fn reverse_cmd(
         error::die("claude not found in PATH");
     }
 
-    // Step 3: Get REVERSE_PROMPT.md template (embedded in binary)
-    let prompt = templates::get_reverse_template();
+    // Step 3: Get the prompt: --reverse-prompt-file, else a local
+    // REVERSE_PROMPT.md (e.g. from an earlier --write-prompt) unless
+    // --refresh-prompt, else the embedded template
+    let prompt_file = args.reverse_prompt_file.as_deref();
+    let (prompt, source) = reverse::resolve_prompt(cwd, args.refresh_prompt, prompt_file)?;
+    let origin = match (source, prompt_file) {
+        (reverse::PromptSource::File, Some(path)) => path.display().to_string(),
+        (reverse::PromptSource::Local, _) => files::REVERSE_PROMPT_FILE.to_string(),
+        _ => "embedded prompt".to_string(),
+    };
+    match source {
+        reverse::PromptSource::File => note(format!("using local {}", origin)),
+        // An old copy can silently hide a newer built-in prompt
+        reverse::PromptSource::Local => eprintln!(
+            "warning: using local {} instead of the built-in prompt \
+             (use --refresh-prompt to ignore it)",
+            origin
+        ),
+        reverse::PromptSource::Embedded => {}
+    }
+    if let Err(e) = reverse::validate_prompt(&prompt) {
+        let hint = match source {
+            reverse::PromptSource::File => "add them or drop --reverse-prompt-file",
+            _ => "use --refresh-prompt to use the built-in prompt",
+        };
+        error::die(&format!("{}: {:#} ({})", origin, e, hint));
+    }
 
-    // Write REVERSE_PROMPT.md to current directory for reference
-    fs::write(files::REVERSE_PROMPT_FILE, &prompt)?;
+    // The prompt stays in memory unless a local copy was asked for
+    if args.write_prompt && source == reverse::PromptSource::Embedded {
+        fs::write(files::REVERSE_PROMPT_FILE, &prompt)?;
+    }
+    let prompt = match &target {
+        Some(target) => {
+            note(format!("investigating {}", target.display()));
+            reverse::with_target_section(&prompt, target, &std::env::current_dir()?)
+        }
+        None => prompt,
+    };
+
+    // Entry numbers carry on from the old log even when it is reset
+    let log_seq = run::resume_log_seq(cwd);
+    // With --fresh, reset the log before the first iteration
+    if args.fresh {
+        run::reset_log()?;
+    }
 
     // Step 4: Set up Ctrl+C handler
     let interrupt_flag = Arc::new(AtomicBool::new(false));
@@ -795,67 +2752,202 @@ async fn reverse_cmd(
     .expect("error setting Ctrl+C handler");
 
     // Step 5: Run investigation loop
+    let run_id = args.tag.clone().unwrap_or_else(ids::generate_run_id);
+    let project = project_name(
+        args.project_name.as_deref(),
+        &cwd.join(files::SPEC_FILE),
+        cwd,
+    );
+    let header_project = configured_project_name(args.project_name.as_deref());
+    ids::save_last_run_id(cwd, &run_id, project.as_deref())?;
+    run::warn_on_log_error(run::log_run_banner(
+        &run_id,
+        "reverse",
+        header_project.as_deref(),
+        args.durable_log,
+    ));
+    let spawn_opts = run::SpawnOptions {
+        model,
+        interrupt_flag: Some(interrupt_flag.clone()),
+        env: vec![(ids::RUN_ID_ENV, run_id.clone())],
+        suppress_echo: args.summary_only || json,
+        env_allowlist: isolated_env(args.isolate_env),
+        stop_on_line: args
+            .early_stop
+            .then_some(reverse::is_terminal_reverse_signal_line as fn(&str) -> bool),
+        current_dir: target,
+        ..Default::default()
+    };
+
+    let run_lock = lock::RunLock::acquire(cwd, &run_id, project.as_deref())?;
+    let logs_dir = files::logs_dir(cwd);
+    let started = std::time::Instant::now();
+    let summarize = |outcome: run::RunOutcome, iterations: u32| {
+        if let Err(e) = log_seq.save(cwd) {
+            eprintln!("warning: {:#}", e);
+        }
+        run_lock.release();
+        porcelain::emit(&porcelain::format_outcome(&outcome, iterations, None));
+        record_stats(cwd, &outcome, iterations, started.elapsed());
+        let stats = reverse::reverse_stats(cwd, iterations, started.elapsed())
+            .with_field("Run ID", run_id.as_str())
+            .with_optional_field("Project", project.as_deref());
+        run::print_run_summary(&outcome, &stats);
+        if json {
+            println!("{}", reverse::outcome_json(&outcome, iterations));
+        }
+        if let Some(url) = &args.webhook {
+            if let Err(e) = run::post_webhook(url, &run::summary_json(&outcome, &stats)) {
+                eprintln!("warning: {:#}", e);
+            }
+        }
+    };
+    // Summarize, then exit with the outcome's code
+    let finish = |outcome: run::RunOutcome, iterations: u32| -> ! {
+        let code = outcome.exit_code();
+        summarize(outcome, iterations);
+        std::process::exit(code)
+    };
+    // The loop cannot go on; still summarize what ran
+    let fail = |reason: String, iterations: u32| -> ! {
+        eprintln!("error: {}", reason);
+        finish(run::RunOutcome::Failed(reason), iterations)
+    };
+    let interrupted_at_prompt = |iterations: u32| -> ! {
+        print_reverse_interrupt_summary(iterations);
+        finish(run::RunOutcome::Interrupted, iterations)
+    };
     let mut iterations_completed = 0u32;
+    // Iteration that introduced each hypothesis heading; None for headings
+    // written before this run
+    let mut hypothesis_origins: Vec<Option<u32>> =
+        fs::read_to_string(cwd.join(files::INVESTIGATION_FILE))
+            .map(|content| vec![None; reverse::hypothesis_headings(&content).len()])
+            .unwrap_or_default();
 
     for iteration in 1..=max_iterations {
-        run::print_iteration_header(iteration);
+        // --max-time is checked between iterations; a running claude is never cut off
+        if let Some(budget) = args.max_time.filter(|&budget| started.elapsed() >= budget) {
+            let reason = reverse::format_time_budget_exhausted(budget, iterations_completed);
+            if !json {
+                eprintln!("=== Investigation inconclusive ===");
+                eprintln!("Stopped: {}", reason);
+            }
+            finish(run::RunOutcome::Inconclusive(reason), iterations_completed);
+        }
+        if !json {
+            run::print_iteration_header(iteration, &run_id, header_project.as_deref());
+        }
+        run_lock
+            .set_iteration(iteration)
+            .unwrap_or_else(|e| fail(format!("{:#}", e), iterations_completed));
+        porcelain::emit(&porcelain::format_iteration_start(iteration));
 
         // Handle pause mode
-        if pause && run::prompt_continue()? == run::PauseAction::Stop {
-            println!("Stopped by user.");
-            return Ok(());
+        if pause {
+            match run::prompt_continue(&interrupt_flag)
+                .unwrap_or_else(|e| fail(format!("{:#}", e), iterations_completed))
+            {
+                run::PauseAction::Continue => {}
+                run::PauseAction::Stop => {
+                    note("Stopped by user.".to_string());
+                    finish(run::RunOutcome::Stopped, iterations_completed);
+                }
+                run::PauseAction::Interrupted => interrupted_at_prompt(iterations_completed),
+            }
         }
 
-        let result = run::spawn_claude(&prompt, model, Some(interrupt_flag.clone()))?;
+        if args.trace {
+            run::trace_command(&run::claude_argv(&spawn_opts), args.durable_log);
+        }
+        let result = match run::spawn_claude(&prompt, &spawn_opts) {
+            Ok(result) => result,
+            Err(e) => fail(format!("{:#}", e), iterations_completed),
+        };
 
         // Log iteration output to ralph.log
-        run::log_iteration(iteration, &result.stdout)?;
+        run::warn_on_log_error(run::log_iteration(
+            iteration,
+            log_seq.next(),
+            &run_id,
+            header_project.as_deref(),
+            &result.stdout,
+            args.durable_log,
+        ));
+        if args.split_logs {
+            run::write_iteration_log(&logs_dir, iteration, &result.stdout)
+                .unwrap_or_else(|e| fail(format!("{:#}", e), iterations_completed));
+        }
+        if args.write_investigation {
+            let entry = reverse::format_iteration_entry(
+                iteration,
+                &run_id,
+                &reverse::detect_reverse_signal(&result.stdout),
+                result.stdout.len(),
+            );
+            if let Err(e) = reverse::append_iteration_log(cwd, &entry) {
+                eprintln!("warning: {:#}", e);
+            }
+        }
 
         // Check if we were interrupted
         if result.was_interrupted {
-            print_reverse_interrupt_summary(iterations_completed);
-            std::process::exit(error::exit::INTERRUPTED);
+            interrupted_at_prompt(iterations_completed);
         }
 
         iterations_completed = iteration;
 
         if !result.success {
-            error::die(&format!(
-                "claude exited with code {}",
-                result.exit_code.unwrap_or(-1)
-            ));
+            fail(
+                format!("claude exited with code {}", result.exit_code.unwrap_or(-1)),
+                iterations_completed,
+            );
         }
 
+        warn_revisited_hypothesis(cwd, iteration, &mut hypothesis_origins);
+
         // Detect reverse mode signals (priority: BLOCKED → FOUND → INCONCLUSIVE → CONTINUE)
-        match reverse::detect_reverse_signal(&result.stdout) {
+        let signal = reverse::detect_reverse_signal(&result.stdout);
+        porcelain::emit(&porcelain::format_reverse_signal(&signal));
+        match signal {
             reverse::ReverseSignal::Blocked(reason) => {
-                eprintln!("blocked: {}", reason);
-                std::process::exit(error::exit::BLOCKED);
+                eprintln!("{}", run::format_blocked(&reason));
+                finish(run::RunOutcome::Blocked(reason), iterations_completed);
             }
             reverse::ReverseSignal::Found(summary) => {
-                println!("=== Investigation complete ===");
-                println!("Found: {}", summary);
-                println!();
-                println!(
-                    "Review FINDINGS.md for the complete answer with evidence and recommendations."
-                );
-                return Ok(());
+                if !json {
+                    println!("=== Investigation complete ===");
+                    println!("Found: {}", summary);
+                    println!();
+                    println!(
+                        "Review FINDINGS.md for the complete answer with evidence and recommendations."
+                    );
+                }
+                finish(run::RunOutcome::Found(summary), iterations_completed);
             }
             reverse::ReverseSignal::Inconclusive(reason) => {
-                eprintln!("=== Investigation inconclusive ===");
-                eprintln!("{}", reason);
-                eprintln!();
-                eprintln!("Review FINDINGS.md for details on what was explored and why it's inconclusive.");
-                std::process::exit(error::exit::INCONCLUSIVE);
+                if !json {
+                    eprintln!("=== Investigation inconclusive ===");
+                    eprintln!("{}", reason);
+                    eprintln!();
+                    eprintln!("Review FINDINGS.md for details on what was explored and why it's inconclusive.");
+                }
+                finish(run::RunOutcome::Inconclusive(reason), iterations_completed);
             }
             reverse::ReverseSignal::Continue => {
                 // Still investigating, continue to next iteration
             }
             reverse::ReverseSignal::NoSignal => {
                 // No signal detected, prompt user for action
-                if run::prompt_no_signal()? == run::NoSignalAction::Stop {
-                    println!("Stopped by user.");
-                    return Ok(());
+                match run::prompt_no_signal(&interrupt_flag)
+                    .unwrap_or_else(|e| fail(format!("{:#}", e), iterations_completed))
+                {
+                    run::NoSignalAction::Continue => {}
+                    run::NoSignalAction::Stop => {
+                        note("Stopped by user.".to_string());
+                        finish(run::RunOutcome::Stopped, iterations_completed);
+                    }
+                    run::NoSignalAction::Interrupted => interrupted_at_prompt(iterations_completed),
                 }
             }
         }
@@ -866,7 +2958,42 @@ async fn reverse_cmd(
         "warning: reached max iterations ({}) without finding an answer",
         max_iterations
     );
-    std::process::exit(error::exit::MAX_ITERATIONS);
+    finish(run::RunOutcome::MaxIterations, iterations_completed);
+}
+
+/// Warn when the newest hypothesis in INVESTIGATION.md repeats an earlier one.
+///
+/// Only headings added since the last check are considered; a revisit is
+/// also recorded under `## Dead Ends` so the next iteration sees it.
+fn warn_revisited_hypothesis(dir: &Path, iteration: u32, origins: &mut Vec<Option<u32>>) {
+    let Ok(content) = fs::read_to_string(dir.join(files::INVESTIGATION_FILE)) else {
+        return;
+    };
+    let headings = reverse::hypothesis_headings(&content);
+    if headings.len() <= origins.len() {
+        origins.truncate(headings.len());
+        return;
+    }
+    origins.resize(headings.len(), Some(iteration));
+
+    let Some(earlier) = reverse::find_revisited_hypothesis(&headings) else {
+        return;
+    };
+    let newest = &headings[headings.len() - 1];
+    let first_iteration = origins[earlier];
+    match first_iteration {
+        Some(n) => eprintln!(
+            "warning: hypothesis \"{}\" was already explored in iteration {}",
+            newest, n
+        ),
+        None => eprintln!(
+            "warning: hypothesis \"{}\" was already explored in an earlier run",
+            newest
+        ),
+    }
+    if let Err(e) = reverse::note_revisited_hypothesis(dir, newest, first_iteration) {
+        eprintln!("warning: {:#}", e);
+    }
 }
 
 /// Print interrupt summary for reverse mode.
@@ -1,10 +1,20 @@
 //! Markdown parsing utilities for ralphctl.
 //!
-//! Provides checkbox counting for IMPLEMENTATION_PLAN.md progress tracking.
-
-#![allow(dead_code)] // Used by status command (next task)
+//! Provides checkbox counting (overall and per section) for IMPLEMENTATION_PLAN.md
+//! progress tracking and `(after: ...)` ordering resolution for picking the
+//! next task.
 
 use regex::Regex;
+use serde::Serialize;
+
+/// Progress bar width when the terminal width is unknown.
+const DEFAULT_BAR_WIDTH: usize = 12;
+
+/// Narrowest bar `render_progress_bar_width` draws.
+const MIN_BAR_WIDTH: usize = 10;
+
+/// Widest bar `render_progress_bar_width` draws.
+const MAX_BAR_WIDTH: usize = 60;
 
 /// Result of parsing checkboxes from markdown content.
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -32,29 +42,171 @@ impl TaskCount {
     /// Render a Unicode progress bar with stats.
     ///
     /// Format: `[████████░░░░] 60% (12/20 tasks)`
-    pub fn render_progress_bar(&self) -> String {
-        const BAR_WIDTH: usize = 12;
-        const FILLED: char = '█';
-        const EMPTY: char = '░';
+    ///
+    /// With a terminal `width` the bar is scaled to it (see
+    /// `render_progress_bar_width`); with `None`, as for files or an unknown
+    /// width, the bar is a fixed 12 columns.
+    pub fn render_progress_bar(&self, width: Option<usize>) -> String {
+        match width {
+            Some(width) => self.render_progress_bar_width(width),
+            None => self.render_bar(DEFAULT_BAR_WIDTH),
+        }
+    }
+
+    /// Render the progress bar to fit a line of `width` columns.
+    ///
+    /// The bar fills whatever the stats text leaves, clamped to
+    /// 10..=60 columns, so very narrow terminals still wrap.
+    pub fn render_progress_bar_width(&self, width: usize) -> String {
+        // `[` + `]` + the stats text
+        let reserved = 2 + self.stats_text().chars().count();
+        let bar_width = width
+            .saturating_sub(reserved)
+            .clamp(MIN_BAR_WIDTH, MAX_BAR_WIDTH);
+        self.render_bar(bar_width)
+    }
 
-        let pct = self.percentage();
+    /// Text after the bar: ` 60% (12/20 tasks)`.
+    fn stats_text(&self) -> String {
+        format!(
+            " {}% ({}/{} tasks)",
+            self.percentage(),
+            self.completed,
+            self.total
+        )
+    }
+
+    /// Render a shields.io endpoint badge for `status --badge`.
+    ///
+    /// Format: `{"schemaVersion":1,"label":"tasks","message":"12/20 (60%)","color":"yellow"}`
+    ///
+    /// The color is red below 34%, yellow below 67%, and green from there.
+    pub fn render_badge_json(&self) -> String {
+        let percentage = self.percentage();
+        let badge = Badge {
+            schema_version: 1,
+            label: "tasks",
+            message: format!("{}/{} ({}%)", self.completed, self.total, percentage),
+            color: match percentage {
+                0..=33 => "red",
+                34..=66 => "yellow",
+                _ => "green",
+            },
+        };
+        serde_json::to_string(&badge).expect("badge serializes")
+    }
+
+    fn render_bar(&self, bar_width: usize) -> String {
+        #[allow(clippy::manual_checked_ops)]
         let filled_count = if self.total == 0 {
             0
         } else {
-            (self.completed * BAR_WIDTH) / self.total
+            (self.completed * bar_width) / self.total
         };
-        let empty_count = BAR_WIDTH - filled_count;
-
-        let filled: String = std::iter::repeat_n(FILLED, filled_count).collect();
-        let empty: String = std::iter::repeat_n(EMPTY, empty_count).collect();
-
         format!(
-            "[{}{}] {}% ({}/{} tasks)",
-            filled, empty, pct, self.completed, self.total
+            "{}{}",
+            bar_cells(filled_count, bar_width),
+            self.stats_text()
         )
     }
 }
 
+/// shields.io endpoint badge, fields in the order the schema documents them.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Badge {
+    schema_version: u8,
+    label: &'static str,
+    message: String,
+    color: &'static str,
+}
+
+impl std::ops::Add for TaskCount {
+    type Output = TaskCount;
+
+    /// Sum the counts of two plans, e.g. for `status --plan-glob`.
+    fn add(self, other: TaskCount) -> TaskCount {
+        TaskCount::new(self.completed + other.completed, self.total + other.total)
+    }
+}
+
+/// Sum the counts of several plans, e.g. the files matched by
+/// `status --plan-glob`.
+pub fn total_task_count(plans: &[(String, TaskCount)]) -> TaskCount {
+    plans
+        .iter()
+        .fold(TaskCount::new(0, 0), |sum, (_, count)| sum + count.clone())
+}
+
+/// Format one progress bar per plan for `status --plan-glob`, then their
+/// combined total.
+///
+/// Format: a `<path>  [████░░░░] 60% (3/5 tasks)` line per file and a last
+/// `Total` line, labels padded so the bars line up. With a terminal `width`
+/// every bar gets the same share of it (see `render_progress_bar_width`);
+/// with `None` the bars are the fixed 12 columns.
+pub fn format_plan_progress(plans: &[(String, TaskCount)], width: Option<usize>) -> String {
+    let total = total_task_count(plans);
+    let lines: Vec<(&str, &TaskCount)> = plans
+        .iter()
+        .map(|(path, count)| (path.as_str(), count))
+        .chain(std::iter::once(("Total", &total)))
+        .collect();
+    let label_width = lines
+        .iter()
+        .map(|(label, _)| label.chars().count())
+        .max()
+        .unwrap_or(0);
+    let bar_width = match width {
+        Some(width) => {
+            // The label, two spaces, `[` + `]`, and the longest stats text
+            let stats_width = lines
+                .iter()
+                .map(|(_, count)| count.stats_text().chars().count())
+                .max()
+                .unwrap_or(0);
+            width
+                .saturating_sub(label_width + 4 + stats_width)
+                .clamp(MIN_BAR_WIDTH, MAX_BAR_WIDTH)
+        }
+        None => DEFAULT_BAR_WIDTH,
+    };
+    lines
+        .iter()
+        .map(|(label, count)| {
+            format!(
+                "{:<label_width$}  {}\n",
+                label,
+                count.render_bar(bar_width),
+                label_width = label_width
+            )
+        })
+        .collect()
+}
+
+/// Draw `[████░░░░]` with `filled_count` of `bar_width` cells filled.
+fn bar_cells(filled_count: usize, bar_width: usize) -> String {
+    const FILLED: char = '█';
+    const EMPTY: char = '░';
+
+    let filled: String = std::iter::repeat_n(FILLED, filled_count).collect();
+    let empty: String = std::iter::repeat_n(EMPTY, bar_width - filled_count).collect();
+    format!("[{}{}]", filled, empty)
+}
+
+/// Render a progress bar for a percentage the agent reported itself.
+///
+/// Format: `[██████░░░░░░] 50% (reported)`
+pub fn render_reported_progress(percent: u8) -> String {
+    let percent = percent.min(100);
+    let filled_count = usize::from(percent) * DEFAULT_BAR_WIDTH / 100;
+    format!(
+        "{} {}% (reported)",
+        bar_cells(filled_count, DEFAULT_BAR_WIDTH),
+        percent
+    )
+}
+
 /// Count completed and total checkboxes in markdown content.
 ///
 /// Matches standard markdown checkbox syntax:
@@ -85,10 +237,514 @@ pub fn count_checkboxes(content: &str) -> TaskCount {
     TaskCount::new(completed, total)
 }
 
+/// How tasks are written in a plan file (`status --plan-format`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum PlanFormat {
+    /// GitHub-style `- [ ]` / `- [x]` checkboxes
+    #[default]
+    Markdown,
+    /// Org-mode `* TODO` / `* DONE` headlines
+    Org,
+}
+
+impl PlanFormat {
+    /// Count tasks in `content` written in this format.
+    pub fn count_tasks(self, content: &str) -> TaskCount {
+        match self {
+            PlanFormat::Markdown => count_checkboxes(content),
+            PlanFormat::Org => count_org_tasks(content),
+        }
+    }
+}
+
+/// Count completed and total tasks in org-mode content.
+///
+/// A task is a headline at any level whose keyword is `TODO` (incomplete) or
+/// `DONE` (complete), e.g. `* TODO Write parser` or `** DONE Add tests`.
+/// Headlines without a keyword and other keywords are not tasks.
+pub fn count_org_tasks(content: &str) -> TaskCount {
+    let headline_re = Regex::new(r"(?m)^\*+[ \t]+(TODO|DONE)(?:[ \t]|$)").unwrap();
+
+    let mut completed = 0;
+    let mut total = 0;
+
+    for cap in headline_re.captures_iter(content) {
+        total += 1;
+        if &cap[1] == "DONE" {
+            completed += 1;
+        }
+    }
+
+    TaskCount::new(completed, total)
+}
+
+/// Checkbox counts for one markdown section of the plan.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SectionCount {
+    /// Heading text without the leading `#`s (empty for tasks before any heading)
+    pub heading: String,
+    /// Checkbox counts under this heading
+    pub count: TaskCount,
+}
+
+/// A markdown section: its heading line and the lines under it.
+struct Section<'a> {
+    /// Heading line (`None` for lines before any heading)
+    heading: Option<&'a str>,
+    /// Lines up to the next heading
+    body: Vec<&'a str>,
+}
+
+/// Split `content` into sections at every heading (`#` through `######`).
+///
+/// The first section holds the lines before any heading and is always present.
+fn split_sections(content: &str) -> Vec<Section<'_>> {
+    let heading_re = Regex::new(r"^#{1,6}\s+").unwrap();
+
+    let mut sections = vec![Section {
+        heading: None,
+        body: Vec::new(),
+    }];
+    for line in content.lines() {
+        if heading_re.is_match(line) {
+            sections.push(Section {
+                heading: Some(line),
+                body: Vec::new(),
+            });
+        } else if let Some(section) = sections.last_mut() {
+            section.body.push(line);
+        }
+    }
+    sections
+}
+
+/// Count checkboxes per markdown section, in file order.
+///
+/// Every heading (`#` through `######`) starts a new section. Sections with
+/// no checkboxes are omitted.
+pub fn count_checkboxes_by_section(content: &str) -> Vec<SectionCount> {
+    let heading_re = Regex::new(r"^#{1,6}\s+(.*?)\s*$").unwrap();
+
+    split_sections(content)
+        .into_iter()
+        .filter_map(|section| {
+            let count = count_checkboxes(&section.body.join("\n"));
+            let heading = section
+                .heading
+                .and_then(|line| heading_re.captures(line))
+                .map(|cap| cap[1].to_string())
+                .unwrap_or_default();
+            (count.total > 0).then_some(SectionCount { heading, count })
+        })
+        .collect()
+}
+
+/// Move incomplete tasks above completed ones within each section.
+///
+/// A task is a top-level `- [ ]`/`- [x]` line plus the indented lines right
+/// below it. Tasks keep their relative order within each group, and every
+/// other line (prose, blank lines, headings) keeps its position. Each line
+/// keeps its own `\n` or `\r\n` ending. Already ordered content is returned
+/// unchanged.
+pub fn reorder_pending_first(content: &str) -> String {
+    let task_re = Regex::new(r"^-\s*\[([ xX])\]").unwrap();
+
+    /// A slot in a section body: a line that stays put, or a movable task.
+    enum Item<'a> {
+        Fixed(&'a str),
+        Task,
+    }
+
+    let mut out: Vec<&str> = Vec::new();
+    for section in split_sections(content) {
+        out.extend(section.heading);
+
+        let mut items: Vec<Item> = Vec::new();
+        let mut pending: Vec<Vec<&str>> = Vec::new();
+        let mut completed: Vec<Vec<&str>> = Vec::new();
+        // Index into `pending`/`completed` of the task that indented lines attach to
+        let mut current: Option<(bool, usize)> = None;
+        for line in section.body {
+            if let Some(cap) = task_re.captures(line) {
+                let done = &cap[1] != " ";
+                let group = if done { &mut completed } else { &mut pending };
+                group.push(vec![line]);
+                current = Some((done, group.len() - 1));
+                items.push(Item::Task);
+                continue;
+            }
+            let continues_task = line.starts_with(char::is_whitespace) && !line.trim().is_empty();
+            match current {
+                Some((done, idx)) if continues_task => {
+                    let group = if done { &mut completed } else { &mut pending };
+                    group[idx].push(line);
+                }
+                _ => {
+                    current = None;
+                    items.push(Item::Fixed(line));
+                }
+            }
+        }
+
+        let mut tasks = pending.into_iter().chain(completed);
+        for item in items {
+            match item {
+                Item::Fixed(line) => out.push(line),
+                Item::Task => out.extend(tasks.next().unwrap_or_default()),
+            }
+        }
+    }
+
+    // An unterminated last line moved up takes the file's usual ending
+    let usual = if content.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+    let ending = |line: &str| {
+        let end = line.as_ptr() as usize - content.as_ptr() as usize + line.len();
+        let rest = &content[end..];
+        if rest.starts_with("\r\n") {
+            "\r\n"
+        } else if rest.starts_with('\n') {
+            "\n"
+        } else {
+            usual
+        }
+    };
+    let mut result = String::with_capacity(content.len() + usual.len());
+    for line in &out {
+        result.push_str(line);
+        result.push_str(ending(line));
+    }
+    if !content.ends_with('\n') {
+        if let Some(last) = out.last() {
+            result.truncate(result.len() - ending(last).len());
+        }
+    }
+    result
+}
+
+/// Heading of the first section that still has incomplete tasks.
+pub fn active_section(content: &str) -> Option<String> {
+    count_checkboxes_by_section(content)
+        .into_iter()
+        .find(|s| s.count.completed < s.count.total)
+        .map(|s| s.heading)
+}
+
+/// A single checkbox task parsed from the plan.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Task {
+    /// Task text with the checkbox and any `(after: ...)` annotation removed
+    pub text: String,
+    /// Whether the checkbox is checked
+    pub completed: bool,
+    /// Substring from a trailing `(after: <substring>)` annotation
+    pub after: Option<String>,
+}
+
+/// Parse every checkbox line into a [`Task`], in file order.
+///
+/// A task line may end with `(after: <substring>)` to declare that it should
+/// not be offered as next until a completed task containing `<substring>`
+/// exists. The annotation does not affect [`count_checkboxes`].
+pub fn parse_tasks(content: &str) -> Vec<Task> {
+    let task_re = Regex::new(r"(?m)^\s*-\s*\[([ xX])\](.*)$").unwrap();
+    let after_re = Regex::new(r"\(after:\s*([^)]*?)\s*\)\s*$").unwrap();
+
+    task_re
+        .captures_iter(content)
+        .map(|cap| {
+            let completed = &cap[1] != " ";
+            let rest = cap[2].trim();
+            let (text, after) = match after_re.captures(rest) {
+                Some(a) if !a[1].is_empty() => (
+                    rest[..a.get(0).unwrap().start()].trim().to_string(),
+                    Some(a[1].to_string()),
+                ),
+                _ => (rest.to_string(), None),
+            };
+            Task {
+                text,
+                completed,
+                after,
+            }
+        })
+        .collect()
+}
+
+/// The task to offer next, plus warnings about annotations that were ignored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NextTask<'a> {
+    /// First unchecked task whose dependency (if any) is satisfied
+    pub task: Option<&'a Task>,
+    /// One warning per unresolved reference or dependency cycle
+    pub warnings: Vec<String>,
+}
+
+/// State of a task's `(after: ...)` dependency.
+enum Dependency {
+    /// No annotation, or a completed task matches the substring
+    Satisfied,
+    /// No other task matches the substring
+    Missing,
+    /// Waiting on the unchecked task at this index
+    Pending(usize),
+}
+
+fn dependency(tasks: &[Task], idx: usize) -> Dependency {
+    let Some(after) = &tasks[idx].after else {
+        return Dependency::Satisfied;
+    };
+    let mut pending = None;
+    for (i, task) in tasks.iter().enumerate() {
+        if i == idx || !task.text.contains(after.as_str()) {
+            continue;
+        }
+        if task.completed {
+            return Dependency::Satisfied;
+        }
+        pending.get_or_insert(i);
+    }
+    pending.map_or(Dependency::Missing, Dependency::Pending)
+}
+
+/// Task-level changes between two versions of a plan (`plan diff`).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PlanDiff {
+    /// Unchecked before, checked now
+    pub completed: Vec<String>,
+    /// Checked before, unchecked now
+    pub reopened: Vec<String>,
+    /// Only in the new plan
+    pub added: Vec<String>,
+    /// Only in the old plan
+    pub removed: Vec<String>,
+}
+
+impl PlanDiff {
+    /// Whether no task changed.
+    pub fn is_empty(&self) -> bool {
+        self.completed.is_empty()
+            && self.reopened.is_empty()
+            && self.added.is_empty()
+            && self.removed.is_empty()
+    }
+}
+
+/// Compare the tasks of an old and a new plan.
+///
+/// Tasks are matched by text in file order; duplicate texts pair up one to
+/// one, so a task that appears twice counts twice. A reworded task shows up
+/// as removed plus added.
+pub fn diff_tasks(old: &[Task], new: &[Task]) -> PlanDiff {
+    let mut unmatched: Vec<&Task> = old.iter().collect();
+    let mut diff = PlanDiff::default();
+    for task in new {
+        match unmatched.iter().position(|t| t.text == task.text) {
+            Some(i) => {
+                let before = unmatched.remove(i);
+                match (before.completed, task.completed) {
+                    (false, true) => diff.completed.push(task.text.clone()),
+                    (true, false) => diff.reopened.push(task.text.clone()),
+                    _ => {}
+                }
+            }
+            None => diff.added.push(task.text.clone()),
+        }
+    }
+    diff.removed = unmatched.into_iter().map(|t| t.text.clone()).collect();
+    diff
+}
+
+/// Format a [`PlanDiff`] as one section per kind of change.
+///
+/// Format: `Completed (2):` followed by `  [x] <task>` lines; reopened,
+/// added, and removed tasks are marked `[ ]`, `+`, and `-`. Empty sections
+/// are left out.
+pub fn format_plan_diff(diff: &PlanDiff) -> String {
+    let mut out = String::new();
+    for (title, marker, tasks) in [
+        ("Completed", "[x]", &diff.completed),
+        ("Reopened", "[ ]", &diff.reopened),
+        ("Added", "+", &diff.added),
+        ("Removed", "-", &diff.removed),
+    ] {
+        if tasks.is_empty() {
+            continue;
+        }
+        out.push_str(&format!("{} ({}):\n", title, tasks.len()));
+        for task in tasks {
+            out.push_str(&format!("  {} {}\n", marker, task));
+        }
+    }
+    out
+}
+
+/// Pick the first unchecked task that is not waiting on a dependency.
+///
+/// Tasks whose `(after: ...)` reference matches no other task are offered
+/// as if unannotated. Dependency cycles are detected and broken at the first
+/// task in the cycle. Each problem produces a single warning.
+pub fn next_task(tasks: &[Task]) -> NextTask<'_> {
+    let mut warnings: Vec<String> = Vec::new();
+    let mut warn = |msg: String| {
+        if !warnings.contains(&msg) {
+            warnings.push(msg);
+        }
+    };
+
+    for (idx, task) in tasks.iter().enumerate() {
+        if task.completed {
+            continue;
+        }
+        let mut current = match dependency(tasks, idx) {
+            Dependency::Satisfied => {
+                return NextTask {
+                    task: Some(task),
+                    warnings,
+                }
+            }
+            Dependency::Missing => {
+                warn(format!(
+                    "no task matches (after: {}) on '{}'; ignoring",
+                    task.after.as_deref().unwrap_or_default(),
+                    task.text
+                ));
+                return NextTask {
+                    task: Some(task),
+                    warnings,
+                };
+            }
+            Dependency::Pending(dep) => dep,
+        };
+
+        // Follow the chain of pending dependencies to detect cycles
+        let mut visited = vec![idx];
+        loop {
+            if current == idx {
+                warn(format!(
+                    "dependency cycle through '{}'; ignoring its (after: ...)",
+                    task.text
+                ));
+                return NextTask {
+                    task: Some(task),
+                    warnings,
+                };
+            }
+            if visited.contains(&current) {
+                // Waiting on a cycle that doesn't include this task
+                break;
+            }
+            visited.push(current);
+            match dependency(tasks, current) {
+                Dependency::Pending(next) => current = next,
+                _ => break,
+            }
+        }
+    }
+
+    NextTask {
+        task: None,
+        warnings,
+    }
+}
+
+/// A line that looks like a checkbox but is not counted as a task.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MalformedCheckbox {
+    /// 1-based line number
+    pub line: usize,
+    /// The checkbox-like prefix, e.g. `- []` or `* [ ]`
+    pub marker: String,
+}
+
+/// Find checkbox-like lines that [`count_checkboxes`] silently skips.
+///
+/// Detection is deliberately looser than counting: any list bullet (`-`,
+/// `*`, `+`, or `1.`) followed by a short bracket (up to three characters)
+/// is a candidate, and candidates the strict pattern does not accept are
+/// reported. Markdown links such as `- [a](url)` are not candidates.
+pub fn find_malformed_checkboxes(content: &str) -> Vec<MalformedCheckbox> {
+    let strict_re = Regex::new(r"^\s*-\s*\[([ xX])\]").unwrap();
+    let loose_re = Regex::new(r"^\s*(?:[-*+]|\d+[.)])\s*\[[^\]]{0,3}\]").unwrap();
+
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !strict_re.is_match(line))
+        .filter_map(|(i, line)| {
+            let m = loose_re.find(line)?;
+            if line[m.end()..].starts_with('(') {
+                return None;
+            }
+            Some(MalformedCheckbox {
+                line: i + 1,
+                marker: m.as_str().trim_start().to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Format a [`MalformedCheckbox`] as a `plan validate` warning line.
+pub fn format_malformed_checkbox(found: &MalformedCheckbox) -> String {
+    format!(
+        "line {}: '{}' looks like a checkbox but won't be counted",
+        found.line, found.marker
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_find_malformed_checkboxes() {
+        let plan = "# Plan\n\
+                    - [ ] ok\n\
+                    - [x] done\n\
+                    - [] no space\n\
+                    - [y] wrong mark\n\
+                    \x20 * [ ] star bullet\n\
+                    1. [x] numbered\n\
+                    - [  ] two spaces\n\
+                    - [docs](https://example.com) a link\n\
+                    - plain item\n";
+        let found = find_malformed_checkboxes(plan);
+        let summary: Vec<(usize, &str)> =
+            found.iter().map(|f| (f.line, f.marker.as_str())).collect();
+        assert_eq!(
+            summary,
+            vec![
+                (4, "- []"),
+                (5, "- [y]"),
+                (6, "* [ ]"),
+                (7, "1. [x]"),
+                (8, "- [  ]"),
+            ]
+        );
+        // Everything reported is really skipped by the counter
+        assert_eq!(count_checkboxes(plan).total, 2);
+    }
+
+    #[test]
+    fn test_find_malformed_checkboxes_clean_plan() {
+        assert!(find_malformed_checkboxes("- [ ] A\n  - [X] B\n-[ ] C\n").is_empty());
+    }
+
+    #[test]
+    fn test_format_malformed_checkbox() {
+        let found = MalformedCheckbox {
+            line: 12,
+            marker: "- []".to_string(),
+        };
+        assert_eq!(
+            format_malformed_checkbox(&found),
+            "line 12: '- []' looks like a checkbox but won't be counted"
+        );
+    }
+
     #[test]
     fn test_empty_content() {
         let count = count_checkboxes("");
@@ -175,6 +831,23 @@ Some other text here.
         assert_eq!(count.percentage(), 67);
     }
 
+    #[test]
+    fn test_render_badge_json() {
+        assert_eq!(
+            TaskCount::new(12, 20).render_badge_json(),
+            r#"{"schemaVersion":1,"label":"tasks","message":"12/20 (60%)","color":"yellow"}"#
+        );
+        assert!(TaskCount::new(1, 3)
+            .render_badge_json()
+            .contains(r#""color":"red""#));
+        assert!(TaskCount::new(2, 3)
+            .render_badge_json()
+            .contains(r#""color":"green""#));
+        assert!(TaskCount::new(0, 0)
+            .render_badge_json()
+            .contains(r#""message":"0/0 (0%)","color":"red""#));
+    }
+
     #[test]
     fn test_checkbox_not_at_line_start_ignored() {
         // Checkboxes embedded in text (not at line start) should still match
@@ -213,14 +886,17 @@ Some other text here.
     #[test]
     fn test_progress_bar_empty() {
         let count = TaskCount::new(0, 0);
-        assert_eq!(count.render_progress_bar(), "[░░░░░░░░░░░░] 0% (0/0 tasks)");
+        assert_eq!(
+            count.render_progress_bar(None),
+            "[░░░░░░░░░░░░] 0% (0/0 tasks)"
+        );
     }
 
     #[test]
     fn test_progress_bar_zero_percent() {
         let count = TaskCount::new(0, 10);
         assert_eq!(
-            count.render_progress_bar(),
+            count.render_progress_bar(None),
             "[░░░░░░░░░░░░] 0% (0/10 tasks)"
         );
     }
@@ -229,7 +905,7 @@ Some other text here.
     fn test_progress_bar_half() {
         let count = TaskCount::new(6, 12);
         assert_eq!(
-            count.render_progress_bar(),
+            count.render_progress_bar(None),
             "[██████░░░░░░] 50% (6/12 tasks)"
         );
     }
@@ -238,7 +914,7 @@ Some other text here.
     fn test_progress_bar_full() {
         let count = TaskCount::new(20, 20);
         assert_eq!(
-            count.render_progress_bar(),
+            count.render_progress_bar(None),
             "[████████████] 100% (20/20 tasks)"
         );
     }
@@ -247,11 +923,115 @@ Some other text here.
     fn test_progress_bar_60_percent() {
         let count = TaskCount::new(12, 20);
         assert_eq!(
-            count.render_progress_bar(),
+            count.render_progress_bar(None),
             "[███████░░░░░] 60% (12/20 tasks)"
         );
     }
 
+    #[test]
+    fn test_task_count_add() {
+        assert_eq!(
+            TaskCount::new(1, 4) + TaskCount::new(2, 2),
+            TaskCount::new(3, 6)
+        );
+        assert_eq!(
+            TaskCount::new(0, 0) + TaskCount::new(0, 0),
+            TaskCount::new(0, 0)
+        );
+    }
+
+    #[test]
+    fn test_total_task_count() {
+        let plans = vec![
+            ("plans/api.md".to_string(), TaskCount::new(2, 3)),
+            ("plans/ui.md".to_string(), TaskCount::new(0, 1)),
+            ("plans/empty.md".to_string(), TaskCount::new(0, 0)),
+        ];
+        assert_eq!(total_task_count(&plans), TaskCount::new(2, 4));
+        assert_eq!(total_task_count(&[]), TaskCount::new(0, 0));
+    }
+
+    #[test]
+    fn test_format_plan_progress() {
+        let plans = vec![
+            ("plans/api.md".to_string(), TaskCount::new(3, 5)),
+            ("plans/ui.md".to_string(), TaskCount::new(0, 0)),
+        ];
+        assert_eq!(
+            format_plan_progress(&plans, None),
+            "plans/api.md  [███████░░░░░] 60% (3/5 tasks)\n\
+             plans/ui.md   [░░░░░░░░░░░░] 0% (0/0 tasks)\n\
+             Total         [███████░░░░░] 60% (3/5 tasks)\n"
+        );
+    }
+
+    #[test]
+    fn test_format_plan_progress_shares_terminal_width() {
+        let plans = vec![
+            ("a.md".to_string(), TaskCount::new(1, 2)),
+            ("b.md".to_string(), TaskCount::new(10, 10)),
+        ];
+        // 50 columns - 5 label - 2 spaces - 2 brackets - 19 longest stats = 22
+        let output = format_plan_progress(&plans, Some(50));
+        let bars: Vec<usize> = output
+            .lines()
+            .map(|line| line.split(['[', ']']).nth(1).unwrap().chars().count())
+            .collect();
+        assert_eq!(bars, vec![22, 22, 22]);
+        assert!(output.ends_with(&format!(
+            "Total  [{}{}] 92% (11/12 tasks)\n",
+            "█".repeat(20),
+            "░".repeat(2)
+        )));
+    }
+
+    #[test]
+    fn test_render_reported_progress() {
+        assert_eq!(render_reported_progress(0), "[░░░░░░░░░░░░] 0% (reported)");
+        assert_eq!(
+            render_reported_progress(50),
+            "[██████░░░░░░] 50% (reported)"
+        );
+        assert_eq!(
+            render_reported_progress(100),
+            "[████████████] 100% (reported)"
+        );
+    }
+
+    #[test]
+    fn test_progress_bar_width_fills_line() {
+        let count = TaskCount::new(12, 20);
+        // 40 columns - 2 brackets - 18 stats = 20-column bar
+        let bar = count.render_progress_bar(Some(40));
+        assert_eq!(bar, "[████████████░░░░░░░░] 60% (12/20 tasks)");
+        assert_eq!(bar.chars().count(), 40);
+    }
+
+    #[test]
+    fn test_progress_bar_width_clamps_narrow() {
+        let count = TaskCount::new(1, 2);
+        let bar = count.render_progress_bar_width(5);
+        assert_eq!(bar, "[█████░░░░░] 50% (1/2 tasks)");
+        assert_eq!(count.render_progress_bar_width(0), bar);
+    }
+
+    #[test]
+    fn test_progress_bar_width_clamps_wide() {
+        let count = TaskCount::new(1, 4);
+        let bar = count.render_progress_bar_width(500);
+        assert!(bar.starts_with(&format!("[{}{}]", "█".repeat(15), "░".repeat(45))));
+        assert!(bar.ends_with("] 25% (1/4 tasks)"));
+    }
+
+    #[test]
+    fn test_progress_bar_width_empty_plan() {
+        let count = TaskCount::new(0, 0);
+        assert_eq!(
+            count.render_progress_bar_width(30),
+            "[░░░░░░░░░░░░░] 0% (0/0 tasks)"
+        );
+    }
+
     // === Edge Case Tests ===
 
     #[test]
@@ -438,11 +1218,14 @@ Some other text here.
     #[test]
     fn test_progress_bar_single_task() {
         let count = TaskCount::new(0, 1);
-        assert_eq!(count.render_progress_bar(), "[░░░░░░░░░░░░] 0% (0/1 tasks)");
+        assert_eq!(
+            count.render_progress_bar(None),
+            "[░░░░░░░░░░░░] 0% (0/1 tasks)"
+        );
 
         let count = TaskCount::new(1, 1);
         assert_eq!(
-            count.render_progress_bar(),
+            count.render_progress_bar(None),
             "[████████████] 100% (1/1 tasks)"
         );
     }
@@ -453,8 +1236,263 @@ Some other text here.
         let count = TaskCount::new(7, 13);
         // 7 * 12 / 13 = 84 / 13 = 6.46 -> 6 filled blocks
         assert_eq!(
-            count.render_progress_bar(),
+            count.render_progress_bar(None),
             "[██████░░░░░░] 54% (7/13 tasks)"
         );
     }
+
+    #[test]
+    fn test_diff_tasks() {
+        let old = parse_tasks(
+            "- [x] Setup\n- [ ] Parser\n- [ ] Docs\n- [x] Lint\n- [ ] Old idea\n- [ ] Test\n- [ ] Test\n",
+        );
+        let new = parse_tasks(
+            "- [x] Setup\n- [x] Parser\n- [ ] Docs\n- [ ] Lint\n- [ ] Deploy\n- [x] Test\n- [ ] Test\n",
+        );
+        assert_eq!(
+            diff_tasks(&old, &new),
+            PlanDiff {
+                completed: vec!["Parser".to_string(), "Test".to_string()],
+                reopened: vec!["Lint".to_string()],
+                added: vec!["Deploy".to_string()],
+                removed: vec!["Old idea".to_string()],
+            }
+        );
+        assert!(diff_tasks(&old, &old).is_empty());
+    }
+
+    #[test]
+    fn test_format_plan_diff() {
+        let diff = PlanDiff {
+            completed: vec!["Parser".to_string(), "Tests".to_string()],
+            reopened: vec![],
+            added: vec!["Deploy".to_string()],
+            removed: vec!["Old idea".to_string()],
+        };
+        assert_eq!(
+            format_plan_diff(&diff),
+            "Completed (2):\n  [x] Parser\n  [x] Tests\n\
+             Added (1):\n  + Deploy\n\
+             Removed (1):\n  - Old idea\n"
+        );
+        assert_eq!(format_plan_diff(&PlanDiff::default()), "");
+    }
+
+    #[test]
+    fn test_parse_tasks_strips_after_annotation() {
+        let tasks = parse_tasks("- [x] Add schema\n- [ ] Wire API (after: schema)\n");
+        assert_eq!(tasks.len(), 2);
+        assert!(tasks[0].completed);
+        assert_eq!(tasks[0].after, None);
+        assert_eq!(tasks[1].text, "Wire API");
+        assert_eq!(tasks[1].after.as_deref(), Some("schema"));
+    }
+
+    #[test]
+    fn test_after_annotation_does_not_change_counts() {
+        let content = "- [ ] A (after: B)\n- [x] B\n- [ ] C (after: nothing)\n";
+        assert_eq!(count_checkboxes(content), TaskCount::new(1, 3));
+    }
+
+    #[test]
+    fn test_next_task_without_annotations_is_first_unchecked() {
+        let tasks = parse_tasks("- [x] One\n- [ ] Two\n- [ ] Three\n");
+        let next = next_task(&tasks);
+        assert_eq!(next.task.unwrap().text, "Two");
+        assert!(next.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_next_task_skips_until_dependency_checked() {
+        let content = "- [ ] Deploy (after: Build image)\n- [ ] Build image\n";
+        let tasks = parse_tasks(content);
+        assert_eq!(next_task(&tasks).task.unwrap().text, "Build image");
+
+        let content = "- [ ] Deploy (after: Build image)\n- [x] Build image\n";
+        let tasks = parse_tasks(content);
+        assert_eq!(next_task(&tasks).task.unwrap().text, "Deploy");
+    }
+
+    #[test]
+    fn test_next_task_chain_of_dependencies() {
+        let content = "- [ ] C (after: task B)\n- [ ] task B (after: task A)\n- [ ] task A\n";
+        let tasks = parse_tasks(content);
+        let next = next_task(&tasks);
+        assert_eq!(next.task.unwrap().text, "task A");
+        assert!(next.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_next_task_unknown_reference_is_ignored_with_warning() {
+        let tasks = parse_tasks("- [ ] Ship it (after: nonexistent)\n- [ ] Other\n");
+        let next = next_task(&tasks);
+        assert_eq!(next.task.unwrap().text, "Ship it");
+        assert_eq!(next.warnings.len(), 1);
+        assert!(next.warnings[0].contains("nonexistent"));
+    }
+
+    #[test]
+    fn test_next_task_detects_cycle() {
+        let tasks = parse_tasks("- [ ] Alpha (after: Beta)\n- [ ] Beta (after: Alpha)\n");
+        let next = next_task(&tasks);
+        assert_eq!(next.task.unwrap().text, "Alpha");
+        assert_eq!(next.warnings.len(), 1);
+        assert!(next.warnings[0].contains("cycle"));
+    }
+
+    #[test]
+    fn test_next_task_waiting_on_cycle_elsewhere() {
+        let content =
+            "- [ ] Gamma (after: Alpha)\n- [ ] Alpha (after: Beta)\n- [ ] Beta (after: Alpha)\n";
+        let tasks = parse_tasks(content);
+        let next = next_task(&tasks);
+        assert_eq!(next.task.unwrap().text, "Alpha");
+        assert_eq!(next.warnings.len(), 1);
+    }
+
+    #[test]
+    fn test_next_task_self_reference_is_unknown() {
+        let tasks = parse_tasks("- [ ] Loop forever (after: Loop forever)\n");
+        let next = next_task(&tasks);
+        assert_eq!(next.task.unwrap().text, "Loop forever");
+        assert_eq!(next.warnings.len(), 1);
+    }
+
+    #[test]
+    fn test_next_task_all_complete() {
+        let tasks = parse_tasks("- [x] Done (after: Other)\n- [x] Other\n");
+        let next = next_task(&tasks);
+        assert!(next.task.is_none());
+        assert!(next.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_count_org_tasks() {
+        let content = "#+TITLE: Plan\n\
+                       * Phase 1\n\
+                       ** DONE Set up project\n\
+                       ** TODO Write parser\n\
+                       *** TODO Handle nested headlines\n\
+                       * DONE Phase 0\n\
+                       Some prose mentioning TODO and DONE.\n\
+                       * TODOS are not a keyword\n\
+                       - [ ] markdown checkboxes are ignored\n";
+        assert_eq!(count_org_tasks(content), TaskCount::new(2, 4));
+    }
+
+    #[test]
+    fn test_count_org_tasks_keyword_only_headline() {
+        assert_eq!(count_org_tasks("* TODO\n* DONE\n"), TaskCount::new(1, 2));
+        assert_eq!(count_org_tasks("No tasks here\n"), TaskCount::new(0, 0));
+    }
+
+    #[test]
+    fn test_plan_format_count_tasks() {
+        let markdown = "- [x] One\n- [ ] Two\n";
+        let org = "* DONE One\n* TODO Two\n* TODO Three\n";
+        assert_eq!(
+            PlanFormat::Markdown.count_tasks(markdown),
+            TaskCount::new(1, 2)
+        );
+        assert_eq!(PlanFormat::Markdown.count_tasks(org), TaskCount::new(0, 0));
+        assert_eq!(PlanFormat::Org.count_tasks(org), TaskCount::new(1, 3));
+    }
+
+    #[test]
+    fn test_count_checkboxes_by_section() {
+        let content = "# Plan\n\n## Phase 1: Setup\n- [x] A\n- [x] B\n\n## Notes\nNo tasks.\n\n## Phase 2: Core\n- [x] C\n- [ ] D\n";
+        let sections = count_checkboxes_by_section(content);
+        assert_eq!(
+            sections,
+            vec![
+                SectionCount {
+                    heading: "Phase 1: Setup".to_string(),
+                    count: TaskCount::new(2, 2),
+                },
+                SectionCount {
+                    heading: "Phase 2: Core".to_string(),
+                    count: TaskCount::new(1, 2),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_count_checkboxes_by_section_tasks_before_heading() {
+        let sections = count_checkboxes_by_section("- [ ] Loose task\n# Later\n- [x] Done\n");
+        assert_eq!(sections[0].heading, "");
+        assert_eq!(sections[0].count, TaskCount::new(0, 1));
+        assert_eq!(sections[1].heading, "Later");
+    }
+
+    #[test]
+    fn test_reorder_pending_first_mixed_plan() {
+        let content = "# Plan\n\n## Phase 1\n- [x] A\n- [ ] B\n- [x] C\n- [ ] D\n\nNotes stay here.\n\n## Phase 2\n- [x] E\n- [ ] F\n";
+        assert_eq!(
+            reorder_pending_first(content),
+            "# Plan\n\n## Phase 1\n- [ ] B\n- [ ] D\n- [x] A\n- [x] C\n\nNotes stay here.\n\n## Phase 2\n- [ ] F\n- [x] E\n"
+        );
+    }
+
+    #[test]
+    fn test_reorder_pending_first_keeps_prose_positions() {
+        let content = "## Phase\n- [x] A\nInterlude\n- [ ] B\n";
+        assert_eq!(
+            reorder_pending_first(content),
+            "## Phase\n- [ ] B\nInterlude\n- [x] A\n"
+        );
+    }
+
+    #[test]
+    fn test_reorder_pending_first_moves_indented_lines_with_task() {
+        let content = "## Phase\n- [X] A\n  details of A\n  - [ ] subtask of A\n- [ ] B\n";
+        assert_eq!(
+            reorder_pending_first(content),
+            "## Phase\n- [ ] B\n- [X] A\n  details of A\n  - [ ] subtask of A\n"
+        );
+    }
+
+    #[test]
+    fn test_reorder_pending_first_stays_within_sections() {
+        let content = "- [x] Loose\n## Phase 1\n- [x] A\n## Phase 2\n- [ ] B\n";
+        assert_eq!(reorder_pending_first(content), content);
+    }
+
+    #[test]
+    fn test_reorder_pending_first_is_idempotent() {
+        let content = "# Plan\n## P\n- [x] A\n- [ ] B\n- [x] C\n";
+        let once = reorder_pending_first(content);
+        assert_ne!(once, content);
+        assert_eq!(reorder_pending_first(&once), once);
+
+        // No trailing newline is preserved as-is
+        assert_eq!(
+            reorder_pending_first("- [ ] A\n- [x] B"),
+            "- [ ] A\n- [x] B"
+        );
+        assert_eq!(reorder_pending_first(""), "");
+    }
+
+    #[test]
+    fn test_reorder_pending_first_keeps_line_endings() {
+        let content = "## Phase\r\n- [x] A\r\n  details of A\r\n- [ ] B\r\n";
+        assert_eq!(
+            reorder_pending_first(content),
+            "## Phase\r\n- [ ] B\r\n- [x] A\r\n  details of A\r\n"
+        );
+
+        // A moved unterminated last line takes the file's ending
+        assert_eq!(
+            reorder_pending_first("- [x] A\r\n- [ ] B"),
+            "- [ ] B\r\n- [x] A"
+        );
+    }
+
+    #[test]
+    fn test_active_section() {
+        let content = "## Phase 1\n- [x] A\n## Phase 2\n- [ ] B\n## Phase 3\n- [ ] C\n";
+        assert_eq!(active_section(content).as_deref(), Some("Phase 2"));
+        assert_eq!(active_section("## Phase 1\n- [x] A\n"), None);
+        assert_eq!(active_section(""), None);
+    }
 }
@@ -0,0 +1,143 @@
+//! Machine-readable event stream for `--porcelain`.
+//!
+//! With `--porcelain`, stdout carries one record per state transition and
+//! everything meant for people (headers, claude's output, the summary) moves
+//! to stderr. A record is a single line of whitespace-separated fields; a
+//! free-text field, when present, is always last so it may contain spaces.
+//! The first record is `porcelain-version 1`. Within a version, fields are
+//! only ever appended, never reordered or removed.
+
+use crate::parser::TaskCount;
+use crate::reverse::ReverseSignal;
+use crate::run::{LoopSignal, RunOutcome};
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::{self, Write};
+use std::sync::Mutex;
+
+/// Version announced in the first record.
+pub const VERSION: u32 = 1;
+
+/// The original stdout, once `enable` has moved human output to stderr.
+static SINK: Mutex<Option<File>> = Mutex::new(None);
+
+/// Start the record stream.
+///
+/// Keeps a handle to the real stdout for records, then points the process's
+/// stdout (and so every `println!` and child echo) at stderr.
+pub fn enable() -> Result<()> {
+    io::stdout().flush()?;
+    let records = nix::unistd::dup(io::stdout()).context("failed to duplicate stdout")?;
+    nix::unistd::dup2_stdout(io::stderr()).context("failed to redirect stdout to stderr")?;
+    *SINK.lock().unwrap_or_else(|e| e.into_inner()) = Some(File::from(records));
+    emit(&format!("porcelain-version {}", VERSION));
+    Ok(())
+}
+
+/// Write one record, if `--porcelain` is on.
+///
+/// Write errors are ignored: a closed reader must not stop the loop.
+pub fn emit(record: &str) {
+    if let Some(sink) = SINK.lock().unwrap_or_else(|e| e.into_inner()).as_mut() {
+        let _ = writeln!(sink, "{}", record);
+        let _ = sink.flush();
+    }
+}
+
+/// Collapse a free-text field onto one line.
+fn text(s: &str) -> String {
+    s.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Append a free-text field, if it has any content.
+fn with_text(record: String, s: &str) -> String {
+    match text(s) {
+        t if t.is_empty() => record,
+        t => format!("{} {}", record, t),
+    }
+}
+
+/// Format: `iteration-start 3`
+pub fn format_iteration_start(iteration: u32) -> String {
+    format!("iteration-start {}", iteration)
+}
+
+/// Format: `signal CONTINUE`, `signal BLOCKED missing API key`
+pub fn format_signal(signal: &LoopSignal) -> String {
+    match signal {
+        LoopSignal::Done => "signal DONE".to_string(),
+        LoopSignal::Continue => "signal CONTINUE".to_string(),
+        LoopSignal::NoSignal => "signal NONE".to_string(),
+        LoopSignal::Blocked(reason) => with_text("signal BLOCKED".to_string(), reason),
+    }
+}
+
+/// Format: `signal FOUND the cache key ignores locale`
+pub fn format_reverse_signal(signal: &ReverseSignal) -> String {
+    match signal {
+        ReverseSignal::Continue => "signal CONTINUE".to_string(),
+        ReverseSignal::NoSignal => "signal NONE".to_string(),
+        ReverseSignal::Found(summary) => with_text("signal FOUND".to_string(), summary),
+        ReverseSignal::Inconclusive(reason) => with_text("signal INCONCLUSIVE".to_string(), reason),
+        ReverseSignal::Blocked(reason) => with_text("signal BLOCKED".to_string(), reason),
+    }
+}
+
+/// Format: `outcome done iterations=5 tasks=12/12`
+///
+/// `tasks` is omitted when there is no countable plan (reverse mode).
+pub fn format_outcome(outcome: &RunOutcome, iterations: u32, tasks: Option<&TaskCount>) -> String {
+    let mut record = format!("outcome {} iterations={}", outcome.kind(), iterations);
+    if let Some(tasks) = tasks {
+        record.push_str(&format!(" tasks={}/{}", tasks.completed, tasks.total));
+    }
+    record
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_signal() {
+        assert_eq!(format_signal(&LoopSignal::Continue), "signal CONTINUE");
+        assert_eq!(format_signal(&LoopSignal::NoSignal), "signal NONE");
+        assert_eq!(
+            format_signal(&LoopSignal::Blocked("missing\nAPI   key".to_string())),
+            "signal BLOCKED missing API key"
+        );
+        assert_eq!(
+            format_signal(&LoopSignal::Blocked(" ".to_string())),
+            "signal BLOCKED"
+        );
+    }
+
+    #[test]
+    fn test_format_reverse_signal() {
+        assert_eq!(
+            format_reverse_signal(&ReverseSignal::Found("it was DNS".to_string())),
+            "signal FOUND it was DNS"
+        );
+        assert_eq!(
+            format_reverse_signal(&ReverseSignal::Inconclusive("no logs".to_string())),
+            "signal INCONCLUSIVE no logs"
+        );
+    }
+
+    #[test]
+    fn test_format_outcome() {
+        let tasks = TaskCount {
+            completed: 12,
+            total: 12,
+        };
+        assert_eq!(
+            format_outcome(&RunOutcome::Done, 5, Some(&tasks)),
+            "outcome done iterations=5 tasks=12/12"
+        );
+        assert_eq!(
+            format_outcome(&RunOutcome::Inconclusive("x".to_string()), 2, None),
+            "outcome inconclusive iterations=2"
+        );
+        assert_eq!(format_iteration_start(3), "iteration-start 3");
+    }
+}
@@ -0,0 +1,224 @@
+//! Project names for telling runs from different projects apart.
+//!
+//! The name is recorded in the run summary, lock file, state.json, and
+//! archive manifest. It comes from `--project-name`, then
+//! `RALPHCTL_PROJECT_NAME`, then the first `# Heading` of the spec (SPEC.md
+//! unless `--spec-file` moves it), then the working directory's name.
+//!
+//! Iteration headers, the run banner, and heartbeat lines only carry the name
+//! when it is configured (flag or environment), so their format is unchanged
+//! unless asked for.
+
+use regex::Regex;
+use std::fs;
+use std::path::Path;
+use std::sync::LazyLock;
+
+/// Environment variable overriding the derived project name.
+pub const PROJECT_NAME_ENV: &str = "RALPHCTL_PROJECT_NAME";
+
+/// Longest project name kept; longer names are cut at a character boundary.
+pub const MAX_NAME_CHARS: usize = 60;
+
+/// SPEC.md headings that only mean the template was never filled in.
+const PLACEHOLDER_HEADINGS: &[&str] = &["Project Specification"];
+
+/// Markdown link: `[text](url)`.
+static LINK_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\[([^\]]*)\]\([^)]*\)").unwrap());
+
+/// Strip markdown formatting and whitespace from `raw`, capped at
+/// `MAX_NAME_CHARS`. Returns `None` if nothing is left.
+pub fn clean_name(raw: &str) -> Option<String> {
+    let unlinked = LINK_RE.replace_all(raw, "$1");
+    let plain: String = unlinked
+        .chars()
+        .filter(|c| !matches!(c, '*' | '_' | '`' | '~'))
+        .collect();
+    let words: Vec<&str> = plain.split_whitespace().collect();
+    let name: String = words.join(" ").chars().take(MAX_NAME_CHARS).collect();
+    let name = name.trim_end().to_string();
+    (!name.is_empty()).then_some(name)
+}
+
+/// Take the project name from the first `# Heading` line of SPEC.md content.
+///
+/// Only level-1 headings count. The unedited template heading and headings
+/// that are empty once formatting is stripped are ignored.
+pub fn name_from_spec(content: &str) -> Option<String> {
+    let heading = content
+        .lines()
+        .find_map(|line| line.strip_prefix("# "))?
+        .trim_end_matches('#');
+    clean_name(heading).filter(|name| !PLACEHOLDER_HEADINGS.contains(&name.as_str()))
+}
+
+/// Take the project name from the name of `dir`.
+pub fn name_from_dir(dir: &Path) -> Option<String> {
+    let dir = dir.canonicalize().ok()?;
+    clean_name(&dir.file_name()?.to_string_lossy())
+}
+
+/// The explicitly configured name: `flag` (`--project-name`), then `env`
+/// (`RALPHCTL_PROJECT_NAME`). Blank values count as unset.
+pub fn configured_name(flag: Option<&str>, env: Option<&str>) -> Option<String> {
+    flag.and_then(clean_name)
+        .or_else(|| env.and_then(clean_name))
+}
+
+/// Resolve the project name for `dir`, whose spec is at `spec`.
+///
+/// `flag` is `--project-name` and `env` the value of `RALPHCTL_PROJECT_NAME`;
+/// an explicit but blank value falls through to the derived name.
+pub fn project_name(
+    flag: Option<&str>,
+    env: Option<String>,
+    spec: &Path,
+    dir: &Path,
+) -> Option<String> {
+    configured_name(flag, env.as_deref())
+        .or_else(|| {
+            fs::read_to_string(spec)
+                .ok()
+                .and_then(|content| name_from_spec(&content))
+        })
+        .or_else(|| name_from_dir(dir))
+}
+
+/// Header prefix for `name`: `myproject — `, or empty without a name.
+pub fn header_prefix(name: Option<&str>) -> String {
+    name.map(|name| format!("{} \u{2014} ", name))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::files;
+
+    #[test]
+    fn test_clean_name_strips_markdown() {
+        assert_eq!(
+            clean_name("  My   Project "),
+            Some("My Project".to_string())
+        );
+        assert_eq!(clean_name("**Bold** `code`"), Some("Bold code".to_string()));
+        assert_eq!(
+            clean_name("[ralphctl](https://github.com/wcygan/ralphctl) v2"),
+            Some("ralphctl v2".to_string())
+        );
+        assert_eq!(clean_name(""), None);
+        assert_eq!(clean_name(" ** __ "), None);
+    }
+
+    #[test]
+    fn test_clean_name_caps_length() {
+        let long = "é".repeat(100);
+        assert_eq!(clean_name(&long).unwrap().chars().count(), MAX_NAME_CHARS);
+        // A cut that lands on a space doesn't leave trailing whitespace
+        let spaced = format!("{} tail", "a".repeat(MAX_NAME_CHARS - 1));
+        assert_eq!(clean_name(&spaced).unwrap(), "a".repeat(MAX_NAME_CHARS - 1));
+    }
+
+    #[test]
+    fn test_name_from_spec() {
+        assert_eq!(
+            name_from_spec("# Widget Service\n\n## Overview\n"),
+            Some("Widget Service".to_string())
+        );
+        assert_eq!(
+            name_from_spec("Intro text\n## Overview\n# Real Name #\n"),
+            Some("Real Name".to_string())
+        );
+        assert_eq!(name_from_spec("## Only subheadings\n"), None);
+        assert_eq!(name_from_spec(""), None);
+        assert_eq!(name_from_spec("#   \n# Later\n"), None);
+        assert_eq!(name_from_spec("# Project Specification\n"), None);
+    }
+
+    #[test]
+    fn test_name_from_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().join("my-app");
+        fs::create_dir(&project).unwrap();
+        assert_eq!(name_from_dir(&project), Some("my-app".to_string()));
+
+        let hidden = dir.path().join(".hidden");
+        fs::create_dir(&hidden).unwrap();
+        assert_eq!(name_from_dir(&hidden), Some(".hidden".to_string()));
+        assert_eq!(name_from_dir(&dir.path().join("missing")), None);
+    }
+
+    #[test]
+    fn test_configured_name() {
+        assert_eq!(configured_name(None, None), None);
+        assert_eq!(
+            configured_name(None, Some("from-env")),
+            Some("from-env".to_string())
+        );
+        assert_eq!(
+            configured_name(Some("from-flag"), Some("from-env")),
+            Some("from-flag".to_string())
+        );
+        assert_eq!(
+            configured_name(Some(" "), Some("from-env")),
+            Some("from-env".to_string())
+        );
+    }
+
+    #[test]
+    fn test_project_name_precedence() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().join("my-app");
+        fs::create_dir(&project).unwrap();
+        let spec = project.join(files::SPEC_FILE);
+
+        assert_eq!(
+            project_name(None, None, &spec, &project),
+            Some("my-app".to_string())
+        );
+
+        fs::write(&spec, "# Widget Service\n").unwrap();
+        assert_eq!(
+            project_name(None, None, &spec, &project),
+            Some("Widget Service".to_string())
+        );
+        assert_eq!(
+            project_name(None, Some("from-env".to_string()), &spec, &project),
+            Some("from-env".to_string())
+        );
+        assert_eq!(
+            project_name(
+                Some("from-flag"),
+                Some("from-env".to_string()),
+                &spec,
+                &project
+            ),
+            Some("from-flag".to_string())
+        );
+        assert_eq!(
+            project_name(Some("  "), Some(String::new()), &spec, &project),
+            Some("Widget Service".to_string())
+        );
+    }
+
+    #[test]
+    fn test_project_name_reads_moved_spec() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().join("my-app");
+        fs::create_dir_all(project.join("docs")).unwrap();
+        let spec = project.join("docs/SPEC.md");
+        fs::write(&spec, "# Moved Spec\n").unwrap();
+        fs::write(project.join(files::SPEC_FILE), "# Default Spec\n").unwrap();
+
+        assert_eq!(
+            project_name(None, None, &spec, &project),
+            Some("Moved Spec".to_string())
+        );
+    }
+
+    #[test]
+    fn test_header_prefix() {
+        assert_eq!(header_prefix(Some("myproject")), "myproject \u{2014} ");
+        assert_eq!(header_prefix(None), "");
+    }
+}
@@ -7,11 +7,13 @@
 
 #![allow(dead_code)] // Components used by future reverse mode implementation
 
-use crate::files::QUESTION_FILE;
-use crate::run;
+use crate::files::{FINDINGS_FILE, INVESTIGATION_FILE, QUESTION_FILE, REVERSE_PROMPT_FILE};
+use crate::{duration, parser, run, templates};
 use anyhow::{Context, Result};
+use std::collections::HashSet;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 /// Reverse mode signal types.
 ///
@@ -37,9 +39,6 @@ pub const RALPH_FOUND_PREFIX: &str = "[[RALPH:FOUND:";
 /// Magic string prefix for INCONCLUSIVE signal.
 pub const RALPH_INCONCLUSIVE_PREFIX: &str = "[[RALPH:INCONCLUSIVE:";
 
-/// Magic string suffix (shared with other signals).
-const SIGNAL_SUFFIX: &str = "]]";
-
 /// Minimal template for QUESTION.md when created without an argument.
 const QUESTION_TEMPLATE: &str = r#"# Investigation Question
 
@@ -71,15 +70,81 @@ pub fn create_question_template(dir: &Path) -> Result<()> {
         .with_context(|| format!("failed to write {}", path.display()))
 }
 
+/// Default limit on the size of a question passed on the command line.
+pub const DEFAULT_MAX_QUESTION_BYTES: usize = 16 * 1024;
+
+/// A question cleaned up for QUESTION.md by `sanitize_question`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SanitizedQuestion {
+    /// Question text to write
+    pub text: String,
+    /// Original size in bytes, if the question was truncated
+    pub truncated_from: Option<usize>,
+}
+
+/// Validate and clean up a question before it is written to QUESTION.md.
+///
+/// Trims surrounding whitespace, keeps only the first `max_bytes` bytes of an
+/// oversized question, and escapes lines starting with `#` as `\#` so user
+/// text cannot pose as the template's own headings.
+///
+/// # Errors
+///
+/// Returns an error if the question is empty or whitespace-only.
+pub fn sanitize_question(question: &str, max_bytes: usize) -> Result<SanitizedQuestion> {
+    let trimmed = question.trim();
+    if trimmed.is_empty() {
+        anyhow::bail!(
+            "question is empty; pass a question or omit it to create {}",
+            QUESTION_FILE
+        );
+    }
+
+    let (head, truncated_from) = if trimmed.len() > max_bytes {
+        let mut end = max_bytes;
+        while !trimmed.is_char_boundary(end) {
+            end -= 1;
+        }
+        (trimmed[..end].trim_end(), Some(trimmed.len()))
+    } else {
+        (trimmed, None)
+    };
+
+    let text = head
+        .lines()
+        .map(|line| match line.find(|c: char| !c.is_whitespace()) {
+            Some(idx) if line[idx..].starts_with('#') => {
+                format!("{}\\{}", &line[..idx], &line[idx..])
+            }
+            _ => line.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    Ok(SanitizedQuestion {
+        text,
+        truncated_from,
+    })
+}
+
 /// Write an investigation question to QUESTION.md.
 ///
 /// Creates QUESTION.md with the provided question formatted
-/// with the standard header and optional context section.
+/// with the standard header and optional context section. The question is
+/// cleaned up with `sanitize_question` first; truncation prints a warning.
 ///
 /// # Errors
 ///
-/// Returns an error if the file cannot be written.
-pub fn write_question(dir: &Path, question: &str) -> Result<()> {
+/// Returns an error if the question is empty or the file cannot be written.
+pub fn write_question(dir: &Path, question: &str, max_bytes: usize) -> Result<()> {
+    let question = sanitize_question(question, max_bytes)?;
+    if let Some(original) = question.truncated_from {
+        eprintln!(
+            "warning: question is {} bytes; keeping the first {} (see --max-question-bytes)",
+            original, max_bytes
+        );
+    }
+
     let path = dir.join(QUESTION_FILE);
     let content = format!(
         r#"# Investigation Question
@@ -90,11 +155,58 @@ pub fn write_question(dir: &Path, question: &str) -> Result<()> {
 
 <Add any additional context here>
 "#,
-        question
+        question.text
     );
     fs::write(&path, content).with_context(|| format!("failed to write {}", path.display()))
 }
 
+/// Build reverse mode run stats for the final summary.
+///
+/// Hypotheses examined are the checked items in INVESTIGATION.md; findings
+/// report whether FINDINGS.md has been written.
+pub fn reverse_stats(dir: &Path, iterations: u32, elapsed: Duration) -> run::RunStats {
+    let hypotheses = match fs::read_to_string(dir.join(INVESTIGATION_FILE)) {
+        Ok(content) => parser::count_checkboxes(&content).completed.to_string(),
+        Err(_) => "0".to_string(),
+    };
+    let findings = if dir.join(FINDINGS_FILE).exists() {
+        "present"
+    } else {
+        "missing"
+    };
+    run::RunStats::new(iterations, elapsed)
+        .with_field("Hypotheses", format!("{} examined", hypotheses))
+        .with_field("Findings", findings)
+}
+
+/// Reason recorded when `--max-time` ends an investigation.
+///
+/// Format: `time budget of 30m 00s exhausted after 3 iterations (--max-time)`
+pub fn format_time_budget_exhausted(budget: Duration, iterations: u32) -> String {
+    format!(
+        "time budget of {} exhausted after {} iteration{} (--max-time)",
+        duration::format_duration(budget),
+        iterations,
+        if iterations == 1 { "" } else { "s" }
+    )
+}
+
+/// Final outcome for `reverse --format json`.
+///
+/// `{"outcome":"found","summary":"...","iterations":3}`; INCONCLUSIVE and
+/// BLOCKED carry a `reason` instead of a `summary`, other outcomes neither.
+pub fn outcome_json(outcome: &run::RunOutcome, iterations: u32) -> serde_json::Value {
+    let mut json = serde_json::json!({ "outcome": outcome.kind(), "iterations": iterations });
+    if let Some(text) = outcome.reason() {
+        let key = match outcome {
+            run::RunOutcome::Found(_) => "summary",
+            _ => "reason",
+        };
+        json[key] = text.into();
+    }
+    json
+}
+
 /// Detect reverse mode signals in output.
 ///
 /// Scans the provided output string for reverse mode magic strings.
@@ -135,6 +247,15 @@ pub fn detect_reverse_signal(output: &str) -> ReverseSignal {
     ReverseSignal::NoSignal
 }
 
+/// Whether `line` alone carries a signal that ends the investigation
+/// (FOUND, INCONCLUSIVE, or BLOCKED); the `--early-stop` matcher for `reverse`.
+pub fn is_terminal_reverse_signal_line(line: &str) -> bool {
+    matches!(
+        detect_reverse_signal(line),
+        ReverseSignal::Found(_) | ReverseSignal::Inconclusive(_) | ReverseSignal::Blocked(_)
+    )
+}
+
 /// Check if the output contains a RALPH:FOUND signal on its own line.
 ///
 /// Scans for `[[RALPH:FOUND:<summary>]]` pattern and extracts the summary.
@@ -145,7 +266,7 @@ fn detect_found_signal(output: &str) -> Option<String> {
     for line in output.lines() {
         let trimmed = line.trim();
         if let Some(rest) = trimmed.strip_prefix(RALPH_FOUND_PREFIX) {
-            if let Some(summary) = rest.strip_suffix(SIGNAL_SUFFIX) {
+            if let Some(summary) = rest.strip_suffix(run::RALPH_MARKER_SUFFIX) {
                 return Some(summary.to_string());
             }
         }
@@ -163,7 +284,7 @@ fn detect_inconclusive_signal(output: &str) -> Option<String> {
     for line in output.lines() {
         let trimmed = line.trim();
         if let Some(rest) = trimmed.strip_prefix(RALPH_INCONCLUSIVE_PREFIX) {
-            if let Some(reason) = rest.strip_suffix(SIGNAL_SUFFIX) {
+            if let Some(reason) = rest.strip_suffix(run::RALPH_MARKER_SUFFIX) {
                 return Some(reason.to_string());
             }
         }
@@ -171,10 +292,375 @@ fn detect_inconclusive_signal(output: &str) -> Option<String> {
     None
 }
 
+/// Word-set similarity at or above which two hypothesis titles count as the
+/// same avenue.
+pub const HYPOTHESIS_SIMILARITY_THRESHOLD: f64 = 0.8;
+
+/// Heading that collects abandoned approaches in INVESTIGATION.md.
+const DEAD_ENDS_HEADING: &str = "## Dead Ends";
+
+/// Extract the titles of `## Hypothesis N: <title>` headings, in file order.
+///
+/// The number and colon are optional; a heading without a title yields an
+/// empty string so indices still line up with the headings.
+pub fn hypothesis_headings(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .filter_map(|line| line.trim_end().strip_prefix("## Hypothesis"))
+        .filter(|rest| rest.is_empty() || rest.starts_with([' ', ':']))
+        .map(|rest| {
+            let rest = rest
+                .trim_start()
+                .trim_start_matches(|c: char| c.is_ascii_digit());
+            rest.trim_start().trim_start_matches(':').trim().to_string()
+        })
+        .collect()
+}
+
+/// Normalize a hypothesis title: lowercase, with whitespace runs collapsed.
+pub fn normalize_hypothesis(title: &str) -> String {
+    title
+        .split_whitespace()
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Jaccard similarity of the word sets of two titles, from 0.0 to 1.0.
+///
+/// Words are split on anything that is not alphanumeric, after
+/// normalization. Two titles without any words are identical.
+pub fn jaccard_similarity(a: &str, b: &str) -> f64 {
+    let words = |s: &str| -> HashSet<String> {
+        normalize_hypothesis(s)
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|w| !w.is_empty())
+            .map(str::to_string)
+            .collect()
+    };
+    let (a, b) = (words(a), words(b));
+    let union = a.union(&b).count();
+    if union == 0 {
+        return 1.0;
+    }
+    a.intersection(&b).count() as f64 / union as f64
+}
+
+/// Find an earlier heading that the newest one revisits.
+///
+/// Returns the index of the first earlier heading whose similarity to the
+/// last heading reaches [`HYPOTHESIS_SIMILARITY_THRESHOLD`].
+pub fn find_revisited_hypothesis(headings: &[String]) -> Option<usize> {
+    let (newest, earlier) = headings.split_last()?;
+    earlier
+        .iter()
+        .position(|h| jaccard_similarity(h, newest) >= HYPOTHESIS_SIMILARITY_THRESHOLD)
+}
+
+/// Append `- <note>` to the `## Dead Ends` section, creating it if missing.
+///
+/// The note goes after the section's last non-blank line, so it stays inside
+/// the section even when other sections follow.
+pub fn append_dead_end(content: &str, note: &str) -> String {
+    append_to_section(content, DEAD_ENDS_HEADING, note)
+}
+
+/// Append `- <entry>` to the `heading` section, creating it at the end if missing.
+fn append_to_section(content: &str, heading: &str, entry: &str) -> String {
+    let entry = format!("- {}", entry);
+    let mut lines: Vec<&str> = content.lines().collect();
+
+    let Some(start) = lines.iter().position(|l| l.trim_end() == heading) else {
+        let mut out = content.trim_end().to_string();
+        if !out.is_empty() {
+            out.push_str("\n\n");
+        }
+        return format!("{}{}\n{}\n", out, heading, entry);
+    };
+
+    let end = lines[start + 1..]
+        .iter()
+        .position(|l| l.starts_with("## "))
+        .map_or(lines.len(), |i| start + 1 + i);
+    let insert_at = (start + 1..end)
+        .rev()
+        .find(|&i| !lines[i].trim().is_empty())
+        .map_or(start + 1, |i| i + 1);
+    lines.insert(insert_at, &entry);
+    lines.join("\n") + "\n"
+}
+
+/// Heading of the section `--write-investigation` appends to.
+const ITERATION_LOG_HEADING: &str = "## Iteration Log";
+
+impl ReverseSignal {
+    /// Lowercase signal name for logs: `continue`, `found`, `inconclusive`,
+    /// `blocked`, or `none`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ReverseSignal::Continue => "continue",
+            ReverseSignal::Found(_) => "found",
+            ReverseSignal::Inconclusive(_) => "inconclusive",
+            ReverseSignal::Blocked(_) => "blocked",
+            ReverseSignal::NoSignal => "none",
+        }
+    }
+}
+
+/// Format one `--write-investigation` entry.
+///
+/// Format: `Iteration 2 (run 1a2b3c4d): found (answer), 812 bytes of output`
+pub fn format_iteration_entry(
+    iteration: u32,
+    run_id: &str,
+    signal: &ReverseSignal,
+    output_bytes: usize,
+) -> String {
+    let detail = match signal {
+        ReverseSignal::Found(s) | ReverseSignal::Inconclusive(s) | ReverseSignal::Blocked(s) => {
+            format!(" ({})", s)
+        }
+        ReverseSignal::Continue | ReverseSignal::NoSignal => String::new(),
+    };
+    format!(
+        "Iteration {} (run {}): {}{}, {} bytes of output",
+        iteration,
+        run_id,
+        signal.name(),
+        detail,
+        output_bytes
+    )
+}
+
+/// Append `entry` to the `## Iteration Log` section of INVESTIGATION.md.
+///
+/// Creates the file if claude has not written it yet, so the investigation
+/// always leaves a trail for `archive`.
+pub fn append_iteration_log(dir: &Path, entry: &str) -> Result<()> {
+    let path = dir.join(INVESTIGATION_FILE);
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => {
+            return Err(e).with_context(|| format!("failed to read {}", path.display()));
+        }
+    };
+    fs::write(
+        &path,
+        append_to_section(&content, ITERATION_LOG_HEADING, entry),
+    )
+    .with_context(|| format!("failed to write {}", path.display()))
+}
+
+/// Record a revisited hypothesis under `## Dead Ends` in INVESTIGATION.md.
+///
+/// `first_iteration` is the iteration that introduced the original heading,
+/// or `None` when it predates this run.
+pub fn note_revisited_hypothesis(
+    dir: &Path,
+    title: &str,
+    first_iteration: Option<u32>,
+) -> Result<()> {
+    let path = dir.join(INVESTIGATION_FILE);
+    let content =
+        fs::read_to_string(&path).with_context(|| format!("failed to read {}", path.display()))?;
+    let when = match first_iteration {
+        Some(n) => format!("in iteration {}", n),
+        None => "in an earlier run".to_string(),
+    };
+    let note = format!(
+        "\"{}\" was already explored {}; pick a different hypothesis (noted by ralphctl)",
+        title, when
+    );
+    fs::write(&path, append_dead_end(&content, &note))
+        .with_context(|| format!("failed to write {}", path.display()))
+}
+
+/// Where the investigation prompt came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PromptSource {
+    /// The file given with `--reverse-prompt-file`
+    File,
+    /// A REVERSE_PROMPT.md in the working directory
+    Local,
+    /// The template compiled into the binary
+    Embedded,
+}
+
+/// Signal markers a reverse prompt must teach claude to print.
+const REQUIRED_PROMPT_MARKERS: &[&str] = &[
+    RALPH_FOUND_PREFIX,
+    RALPH_INCONCLUSIVE_PREFIX,
+    run::RALPH_CONTINUE_MARKER,
+    run::RALPH_BLOCKED_PREFIX,
+];
+
+/// Get the investigation prompt.
+///
+/// An explicit `file` is always used and must exist and be non-empty.
+/// Otherwise a non-empty REVERSE_PROMPT.md in `dir` wins, so a resumed
+/// investigation keeps the prompt it started with; without one, or with
+/// `refresh`, the embedded template is used.
+pub fn resolve_prompt(
+    dir: &Path,
+    refresh: bool,
+    file: Option<&Path>,
+) -> Result<(String, PromptSource)> {
+    if let Some(file) = file {
+        let content = fs::read_to_string(file)
+            .with_context(|| format!("failed to read {}", file.display()))?;
+        if content.trim().is_empty() {
+            anyhow::bail!("{} is empty", file.display());
+        }
+        return Ok((content, PromptSource::File));
+    }
+    let path = dir.join(REVERSE_PROMPT_FILE);
+    if !refresh && path.exists() {
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        if !content.trim().is_empty() {
+            return Ok((content, PromptSource::Local));
+        }
+    }
+    Ok((templates::get_reverse_template(), PromptSource::Embedded))
+}
+
+/// Resolve `reverse --target` to an absolute directory.
+pub fn resolve_target(target: &Path) -> Result<PathBuf> {
+    let resolved = target
+        .canonicalize()
+        .with_context(|| format!("--target {}: no such directory", target.display()))?;
+    if !resolved.is_dir() {
+        anyhow::bail!("--target {}: not a directory", target.display());
+    }
+    Ok(resolved)
+}
+
+/// Append the section that points claude at `--target`.
+///
+/// Claude runs in `target`, so the investigation files are named by their
+/// absolute paths in `home`, the directory ralphctl was started in.
+pub fn with_target_section(prompt: &str, target: &Path, home: &Path) -> String {
+    format!(
+        "{}\n\n## Investigation Target\n\n\
+         The codebase under investigation is at `{}`. It is your working \
+         directory; do all code reading there and do not write files in it.\n\n\
+         The investigation files live in `{}` instead: read `{}`, and write \
+         `{}` and `{}` there.\n",
+        prompt.trim_end(),
+        target.display(),
+        home.display(),
+        home.join(QUESTION_FILE).display(),
+        home.join(INVESTIGATION_FILE).display(),
+        home.join(FINDINGS_FILE).display(),
+    )
+}
+
+/// Check that `prompt` mentions every reverse signal marker.
+///
+/// Without them claude never prints a signal the loop can detect, and every
+/// iteration ends as NO_SIGNAL.
+pub fn validate_prompt(prompt: &str) -> Result<()> {
+    let missing: Vec<&str> = REQUIRED_PROMPT_MARKERS
+        .iter()
+        .copied()
+        .filter(|marker| !prompt.contains(marker))
+        .collect();
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        anyhow::bail!("missing signal markers: {}", missing.join(", "))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_resolve_prompt_prefers_local_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let (prompt, source) = resolve_prompt(dir.path(), false, None).unwrap();
+        assert_eq!(source, PromptSource::Embedded);
+        assert_eq!(prompt, templates::get_reverse_template());
+
+        fs::write(dir.path().join(REVERSE_PROMPT_FILE), "  \n").unwrap();
+        let (_, source) = resolve_prompt(dir.path(), false, None).unwrap();
+        assert_eq!(source, PromptSource::Embedded);
+
+        fs::write(dir.path().join(REVERSE_PROMPT_FILE), "# Local prompt\n").unwrap();
+        assert_eq!(
+            resolve_prompt(dir.path(), false, None).unwrap(),
+            ("# Local prompt\n".to_string(), PromptSource::Local)
+        );
+        let (_, source) = resolve_prompt(dir.path(), true, None).unwrap();
+        assert_eq!(source, PromptSource::Embedded);
+    }
+
+    #[test]
+    fn test_resolve_target() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(
+            resolve_target(dir.path()).unwrap(),
+            dir.path().canonicalize().unwrap()
+        );
+
+        let file = dir.path().join("file.txt");
+        fs::write(&file, "").unwrap();
+        let err = resolve_target(&file).unwrap_err();
+        assert!(err.to_string().ends_with("file.txt: not a directory"));
+
+        let err = resolve_target(&dir.path().join("missing")).unwrap_err();
+        assert!(err.to_string().ends_with("missing: no such directory"));
+    }
+
+    #[test]
+    fn test_with_target_section() {
+        let prompt = with_target_section(
+            "# Prompt\n\n",
+            Path::new("/src/vendor/lib"),
+            Path::new("/work/notes"),
+        );
+        assert!(prompt.starts_with("# Prompt\n\n## Investigation Target\n\n"));
+        assert!(prompt.contains("The codebase under investigation is at `/src/vendor/lib`."));
+        assert!(prompt.contains("read `/work/notes/QUESTION.md`"));
+        assert!(
+            prompt.contains("write `/work/notes/INVESTIGATION.md` and `/work/notes/FINDINGS.md`")
+        );
+    }
+
+    #[test]
+    fn test_resolve_prompt_explicit_file_wins() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join(REVERSE_PROMPT_FILE), "# Local prompt\n").unwrap();
+        let custom = dir.path().join("custom.md");
+        fs::write(&custom, "# Custom prompt\n").unwrap();
+        assert_eq!(
+            resolve_prompt(dir.path(), false, Some(&custom)).unwrap(),
+            ("# Custom prompt\n".to_string(), PromptSource::File)
+        );
+
+        fs::write(&custom, "\n").unwrap();
+        let err = resolve_prompt(dir.path(), false, Some(&custom)).unwrap_err();
+        assert!(err.to_string().ends_with("custom.md is empty"));
+
+        let missing = dir.path().join("missing.md");
+        let err = resolve_prompt(dir.path(), false, Some(&missing)).unwrap_err();
+        assert!(err.to_string().starts_with("failed to read"));
+    }
+
+    #[test]
+    fn test_validate_prompt() {
+        assert!(validate_prompt(&templates::get_reverse_template()).is_ok());
+        let err = validate_prompt("[[RALPH:FOUND:x]] [[RALPH:CONTINUE]]")
+            .unwrap_err()
+            .to_string();
+        assert_eq!(
+            err,
+            "missing signal markers: [[RALPH:INCONCLUSIVE:, [[RALPH:BLOCKED:"
+        );
+    }
+
     #[test]
     fn test_reverse_signal_equality() {
         assert_eq!(ReverseSignal::Continue, ReverseSignal::Continue);
@@ -267,8 +753,56 @@ mod tests {
         assert_eq!(RALPH_INCONCLUSIVE_PREFIX, "[[RALPH:INCONCLUSIVE:");
     }
 
+    #[test]
+    fn test_format_time_budget_exhausted() {
+        assert_eq!(
+            format_time_budget_exhausted(Duration::from_secs(1800), 3),
+            "time budget of 30m 00s exhausted after 3 iterations (--max-time)"
+        );
+        assert_eq!(
+            format_time_budget_exhausted(Duration::from_secs(45), 1),
+            "time budget of 45s exhausted after 1 iteration (--max-time)"
+        );
+    }
+
+    #[test]
+    fn test_outcome_json() {
+        let found = outcome_json(&run::RunOutcome::Found("the cache".to_string()), 3);
+        assert_eq!(
+            found,
+            serde_json::json!({"outcome": "found", "summary": "the cache", "iterations": 3})
+        );
+
+        let inconclusive = outcome_json(&run::RunOutcome::Inconclusive("no logs".to_string()), 2);
+        assert_eq!(
+            inconclusive,
+            serde_json::json!({"outcome": "inconclusive", "reason": "no logs", "iterations": 2})
+        );
+
+        let max = outcome_json(&run::RunOutcome::MaxIterations, 100);
+        assert_eq!(
+            max,
+            serde_json::json!({"outcome": "max_iterations", "iterations": 100})
+        );
+    }
+
     // ========== detect_reverse_signal() tests ==========
 
+    #[test]
+    fn test_is_terminal_reverse_signal_line() {
+        assert!(is_terminal_reverse_signal_line(
+            "[[RALPH:FOUND:it was the cache]]"
+        ));
+        assert!(is_terminal_reverse_signal_line(
+            "[[RALPH:INCONCLUSIVE:no logs]]"
+        ));
+        assert!(is_terminal_reverse_signal_line(
+            "[[RALPH:BLOCKED:no access]]"
+        ));
+        assert!(!is_terminal_reverse_signal_line("[[RALPH:CONTINUE]]"));
+        assert!(!is_terminal_reverse_signal_line("[[RALPH:DONE]]"));
+    }
+
     #[test]
     fn test_detect_reverse_signal_continue() {
         let output = "Still investigating.\n[[RALPH:CONTINUE]]\n";
@@ -732,7 +1266,7 @@ More investigation needed.
         let dir = create_temp_dir();
         let question = "Why does the cache fail after 5 minutes?";
 
-        write_question(dir.path(), question).unwrap();
+        write_question(dir.path(), question, DEFAULT_MAX_QUESTION_BYTES).unwrap();
 
         let content = std::fs::read_to_string(dir.path().join("QUESTION.md")).unwrap();
         assert!(content.contains("# Investigation Question"));
@@ -740,12 +1274,70 @@ More investigation needed.
         assert!(content.contains("## Context (Optional)"));
     }
 
+    #[test]
+    fn test_sanitize_question_trims() {
+        let question = sanitize_question("  \n Why is it slow?\n\n", 100).unwrap();
+        assert_eq!(question.text, "Why is it slow?");
+        assert_eq!(question.truncated_from, None);
+    }
+
+    #[test]
+    fn test_sanitize_question_rejects_empty() {
+        assert!(sanitize_question("", 100).is_err());
+        let err = sanitize_question(" \n\t ", 100).unwrap_err();
+        assert!(err.to_string().contains("question is empty"));
+    }
+
+    #[test]
+    fn test_sanitize_question_truncates_keeping_head() {
+        let question = sanitize_question("abcdef ghij", 7).unwrap();
+        assert_eq!(question.text, "abcdef");
+        assert_eq!(question.truncated_from, Some(11));
+
+        // Never splits a multi-byte character
+        let question = sanitize_question("ééé", 3).unwrap();
+        assert_eq!(question.text, "é");
+        assert_eq!(question.truncated_from, Some(6));
+    }
+
+    #[test]
+    fn test_sanitize_question_escapes_headings() {
+        let question =
+            sanitize_question("Why?\n## Context (Optional)\n  # note\nuse #hash", 100).unwrap();
+        assert_eq!(
+            question.text,
+            "Why?\n\\## Context (Optional)\n  \\# note\nuse #hash"
+        );
+    }
+
+    #[test]
+    fn test_write_question_keeps_single_context_heading() {
+        let dir = create_temp_dir();
+        write_question(
+            dir.path(),
+            "Why?\n## Context (Optional)\nfake",
+            DEFAULT_MAX_QUESTION_BYTES,
+        )
+        .unwrap();
+
+        let content = std::fs::read_to_string(dir.path().join("QUESTION.md")).unwrap();
+        assert_eq!(content.matches("\n## Context (Optional)").count(), 1);
+        assert!(content.contains("\\## Context (Optional)\nfake"));
+    }
+
+    #[test]
+    fn test_write_question_rejects_empty() {
+        let dir = create_temp_dir();
+        assert!(write_question(dir.path(), "   ", DEFAULT_MAX_QUESTION_BYTES).is_err());
+        assert!(!dir.path().join("QUESTION.md").exists());
+    }
+
     #[test]
     fn test_write_question_multiline() {
         let dir = create_temp_dir();
         let question = "Why does the auth fail?\n\n- Happens on OAuth users\n- Only in production";
 
-        write_question(dir.path(), question).unwrap();
+        write_question(dir.path(), question, DEFAULT_MAX_QUESTION_BYTES).unwrap();
 
         let content = std::fs::read_to_string(dir.path().join("QUESTION.md")).unwrap();
         assert!(content.contains("# Investigation Question"));
@@ -758,7 +1350,7 @@ More investigation needed.
         let dir = create_temp_dir();
         std::fs::write(dir.path().join("QUESTION.md"), "old question").unwrap();
 
-        write_question(dir.path(), "new question").unwrap();
+        write_question(dir.path(), "new question", DEFAULT_MAX_QUESTION_BYTES).unwrap();
 
         let content = std::fs::read_to_string(dir.path().join("QUESTION.md")).unwrap();
         assert!(!content.contains("old question"));
@@ -770,7 +1362,7 @@ More investigation needed.
         let dir = create_temp_dir();
         let question = "What causes the memory leak?";
 
-        write_question(dir.path(), question).unwrap();
+        write_question(dir.path(), question, DEFAULT_MAX_QUESTION_BYTES).unwrap();
         let content = read_question(dir.path()).unwrap();
 
         assert!(content.contains(question));
@@ -781,7 +1373,7 @@ More investigation needed.
         let dir = create_temp_dir();
         let question = "Why does `fn foo<T>()` fail with error \"E0277\"?";
 
-        write_question(dir.path(), question).unwrap();
+        write_question(dir.path(), question, DEFAULT_MAX_QUESTION_BYTES).unwrap();
         let content = read_question(dir.path()).unwrap();
 
         assert!(content.contains(question));
@@ -792,9 +1384,170 @@ More investigation needed.
         let dir = create_temp_dir();
         let question = "为什么缓存在5分钟后失败？";
 
-        write_question(dir.path(), question).unwrap();
+        write_question(dir.path(), question, DEFAULT_MAX_QUESTION_BYTES).unwrap();
         let content = read_question(dir.path()).unwrap();
 
         assert!(content.contains(question));
     }
+
+    #[test]
+    fn test_reverse_stats_fields() {
+        let dir = create_temp_dir();
+        fs::write(
+            dir.path().join(INVESTIGATION_FILE),
+            "# Investigation Log\n\n- [x] H1\n- [x] H2\n- [ ] H3\n",
+        )
+        .unwrap();
+        fs::write(dir.path().join(FINDINGS_FILE), "# Findings").unwrap();
+
+        let stats = reverse_stats(dir.path(), 2, Duration::from_secs(5));
+        assert_eq!(stats.iterations, 2);
+        assert_eq!(
+            stats.fields,
+            vec![
+                ("Hypotheses", "2 examined".to_string()),
+                ("Findings", "present".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reverse_stats_without_files() {
+        let dir = create_temp_dir();
+        let stats = reverse_stats(dir.path(), 0, Duration::from_secs(0));
+        assert_eq!(
+            stats.fields,
+            vec![
+                ("Hypotheses", "0 examined".to_string()),
+                ("Findings", "missing".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_hypothesis_headings() {
+        let content = "# Investigation Log\n\n## Hypothesis 1: Cache  is stale\n- [x] check\n\n## Hypothesis 2 : Race in writer\n## Hypothesis\n## Hypotheses overview\n### Hypothesis 3: nested\n## Dead Ends\n";
+        assert_eq!(
+            hypothesis_headings(content),
+            vec!["Cache  is stale", "Race in writer", ""]
+        );
+        assert!(hypothesis_headings("").is_empty());
+    }
+
+    #[test]
+    fn test_normalize_hypothesis() {
+        assert_eq!(
+            normalize_hypothesis("  Cache\tIS   Stale "),
+            "cache is stale"
+        );
+    }
+
+    #[test]
+    fn test_jaccard_similarity() {
+        assert_eq!(jaccard_similarity("Cache is stale", "cache  IS stale"), 1.0);
+        assert_eq!(jaccard_similarity("a b", "c d"), 0.0);
+        assert_eq!(jaccard_similarity("a b c d", "a b c d e"), 0.8);
+        assert_eq!(jaccard_similarity("a b c", "a b d"), 0.5);
+        assert_eq!(jaccard_similarity("", "  "), 1.0);
+        assert_eq!(jaccard_similarity("stale-cache", "stale cache"), 1.0);
+    }
+
+    #[test]
+    fn test_find_revisited_hypothesis() {
+        let headings = |h: &[&str]| h.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!(find_revisited_hypothesis(&[]), None);
+        assert_eq!(find_revisited_hypothesis(&headings(&["only one"])), None);
+        assert_eq!(
+            find_revisited_hypothesis(&headings(&[
+                "Race in writer",
+                "Config cache is stale",
+                "Network timeout",
+                "config cache is  STALE"
+            ])),
+            Some(1)
+        );
+        assert_eq!(
+            find_revisited_hypothesis(&headings(&["Race in writer", "Race in reader"])),
+            None
+        );
+    }
+
+    #[test]
+    fn test_format_iteration_entry() {
+        assert_eq!(
+            format_iteration_entry(2, "0badc0de", &ReverseSignal::Continue, 812),
+            "Iteration 2 (run 0badc0de): continue, 812 bytes of output"
+        );
+        assert_eq!(
+            format_iteration_entry(
+                3,
+                "0badc0de",
+                &ReverseSignal::Found("race in cache".to_string()),
+                10
+            ),
+            "Iteration 3 (run 0badc0de): found (race in cache), 10 bytes of output"
+        );
+        assert_eq!(ReverseSignal::NoSignal.name(), "none");
+    }
+
+    #[test]
+    fn test_append_iteration_log() {
+        let dir = tempfile::tempdir().unwrap();
+        append_iteration_log(dir.path(), "first").unwrap();
+        let path = dir.path().join(INVESTIGATION_FILE);
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "## Iteration Log\n- first\n"
+        );
+
+        // Claude's own edits are kept; entries stay inside the section
+        fs::write(
+            &path,
+            "# Investigation\n\n## Iteration Log\n- first\n\n## Hypothesis 1: cache\n",
+        )
+        .unwrap();
+        append_iteration_log(dir.path(), "second").unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "# Investigation\n\n## Iteration Log\n- first\n- second\n\n## Hypothesis 1: cache\n"
+        );
+    }
+
+    #[test]
+    fn test_append_dead_end_existing_section() {
+        let content = "## Hypothesis 1: x\n\n## Dead Ends\n- old\n\n## Key Findings\n- found\n";
+        assert_eq!(
+            append_dead_end(content, "new"),
+            "## Hypothesis 1: x\n\n## Dead Ends\n- old\n- new\n\n## Key Findings\n- found\n"
+        );
+
+        let content = "## Dead Ends\n\n## Key Findings\n";
+        assert_eq!(
+            append_dead_end(content, "new"),
+            "## Dead Ends\n- new\n\n## Key Findings\n"
+        );
+    }
+
+    #[test]
+    fn test_append_dead_end_creates_section() {
+        assert_eq!(
+            append_dead_end("## Hypothesis 1: x\n\n", "new"),
+            "## Hypothesis 1: x\n\n## Dead Ends\n- new\n"
+        );
+        assert_eq!(append_dead_end("", "new"), "## Dead Ends\n- new\n");
+    }
+
+    #[test]
+    fn test_note_revisited_hypothesis() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(INVESTIGATION_FILE);
+        fs::write(&path, "## Hypothesis 1: stale cache\n").unwrap();
+
+        note_revisited_hypothesis(dir.path(), "Stale cache", Some(2)).unwrap();
+        note_revisited_hypothesis(dir.path(), "Stale cache", None).unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(content.contains(
+            "## Dead Ends\n- \"Stale cache\" was already explored in iteration 2; pick a different hypothesis (noted by ralphctl)\n- \"Stale cache\" was already explored in an earlier run;"
+        ));
+    }
 }
@@ -2,51 +2,137 @@
 //!
 //! Provides the core ralph loop execution logic.
 
-use crate::{error, files, parser};
-use anyhow::Result;
+use crate::error::exit;
+use crate::{cli, duration, error, files, ids, parser, project, templates, tools};
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
 use std::fs;
-use std::io::{self, BufRead, BufReader, Write};
-use std::path::Path;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::Sender;
 use std::sync::Arc;
 use std::thread;
-
-/// Required files that must exist before running.
-const REQUIRED_FILES: &[&str] = &[
-    files::PROMPT_FILE,
-    files::SPEC_FILE,
-    files::IMPLEMENTATION_PLAN_FILE,
-];
+use std::time::Duration;
 
 /// Format the iteration header string.
 ///
-/// Format: `=== Iteration N starting ===`
-pub fn format_iteration_header(iteration: u32) -> String {
-    format!("=== Iteration {} starting ===", iteration)
+/// Format: `=== Iteration N starting ===`, followed by ` (run <id>)` when a
+/// run ID is given. A project name goes in front of the iteration:
+/// `=== myproject — Iteration N starting ===`.
+pub fn format_iteration_header(
+    iteration: u32,
+    run_id: Option<&str>,
+    project: Option<&str>,
+) -> String {
+    let prefix = project::header_prefix(project);
+    match run_id {
+        Some(id) => format!(
+            "=== {}Iteration {} starting === (run {})",
+            prefix, iteration, id
+        ),
+        None => format!("=== {}Iteration {} starting ===", prefix, iteration),
+    }
 }
 
 /// Print the iteration header to stdout.
-pub fn print_iteration_header(iteration: u32) {
-    println!("{}", format_iteration_header(iteration));
+pub fn print_iteration_header(iteration: u32, run_id: &str, project: Option<&str>) {
+    println!(
+        "{}",
+        format_iteration_header(iteration, Some(run_id), project)
+    );
+}
+
+/// Format the banner written to ralph.log when a run starts.
+///
+/// Format: `=== Run <id> (<mode>) started <timestamp> ===`, with the
+/// project name in front of `Run` when there is one.
+pub fn format_run_banner(run_id: &str, mode: &str, started: &str, project: Option<&str>) -> String {
+    format!(
+        "=== {}Run {} ({}) started {} ===",
+        project::header_prefix(project),
+        run_id,
+        mode,
+        started
+    )
+}
+
+/// Append the run banner to ralph.log.
+pub fn log_run_banner(
+    run_id: &str,
+    mode: &str,
+    project: Option<&str>,
+    durable: bool,
+) -> Result<()> {
+    let started = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+    append_log(
+        &format!("{}\n\n", format_run_banner(run_id, mode, &started, project)),
+        durable,
+    )
 }
 
 /// Validate that all required files exist before starting the loop.
-pub fn validate_required_files() -> Result<()> {
-    let cwd = Path::new(".");
-    let missing: Vec<_> = REQUIRED_FILES
-        .iter()
-        .filter(|f| !cwd.join(f).exists())
-        .copied()
+///
+/// `spec_file` and `plan_file` are the spec and implementation plan
+/// locations (SPEC.md and IMPLEMENTATION_PLAN.md unless overridden with
+/// `--spec-file` and `--plan-file`). PROMPT.md is skipped unless
+/// `prompt_required` (it is not needed with `--prompt-from-url`).
+pub fn validate_required_files(
+    spec_file: &Path,
+    plan_file: &Path,
+    prompt_required: bool,
+) -> Result<()> {
+    let prompt = Path::new(files::PROMPT_FILE);
+    let missing: Vec<&Path> = [prompt, spec_file, plan_file]
+        .into_iter()
+        .filter(|f| prompt_required || *f != prompt)
+        .filter(|f| !f.exists())
         .collect();
 
     if !missing.is_empty() {
-        error::die(&format!("missing required files: {}", missing.join(", ")));
+        error::die(&format_missing_files(&missing));
     }
 
     Ok(())
 }
 
+/// Format the error for missing required files.
+///
+/// Each file is named as given and by its absolute path, so the message
+/// still makes sense when ralphctl was started from another directory.
+///
+/// Format: `missing required files: SPEC.md (/work/app/SPEC.md); run 'ralphctl init' to create them`
+pub fn format_missing_files(missing: &[&Path]) -> String {
+    let names: Vec<String> = missing
+        .iter()
+        .map(|f| match std::path::absolute(f) {
+            Ok(abs) if abs.as_path() != *f => format!("{} ({})", f.display(), abs.display()),
+            _ => f.display().to_string(),
+        })
+        .collect();
+    format!(
+        "missing required files: {}; run 'ralphctl init' to create them",
+        names.join(", ")
+    )
+}
+
+/// Abort if the plan has no tasks.
+///
+/// An empty plan leaves the loop without direction, so it's treated as an
+/// error unless `allow_empty` is set, in which case only a warning is shown.
+pub fn validate_plan_has_tasks(plan_file: &Path, format: parser::PlanFormat, allow_empty: bool) {
+    if plan_task_count(plan_file, format).is_none_or(|count| count.total > 0) {
+        return;
+    }
+    let msg = format!("{} has no tasks", plan_file.display());
+    if !allow_empty {
+        error::die(&msg);
+    }
+    eprintln!("warning: {}", msg);
+}
+
 /// Read the contents of PROMPT.md.
 ///
 /// Returns the full prompt content as a string to be piped to claude.
@@ -64,25 +150,457 @@ pub fn read_prompt() -> Result<String> {
     Ok(content)
 }
 
+/// What `run --prompt-max-bytes` does with a longer prompt.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum PromptOverflow {
+    /// Stop the run before sending it
+    #[default]
+    Error,
+    /// Send only the first N bytes, with a warning
+    Truncate,
+}
+
+/// Hold the composed prompt to `max_bytes` before it is piped to claude.
+///
+/// Returns the prompt unchanged when it fits. A longer prompt is an error,
+/// or with `PromptOverflow::Truncate` is cut back to the last character
+/// boundary within the limit and the original size is returned alongside.
+pub fn limit_prompt(
+    prompt: &str,
+    max_bytes: u64,
+    overflow: PromptOverflow,
+) -> Result<(&str, Option<usize>)> {
+    let len = prompt.len();
+    if len as u64 <= max_bytes {
+        return Ok((prompt, None));
+    }
+    if overflow == PromptOverflow::Error {
+        anyhow::bail!(
+            "prompt is {} bytes, over --prompt-max-bytes {}; shorten {} or pass --prompt-overflow truncate",
+            len,
+            max_bytes,
+            files::PROMPT_FILE
+        );
+    }
+    let mut end = max_bytes as usize;
+    while !prompt.is_char_boundary(end) {
+        end -= 1;
+    }
+    Ok((&prompt[..end], Some(len)))
+}
+
+/// Fingerprint of PROMPT.md's bytes, or `None` if it cannot be read.
+///
+/// Taken at run start and after every iteration to notice claude editing
+/// its own instructions.
+pub fn prompt_fingerprint() -> Option<u64> {
+    use std::hash::{DefaultHasher, Hash, Hasher};
+
+    let bytes = fs::read(files::PROMPT_FILE).ok()?;
+    let mut hasher = DefaultHasher::new();
+    bytes.hash(&mut hasher);
+    Some(hasher.finish())
+}
+
+/// Error for a PROMPT.md changed by `iteration`.
+pub fn format_prompt_modified(iteration: u32) -> String {
+    format!(
+        "{} was modified during iteration {} \u{2014} refusing to continue; \
+         restore it or rerun with --allow-prompt-edits",
+        files::PROMPT_FILE,
+        iteration
+    )
+}
+
+/// Record in ralph.log that PROMPT.md changed and the run stopped.
+pub fn log_prompt_modified(iteration: u32, durable: bool) -> Result<()> {
+    append_log(
+        &format!(
+            "--- {} modified during iteration {}; run stopped ---\n\n",
+            files::PROMPT_FILE,
+            iteration
+        ),
+        durable,
+    )
+}
+
+/// Format one iteration's ralph.log entry.
+///
+/// `seq` numbers entries across runs, so a resumed run's "Iteration 1" is
+/// still distinguishable from an earlier one.
+pub fn format_log_entry(
+    seq: u64,
+    iteration: u32,
+    run_id: &str,
+    project: Option<&str>,
+    stdout: &str,
+) -> String {
+    format!(
+        "{} [entry {}]\n{}\n--- end iteration {} ---\n\n",
+        format_iteration_header(iteration, Some(run_id), project),
+        seq,
+        stdout,
+        iteration
+    )
+}
+
+/// Highest `[entry N]` number in ralph.log `content`, or 0 without entries.
+pub fn highest_log_entry(content: &str) -> u64 {
+    content
+        .lines()
+        .filter_map(|line| {
+            line.rsplit_once(" [entry ")?
+                .1
+                .strip_suffix(']')?
+                .parse()
+                .ok()
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+/// Start numbering this run's ralph.log entries in `dir`.
+///
+/// Call before `--fresh` or `--fresh-log` clears the log, so its entries
+/// still count.
+pub fn resume_log_seq(dir: &Path) -> ids::LogSeq {
+    let log = fs::read_to_string(dir.join(files::LOG_FILE)).unwrap_or_default();
+    ids::LogSeq::resume(dir, highest_log_entry(&log))
+}
+
+/// Write a complete log entry with a single `write_all`, then flush.
+///
+/// The entry is formatted up front, so a failure cannot leave a header
+/// without its body from separate writes.
+pub fn write_log_entry<W: Write>(writer: &mut W, entry: &str) -> io::Result<()> {
+    writer.write_all(entry.as_bytes())?;
+    writer.flush()
+}
+
+/// Append `entry` to ralph.log, creating it if needed.
+///
+/// With `durable`, the file is fsynced before returning.
+fn append_log(entry: &str, durable: bool) -> Result<()> {
+    use std::fs::OpenOptions;
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(files::LOG_FILE)
+        .with_context(|| format!("failed to open {}", files::LOG_FILE))?;
+    write_log_entry(&mut file, entry)
+        .with_context(|| format!("failed to write {}", files::LOG_FILE))?;
+    if durable {
+        file.sync_all()
+            .with_context(|| format!("failed to sync {}", files::LOG_FILE))?;
+    }
+    Ok(())
+}
+
 /// Append iteration output to ralph.log.
 ///
 /// Creates the log file if it doesn't exist. Each iteration is logged with
-/// a header and separator for easy parsing.
-pub fn log_iteration(iteration: u32, stdout: &str) -> Result<()> {
+/// a header carrying the next entry number (from `.ralphctl/state.json`)
+/// and a separator for easy parsing.
+pub fn log_iteration(
+    iteration: u32,
+    seq: u64,
+    run_id: &str,
+    project: Option<&str>,
+    stdout: &str,
+    durable: bool,
+) -> Result<()> {
+    append_log(
+        &format_log_entry(seq, iteration, run_id, project, stdout),
+        durable,
+    )
+}
+
+/// Append an iteration's changed-file summary to ralph.log (`--show-diff`).
+pub fn log_changed_files(iteration: u32, summary: &str, durable: bool) -> Result<()> {
+    append_log(
+        &format!("--- changes in iteration {} ---\n{}\n", iteration, summary),
+        durable,
+    )
+}
+
+/// Append an iteration's tool-use summary to ralph.log.
+///
+/// Format: `--- tools in iteration 3: Edit×4, Read×9 ---`
+pub fn log_tool_summary(iteration: u32, summary: &tools::ToolSummary, durable: bool) -> Result<()> {
+    let mut line = format!(
+        "--- tools in iteration {}: {}",
+        iteration,
+        tools::format_counts(summary)
+    );
+    if summary.is_suspicious() {
+        line.push_str(" (no Edit/Write calls)");
+    }
+    append_log(&format!("{} ---\n\n", line), durable)
+}
+
+/// Format the plan progress recorded after an iteration (`--plan-progress-in-log`).
+///
+/// Format: `--- progress after iteration 3 (2026-01-02 10:00:00): [████░░░░░░░░] 33% (1/3 tasks) ---`
+pub fn format_progress_log(iteration: u32, at: &str, count: &parser::TaskCount) -> String {
+    format!(
+        "--- progress after iteration {} ({}): {} ---",
+        iteration,
+        at,
+        count.render_progress_bar(None)
+    )
+}
+
+/// Append the plan's progress after an iteration to ralph.log.
+pub fn log_plan_progress(iteration: u32, count: &parser::TaskCount, durable: bool) -> Result<()> {
+    let at = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+    append_log(
+        &format!("{}\n\n", format_progress_log(iteration, &at, count)),
+        durable,
+    )
+}
+
+/// Format one `--progress-file` line.
+///
+/// Format: `- 2026-01-02 10:00:00 — iteration 3: [████░░░░░░░░] 33% (1/3 tasks)`
+pub fn format_progress_line(iteration: u32, at: &str, count: &parser::TaskCount) -> String {
+    format!(
+        "- {} — iteration {}: {}",
+        at,
+        iteration,
+        count.render_progress_bar(None)
+    )
+}
+
+/// Append the plan's progress after an iteration to a markdown file,
+/// starting it with a `# Progress` heading if it does not exist yet.
+pub fn append_progress_file(path: &Path, iteration: u32, count: &parser::TaskCount) -> Result<()> {
     use std::fs::OpenOptions;
 
+    let at = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+    let mut entry = String::new();
+    if !path.exists() {
+        entry.push_str("# Progress\n\n");
+    }
+    entry.push_str(&format_progress_line(iteration, &at, count));
+    entry.push('\n');
     let mut file = OpenOptions::new()
         .create(true)
         .append(true)
-        .open(files::LOG_FILE)?;
+        .open(path)
+        .with_context(|| format!("failed to open {}", path.display()))?;
+    file.write_all(entry.as_bytes())
+        .with_context(|| format!("failed to write {}", path.display()))
+}
+
+/// Run a hook's shell command (`--pre-cmd`, `--on-blocked-retry`) in the
+/// working directory.
+///
+/// Returns its exit code (`None` if killed by a signal) and its stdout
+/// followed by its stderr.
+pub fn run_hook(flag: &str, cmd: &str) -> Result<(Option<i32>, String)> {
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(cmd)
+        .stdin(Stdio::null())
+        .output()
+        .with_context(|| format!("failed to run {}: {}", flag, cmd))?;
+    let mut text = String::from_utf8_lossy(&output.stdout).into_owned();
+    text.push_str(&String::from_utf8_lossy(&output.stderr));
+    Ok((output.status.code(), text))
+}
+
+/// Describe a command's exit: `exit code 2`, or `killed by a signal`.
+pub fn format_exit_status(code: Option<i32>) -> String {
+    match code {
+        Some(code) => format!("exit code {}", code),
+        None => "killed by a signal".to_string(),
+    }
+}
+
+/// Format the ralph.log entry for a hook run; `when` says which hook and
+/// iteration.
+///
+/// Format: `--- pre-cmd before iteration 2: make db-reset (exit code 0) ---`,
+/// then the command's output.
+pub fn format_hook_log(when: &str, cmd: &str, code: Option<i32>, output: &str) -> String {
+    let mut entry = format!(
+        "--- {}: {} ({}) ---\n{}",
+        when,
+        cmd,
+        format_exit_status(code),
+        output
+    );
+    if !entry.ends_with('\n') {
+        entry.push('\n');
+    }
+    entry.push('\n');
+    entry
+}
+
+/// Append a hook run and its output to ralph.log.
+pub fn log_hook(
+    when: &str,
+    cmd: &str,
+    code: Option<i32>,
+    output: &str,
+    durable: bool,
+) -> Result<()> {
+    append_log(&format_hook_log(when, cmd, code, output), durable)
+}
+
+/// Token overlap between two iteration outputs, from 0.0 to 1.0.
+///
+/// Outputs are split on whitespace and compared as multisets: twice the
+/// number of shared tokens over the total token count. Two empty outputs are
+/// identical. Linear in the output size, so it is safe on long iterations.
+pub fn output_similarity(previous: &str, current: &str) -> f64 {
+    let (a, b) = (token_counts(previous), token_counts(current));
+    let total: usize = a.values().sum::<usize>() + b.values().sum::<usize>();
+    if total == 0 {
+        return 1.0;
+    }
+    let shared: usize = a
+        .iter()
+        .map(|(token, n)| (*n).min(b.get(token).copied().unwrap_or(0)))
+        .sum();
+    2.0 * shared as f64 / total as f64
+}
+
+/// Count each whitespace-separated token in `s`.
+fn token_counts(s: &str) -> HashMap<&str, usize> {
+    let mut counts = HashMap::new();
+    for token in s.split_whitespace() {
+        *counts.entry(token).or_default() += 1;
+    }
+    counts
+}
+
+/// Append the similarity of an iteration's output to the previous one
+/// (`--log-similarity`).
+///
+/// Format: `--- similarity to previous: 0.94 ---`
+pub fn log_similarity(similarity: f64, durable: bool) -> Result<()> {
+    append_log(
+        &format!("--- similarity to previous: {:.2} ---\n\n", similarity),
+        durable,
+    )
+}
+
+/// Print the command line about to run (`--trace`) and append it to ralph.log.
+///
+/// Format: `+ claude -p --dangerously-skip-permissions --model opus`, quoted
+/// by `format_command` so it can be pasted into a shell.
+pub fn trace_command(argv: &[String], durable: bool) {
+    let line = format!("+ {}", format_command(argv));
+    eprintln!("{}", line);
+    warn_on_log_error(append_log(&format!("{}\n", line), durable));
+}
+
+/// Warn about a failed ralph.log write; logging never stops the loop.
+pub fn warn_on_log_error(result: Result<()>) {
+    if let Err(e) = result {
+        eprintln!("warning: {:#}", e);
+    }
+}
 
-    writeln!(file, "{}", format_iteration_header(iteration))?;
-    writeln!(file, "{}", stdout)?;
-    writeln!(file, "--- end iteration {} ---\n", iteration)?;
+/// Write one iteration's captured output to `<logs_dir>/iteration-<N>.log`.
+///
+/// Used by `--split-logs` in addition to `log_iteration`. An existing file
+/// for the same iteration (from an earlier run) is overwritten.
+pub fn write_iteration_log(logs_dir: &Path, iteration: u32, stdout: &str) -> Result<PathBuf> {
+    fs::create_dir_all(logs_dir)
+        .with_context(|| format!("failed to create {}", logs_dir.display()))?;
+    let path = logs_dir.join(format!("iteration-{}.log", iteration));
+    fs::write(&path, stdout).with_context(|| format!("failed to write {}", path.display()))?;
+    Ok(path)
+}
 
+/// Truncate ralph.log so the next run starts with a clean log.
+///
+/// Prints what was reset. Never touches SPEC.md or IMPLEMENTATION_PLAN.md.
+pub fn reset_log() -> Result<()> {
+    let path = Path::new(files::LOG_FILE);
+    match fs::metadata(path) {
+        Ok(meta) => {
+            fs::write(path, "")?;
+            println!("Reset {} ({} bytes cleared).", files::LOG_FILE, meta.len());
+        }
+        Err(_) => println!("Nothing to reset: {} not found.", files::LOG_FILE),
+    }
     Ok(())
 }
 
+/// Rename ralph.log to `ralph.log.<timestamp>` so the next run starts a new log.
+///
+/// A `-N` suffix is added if the rotated name is already taken. Returns the
+/// new file name, or `None` when there was no log to rotate.
+pub fn rotate_log(timestamp: &str) -> Result<Option<String>> {
+    let path = Path::new(files::LOG_FILE);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let base = format!("{}.{}", files::LOG_FILE, timestamp);
+    let rotated = std::iter::once(base.clone())
+        .chain((1..).map(|n| format!("{}-{}", base, n)))
+        .find(|name| !Path::new(name).exists())
+        .expect("unbounded candidate names");
+    fs::rename(path, &rotated)
+        .with_context(|| format!("failed to rotate {} to {}", files::LOG_FILE, rotated))?;
+    Ok(Some(rotated))
+}
+
+/// How often prompts check for Ctrl+C while waiting for input.
+const PROMPT_POLL_INTERVAL: u16 = 100;
+
+/// Read one line from stdin, giving up once `interrupt_flag` is set.
+///
+/// Returns `Ok(None)` if interrupted (Ctrl+C) before a line arrived, or if
+/// the flag was set by the time the line was read; a typed answer never
+/// overrides a Ctrl+C. The Ctrl+C handler restarts blocking reads, so stdin
+/// is polled in short intervals instead of blocking in `read_line`.
+///
+/// Bytes are read one at a time from the raw descriptor rather than through
+/// the buffered `Stdin`: a buffer could hold input that `poll` never
+/// reports, and would swallow the answers to later prompts.
+pub fn read_line_interruptible(interrupt_flag: &AtomicBool) -> Result<Option<String>> {
+    use nix::errno::Errno;
+    use nix::poll::{poll, PollFd, PollFlags, PollTimeout};
+    use std::os::fd::AsFd;
+
+    let stdin = io::stdin();
+    let mut line = Vec::new();
+    loop {
+        if interrupt_flag.load(Ordering::SeqCst) {
+            return Ok(None);
+        }
+        let mut fds = [PollFd::new(stdin.as_fd(), PollFlags::POLLIN)];
+        match poll(&mut fds, PollTimeout::from(PROMPT_POLL_INTERVAL)) {
+            Ok(0) | Err(Errno::EINTR) => continue,
+            Ok(_) => {}
+            Err(e) => return Err(e).context("failed to wait for input"),
+        }
+        let mut byte = [0u8; 1];
+        match nix::unistd::read(stdin.as_fd(), &mut byte) {
+            // End of input answers with whatever was typed so far
+            Ok(0) => break,
+            Ok(_) => {
+                line.push(byte[0]);
+                if byte[0] == b'\n' {
+                    break;
+                }
+            }
+            Err(Errno::EINTR) => continue,
+            Err(e) => return Err(e).context("failed to read input"),
+        }
+    }
+
+    if interrupt_flag.load(Ordering::SeqCst) {
+        return Ok(None);
+    }
+    Ok(Some(String::from_utf8_lossy(&line).into_owned()))
+}
+
 /// Result of prompting user to continue.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum PauseAction {
@@ -90,25 +608,42 @@ pub enum PauseAction {
     Continue,
     /// Stop the loop gracefully
     Stop,
+    /// Ctrl+C was pressed at the prompt
+    Interrupted,
+}
+
+/// Decide a `PauseAction` from the prompt answer (`None` if interrupted).
+fn pause_action(input: Option<&str>) -> PauseAction {
+    let Some(input) = input else {
+        return PauseAction::Interrupted;
+    };
+    let answer = input.trim().to_lowercase();
+    if answer.is_empty() || answer == "y" || answer == "yes" {
+        PauseAction::Continue
+    } else {
+        PauseAction::Stop
+    }
 }
 
 /// Prompt user to continue to next iteration.
 ///
 /// Returns `PauseAction::Continue` on 'y', 'Y', or empty input.
 /// Returns `PauseAction::Stop` on 'n', 'N', 'q', or 'Q'.
-pub fn prompt_continue() -> Result<PauseAction> {
+/// Returns `PauseAction::Interrupted` if `interrupt_flag` is set while waiting.
+/// Under `--yes` it continues without reading stdin.
+pub fn prompt_continue(interrupt_flag: &AtomicBool) -> Result<PauseAction> {
+    if cli::assume_yes() {
+        eprintln!("Continue? [Y/n] y (--yes)");
+        return Ok(PauseAction::Continue);
+    }
     eprint!("Continue? [Y/n] ");
     io::stderr().flush()?;
 
-    let mut input = String::new();
-    io::stdin().read_line(&mut input)?;
-
-    let answer = input.trim().to_lowercase();
-    if answer.is_empty() || answer == "y" || answer == "yes" {
-        Ok(PauseAction::Continue)
-    } else {
-        Ok(PauseAction::Stop)
+    let input = read_line_interruptible(interrupt_flag)?;
+    if input.is_none() {
+        eprintln!();
     }
+    Ok(pause_action(input.as_deref()))
 }
 
 /// Result of prompting user when no magic string was detected.
@@ -118,6 +653,21 @@ pub enum NoSignalAction {
     Continue,
     /// Stop the loop gracefully
     Stop,
+    /// Ctrl+C was pressed at the prompt
+    Interrupted,
+}
+
+/// Decide a `NoSignalAction` from the prompt answer (`None` if interrupted).
+fn no_signal_action(input: Option<&str>) -> NoSignalAction {
+    let Some(input) = input else {
+        return NoSignalAction::Interrupted;
+    };
+    let answer = input.trim().to_lowercase();
+    if answer.is_empty() || answer == "c" || answer == "continue" {
+        NoSignalAction::Continue
+    } else {
+        NoSignalAction::Stop
+    }
 }
 
 /// Prompt user for action when no magic string (DONE or BLOCKED) was detected.
@@ -127,29 +677,97 @@ pub enum NoSignalAction {
 ///
 /// Returns `NoSignalAction::Continue` on 'c', 'C', or empty input.
 /// Returns `NoSignalAction::Stop` on 's', 'S', 'q', or 'Q'.
-pub fn prompt_no_signal() -> Result<NoSignalAction> {
+/// Returns `NoSignalAction::Interrupted` if `interrupt_flag` is set while waiting.
+/// Under `--yes` it continues without reading stdin.
+pub fn prompt_no_signal(interrupt_flag: &AtomicBool) -> Result<NoSignalAction> {
     eprintln!("warning: no [[RALPH:DONE]] or [[RALPH:BLOCKED:...]] signal detected");
+    if cli::assume_yes() {
+        eprintln!("Continue or stop? [C/s] c (--yes)");
+        return Ok(NoSignalAction::Continue);
+    }
     eprint!("Continue or stop? [C/s] ");
     io::stderr().flush()?;
 
-    let mut input = String::new();
-    io::stdin().read_line(&mut input)?;
+    let input = read_line_interruptible(interrupt_flag)?;
+    if input.is_none() {
+        eprintln!();
+    }
+    Ok(no_signal_action(input.as_deref()))
+}
 
-    let answer = input.trim().to_lowercase();
-    if answer.is_empty() || answer == "c" || answer == "continue" {
-        Ok(NoSignalAction::Continue)
-    } else {
-        Ok(NoSignalAction::Stop)
+/// Result of the `--interactive-blocked` prompt.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BlockedAction {
+    /// Run the blocked iteration again
+    Retry,
+    /// Open a subshell, then ask again
+    Shell,
+    /// Stop as blocked, as without the flag
+    Abort,
+    /// Ctrl+C was pressed at the prompt
+    Interrupted,
+}
+
+/// Decide a `BlockedAction` from the prompt answer (`None` if interrupted).
+///
+/// End of input aborts; any other unrecognized answer returns `None` so the
+/// question is asked again.
+fn blocked_action(input: Option<&str>) -> Option<BlockedAction> {
+    let Some(input) = input else {
+        return Some(BlockedAction::Interrupted);
+    };
+    if input.is_empty() {
+        return Some(BlockedAction::Abort);
+    }
+    match input.trim().to_lowercase().as_str() {
+        "r" | "retry" => Some(BlockedAction::Retry),
+        "s" | "shell" => Some(BlockedAction::Shell),
+        "a" | "abort" => Some(BlockedAction::Abort),
+        _ => None,
+    }
+}
+
+/// Ask how to handle a BLOCKED signal (`--interactive-blocked`).
+///
+/// Never returns `BlockedAction::Shell`: choosing it runs `$SHELL` (or
+/// `/bin/sh`) in the working directory and asks again once it exits.
+pub fn prompt_blocked(interrupt_flag: &AtomicBool) -> Result<BlockedAction> {
+    loop {
+        eprint!("[r]etry after fixing, [s]hell, [a]bort? ");
+        io::stderr().flush()?;
+
+        let input = read_line_interruptible(interrupt_flag)?;
+        if input.as_deref().is_none_or(str::is_empty) {
+            eprintln!();
+        }
+        match blocked_action(input.as_deref()) {
+            Some(BlockedAction::Shell) => {
+                let shell = std::env::var("SHELL")
+                    .ok()
+                    .filter(|s| !s.is_empty())
+                    .unwrap_or_else(|| "/bin/sh".to_string());
+                eprintln!("Starting {}; exit it to return to this prompt.", shell);
+                if let Err(e) = Command::new(&shell).status() {
+                    eprintln!("warning: failed to start {}: {}", shell, e);
+                }
+            }
+            Some(action) => return Ok(action),
+            None => {}
+        }
     }
 }
 
 /// Print interrupt summary showing iterations completed and task progress.
 ///
 /// Format: `Interrupted after N iterations. X/Y tasks complete.`
-pub fn print_interrupt_summary(iterations_completed: u32) {
-    let task_summary = match fs::read_to_string(files::IMPLEMENTATION_PLAN_FILE) {
+pub fn print_interrupt_summary(
+    iterations_completed: u32,
+    plan_file: &Path,
+    format: parser::PlanFormat,
+) {
+    let task_summary = match fs::read_to_string(plan_file) {
         Ok(content) => {
-            let count = parser::count_checkboxes(&content);
+            let count = format.count_tasks(&content);
             format!("{}/{} tasks complete", count.completed, count.total)
         }
         Err(_) => "task status unknown".to_string(),
@@ -163,132 +781,1205 @@ pub fn print_interrupt_summary(iterations_completed: u32) {
     );
 }
 
-/// Print current progress from IMPLEMENTATION_PLAN.md.
+/// Print current progress from the implementation plan.
 ///
 /// Displays a progress bar showing task completion status after each iteration.
 /// Format: `[████████░░░░] 67% (67/100 tasks)`
-pub fn print_progress() {
-    match fs::read_to_string(files::IMPLEMENTATION_PLAN_FILE) {
+pub fn print_progress(plan_file: &Path, format: parser::PlanFormat) {
+    match fs::read_to_string(plan_file) {
         Ok(content) => {
-            let count = parser::count_checkboxes(&content);
-            println!("\n{}", count.render_progress_bar());
+            let count = format.count_tasks(&content);
+            println!("\n{}", count.render_progress_bar(cli::terminal_width()));
         }
         Err(_) => {
             eprintln!(
                 "warning: could not read {} for progress",
-                files::IMPLEMENTATION_PLAN_FILE
+                plan_file.display()
             );
         }
     }
 }
 
-/// Magic string indicating the ralph loop completed successfully (all tasks done).
-pub const RALPH_DONE_MARKER: &str = "[[RALPH:DONE]]";
-
-/// Magic string indicating a task was completed and the loop should continue.
-pub const RALPH_CONTINUE_MARKER: &str = "[[RALPH:CONTINUE]]";
-
-/// Result of running a single iteration of the claude subprocess.
-#[derive(Debug)]
-pub struct IterationResult {
-    /// Whether the subprocess exited successfully (exit code 0)
-    pub success: bool,
-    /// Exit code from the subprocess
-    pub exit_code: Option<i32>,
-    /// Captured stdout output for magic string detection
-    pub stdout: String,
-    /// Captured stderr output (used for BLOCKED signal detection)
-    #[allow(dead_code)]
-    pub stderr: String,
-    /// Whether the iteration was interrupted by Ctrl+C
-    pub was_interrupted: bool,
+/// Read the plan file and count its tasks in `format`, or `None` if it can't be read.
+pub fn plan_task_count(plan_file: &Path, format: parser::PlanFormat) -> Option<parser::TaskCount> {
+    fs::read_to_string(plan_file)
+        .ok()
+        .map(|content| format.count_tasks(&content))
 }
 
-/// Outcome of checking for magic strings in iteration output.
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub enum LoopSignal {
-    /// All tasks completed (RALPH:DONE detected)
-    Done,
-    /// Task completed, continue to next iteration (RALPH:CONTINUE detected)
-    Continue,
-    /// No signal detected
-    NoSignal,
-}
+/// Upper bound for the auto-derived `--max-iterations` default.
+pub const DEFAULT_MAX_ITERATIONS: u32 = 50;
 
-/// Check if the output contains a RALPH signal marker on its own line.
-///
-/// Scans the provided output string for magic strings `[[RALPH:DONE]]` or
-/// `[[RALPH:CONTINUE]]`. The marker must appear alone on a line (with optional
-/// whitespace) to be detected. This prevents false positives when Claude
-/// discusses or quotes the marker in its output.
+/// Derive a `--max-iterations` default from the plan's task count.
 ///
-/// Returns `LoopSignal::Done`, `LoopSignal::Continue`, or `LoopSignal::NoSignal`.
-pub fn detect_signal(output: &str) -> LoopSignal {
-    for line in output.lines() {
-        let trimmed = line.trim();
-        if trimmed == RALPH_DONE_MARKER {
-            return LoopSignal::Done;
-        }
-        if trimmed == RALPH_CONTINUE_MARKER {
-            return LoopSignal::Continue;
+/// Two iterations per open task plus five of slack, capped at
+/// `DEFAULT_MAX_ITERATIONS`. Falls back to the cap when the plan can't be
+/// read or has no checkboxes.
+pub fn auto_max_iterations(count: Option<&parser::TaskCount>) -> u32 {
+    match count {
+        Some(count) if count.total > 0 => {
+            let open = count.total.saturating_sub(count.completed);
+            open.saturating_mul(2)
+                .saturating_add(5)
+                .min(DEFAULT_MAX_ITERATIONS as usize) as u32
         }
+        _ => DEFAULT_MAX_ITERATIONS,
     }
-    LoopSignal::NoSignal
 }
 
-/// Magic string prefix for blocked signal.
-pub const RALPH_BLOCKED_PREFIX: &str = "[[RALPH:BLOCKED:";
-/// Magic string suffix for blocked signal.
-pub const RALPH_BLOCKED_SUFFIX: &str = "]]";
-
-/// Check if the output contains a RALPH:BLOCKED signal on its own line.
+/// Format how many tasks were checked off between loop start and end.
 ///
-/// Scans for `[[RALPH:BLOCKED:<reason>]]` pattern and extracts the reason.
-/// The marker must appear alone on a line (with optional whitespace) to be
-/// detected. This prevents false positives when Claude discusses or quotes
-/// the marker in its output.
+/// Format: `completed 7 tasks this session (13→20)`
+pub fn format_session_delta(start: &parser::TaskCount, end: &parser::TaskCount) -> String {
+    let delta = end.completed.saturating_sub(start.completed);
+    format!(
+        "completed {} task{} this session ({}→{})",
+        delta,
+        if delta == 1 { "" } else { "s" },
+        start.completed,
+        end.completed
+    )
+}
+
+/// Tasks in the plan at `plan_file`, or none if it cannot be read.
+pub fn plan_tasks(plan_file: &Path) -> Vec<parser::Task> {
+    fs::read_to_string(plan_file)
+        .map(|content| parser::parse_tasks(&content))
+        .unwrap_or_default()
+}
+
+/// Texts of tasks checked in `after` that were not checked in `before`.
 ///
-/// Returns `Some(reason)` if found, `None` otherwise.
-pub fn detect_blocked_signal(output: &str) -> Option<String> {
-    for line in output.lines() {
-        let trimmed = line.trim();
-        if let Some(rest) = trimmed.strip_prefix(RALPH_BLOCKED_PREFIX) {
-            if let Some(reason) = rest.strip_suffix(RALPH_BLOCKED_SUFFIX) {
-                return Some(reason.to_string());
+/// Tasks are matched by text, so a task that was reworded while being
+/// checked off still shows up. Duplicate texts are counted individually.
+pub fn newly_completed_tasks(before: &[parser::Task], after: &[parser::Task]) -> Vec<String> {
+    let mut done_before: Vec<&str> = before
+        .iter()
+        .filter(|t| t.completed)
+        .map(|t| t.text.as_str())
+        .collect();
+    after
+        .iter()
+        .filter(|t| t.completed)
+        .filter(|t| match done_before.iter().position(|d| *d == t.text) {
+            Some(i) => {
+                done_before.swap_remove(i);
+                false
             }
-        }
+            None => true,
+        })
+        .map(|t| t.text.clone())
+        .collect()
+}
+
+/// A `--model-per-phase` mapping from a plan heading to a claude model.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PhaseModel {
+    /// Text matched (case-insensitively) against the active phase heading
+    pub heading: String,
+    /// Model to use while that phase is active
+    pub model: String,
+}
+
+/// Parse a `HEADING=MODEL` argument for `--model-per-phase`.
+pub fn parse_phase_model(s: &str) -> Result<PhaseModel, String> {
+    let (heading, model) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected HEADING=MODEL, got '{}'", s))?;
+    let (heading, model) = (heading.trim(), model.trim());
+    if heading.is_empty() || model.is_empty() {
+        return Err(format!("expected HEADING=MODEL, got '{}'", s));
     }
-    None
+    Ok(PhaseModel {
+        heading: heading.to_string(),
+        model: model.to_string(),
+    })
 }
 
-/// Spawn `claude -p` as a subprocess and pipe the prompt via stdin.
-///
-/// Streams stdout and stderr to the terminal in real-time while also
-/// capturing the output for magic string detection.
-/// Returns the result of the iteration after claude completes.
+/// Pick the mapped model for the plan's active phase.
 ///
-/// If `interrupt_flag` is provided and set to true during execution,
-/// the child process will be killed and the function returns with
-/// `was_interrupted` set to true in the result.
-pub fn spawn_claude(
+/// The active phase is the first section with incomplete tasks. Returns the
+/// first mapping whose heading text it contains, along with that phase
+/// heading, or `None` when the phase is unmapped.
+pub fn resolve_phase_model<'a>(
+    plan_content: &str,
+    mappings: &'a [PhaseModel],
+) -> Option<(String, &'a str)> {
+    let phase = parser::active_section(plan_content)?;
+    let lower = phase.to_lowercase();
+    mappings
+        .iter()
+        .find(|m| lower.contains(&m.heading.to_lowercase()))
+        .map(|m| (phase, m.model.as_str()))
+}
+
+/// Terminal outcome of a loop run, reported in the final summary.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RunOutcome {
+    /// All tasks complete (RALPH:DONE detected)
+    Done,
+    /// Blocked, requires human intervention
+    Blocked(String),
+    /// Max iterations reached without a terminal signal
+    MaxIterations,
+    /// Stopped at a pause or no-signal prompt
+    Stopped,
+    /// Interrupted by Ctrl+C
+    Interrupted,
+    /// An iteration ended without a signal under `--strict-signals`
+    NoSignal,
+    /// An iteration exceeded `--max-output-bytes` and was killed
+    OutputLimit,
+    /// Too many consecutive iterations printed nothing (`--max-blank-iterations`)
+    BlankOutput,
+    /// A task was checked off under `--once-per-task`
+    TaskCompleted(String),
+    /// Investigation answered (reverse mode)
+    Found(String),
+    /// Investigation inconclusive (reverse mode)
+    Inconclusive(String),
+    /// The loop could not go on, e.g. claude exited non-zero
+    Failed(String),
+    /// An iteration edited PROMPT.md without `--allow-prompt-edits`
+    PromptModified(u32),
+}
+
+impl RunOutcome {
+    /// Short human-readable label for the summary block.
+    pub fn label(&self) -> String {
+        match self {
+            RunOutcome::Done => "done".to_string(),
+            RunOutcome::Blocked(reason) => format!("blocked ({})", reason),
+            RunOutcome::MaxIterations => "max iterations reached".to_string(),
+            RunOutcome::Stopped => "stopped by user".to_string(),
+            RunOutcome::Interrupted => "interrupted".to_string(),
+            RunOutcome::NoSignal => "no signal (strict)".to_string(),
+            RunOutcome::OutputLimit => "output limit exceeded".to_string(),
+            RunOutcome::BlankOutput => "blank output".to_string(),
+            RunOutcome::TaskCompleted(task) => format!("task completed ({})", task),
+            RunOutcome::Found(summary) => format!("found ({})", summary),
+            RunOutcome::Inconclusive(reason) => format!("inconclusive ({})", reason),
+            RunOutcome::Failed(reason) => format!("failed ({})", reason),
+            RunOutcome::PromptModified(iteration) => {
+                format!("prompt modified (iteration {})", iteration)
+            }
+        }
+    }
+
+    /// Machine-readable outcome name, as used in `--webhook` payloads.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            RunOutcome::Done => "done",
+            RunOutcome::Blocked(_) => "blocked",
+            RunOutcome::MaxIterations => "max_iterations",
+            RunOutcome::Stopped => "stopped",
+            RunOutcome::Interrupted => "interrupted",
+            RunOutcome::NoSignal => "no_signal",
+            RunOutcome::OutputLimit => "output_limit",
+            RunOutcome::BlankOutput => "blank_output",
+            RunOutcome::TaskCompleted(_) => "task_completed",
+            RunOutcome::Found(_) => "found",
+            RunOutcome::Inconclusive(_) => "inconclusive",
+            RunOutcome::Failed(_) => "failed",
+            RunOutcome::PromptModified(_) => "prompt_modified",
+        }
+    }
+
+    /// Process exit code `run` and `reverse` end with for this outcome.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            RunOutcome::Done | RunOutcome::Stopped | RunOutcome::TaskCompleted(_) => exit::SUCCESS,
+            RunOutcome::Found(_) => exit::FOUND,
+            RunOutcome::Blocked(_) => exit::BLOCKED,
+            RunOutcome::MaxIterations => exit::MAX_ITERATIONS,
+            RunOutcome::Interrupted => exit::INTERRUPTED,
+            RunOutcome::NoSignal => exit::NO_SIGNAL,
+            RunOutcome::BlankOutput => exit::BLANK_OUTPUT,
+            RunOutcome::OutputLimit => exit::OUTPUT_LIMIT,
+            RunOutcome::Inconclusive(_) => exit::INCONCLUSIVE,
+            RunOutcome::Failed(_) | RunOutcome::PromptModified(_) => exit::ERROR,
+        }
+    }
+
+    /// Reason or summary carried by the outcome, if any.
+    pub fn reason(&self) -> Option<&str> {
+        match self {
+            RunOutcome::Blocked(s)
+            | RunOutcome::TaskCompleted(s)
+            | RunOutcome::Found(s)
+            | RunOutcome::Inconclusive(s)
+            | RunOutcome::Failed(s) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+/// Statistics shown in the final run summary.
+///
+/// Mode-specific lines (task progress, hypotheses, ...) are carried in `fields`
+/// so forward and reverse mode share the same summary format.
+#[derive(Debug, Clone)]
+pub struct RunStats {
+    /// Number of iterations that ran to completion
+    pub iterations: u32,
+    /// Wall time since the loop started
+    pub elapsed: Duration,
+    /// Additional `(label, value)` lines in display order
+    pub fields: Vec<(&'static str, String)>,
+    /// Percentage of plan tasks complete (forward mode only)
+    pub task_percentage: Option<u8>,
+}
+
+impl RunStats {
+    /// Create stats with no mode-specific fields.
+    pub fn new(iterations: u32, elapsed: Duration) -> Self {
+        Self {
+            iterations,
+            elapsed,
+            fields: Vec::new(),
+            task_percentage: None,
+        }
+    }
+
+    /// Append a summary line only when `value` is present.
+    pub fn with_optional_field(self, label: &'static str, value: Option<&str>) -> Self {
+        match value {
+            Some(value) => self.with_field(label, value),
+            None => self,
+        }
+    }
+
+    /// Append a mode-specific summary line.
+    pub fn with_field(mut self, label: &'static str, value: impl Into<String>) -> Self {
+        self.fields.push((label, value.into()));
+        self
+    }
+}
+
+/// Build forward mode run stats, reading task progress from the plan file.
+pub fn forward_stats(
+    plan_file: &Path,
+    format: parser::PlanFormat,
+    iterations: u32,
+    elapsed: Duration,
+) -> RunStats {
+    let count = plan_task_count(plan_file, format);
+    let tasks = match &count {
+        Some(count) => format!(
+            "{}/{} ({}%)",
+            count.completed,
+            count.total,
+            count.percentage()
+        ),
+        None => "unknown".to_string(),
+    };
+    let mut stats = RunStats::new(iterations, elapsed).with_field("Tasks", tasks);
+    stats.task_percentage = count.map(|c| c.percentage());
+    stats
+}
+
+/// Format the multi-line summary block printed when a loop exits.
+pub fn format_run_summary(outcome: &RunOutcome, stats: &RunStats) -> String {
+    let log_path = std::env::current_dir()
+        .map(|d| d.join(files::LOG_FILE).display().to_string())
+        .unwrap_or_else(|_| files::LOG_FILE.to_string());
+
+    let mut lines = vec![
+        "=== Run summary ===".to_string(),
+        format!("{:<12}{}", "Outcome:", outcome.label()),
+        format!("{:<12}{}", "Iterations:", stats.iterations),
+    ];
+    for (label, value) in &stats.fields {
+        lines.push(format!("{:<12}{}", format!("{}:", label), value));
+    }
+    lines.push(format!(
+        "{:<12}{}",
+        "Elapsed:",
+        duration::format_duration(stats.elapsed)
+    ));
+    lines.push(format!("{:<12}{}", "Log:", log_path));
+    lines.join("\n")
+}
+
+/// Print the run summary block to stderr.
+///
+/// Called on every terminal outcome of `run` and `reverse` so all exit paths
+/// report the same information.
+pub fn print_run_summary(outcome: &RunOutcome, stats: &RunStats) {
+    eprintln!();
+    eprintln!("{}", format_run_summary(outcome, stats));
+}
+
+/// Build the JSON form of the run summary, as posted by `--webhook`.
+///
+/// Carries the same information as the printed summary block: outcome (with
+/// its reason, if any), iterations, duration, task percentage, and the
+/// mode-specific fields keyed by label.
+pub fn summary_json(outcome: &RunOutcome, stats: &RunStats) -> serde_json::Value {
+    let fields: serde_json::Map<String, serde_json::Value> = stats
+        .fields
+        .iter()
+        .map(|(label, value)| (label.to_string(), value.clone().into()))
+        .collect();
+    serde_json::json!({
+        "outcome": outcome.kind(),
+        "reason": outcome.reason(),
+        "blocked_category": blocked_category(outcome),
+        "iterations": stats.iterations,
+        "duration_secs": stats.elapsed.as_secs_f64(),
+        "task_percentage": stats.task_percentage,
+        "fields": fields,
+    })
+}
+
+/// How long `post_webhook` waits for the server before giving up.
+pub const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// POST `payload` as JSON to `url`, blocking until the server answers or
+/// `WEBHOOK_TIMEOUT` passes.
+///
+/// Runs on its own thread and runtime so the synchronous loop can call it,
+/// and waits for that thread, so a slow server delays exit by up to the
+/// timeout. Errors (including timeouts and non-2xx responses) are returned
+/// for the caller to report; they should never fail the run.
+pub fn post_webhook(url: &str, payload: &serde_json::Value) -> Result<()> {
+    let target = url.to_string();
+    let body = serde_json::to_string(payload)?;
+    thread::spawn(move || -> Result<()> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        runtime.block_on(async {
+            let response = reqwest::Client::builder()
+                .timeout(WEBHOOK_TIMEOUT)
+                .build()?
+                .post(&target)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(body)
+                .send()
+                .await?;
+            if !response.status().is_success() {
+                anyhow::bail!("HTTP {}", response.status().as_u16());
+            }
+            Ok(())
+        })
+    })
+    .join()
+    .map_err(|_| anyhow::anyhow!("webhook thread panicked"))?
+    .with_context(|| format!("webhook POST to {} failed", url))
+}
+
+/// Download the prompt for `run --prompt-from-url`.
+///
+/// Runs on its own thread and runtime so the synchronous loop can call it.
+/// The prompt is fetched fresh on every run and never cached.
+pub fn fetch_prompt_from_url(url: &str) -> Result<String> {
+    let target = url.to_string();
+    let content = thread::spawn(move || -> Result<String> {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?
+            .block_on(templates::fetch_url(&target))
+    })
+    .join()
+    .map_err(|_| anyhow::anyhow!("prompt download thread panicked"))??;
+
+    if content.trim().is_empty() {
+        anyhow::bail!("prompt at {} is empty", url);
+    }
+    Ok(content)
+}
+
+/// One iteration's entry in the `--report` JSON array.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct IterationRecord {
+    /// 1-based iteration number
+    pub iteration: u32,
+    /// Size of claude's stdout in bytes
+    pub output_bytes: usize,
+    /// Detected signal: `done`, `continue`, `blocked`, `interrupted`, `truncated`,
+    /// or `none`
+    pub signal: &'static str,
+    /// Wall time of the claude invocation in milliseconds
+    pub duration_ms: u64,
+    /// Category of a `blocked` signal (`env`, `decision`, `access`), if given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blocked_category: Option<&'static str>,
+    /// Tool calls, when claude's output was JSON carrying them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tools: Option<tools::ToolSummary>,
+}
+
+impl IterationRecord {
+    /// Build a record from a finished iteration.
+    pub fn new(iteration: u32, result: &IterationResult, duration: Duration) -> Self {
+        let mut blocked_category = None;
+        let signal = if result.was_interrupted {
+            "interrupted"
+        } else if result.truncated {
+            "truncated"
+        } else {
+            match detect_first_signal(&result.stdout) {
+                LoopSignal::Done => "done",
+                LoopSignal::Continue => "continue",
+                LoopSignal::Blocked(payload) => {
+                    blocked_category = blocked_category_name(&payload);
+                    "blocked"
+                }
+                LoopSignal::NoSignal => "none",
+            }
+        };
+        Self {
+            iteration,
+            output_bytes: result.stdout.len(),
+            signal,
+            duration_ms: duration.as_millis() as u64,
+            blocked_category,
+            tools: tools::summarize(&result.stdout),
+        }
+    }
+}
+
+/// Write the iteration records to `path` as a pretty-printed JSON array.
+pub fn write_report(path: &Path, records: &[IterationRecord]) -> Result<()> {
+    let json = serde_json::to_string_pretty(records)?;
+    fs::write(path, json + "\n")
+        .with_context(|| format!("failed to write report: {}", path.display()))
+}
+
+/// Magic string indicating the ralph loop completed successfully (all tasks done).
+pub const RALPH_DONE_MARKER: &str = "[[RALPH:DONE]]";
+
+/// Magic string indicating a task was completed and the loop should continue.
+pub const RALPH_CONTINUE_MARKER: &str = "[[RALPH:CONTINUE]]";
+
+/// Closing `]]` of every marker that carries an argument.
+pub const RALPH_MARKER_SUFFIX: &str = "]]";
+
+/// Result of running a single iteration of the claude subprocess.
+#[derive(Debug, Clone)]
+pub struct IterationResult {
+    /// Whether the subprocess exited successfully (exit code 0)
+    pub success: bool,
+    /// Exit code from the subprocess
+    pub exit_code: Option<i32>,
+    /// Captured stdout output for magic string detection
+    pub stdout: String,
+    /// Captured stderr output (used for BLOCKED signal detection)
+    #[allow(dead_code)]
+    pub stderr: String,
+    /// Whether the iteration was interrupted by Ctrl+C
+    pub was_interrupted: bool,
+    /// Whether claude was killed for exceeding `max_output_bytes`
+    pub truncated: bool,
+    /// Whether claude was stopped after printing a terminal signal
+    /// (`--early-stop`); the signal line is the last one that matters in `stdout`
+    pub stopped_early: bool,
+}
+
+/// Outcome of checking for magic strings in iteration output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LoopSignal {
+    /// All tasks completed (RALPH:DONE detected)
+    Done,
+    /// Task completed, continue to next iteration (RALPH:CONTINUE detected)
+    Continue,
+    /// Cannot proceed without a human (RALPH:BLOCKED detected); only returned
+    /// by `detect_first_signal`
+    Blocked(String),
+    /// No signal detected
+    NoSignal,
+}
+
+/// Check if the output contains a RALPH signal marker on its own line.
+///
+/// Scans the provided output string for magic strings `[[RALPH:DONE]]` or
+/// `[[RALPH:CONTINUE]]`. The marker must appear alone on a line (with optional
+/// whitespace) to be detected. This prevents false positives when Claude
+/// discusses or quotes the marker in its output.
+///
+/// Callers pass the full captured stdout once the child has exited, never
+/// individual pipe reads. A marker that claude flushes in pieces (e.g.
+/// `[[RALPH:` then `DONE]]`) or writes without a trailing newline is therefore
+/// still detected, even if the streamed display showed it mid-line.
+///
+/// Returns `LoopSignal::Done`, `LoopSignal::Continue`, or `LoopSignal::NoSignal`.
+/// The loops use `detect_first_signal`, which also handles BLOCKED.
+pub fn detect_signal(output: &str) -> LoopSignal {
+    for line in output.lines() {
+        let trimmed = line.trim();
+        if trimmed == RALPH_DONE_MARKER {
+            return LoopSignal::Done;
+        }
+        if trimmed == RALPH_CONTINUE_MARKER {
+            return LoopSignal::Continue;
+        }
+    }
+    LoopSignal::NoSignal
+}
+
+/// Detect the iteration's signal in a single pass over the output lines.
+///
+/// A BLOCKED marker anywhere wins, since it needs a human whatever else the
+/// output says. Otherwise the first DONE or CONTINUE line decides, so output
+/// such as a CONTINUE followed by a DONE is always read the same way. Markers
+/// must be alone on their line, as with `detect_signal`.
+pub fn detect_first_signal(output: &str) -> LoopSignal {
+    let mut first = LoopSignal::NoSignal;
+    for line in output.lines() {
+        let trimmed = line.trim();
+        if let Some(reason) = trimmed
+            .strip_prefix(RALPH_BLOCKED_PREFIX)
+            .and_then(|rest| rest.strip_suffix(RALPH_BLOCKED_SUFFIX))
+        {
+            return LoopSignal::Blocked(reason.to_string());
+        }
+        if first == LoopSignal::NoSignal {
+            if trimmed == RALPH_DONE_MARKER {
+                first = LoopSignal::Done;
+            } else if trimmed == RALPH_CONTINUE_MARKER {
+                first = LoopSignal::Continue;
+            }
+        }
+    }
+    first
+}
+
+/// Find a DONE or CONTINUE marker mentioned anywhere in the output.
+///
+/// Unlike `detect_signal`, the marker need not be alone on its line; the
+/// earliest marker wins. Used by `--interactive-signals` to ask the user
+/// whether an inline mention was meant as a signal. Returns `None` if no
+/// marker appears at all.
+pub fn detect_inline_signal(output: &str) -> Option<LoopSignal> {
+    let done = output.find(RALPH_DONE_MARKER);
+    let cont = output.find(RALPH_CONTINUE_MARKER);
+    match (done, cont) {
+        (Some(d), Some(c)) if c < d => Some(LoopSignal::Continue),
+        (Some(_), _) => Some(LoopSignal::Done),
+        (None, Some(_)) => Some(LoopSignal::Continue),
+        (None, None) => None,
+    }
+}
+
+/// Ask whether an inline marker found by `detect_inline_signal` should count.
+///
+/// Returns `Some(true)` on 'y' or 'yes'; anything else (including empty
+/// input) keeps the no-signal behavior. Returns `None` if `interrupt_flag`
+/// is set while waiting.
+pub fn prompt_inline_signal(
+    signal: &LoopSignal,
+    interrupt_flag: &AtomicBool,
+) -> Result<Option<bool>> {
+    let name = match signal {
+        LoopSignal::Done => "DONE",
+        LoopSignal::Continue => "CONTINUE",
+        LoopSignal::Blocked(_) | LoopSignal::NoSignal => return Ok(Some(false)),
+    };
+    eprint!(
+        "looks like {} was mentioned inline\u{2014}treat as {}? [y/N] ",
+        name,
+        name.to_lowercase()
+    );
+    io::stderr().flush()?;
+
+    let Some(input) = read_line_interruptible(interrupt_flag)? else {
+        eprintln!();
+        return Ok(None);
+    };
+
+    let answer = input.trim().to_lowercase();
+    Ok(Some(answer == "y" || answer == "yes"))
+}
+
+/// Magic string prefix for blocked signal.
+pub const RALPH_BLOCKED_PREFIX: &str = "[[RALPH:BLOCKED:";
+/// Magic string suffix for blocked signal.
+pub const RALPH_BLOCKED_SUFFIX: &str = RALPH_MARKER_SUFFIX;
+
+/// Check if the output contains a RALPH:BLOCKED signal on its own line.
+///
+/// Scans for `[[RALPH:BLOCKED:<reason>]]` pattern and extracts the reason.
+/// The marker must appear alone on a line (with optional whitespace) to be
+/// detected. This prevents false positives when Claude discusses or quotes
+/// the marker in its output.
+///
+/// Returns `Some(reason)` if found, `None` otherwise.
+pub fn detect_blocked_signal(output: &str) -> Option<String> {
+    for line in output.lines() {
+        let trimmed = line.trim();
+        if let Some(rest) = trimmed.strip_prefix(RALPH_BLOCKED_PREFIX) {
+            if let Some(reason) = rest.strip_suffix(RALPH_BLOCKED_SUFFIX) {
+                return Some(reason.to_string());
+            }
+        }
+    }
+    None
+}
+
+/// Output shorter than this once trimmed counts as blank.
+pub const BLANK_OUTPUT_BYTES: usize = 8;
+
+/// Whether an iteration's output is blank: empty or under
+/// `BLANK_OUTPUT_BYTES` once surrounding whitespace is trimmed.
+///
+/// Such output cannot hold a signal; several in a row usually mean claude is
+/// failing silently (exit 0, no tokens).
+pub fn is_blank_output(output: &str) -> bool {
+    output.trim().len() < BLANK_OUTPUT_BYTES
+}
+
+/// Least output, once trimmed, that `--strict-signals` fails on.
+pub const STRICT_SIGNAL_MIN_BYTES: usize = 64;
+
+/// Whether an iteration said enough that a missing signal is a mistake
+/// rather than a stall: at least `STRICT_SIGNAL_MIN_BYTES` once trimmed.
+///
+/// Shorter output under `--strict-signals` gets the usual no-signal handling.
+pub fn is_substantial_output(output: &str) -> bool {
+    output.trim().len() >= STRICT_SIGNAL_MIN_BYTES
+}
+
+/// Whether `line` alone carries a signal that ends the loop (DONE or BLOCKED).
+///
+/// The `--early-stop` matcher for `run`: claude is stopped as soon as it
+/// prints such a line instead of after it exits.
+pub fn is_terminal_signal_line(line: &str) -> bool {
+    matches!(
+        detect_first_signal(line),
+        LoopSignal::Done | LoopSignal::Blocked(_)
+    )
+}
+
+/// Magic string prefix for the self-reported progress marker.
+pub const RALPH_PROGRESS_PREFIX: &str = "[[RALPH:PROGRESS:";
+
+/// Find a `[[RALPH:PROGRESS:<pct>]]` marker on its own line.
+///
+/// The marker is informational and never ends the loop. The percentage must
+/// be a whole number from 0 to 100; malformed markers are ignored. When the
+/// output reports progress more than once, the last valid marker wins.
+pub fn detect_progress(output: &str) -> Option<u8> {
+    output.lines().rev().find_map(|line| {
+        line.trim()
+            .strip_prefix(RALPH_PROGRESS_PREFIX)?
+            .strip_suffix(RALPH_MARKER_SUFFIX)
+            .filter(|pct| !pct.is_empty() && pct.chars().all(|c| c.is_ascii_digit()))?
+            .parse::<u8>()
+            .ok()
+            .filter(|pct| *pct <= 100)
+    })
+}
+
+/// Kind of blocker, given as an optional `<category>:` prefix on the reason.
+///
+/// Lets automation tell machine-fixable blockers (a missing env var) from
+/// ones that need a person (a design decision).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockedCategory {
+    /// Missing or wrong environment variable or configuration (`env:`)
+    Env,
+    /// A human has to make a choice (`decision:`)
+    Decision,
+    /// Missing credentials or permissions (`access:`)
+    Access,
+    /// No category, or one that isn't recognized
+    Other,
+}
+
+impl BlockedCategory {
+    /// Accepted category words; anything else leaves the reason untouched.
+    pub const KNOWN: &'static [(&'static str, BlockedCategory)] = &[
+        ("env", BlockedCategory::Env),
+        ("decision", BlockedCategory::Decision),
+        ("access", BlockedCategory::Access),
+    ];
+
+    /// Category name as written in the marker and in JSON output.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BlockedCategory::Env => "env",
+            BlockedCategory::Decision => "decision",
+            BlockedCategory::Access => "access",
+            BlockedCategory::Other => "other",
+        }
+    }
+}
+
+/// A BLOCKED payload split into its category and reason.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockedReason {
+    pub category: BlockedCategory,
+    pub reason: String,
+}
+
+/// Split a BLOCKED payload such as `env:DATABASE_URL not set`.
+///
+/// Only an exact, lowercase word from `BlockedCategory::KNOWN` followed by a
+/// colon and a non-empty reason counts as a category, so reasons that merely
+/// start with `word:` (`note: ...`, `https://...`) stay whole under `Other`.
+pub fn parse_blocked_reason(payload: &str) -> BlockedReason {
+    let categorized = payload.split_once(':').and_then(|(word, rest)| {
+        let (_, category) = BlockedCategory::KNOWN.iter().find(|(w, _)| *w == word)?;
+        let reason = rest.trim();
+        (!reason.is_empty()).then(|| BlockedReason {
+            category: *category,
+            reason: reason.to_string(),
+        })
+    });
+    categorized.unwrap_or_else(|| BlockedReason {
+        category: BlockedCategory::Other,
+        reason: payload.to_string(),
+    })
+}
+
+/// Format the `blocked:` line printed when a loop stops on a blocker.
+///
+/// Format: `blocked [env]: DATABASE_URL not set`, or `blocked: <reason>`
+/// without a category.
+pub fn format_blocked(payload: &str) -> String {
+    let blocked = parse_blocked_reason(payload);
+    match blocked.category {
+        BlockedCategory::Other => format!("blocked: {}", blocked.reason),
+        category => format!("blocked [{}]: {}", category.as_str(), blocked.reason),
+    }
+}
+
+/// Known category name of a BLOCKED payload for JSON output.
+fn blocked_category_name(payload: &str) -> Option<&'static str> {
+    match parse_blocked_reason(payload).category {
+        BlockedCategory::Other => None,
+        category => Some(category.as_str()),
+    }
+}
+
+/// Category of a blocked outcome for JSON output, if it has a known one.
+fn blocked_category(outcome: &RunOutcome) -> Option<&'static str> {
+    match outcome {
+        RunOutcome::Blocked(payload) => blocked_category_name(payload),
+        _ => None,
+    }
+}
+
+/// Environment variable holding the 1-based iteration number.
+pub const ITERATION_ENV: &str = "RALPH_ITERATION";
+
+/// Environment variable holding the run's iteration limit.
+pub const MAX_ITERATIONS_ENV: &str = "RALPH_MAX_ITERATIONS";
+
+/// Environment variable holding the number of checked plan tasks.
+pub const TASKS_COMPLETED_ENV: &str = "RALPH_TASKS_COMPLETED";
+
+/// Environment variable holding the total number of plan tasks.
+pub const TASKS_TOTAL_ENV: &str = "RALPH_TASKS_TOTAL";
+
+/// Environment variables telling claude where it is in the run's budget.
+///
+/// The task variables are omitted when the plan could not be read.
+pub fn budget_env(
+    iteration: u32,
+    max_iterations: u32,
+    tasks: Option<&parser::TaskCount>,
+) -> Vec<(&'static str, String)> {
+    let mut env = vec![
+        (ITERATION_ENV, iteration.to_string()),
+        (MAX_ITERATIONS_ENV, max_iterations.to_string()),
+    ];
+    if let Some(tasks) = tasks {
+        env.push((TASKS_COMPLETED_ENV, tasks.completed.to_string()));
+        env.push((TASKS_TOTAL_ENV, tasks.total.to_string()));
+    }
+    env
+}
+
+/// Environment variable holding the iteration's scratch directory.
+pub const SCRATCH_DIR_ENV: &str = "RALPH_SCRATCH_DIR";
+
+/// Prompt placeholder replaced with the iteration's scratch directory.
+pub const SCRATCH_DIR_PLACEHOLDER: &str = "{{SCRATCH_DIR}}";
+
+/// Parse the iteration number from a scratch directory name (`iter-<N>`).
+fn scratch_iteration(name: &str) -> Option<u32> {
+    name.strip_prefix("iter-")?.parse().ok()
+}
+
+/// The `iter-<N>` scratch directory under `base`, whether or not it exists.
+pub fn scratch_dir(base: &Path, iteration: u32) -> PathBuf {
+    base.join(format!("iter-{}", iteration))
+}
+
+/// Create an empty `iter-<N>` scratch directory under `base`.
+///
+/// A leftover directory with the same number is emptied first. Returns the
+/// absolute path so it can be handed to claude regardless of its cwd.
+pub fn prepare_scratch_dir(base: &Path, iteration: u32) -> Result<PathBuf> {
+    let dir = scratch_dir(base, iteration);
+    if dir.exists() {
+        fs::remove_dir_all(&dir).with_context(|| format!("failed to clear {}", dir.display()))?;
+    }
+    fs::create_dir_all(&dir).with_context(|| format!("failed to create {}", dir.display()))?;
+    Ok(std::path::absolute(&dir)?)
+}
+
+/// Remove all but the `keep` highest-numbered scratch directories under `base`.
+///
+/// Entries that aren't `iter-<N>` directories are left alone. Returns the
+/// number of directories removed.
+pub fn prune_scratch_dirs(base: &Path, keep: usize) -> Result<usize> {
+    let Ok(entries) = fs::read_dir(base) else {
+        return Ok(0);
+    };
+    let mut dirs: Vec<(u32, PathBuf)> = entries
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().is_dir())
+        .filter_map(|e| Some((scratch_iteration(e.file_name().to_str()?)?, e.path())))
+        .collect();
+    dirs.sort_by_key(|(n, _)| std::cmp::Reverse(*n));
+
+    let mut removed = 0;
+    for (_, path) in dirs.into_iter().skip(keep) {
+        fs::remove_dir_all(&path)
+            .with_context(|| format!("failed to remove {}", path.display()))?;
+        removed += 1;
+    }
+    Ok(removed)
+}
+
+/// Tiny prompt used by [`preflight_agent`] to confirm the agent can answer.
+const PREFLIGHT_PROMPT: &str = "Reply with the single word OK.";
+
+/// Check that the agent CLI can run a prompt before starting the loop.
+///
+/// Runs `<agent> -p` with a tiny prompt. An installed but unauthenticated
+/// `claude` fails here with guidance instead of on the first iteration.
+pub fn preflight_agent(agent: &str) -> Result<()> {
+    let output = match Command::new(agent)
+        .arg("-p")
+        .arg(PREFLIGHT_PROMPT)
+        .stdin(Stdio::null())
+        .output()
+    {
+        Ok(output) => output,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            anyhow::bail!("{} not found in PATH", agent)
+        }
+        Err(e) => return Err(e).with_context(|| format!("failed to run {}", agent)),
+    };
+
+    if output.status.success() {
+        return Ok(());
+    }
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let detail = stderr
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .map(|line| format!(": {}", line))
+        .unwrap_or_default();
+    anyhow::bail!(
+        "{} preflight failed (exit code {}){}\n\
+         hint: run `{} login` (or `{}` interactively once) to authenticate, then retry",
+        agent,
+        output.status.code().unwrap_or(-1),
+        detail,
+        agent,
+        agent
+    )
+}
+
+/// Options controlling a single `claude -p` invocation.
+#[derive(Debug, Clone, Default)]
+pub struct SpawnOptions<'a> {
+    /// Claude model to use (passed as `--model`)
+    pub model: Option<&'a str>,
+    /// Flag set by the Ctrl+C handler; the child is killed when it flips
+    pub interrupt_flag: Option<Arc<AtomicBool>>,
+    /// Interval for "still running" status lines on stderr
+    pub heartbeat: Option<Duration>,
+    /// Project name to label heartbeat lines with
+    pub project: Option<&'a str>,
+    /// Extra environment variables for the child
+    pub env: Vec<(&'static str, String)>,
+    /// Also send each stdout line here, newline included
+    pub output_tx: Option<Sender<String>>,
+    /// Capture stdout without echoing it; stderr is still echoed (`--tui`
+    /// shows stdout in its dashboard instead)
+    pub hide_stdout: bool,
+    /// Kill the child once stdout and stderr together exceed this many bytes
+    pub max_output_bytes: Option<u64>,
+    /// Capture stdout and stderr without echoing them to the terminal
+    pub suppress_echo: bool,
+    /// Start the child with an empty environment plus only these inherited
+    /// variables (`--isolate-env`); `env` is still added on top
+    pub env_allowlist: Option<Vec<String>>,
+    /// Echo only the first and last half of this many lines of each stream;
+    /// the captured output is unaffected
+    pub max_output_lines: Option<usize>,
+    /// Stop the child as soon as a stdout line matches (`--early-stop`);
+    /// the iteration then counts as a normal exit
+    pub stop_on_line: Option<fn(&str) -> bool>,
+    /// Plan to re-read while the child runs, printing a progress line on
+    /// stderr whenever its checkbox counts change (`--live-progress`)
+    pub live_progress: Option<PathBuf>,
+    /// How tasks are written in the `live_progress` plan
+    pub plan_format: parser::PlanFormat,
+    /// Working directory for the child instead of ours (`reverse --target`)
+    pub current_dir: Option<PathBuf>,
+}
+
+/// Inherited variables `--isolate-env` always passes through.
+pub const ISOLATED_ENV_BASE: &[&str] = &["PATH", "HOME", "TERM", "LANG"];
+
+/// Environment variable naming extra variables for `--isolate-env` to keep.
+pub const ENV_ALLOW_ENV: &str = "RALPHCTL_ENV_ALLOW";
+
+/// Build the `--isolate-env` allowlist.
+///
+/// `extra` is the value of `RALPHCTL_ENV_ALLOW`, a comma- or
+/// whitespace-separated list of variable names added to `ISOLATED_ENV_BASE`.
+pub fn isolated_env_allowlist(extra: Option<&str>) -> Vec<String> {
+    let mut allow: Vec<String> = ISOLATED_ENV_BASE.iter().map(|s| s.to_string()).collect();
+    for name in extra
+        .unwrap_or_default()
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|name| !name.is_empty())
+    {
+        if !allow.iter().any(|a| a == name) {
+            allow.push(name.to_string());
+        }
+    }
+    allow
+}
+
+/// Names of the inherited variables `allowlist` drops, sorted.
+pub fn dropped_env_vars(
+    allowlist: &[String],
+    inherited: impl Iterator<Item = String>,
+) -> Vec<String> {
+    let mut dropped: Vec<String> = inherited.filter(|name| !allowlist.contains(name)).collect();
+    dropped.sort();
+    dropped.dedup();
+    dropped
+}
+
+/// Print which inherited variables `--isolate-env` keeps from the child.
+///
+/// Only names are printed, never values.
+pub fn print_dropped_env(allowlist: &[String]) {
+    let inherited = std::env::vars_os().map(|(name, _)| name.to_string_lossy().into_owned());
+    let dropped = dropped_env_vars(allowlist, inherited);
+    if dropped.is_empty() {
+        eprintln!("--isolate-env: no inherited variables dropped");
+    } else {
+        eprintln!(
+            "--isolate-env: dropping {} inherited variable{}: {}",
+            dropped.len(),
+            if dropped.len() == 1 { "" } else { "s" },
+            dropped.join(", ")
+        );
+    }
+}
+
+/// Running total of captured output, shared by the streaming threads.
+#[derive(Debug)]
+struct OutputBudget {
+    limit: u64,
+    used: AtomicU64,
+    exceeded: AtomicBool,
+}
+
+impl OutputBudget {
+    fn new(limit: u64) -> Self {
+        Self {
+            limit,
+            used: AtomicU64::new(0),
+            exceeded: AtomicBool::new(false),
+        }
+    }
+
+    /// Count `bytes` more output; returns how many of them fit within the
+    /// limit, which is fewer than `bytes` once it is exceeded.
+    fn spend(&self, bytes: u64) -> u64 {
+        let before = self.used.fetch_add(bytes, Ordering::SeqCst);
+        let fits = self.limit.saturating_sub(before).min(bytes);
+        if fits < bytes {
+            self.exceeded.store(true, Ordering::SeqCst);
+        }
+        fits
+    }
+
+    fn exceeded(&self) -> bool {
+        self.exceeded.load(Ordering::SeqCst)
+    }
+}
+
+/// Format the heartbeat status line printed during long iterations.
+///
+/// Format: `…still running (Ns elapsed)`, or `myproject — …still running
+/// (Ns elapsed)` with a project name.
+pub fn format_heartbeat(elapsed: Duration, project: Option<&str>) -> String {
+    format!(
+        "{}…still running ({} elapsed)",
+        project::header_prefix(project),
+        duration::format_duration(elapsed)
+    )
+}
+
+/// How often `--live-progress` re-reads the plan during an iteration.
+pub const LIVE_PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Re-reads the plan during an iteration for `--live-progress`.
+///
+/// Reads are debounced to one per interval, and a count is only reported
+/// when it differs from the last one seen, so an idle plan prints nothing.
+#[derive(Debug)]
+pub struct PlanWatcher {
+    path: PathBuf,
+    format: parser::PlanFormat,
+    interval: Duration,
+    next_check: Duration,
+    last: Option<parser::TaskCount>,
+}
+
+impl PlanWatcher {
+    /// Start watching `path`; the current counts are the baseline.
+    pub fn new(path: PathBuf, format: parser::PlanFormat, interval: Duration) -> Self {
+        let last = plan_task_count(&path, format);
+        Self {
+            path,
+            format,
+            interval,
+            next_check: interval,
+            last,
+        }
+    }
+
+    /// Re-read the plan if a check is due at `elapsed` (time since the
+    /// iteration started), returning the counts only if they changed.
+    ///
+    /// An unreadable plan is skipped and keeps the last counts.
+    pub fn poll(&mut self, elapsed: Duration) -> Option<parser::TaskCount> {
+        if elapsed < self.next_check {
+            return None;
+        }
+        self.next_check = elapsed + self.interval;
+        let count = plan_task_count(&self.path, self.format)?;
+        if self.last.as_ref() == Some(&count) {
+            return None;
+        }
+        self.last = Some(count.clone());
+        Some(count)
+    }
+}
+
+/// Format the `--live-progress` line printed when the plan changes mid-iteration.
+///
+/// `width` is the terminal's, if known; see `TaskCount::render_progress_bar`.
+pub fn format_live_progress(count: &parser::TaskCount, width: Option<usize>) -> String {
+    format!("…plan updated: {}", count.render_progress_bar(width))
+}
+
+/// Print a live progress line to stderr, dimmed when color is on.
+fn print_live_progress(count: &parser::TaskCount) {
+    eprintln!(
+        "{}",
+        cli::dim(&format_live_progress(count, cli::terminal_width()))
+    );
+}
+
+/// Command line `spawn_claude` runs for `opts`, program first.
+pub fn claude_argv(opts: &SpawnOptions) -> Vec<String> {
+    let mut argv = vec![
+        "claude".to_string(),
+        "-p".to_string(),
+        "--dangerously-skip-permissions".to_string(),
+    ];
+    if let Some(m) = opts.model {
+        argv.push("--model".to_string());
+        argv.push(m.to_string());
+    }
+    argv
+}
+
+/// Format `argv` as a shell command line, single-quoting arguments that
+/// contain anything beyond `[A-Za-z0-9_./:=@%+-]`.
+pub fn format_command(argv: &[String]) -> String {
+    argv.iter()
+        .map(|arg| {
+            let plain = !arg.is_empty()
+                && arg
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "_./:=@%+-".contains(c));
+            if plain {
+                arg.clone()
+            } else {
+                format!("'{}'", arg.replace('\'', r"'\''"))
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Format the `--dry-run` report: the command that would run, where the
+/// prompt comes from, the iteration limit, and the state of each file.
+pub fn format_dry_run(
+    argv: &[String],
+    prompt_source: &str,
     prompt: &str,
-    model: Option<&str>,
-    interrupt_flag: Option<Arc<AtomicBool>>,
-) -> Result<IterationResult> {
-    let mut cmd = Command::new("claude");
-    cmd.arg("-p")
-        .arg("--dangerously-skip-permissions")
+    max_iterations: u32,
+    files: &[(String, &str)],
+) -> String {
+    let mut out = String::from("Dry run: claude will not be called.\n");
+    out.push_str(&format!("Command:        {}\n", format_command(argv)));
+    out.push_str(&format!(
+        "Prompt:         {} ({} bytes)\n",
+        prompt_source,
+        prompt.len()
+    ));
+    out.push_str(&format!("Max iterations: {}\n", max_iterations));
+    out.push_str("Files:\n");
+    for (name, state) in files {
+        out.push_str(&format!("  {:<24} {}\n", name, state));
+    }
+    out
+}
+
+/// Spawn `claude -p` as a subprocess and pipe the prompt via stdin.
+///
+/// Streams stdout and stderr to the terminal in real-time (unless
+/// `opts.suppress_echo` is set) while also capturing the output for magic
+/// string detection.
+/// Returns the result of the iteration after claude completes.
+///
+/// If `opts.interrupt_flag` is provided and set to true during execution,
+/// the child process will be killed and the function returns with
+/// `was_interrupted` set to true in the result. If `opts.heartbeat` is set,
+/// a dim status line is printed to stderr at that interval until the child exits.
+pub fn spawn_claude(prompt: &str, opts: &SpawnOptions) -> Result<IterationResult> {
+    let argv = claude_argv(opts);
+    let program = argv[0].as_str();
+    let mut cmd = Command::new(program);
+    cmd.args(&argv[1..])
         .stdin(Stdio::piped())
         .stdout(Stdio::piped())
         .stderr(Stdio::piped());
-
-    if let Some(m) = model {
-        cmd.arg("--model").arg(m);
+    if let Some(allowlist) = &opts.env_allowlist {
+        cmd.env_clear();
+        for name in allowlist {
+            if let Some(value) = std::env::var_os(name) {
+                cmd.env(name, value);
+            }
+        }
+    }
+    cmd.envs(opts.env.iter().map(|(k, v)| (k, v)));
+    if let Some(dir) = &opts.current_dir {
+        cmd.current_dir(dir);
     }
 
     let mut child = cmd.spawn().inspect_err(|e| {
         if e.kind() == std::io::ErrorKind::NotFound {
-            error::die("claude not found in PATH");
+            error::die(&format!("{} not found in PATH", program));
         }
     })?;
 
@@ -308,30 +1999,99 @@ pub fn spawn_claude(
     let stderr_pipe = child.stderr.take();
 
     // Clone interrupt flag for the polling thread
+    let interrupt_flag = opts.interrupt_flag.clone();
     let interrupt_flag_clone = interrupt_flag.clone();
+    let heartbeat = opts.heartbeat;
+    let heartbeat_project = opts.project.map(str::to_string);
+    let plan_format = opts.plan_format;
+    let mut plan_watcher = opts
+        .live_progress
+        .clone()
+        .map(|path| PlanWatcher::new(path, plan_format, LIVE_PROGRESS_INTERVAL));
     let child_id = child.id();
 
     // Flag to signal the kill thread to stop when child exits normally
     let child_done = Arc::new(AtomicBool::new(false));
     let child_done_clone = child_done.clone();
 
-    // Spawn thread to stream and capture stdout
-    let stdout_handle = thread::spawn(move || stream_and_capture(stdout_pipe, io::stdout()));
+    // Both streams draw from one budget; the poll thread kills the child
+    // once it runs out
+    let budget = opts
+        .max_output_bytes
+        .map(|n| Arc::new(OutputBudget::new(n)));
+    let stdout_budget = budget.clone();
+    let stderr_budget = budget.clone();
+    let poll_budget = budget.clone();
+
+    // Set by the stdout thread when a line matches `stop_on_line`; the poll
+    // thread then stops the child as it would for Ctrl+C
+    let early_stop = opts.stop_on_line.map(|matches| EarlyStop {
+        matches,
+        fired: Arc::new(AtomicBool::new(false)),
+    });
+    let stdout_early_stop = early_stop.clone();
+    let poll_early_stop = early_stop.as_ref().map(|e| e.fired.clone());
+
+    // Spawn thread to stream and capture stdout (also to the channel if one is given)
+    let output_tx = opts.output_tx.clone();
+    let suppress_echo = opts.suppress_echo;
+    let hide_stdout = opts.hide_stdout;
+    let max_lines = opts.max_output_lines;
+    let stdout_handle = thread::spawn(move || {
+        let (output, echo_lines): (Box<dyn Write>, _) = if suppress_echo || hide_stdout {
+            (Box::new(io::sink()), None)
+        } else {
+            (Box::new(io::stdout()), max_lines)
+        };
+        let stream = StreamOptions {
+            tx: output_tx,
+            budget: stdout_budget,
+            max_lines: echo_lines,
+            early_stop: stdout_early_stop,
+        };
+        stream_capture_and_forward(stdout_pipe, output, stream)
+    });
 
     // Spawn thread to stream and capture stderr
-    let stderr_handle = thread::spawn(move || stream_and_capture(stderr_pipe, io::stderr()));
+    let stderr_handle = thread::spawn(move || {
+        let stream = StreamOptions {
+            budget: stderr_budget,
+            max_lines: (!suppress_echo).then_some(max_lines).flatten(),
+            ..Default::default()
+        };
+        if suppress_echo {
+            stream_capture_and_forward(stderr_pipe, io::sink(), stream)
+        } else {
+            stream_capture_and_forward(stderr_pipe, io::stderr(), stream)
+        }
+    });
 
-    // Spawn thread to poll for interrupt and kill child if needed
-    let kill_handle = interrupt_flag_clone.map(|flag| {
+    // Spawn thread to poll for interrupt or an exhausted output budget
+    // (killing the child if needed) and print heartbeat lines
+    let poll_needed = interrupt_flag_clone.is_some()
+        || heartbeat.is_some()
+        || poll_budget.is_some()
+        || poll_early_stop.is_some()
+        || plan_watcher.is_some();
+    let kill_handle = poll_needed.then(|| {
         thread::spawn(move || {
+            let started = std::time::Instant::now();
+            let mut next_beat = heartbeat;
             // Poll every 100ms for interrupt signal or child completion
             loop {
                 if child_done_clone.load(Ordering::SeqCst) {
                     // Child completed normally, no need to kill
                     break;
                 }
-                if flag.load(Ordering::SeqCst) {
-                    // Interrupt received, kill the child process
+                let interrupted = interrupt_flag_clone
+                    .as_ref()
+                    .is_some_and(|f| f.load(Ordering::SeqCst));
+                let signalled = poll_early_stop
+                    .as_ref()
+                    .is_some_and(|f| f.load(Ordering::SeqCst));
+                if interrupted || signalled || poll_budget.as_ref().is_some_and(|b| b.exceeded()) {
+                    // Interrupt received, terminal signal seen, or output
+                    // limit hit: kill the child process
                     #[cfg(unix)]
                     {
                         use nix::sys::signal::{kill, Signal};
@@ -341,7 +2101,20 @@ pub fn spawn_claude(
                     }
                     break;
                 }
-                thread::sleep(std::time::Duration::from_millis(100));
+                if let (Some(interval), Some(due)) = (heartbeat, next_beat) {
+                    let elapsed = started.elapsed();
+                    if elapsed >= due {
+                        print_heartbeat(elapsed, heartbeat_project.as_deref());
+                        next_beat = Some(due + interval);
+                    }
+                }
+                if let Some(count) = plan_watcher
+                    .as_mut()
+                    .and_then(|w| w.poll(started.elapsed()))
+                {
+                    print_live_progress(&count);
+                }
+                thread::sleep(Duration::from_millis(100));
             }
         })
     });
@@ -367,50 +2140,181 @@ pub fn spawn_claude(
     let stdout = stdout_handle.join().unwrap_or_default();
     let stderr = stderr_handle.join().unwrap_or_default();
 
+    let truncated = !was_interrupted && budget.is_some_and(|b| b.exceeded());
+    let stopped_early = !was_interrupted
+        && !truncated
+        && early_stop.is_some_and(|e| e.fired.load(Ordering::SeqCst));
+
     Ok(IterationResult {
-        success: status.success() && !was_interrupted,
+        success: (status.success() || stopped_early) && !was_interrupted && !truncated,
         exit_code: status.code(),
         stdout,
         stderr,
         was_interrupted,
+        truncated,
+        stopped_early,
     })
 }
 
+/// Print a heartbeat line to stderr, dimmed when color is on.
+fn print_heartbeat(elapsed: Duration, project: Option<&str>) {
+    eprintln!("{}", cli::dim(&format_heartbeat(elapsed, project)));
+}
+
+/// Bytes read from a child's pipe at a time.
+const READ_CHUNK: usize = 8192;
+
 /// Stream data from a pipe to an output writer while capturing it.
 ///
-/// Reads lines from the pipe, writes them to the output immediately,
-/// and returns the accumulated content.
+/// Reads the pipe in chunks, writes each complete line to the output
+/// immediately, and returns the accumulated content. Lines split across
+/// reads are joined, so the result is the full output regardless of how the
+/// child chunked its writes.
 #[allow(dead_code)] // Used by spawn_claude
-fn stream_and_capture<R, W>(pipe: Option<R>, mut output: W) -> String
+fn stream_and_capture<R, W>(pipe: Option<R>, output: W) -> String
+where
+    R: std::io::Read + Send,
+    W: Write,
+{
+    stream_capture_and_forward(pipe, output, StreamOptions::default())
+}
+
+/// A line matcher and the flag it sets on the first match.
+#[derive(Clone)]
+struct EarlyStop {
+    matches: fn(&str) -> bool,
+    fired: Arc<AtomicBool>,
+}
+
+/// Extras for `stream_capture_and_forward` beyond plain echo and capture.
+#[derive(Default)]
+struct StreamOptions {
+    /// Also send each line here; a closed receiver is ignored
+    tx: Option<Sender<String>>,
+    /// Charge each chunk against this budget as it is read. Reading stops at
+    /// the chunk that exceeds it; the line the limit falls in is the last one
+    /// captured, cut at the end of that chunk if it runs on
+    budget: Option<Arc<OutputBudget>>,
+    /// Echo only the first half of this many lines as they arrive; the last
+    /// half is held back and echoed at the end after an elision marker
+    max_lines: Option<usize>,
+    /// Set the flag when a line matches; reading continues until the child
+    /// is stopped so nothing already written is lost
+    early_stop: Option<EarlyStop>,
+}
+
+/// Like `stream_and_capture`, with the extras in `opts`.
+fn stream_capture_and_forward<R, W>(pipe: Option<R>, mut output: W, opts: StreamOptions) -> String
 where
     R: std::io::Read + Send,
     W: Write,
 {
+    let StreamOptions {
+        tx,
+        budget,
+        max_lines,
+        early_stop,
+    } = opts;
     let Some(pipe) = pipe else {
         return String::new();
     };
 
-    let reader = BufReader::new(pipe);
+    let mut pipe = pipe;
     let mut captured = String::new();
+    let (head, tail_cap) = match max_lines {
+        Some(n) => (n - n / 2, n / 2),
+        None => (usize::MAX, 0),
+    };
+    let mut tail: VecDeque<String> = VecDeque::with_capacity(tail_cap);
+    let mut seen = 0usize;
+
+    let mut handle_line = |line: &[u8]| {
+        let line = String::from_utf8_lossy(line.strip_suffix(b"\r").unwrap_or(line));
+        seen += 1;
+        if seen <= head {
+            // Echo to output immediately for real-time streaming
+            let _ = writeln!(output, "{}", line);
+            let _ = output.flush();
+        } else if tail_cap > 0 {
+            if tail.len() == tail_cap {
+                tail.pop_front();
+            }
+            tail.push_back(line.to_string());
+        }
 
-    for line in reader.lines() {
-        match line {
-            Ok(line) => {
-                // Echo to output immediately for real-time streaming
-                let _ = writeln!(output, "{}", line);
-                let _ = output.flush();
+        if let Some(tx) = &tx {
+            let _ = tx.send(format!("{}\n", line));
+        }
+
+        // Capture for later inspection
+        captured.push_str(&line);
+        captured.push('\n');
 
-                // Capture for later inspection
-                captured.push_str(&line);
-                captured.push('\n');
+        if let Some(stop) = &early_stop {
+            if (stop.matches)(&line) {
+                stop.fired.store(true, Ordering::SeqCst);
             }
+        }
+    };
+
+    let mut buf = vec![0u8; READ_CHUNK];
+    let mut pending: Vec<u8> = Vec::new();
+    loop {
+        let n = match pipe.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
             Err(_) => break,
+        };
+        let mut chunk = &buf[..n];
+        // Bytes are charged as they arrive, before any line is complete
+        let fits = budget.as_ref().map_or(n, |b| b.spend(n as u64) as usize);
+        if fits < n {
+            let end = chunk[fits..]
+                .iter()
+                .position(|&b| b == b'\n')
+                .map_or(n, |i| fits + i + 1);
+            chunk = &chunk[..end];
+        }
+        for piece in chunk.split_inclusive(|&b| b == b'\n') {
+            match piece.strip_suffix(b"\n") {
+                Some(rest) => {
+                    pending.extend_from_slice(rest);
+                    handle_line(&pending);
+                    pending.clear();
+                }
+                None => pending.extend_from_slice(piece),
+            }
+        }
+        if fits < n {
+            break;
         }
     }
+    if !pending.is_empty() {
+        handle_line(&pending);
+    }
+
+    let elided = seen.saturating_sub(head).saturating_sub(tail.len());
+    if elided > 0 {
+        let _ = writeln!(output, "{}", format_elision(elided));
+    }
+    for line in &tail {
+        let _ = writeln!(output, "{}", line);
+    }
+    let _ = output.flush();
 
     captured
 }
 
+/// Marker echoed in place of the lines `--max-output-lines` hides.
+pub fn format_elision(lines: usize) -> String {
+    format!(
+        "... ({} line{} elided) ...",
+        lines,
+        if lines == 1 { "" } else { "s" }
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -435,101 +2339,529 @@ mod tests {
     }
 
     #[test]
-    fn test_read_prompt_success() {
-        with_temp_dir(|dir| {
-            let prompt_content = "# Ralph Loop Prompt\n\nDo the thing.";
-            fs::write(dir.path().join(files::PROMPT_FILE), prompt_content).unwrap();
-
-            let result = read_prompt().unwrap();
-            assert_eq!(result, prompt_content);
-        });
+    fn test_read_prompt_success() {
+        with_temp_dir(|dir| {
+            let prompt_content = "# Ralph Loop Prompt\n\nDo the thing.";
+            fs::write(dir.path().join(files::PROMPT_FILE), prompt_content).unwrap();
+
+            let result = read_prompt().unwrap();
+            assert_eq!(result, prompt_content);
+        });
+    }
+
+    #[test]
+    fn test_limit_prompt_passes_short_prompt() {
+        let (prompt, truncated_from) = limit_prompt("do it", 5, PromptOverflow::Error).unwrap();
+        assert_eq!(prompt, "do it");
+        assert_eq!(truncated_from, None);
+    }
+
+    #[test]
+    fn test_limit_prompt_rejects_long_prompt() {
+        let err = limit_prompt("do it now", 5, PromptOverflow::Error).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "prompt is 9 bytes, over --prompt-max-bytes 5; shorten PROMPT.md or pass --prompt-overflow truncate"
+        );
+    }
+
+    #[test]
+    fn test_limit_prompt_truncates_on_char_boundary() {
+        let (prompt, truncated_from) =
+            limit_prompt("ab\u{2588}cd", 4, PromptOverflow::Truncate).unwrap();
+        assert_eq!(prompt, "ab");
+        assert_eq!(truncated_from, Some(7));
+    }
+
+    #[test]
+    fn test_validate_required_files_all_present() {
+        with_temp_dir(|dir| {
+            // Create all required files
+            fs::write(dir.path().join(files::PROMPT_FILE), "prompt").unwrap();
+            fs::write(dir.path().join(files::SPEC_FILE), "spec").unwrap();
+            fs::write(dir.path().join(files::IMPLEMENTATION_PLAN_FILE), "plan").unwrap();
+
+            let result = validate_required_files(
+                Path::new(files::SPEC_FILE),
+                Path::new(files::IMPLEMENTATION_PLAN_FILE),
+                true,
+            );
+            assert!(result.is_ok());
+        });
+    }
+
+    #[test]
+    fn test_spawn_echo_command() {
+        // Test subprocess spawning using echo instead of claude
+        // This verifies the piping mechanism works correctly
+        let mut child = Command::new("cat")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("Failed to spawn cat");
+
+        let test_input = "Hello from stdin";
+
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(test_input.as_bytes()).unwrap();
+        }
+
+        let output = child.wait_with_output().expect("Failed to wait on child");
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8_lossy(&output.stdout), test_input);
+    }
+
+    #[test]
+    fn test_iteration_result_debug() {
+        let result = IterationResult {
+            success: true,
+            exit_code: Some(0),
+            stdout: "output".to_string(),
+            stderr: String::new(),
+            was_interrupted: false,
+            truncated: false,
+            stopped_early: false,
+        };
+        // Verify Debug trait is implemented
+        let debug_str = format!("{:?}", result);
+        assert!(debug_str.contains("success: true"));
+        assert!(debug_str.contains("exit_code: Some(0)"));
+        assert!(debug_str.contains("stdout"));
+    }
+
+    #[test]
+    fn test_stream_and_capture_with_data() {
+        use std::io::Cursor;
+
+        let input = "line1\nline2\nline3\n";
+        let pipe = Some(Cursor::new(input.as_bytes().to_vec()));
+        let mut output_buffer = Vec::new();
+
+        let captured = stream_and_capture(pipe, &mut output_buffer);
+
+        // Verify content was captured
+        assert!(captured.contains("line1"));
+        assert!(captured.contains("line2"));
+        assert!(captured.contains("line3"));
+
+        // Verify content was written to output
+        let output_str = String::from_utf8_lossy(&output_buffer);
+        assert!(output_str.contains("line1"));
+        assert!(output_str.contains("line2"));
+        assert!(output_str.contains("line3"));
+    }
+
+    #[test]
+    fn test_stream_and_capture_empty_pipe() {
+        let captured = stream_and_capture::<std::io::Empty, Vec<u8>>(None, Vec::new());
+        assert_eq!(captured, "");
+    }
+
+    #[test]
+    fn test_output_budget_spend() {
+        let budget = OutputBudget::new(10);
+        assert_eq!(budget.spend(4), 4);
+        assert_eq!(budget.spend(6), 6);
+        assert!(!budget.exceeded());
+        assert_eq!(budget.spend(3), 0);
+        assert!(budget.exceeded());
+        assert_eq!(budget.spend(0), 0);
+
+        let budget = OutputBudget::new(10);
+        assert_eq!(budget.spend(12), 10);
+        assert!(budget.exceeded());
+    }
+
+    #[test]
+    fn test_stream_capture_stops_at_budget() {
+        let pipe = Some(std::io::Cursor::new("aaaa\nbbbb\ncccc\ndddd\n"));
+        let budget = Arc::new(OutputBudget::new(8));
+        let mut output = Vec::new();
+        let captured = stream_capture_and_forward(
+            pipe,
+            &mut output,
+            StreamOptions {
+                budget: Some(budget.clone()),
+                ..Default::default()
+            },
+        );
+        // The line that crosses the limit is the last one kept
+        assert_eq!(captured, "aaaa\nbbbb\n");
+        assert_eq!(String::from_utf8(output).unwrap(), captured);
+        assert!(budget.exceeded());
+    }
+
+    #[test]
+    fn test_stream_capture_budget_stops_inside_a_long_line() {
+        // One line far over the limit, never terminated
+        let pipe = Some(std::io::Cursor::new("x".repeat(20 * READ_CHUNK)));
+        let budget = Arc::new(OutputBudget::new(10));
+        let captured = stream_capture_and_forward(
+            pipe,
+            io::sink(),
+            StreamOptions {
+                budget: Some(budget.clone()),
+                ..Default::default()
+            },
+        );
+        assert!(budget.exceeded());
+        assert_eq!(captured.len(), READ_CHUNK + 1);
+    }
+
+    #[test]
+    fn test_stream_capture_joins_lines_across_reads() {
+        // Hands out at most 3 bytes per read
+        struct Trickle<'a>(&'a [u8]);
+        impl std::io::Read for Trickle<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                let n = self.0.len().min(buf.len()).min(3);
+                buf[..n].copy_from_slice(&self.0[..n]);
+                self.0 = &self.0[n..];
+                Ok(n)
+            }
+        }
+        let mut output = Vec::new();
+        let captured = stream_and_capture(
+            Some(Trickle(b"first line\r\nsecond\nno newline")),
+            &mut output,
+        );
+        assert_eq!(captured, "first line\nsecond\nno newline\n");
+        assert_eq!(String::from_utf8(output).unwrap(), captured);
+    }
+
+    #[test]
+    fn test_stream_capture_elides_middle_of_echo() {
+        let input: String = (1..=10).map(|n| format!("line {}\n", n)).collect();
+        let mut output = Vec::new();
+        let captured = stream_capture_and_forward(
+            Some(std::io::Cursor::new(input.clone())),
+            &mut output,
+            StreamOptions {
+                max_lines: Some(4),
+                ..Default::default()
+            },
+        );
+        assert_eq!(captured, input);
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "line 1\nline 2\n... (6 lines elided) ...\nline 9\nline 10\n"
+        );
+
+        // Output within the limit is echoed unchanged
+        let mut output = Vec::new();
+        stream_capture_and_forward(
+            Some(std::io::Cursor::new("a\nb\nc\n")),
+            &mut output,
+            StreamOptions {
+                max_lines: Some(3),
+                ..Default::default()
+            },
+        );
+        assert_eq!(String::from_utf8(output).unwrap(), "a\nb\nc\n");
+    }
+
+    #[test]
+    fn test_format_elision() {
+        assert_eq!(format_elision(1), "... (1 line elided) ...");
+        assert_eq!(format_elision(42), "... (42 lines elided) ...");
+    }
+
+    #[test]
+    fn test_plan_watcher_debounces_and_reports_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let plan = dir.path().join("plan.md");
+        fs::write(&plan, "- [ ] A\n- [ ] B\n").unwrap();
+        let secs = Duration::from_secs;
+        let mut watcher = PlanWatcher::new(plan.clone(), parser::PlanFormat::Markdown, secs(2));
+
+        // Unchanged plan: nothing to report
+        assert_eq!(watcher.poll(secs(2)), None);
+
+        // A change before the next check is not seen until it is due
+        fs::write(&plan, "- [x] A\n- [ ] B\n").unwrap();
+        assert_eq!(watcher.poll(secs(3)), None);
+        assert_eq!(watcher.poll(secs(4)), Some(parser::TaskCount::new(1, 2)));
+
+        // Reported once, then quiet until it changes again
+        assert_eq!(watcher.poll(secs(6)), None);
+        fs::remove_file(&plan).unwrap();
+        assert_eq!(watcher.poll(secs(8)), None);
+        fs::write(&plan, "- [x] A\n- [x] B\n").unwrap();
+        assert_eq!(watcher.poll(secs(10)), Some(parser::TaskCount::new(2, 2)));
+    }
+
+    #[test]
+    fn test_format_live_progress() {
+        let count = parser::TaskCount::new(1, 2);
+        assert_eq!(
+            format_live_progress(&count, None),
+            "…plan updated: [██████░░░░░░] 50% (1/2 tasks)"
+        );
+        assert_eq!(
+            format_live_progress(&count, Some(40)),
+            format!(
+                "…plan updated: [{}{}] 50% (1/2 tasks)",
+                "█".repeat(11),
+                "░".repeat(11)
+            )
+        );
+    }
+
+    #[test]
+    fn test_format_progress_records() {
+        let count = parser::TaskCount::new(1, 3);
+        assert_eq!(
+            format_progress_log(3, "2026-01-02 10:00:00", &count),
+            "--- progress after iteration 3 (2026-01-02 10:00:00): [████░░░░░░░░] 33% (1/3 tasks) ---"
+        );
+        assert_eq!(
+            format_progress_line(3, "2026-01-02 10:00:00", &count),
+            "- 2026-01-02 10:00:00 — iteration 3: [████░░░░░░░░] 33% (1/3 tasks)"
+        );
+    }
+
+    #[test]
+    fn test_run_hook_captures_output_and_code() {
+        let (code, output) = run_hook("--pre-cmd", "echo out; echo err >&2; exit 3").unwrap();
+        assert_eq!(code, Some(3));
+        assert_eq!(output, "out\nerr\n");
+        assert_eq!(
+            run_hook("--pre-cmd", "true").unwrap(),
+            (Some(0), String::new())
+        );
+    }
+
+    #[test]
+    fn test_format_hook_log() {
+        assert_eq!(
+            format_hook_log(
+                "pre-cmd before iteration 2",
+                "make db-reset",
+                Some(0),
+                "reset\n"
+            ),
+            "--- pre-cmd before iteration 2: make db-reset (exit code 0) ---\nreset\n\n"
+        );
+        assert_eq!(
+            format_hook_log("pre-cmd before iteration 1", "git pull", None, "partial"),
+            "--- pre-cmd before iteration 1: git pull (killed by a signal) ---\npartial\n\n"
+        );
+    }
+
+    #[test]
+    fn test_prompt_fingerprint_tracks_content() {
+        with_temp_dir(|_| {
+            assert_eq!(prompt_fingerprint(), None);
+            fs::write(files::PROMPT_FILE, "Do the task.\n").unwrap();
+            let original = prompt_fingerprint().unwrap();
+            assert_eq!(prompt_fingerprint(), Some(original));
+            fs::write(files::PROMPT_FILE, "Do the task better.\n").unwrap();
+            assert_ne!(prompt_fingerprint(), Some(original));
+        });
+    }
+
+    #[test]
+    fn test_format_prompt_modified() {
+        assert_eq!(
+            format_prompt_modified(6),
+            "PROMPT.md was modified during iteration 6 \u{2014} refusing to continue; \
+             restore it or rerun with --allow-prompt-edits"
+        );
+    }
+
+    #[test]
+    fn test_is_blank_output() {
+        assert!(is_blank_output(""));
+        assert!(is_blank_output(" \n\t\n"));
+        assert!(is_blank_output("\nok\n"));
+        assert!(!is_blank_output("[[RALPH:DONE]]\n"));
+        assert!(!is_blank_output("Still working...\n"));
+    }
+
+    #[test]
+    fn test_is_substantial_output() {
+        assert!(!is_substantial_output(""));
+        assert!(!is_substantial_output("Still working...\n"));
+        assert!(!is_substantial_output(&format!(
+            "  {}  \n",
+            "x".repeat(STRICT_SIGNAL_MIN_BYTES - 1)
+        )));
+        assert!(is_substantial_output(&"x".repeat(STRICT_SIGNAL_MIN_BYTES)));
+    }
+
+    #[test]
+    fn test_is_terminal_signal_line() {
+        assert!(is_terminal_signal_line("[[RALPH:DONE]]"));
+        assert!(is_terminal_signal_line("  [[RALPH:BLOCKED:need creds]]  "));
+        assert!(!is_terminal_signal_line("[[RALPH:CONTINUE]]"));
+        assert!(!is_terminal_signal_line("done: [[RALPH:DONE]]"));
     }
 
     #[test]
-    fn test_validate_required_files_all_present() {
-        with_temp_dir(|dir| {
-            // Create all required files
-            fs::write(dir.path().join(files::PROMPT_FILE), "prompt").unwrap();
-            fs::write(dir.path().join(files::SPEC_FILE), "spec").unwrap();
-            fs::write(dir.path().join(files::IMPLEMENTATION_PLAN_FILE), "plan").unwrap();
+    fn test_stream_capture_flags_early_stop_and_keeps_reading() {
+        let fired = Arc::new(AtomicBool::new(false));
+        let captured = stream_capture_and_forward(
+            Some(std::io::Cursor::new("work\n[[RALPH:DONE]]\ntrailing\n")),
+            Vec::new(),
+            StreamOptions {
+                early_stop: Some(EarlyStop {
+                    matches: is_terminal_signal_line,
+                    fired: fired.clone(),
+                }),
+                ..Default::default()
+            },
+        );
+        assert!(fired.load(Ordering::SeqCst));
+        assert_eq!(captured, "work\n[[RALPH:DONE]]\ntrailing\n");
+    }
 
-            let result = validate_required_files();
-            assert!(result.is_ok());
-        });
+    /// Write an executable `claude` running `script` into `dir`, returning a
+    /// PATH that finds it first.
+    fn fake_claude_path(dir: &tempfile::TempDir, script: &str) -> String {
+        use std::os::unix::fs::PermissionsExt;
+
+        let program = dir.path().join("claude");
+        fs::write(&program, format!("#!/bin/sh\n{}", script)).unwrap();
+        fs::set_permissions(&program, fs::Permissions::from_mode(0o755)).unwrap();
+        format!("{}:/usr/bin:/bin", dir.path().display())
     }
 
     #[test]
-    fn test_spawn_echo_command() {
-        // Test subprocess spawning using echo instead of claude
-        // This verifies the piping mechanism works correctly
-        let mut child = Command::new("cat")
-            .stdin(Stdio::piped())
-            .stdout(Stdio::piped())
-            .spawn()
-            .expect("Failed to spawn cat");
+    fn test_spawn_claude_stops_early_on_terminal_signal() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = fake_claude_path(&dir, "echo working\necho '[[RALPH:DONE]]'\nexec sleep 30\n");
+
+        let opts = SpawnOptions {
+            env: vec![("PATH", path)],
+            suppress_echo: true,
+            stop_on_line: Some(is_terminal_signal_line),
+            ..Default::default()
+        };
+        let started = std::time::Instant::now();
+        let result = spawn_claude("", &opts).unwrap();
+
+        assert!(started.elapsed() < Duration::from_secs(10));
+        assert!(result.stopped_early);
+        assert!(result.success);
+        assert!(!result.was_interrupted);
+        assert_eq!(result.stdout, "working\n[[RALPH:DONE]]\n");
+    }
 
-        let test_input = "Hello from stdin";
+    #[test]
+    fn test_spawn_claude_kills_child_over_output_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = fake_claude_path(&dir, "while :; do echo 0123456789; done\n");
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        let opts = SpawnOptions {
+            env: vec![("PATH", path)],
+            output_tx: Some(tx),
+            hide_stdout: true,
+            max_output_bytes: Some(1000),
+            ..Default::default()
+        };
+        let result = spawn_claude("", &opts).unwrap();
+        drop(opts);
+        drop(rx);
 
-        if let Some(mut stdin) = child.stdin.take() {
-            stdin.write_all(test_input.as_bytes()).unwrap();
-        }
+        assert!(result.truncated);
+        assert!(!result.success);
+        assert!(!result.was_interrupted);
+        assert!(result.stdout.len() <= 1000 + 11);
+    }
 
-        let output = child.wait_with_output().expect("Failed to wait on child");
-        assert!(output.status.success());
-        assert_eq!(String::from_utf8_lossy(&output.stdout), test_input);
+    #[test]
+    fn test_format_iteration_header() {
+        assert_eq!(
+            format_iteration_header(1, None, None),
+            "=== Iteration 1 starting ==="
+        );
+        assert_eq!(
+            format_iteration_header(42, None, None),
+            "=== Iteration 42 starting ==="
+        );
+        assert_eq!(
+            format_iteration_header(100, None, None),
+            "=== Iteration 100 starting ==="
+        );
     }
 
     #[test]
-    fn test_iteration_result_debug() {
-        let result = IterationResult {
-            success: true,
-            exit_code: Some(0),
-            stdout: "output".to_string(),
-            stderr: String::new(),
-            was_interrupted: false,
-        };
-        // Verify Debug trait is implemented
-        let debug_str = format!("{:?}", result);
-        assert!(debug_str.contains("success: true"));
-        assert!(debug_str.contains("exit_code: Some(0)"));
-        assert!(debug_str.contains("stdout"));
+    fn test_format_iteration_header_with_run_id() {
+        assert_eq!(
+            format_iteration_header(3, Some("1a2b3c4d"), None),
+            "=== Iteration 3 starting === (run 1a2b3c4d)"
+        );
     }
 
     #[test]
-    fn test_stream_and_capture_with_data() {
-        use std::io::Cursor;
+    fn test_format_iteration_header_with_project() {
+        assert_eq!(
+            format_iteration_header(3, Some("1a2b3c4d"), Some("myproject")),
+            "=== myproject \u{2014} Iteration 3 starting === (run 1a2b3c4d)"
+        );
+        assert_eq!(
+            format_iteration_header(1, None, Some("myproject")),
+            "=== myproject \u{2014} Iteration 1 starting ==="
+        );
+    }
 
-        let input = "line1\nline2\nline3\n";
-        let pipe = Some(Cursor::new(input.as_bytes().to_vec()));
-        let mut output_buffer = Vec::new();
+    #[test]
+    fn test_auto_max_iterations() {
+        let count = |completed, total| parser::TaskCount::new(completed, total);
+        assert_eq!(auto_max_iterations(Some(&count(0, 3))), 11);
+        assert_eq!(auto_max_iterations(Some(&count(2, 3))), 7);
+        // All tasks done: just the slack
+        assert_eq!(auto_max_iterations(Some(&count(4, 4))), 5);
+        // Huge plans are capped
+        assert_eq!(auto_max_iterations(Some(&count(0, 120))), 50);
+        assert_eq!(auto_max_iterations(Some(&count(0, usize::MAX))), 50);
+        // No checkboxes or unreadable plan fall back to the cap
+        assert_eq!(auto_max_iterations(Some(&count(0, 0))), 50);
+        assert_eq!(auto_max_iterations(None), 50);
+    }
 
-        let captured = stream_and_capture(pipe, &mut output_buffer);
+    #[test]
+    fn test_write_iteration_log() {
+        let dir = tempfile::tempdir().unwrap();
+        let logs = dir.path().join("logs");
 
-        // Verify content was captured
-        assert!(captured.contains("line1"));
-        assert!(captured.contains("line2"));
-        assert!(captured.contains("line3"));
+        let path = write_iteration_log(&logs, 3, "first").unwrap();
+        assert_eq!(path, logs.join("iteration-3.log"));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "first");
 
-        // Verify content was written to output
-        let output_str = String::from_utf8_lossy(&output_buffer);
-        assert!(output_str.contains("line1"));
-        assert!(output_str.contains("line2"));
-        assert!(output_str.contains("line3"));
+        write_iteration_log(&logs, 3, "second").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "second");
     }
 
     #[test]
-    fn test_stream_and_capture_empty_pipe() {
-        let captured = stream_and_capture::<std::io::Empty, Vec<u8>>(None, Vec::new());
-        assert_eq!(captured, "");
+    fn test_output_similarity_near_identical() {
+        let first = "Read src/main.rs\nTests fail: 3 errors in parser\nRetrying the fix\n";
+        let second = "Read src/main.rs\nTests fail: 3 errors in parser\nRetrying the same fix\n";
+        let ratio = output_similarity(first, second);
+        assert!(ratio > 0.9 && ratio < 1.0, "ratio {}", ratio);
     }
 
     #[test]
-    fn test_format_iteration_header() {
-        assert_eq!(format_iteration_header(1), "=== Iteration 1 starting ===");
-        assert_eq!(format_iteration_header(42), "=== Iteration 42 starting ===");
+    fn test_output_similarity_bounds() {
+        assert_eq!(output_similarity("a b c", "a b c"), 1.0);
+        assert_eq!(output_similarity("a b c", "x y z"), 0.0);
+        assert_eq!(output_similarity("", ""), 1.0);
+        assert_eq!(output_similarity("a b", ""), 0.0);
+        // Token counts matter, not just the set of words
+        assert_eq!(output_similarity("ok ok ok ok", "ok"), 0.4);
+        assert_eq!(output_similarity("a b", "b a"), 1.0);
+    }
+
+    #[test]
+    fn test_format_run_banner() {
         assert_eq!(
-            format_iteration_header(100),
-            "=== Iteration 100 starting ==="
+            format_run_banner("1a2b3c4d", "run", "2026-01-02 03:04:05", None),
+            "=== Run 1a2b3c4d (run) started 2026-01-02 03:04:05 ==="
+        );
+        assert_eq!(
+            format_run_banner("1a2b3c4d", "run", "2026-01-02 03:04:05", Some("app")),
+            "=== app \u{2014} Run 1a2b3c4d (run) started 2026-01-02 03:04:05 ==="
         );
     }
 
@@ -601,6 +2933,32 @@ mod tests {
         assert_eq!(detect_signal(output), LoopSignal::NoSignal);
     }
 
+    #[test]
+    fn test_detect_inline_signal() {
+        assert_eq!(
+            detect_inline_signal("Work finished [[RALPH:DONE]] done"),
+            Some(LoopSignal::Done)
+        );
+        assert_eq!(
+            detect_inline_signal("Output [[RALPH:CONTINUE]] more text"),
+            Some(LoopSignal::Continue)
+        );
+        assert_eq!(detect_inline_signal("no markers here"), None);
+        assert_eq!(detect_inline_signal("[[ralph:done]]"), None);
+    }
+
+    #[test]
+    fn test_detect_inline_signal_earliest_wins() {
+        assert_eq!(
+            detect_inline_signal("next: [[RALPH:CONTINUE]] then [[RALPH:DONE]]"),
+            Some(LoopSignal::Continue)
+        );
+        assert_eq!(
+            detect_inline_signal("[[RALPH:DONE]] not [[RALPH:CONTINUE]]"),
+            Some(LoopSignal::Done)
+        );
+    }
+
     #[test]
     fn test_detect_signal_done_with_whitespace() {
         // Marker can have leading/trailing whitespace on its line
@@ -687,6 +3045,127 @@ mod tests {
         assert_eq!(RALPH_CONTINUE_MARKER, "[[RALPH:CONTINUE]]");
     }
 
+    #[test]
+    fn test_detect_first_signal_first_marker_wins() {
+        assert_eq!(
+            detect_first_signal("work\n[[RALPH:CONTINUE]]\nmore\n[[RALPH:DONE]]\n"),
+            LoopSignal::Continue
+        );
+        assert_eq!(
+            detect_first_signal("[[RALPH:DONE]]\n[[RALPH:CONTINUE]]\n"),
+            LoopSignal::Done
+        );
+        assert_eq!(
+            detect_first_signal("mentions [[RALPH:DONE]] inline\n  [[RALPH:CONTINUE]]  \n"),
+            LoopSignal::Continue
+        );
+        assert_eq!(detect_first_signal("no markers\n"), LoopSignal::NoSignal);
+        assert_eq!(detect_first_signal(""), LoopSignal::NoSignal);
+    }
+
+    #[test]
+    fn test_detect_first_signal_blocked_wins() {
+        assert_eq!(
+            detect_first_signal("[[RALPH:DONE]]\n[[RALPH:BLOCKED:need creds]]\n"),
+            LoopSignal::Blocked("need creds".to_string())
+        );
+        assert_eq!(
+            detect_first_signal(
+                "[[RALPH:BLOCKED:first]]\n[[RALPH:CONTINUE]]\n[[RALPH:BLOCKED:second]]\n"
+            ),
+            LoopSignal::Blocked("first".to_string())
+        );
+        // Inline BLOCKED mentions do not count
+        assert_eq!(
+            detect_first_signal("see [[RALPH:BLOCKED:x]]\n[[RALPH:DONE]]\n"),
+            LoopSignal::Done
+        );
+    }
+
+    #[test]
+    fn test_detect_first_signal_agrees_with_detect_signal() {
+        for output in [
+            "[[RALPH:DONE]]",
+            "a\n[[RALPH:CONTINUE]]\nb\n[[RALPH:DONE]]",
+            "\t[[RALPH:DONE]]\r\n",
+            "[[RALPH:DONE]] trailing",
+        ] {
+            assert_eq!(detect_first_signal(output), detect_signal(output));
+        }
+    }
+
+    fn blocked(category: BlockedCategory, reason: &str) -> BlockedReason {
+        BlockedReason {
+            category,
+            reason: reason.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_parse_blocked_reason_categories() {
+        assert_eq!(
+            parse_blocked_reason("env:DATABASE_URL not set"),
+            blocked(BlockedCategory::Env, "DATABASE_URL not set")
+        );
+        assert_eq!(
+            parse_blocked_reason("decision:choose auth provider"),
+            blocked(BlockedCategory::Decision, "choose auth provider")
+        );
+        assert_eq!(
+            parse_blocked_reason("access: need repo permissions"),
+            blocked(BlockedCategory::Access, "need repo permissions")
+        );
+        assert_eq!(
+            parse_blocked_reason("tests keep failing"),
+            blocked(BlockedCategory::Other, "tests keep failing")
+        );
+        for (word, category) in BlockedCategory::KNOWN {
+            assert_eq!(category.as_str(), *word);
+        }
+    }
+
+    #[test]
+    fn test_parse_blocked_reason_ambiguous_prefixes() {
+        // Unknown words, wrong case, missing reasons, and later colons
+        // all leave the payload whole
+        for payload in [
+            "note: the build is broken",
+            "https://example.com is down",
+            "Env:DATABASE_URL not set",
+            "environment:DATABASE_URL not set",
+            " env:DATABASE_URL not set",
+            "env:",
+            "env:   ",
+            "need env:DATABASE_URL",
+            "",
+        ] {
+            assert_eq!(
+                parse_blocked_reason(payload),
+                blocked(BlockedCategory::Other, payload),
+                "payload: {:?}",
+                payload
+            );
+        }
+        // Only the first colon separates the category
+        assert_eq!(
+            parse_blocked_reason("env:KEY: value missing"),
+            blocked(BlockedCategory::Env, "KEY: value missing")
+        );
+    }
+
+    #[test]
+    fn test_format_blocked() {
+        assert_eq!(
+            format_blocked("env:DATABASE_URL not set"),
+            "blocked [env]: DATABASE_URL not set"
+        );
+        assert_eq!(format_blocked("need creds"), "blocked: need creds");
+        assert_eq!(
+            format_blocked("note: build broken"),
+            "blocked: note: build broken"
+        );
+    }
+
     #[test]
     fn test_detect_blocked_signal_found() {
         let output = "Cannot proceed.\n[[RALPH:BLOCKED:missing API key]]\n";
@@ -731,6 +3210,53 @@ mod tests {
         assert_eq!(detect_blocked_signal(""), None);
     }
 
+    #[test]
+    fn test_detect_progress_found() {
+        assert_eq!(
+            detect_progress("Halfway.\n[[RALPH:PROGRESS:50]]\n"),
+            Some(50)
+        );
+        assert_eq!(detect_progress("[[RALPH:PROGRESS:0]]"), Some(0));
+        assert_eq!(detect_progress("  [[RALPH:PROGRESS:100]]  \n"), Some(100));
+    }
+
+    #[test]
+    fn test_detect_progress_rejects_inline() {
+        assert_eq!(detect_progress("I'm at [[RALPH:PROGRESS:40]] now"), None);
+    }
+
+    #[test]
+    fn test_detect_progress_ignores_malformed() {
+        for output in [
+            "[[RALPH:PROGRESS:101]]",
+            "[[RALPH:PROGRESS:-5]]",
+            "[[RALPH:PROGRESS:+5]]",
+            "[[RALPH:PROGRESS:50%]]",
+            "[[RALPH:PROGRESS:4.5]]",
+            "[[RALPH:PROGRESS:]]",
+            "[[RALPH:PROGRESS:999999]]",
+            "[[RALPH:PROGRESS:50]",
+        ] {
+            assert_eq!(detect_progress(output), None, "accepted {:?}", output);
+        }
+    }
+
+    #[test]
+    fn test_detect_progress_last_valid_wins() {
+        let output = "[[RALPH:PROGRESS:20]]\n[[RALPH:PROGRESS:60]]\n[[RALPH:PROGRESS:abc]]\n";
+        assert_eq!(detect_progress(output), Some(60));
+    }
+
+    #[test]
+    fn test_detect_progress_does_not_affect_signal() {
+        let output = "[[RALPH:PROGRESS:80]]\n[[RALPH:CONTINUE]]\n";
+        assert_eq!(detect_first_signal(output), LoopSignal::Continue);
+        assert_eq!(
+            detect_first_signal("[[RALPH:PROGRESS:80]]\n"),
+            LoopSignal::NoSignal
+        );
+    }
+
     #[test]
     fn test_detect_blocked_signal_empty_reason() {
         let output = "[[RALPH:BLOCKED:]]";
@@ -965,7 +3491,7 @@ Some educational content here.
     #[test]
     fn test_log_iteration_creates_file() {
         with_temp_dir(|_dir| {
-            log_iteration(1, "Test output").unwrap();
+            log_iteration(1, 1, "0badc0de", None, "Test output", false).unwrap();
             assert!(Path::new(files::LOG_FILE).exists());
         });
     }
@@ -973,7 +3499,7 @@ Some educational content here.
     #[test]
     fn test_log_iteration_content_format() {
         with_temp_dir(|_dir| {
-            log_iteration(1, "First iteration output").unwrap();
+            log_iteration(1, 1, "0badc0de", None, "First iteration output", false).unwrap();
 
             let content = fs::read_to_string(files::LOG_FILE).unwrap();
             assert!(content.contains("=== Iteration 1 starting ==="));
@@ -985,17 +3511,148 @@ Some educational content here.
     #[test]
     fn test_log_iteration_appends() {
         with_temp_dir(|_dir| {
-            log_iteration(1, "First").unwrap();
-            log_iteration(2, "Second").unwrap();
+            log_iteration(1, 1, "0badc0de", None, "First", false).unwrap();
+            log_iteration(2, 2, "0badc0de", None, "Second", false).unwrap();
 
             let content = fs::read_to_string(files::LOG_FILE).unwrap();
             assert!(content.contains("=== Iteration 1 starting ==="));
             assert!(content.contains("First"));
             assert!(content.contains("=== Iteration 2 starting ==="));
             assert!(content.contains("Second"));
+            assert!(content.contains("[entry 1]"));
+            assert!(content.contains("[entry 2]"));
+        });
+    }
+
+    #[test]
+    fn test_highest_log_entry() {
+        let log = format!(
+            "{}{}",
+            format_log_entry(4, 1, "0badc0de", None, "out [entry 99"),
+            format_log_entry(12, 2, "0badc0de", Some("myproject"), "out")
+        );
+        assert_eq!(highest_log_entry(&log), 12);
+        assert_eq!(highest_log_entry("no entries here\n"), 0);
+    }
+
+    #[test]
+    fn test_log_iteration_durable() {
+        with_temp_dir(|_dir| {
+            log_iteration(1, 1, "0badc0de", None, "Synced", true).unwrap();
+            let content = fs::read_to_string(files::LOG_FILE).unwrap();
+            assert!(content.contains("Synced"));
+        });
+    }
+
+    #[test]
+    fn test_rotate_log() {
+        with_temp_dir(|_dir| {
+            assert_eq!(rotate_log("2026-01-02T03-04-05").unwrap(), None);
+
+            fs::write(files::LOG_FILE, "first").unwrap();
+            assert_eq!(
+                rotate_log("2026-01-02T03-04-05").unwrap().as_deref(),
+                Some("ralph.log.2026-01-02T03-04-05")
+            );
+            assert!(!Path::new(files::LOG_FILE).exists());
+
+            fs::write(files::LOG_FILE, "second").unwrap();
+            assert_eq!(
+                rotate_log("2026-01-02T03-04-05").unwrap().as_deref(),
+                Some("ralph.log.2026-01-02T03-04-05-1")
+            );
+            assert_eq!(
+                fs::read_to_string("ralph.log.2026-01-02T03-04-05").unwrap(),
+                "first"
+            );
+            assert_eq!(
+                fs::read_to_string("ralph.log.2026-01-02T03-04-05-1").unwrap(),
+                "second"
+            );
         });
     }
 
+    #[test]
+    fn test_trace_command_logs_line() {
+        with_temp_dir(|_dir| {
+            let argv = claude_argv(&SpawnOptions {
+                model: Some("claude opus"),
+                ..Default::default()
+            });
+            trace_command(&argv, false);
+            assert_eq!(
+                fs::read_to_string(files::LOG_FILE).unwrap(),
+                "+ claude -p --dangerously-skip-permissions --model 'claude opus'\n"
+            );
+        });
+    }
+
+    #[test]
+    fn test_format_log_entry() {
+        assert_eq!(
+            format_log_entry(7, 2, "0badc0de", None, "out"),
+            "=== Iteration 2 starting === (run 0badc0de) [entry 7]\nout\n--- end iteration 2 ---\n\n"
+        );
+    }
+
+    /// Writer that counts calls and accepts at most `capacity` bytes,
+    /// failing like a full disk after that.
+    struct LimitedWriter {
+        data: Vec<u8>,
+        capacity: usize,
+        writes: usize,
+        flushes: usize,
+    }
+
+    impl LimitedWriter {
+        fn new(capacity: usize) -> Self {
+            LimitedWriter {
+                data: Vec::new(),
+                capacity,
+                writes: 0,
+                flushes: 0,
+            }
+        }
+    }
+
+    impl Write for LimitedWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.writes += 1;
+            let room = self.capacity - self.data.len();
+            if room == 0 {
+                return Err(io::Error::from_raw_os_error(28)); // ENOSPC
+            }
+            let n = buf.len().min(room);
+            self.data.extend_from_slice(&buf[..n]);
+            Ok(n)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.flushes += 1;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_write_log_entry_single_write_and_flush() {
+        let entry = format_log_entry(1, 1, "0badc0de", None, "line one\nline two");
+        let mut writer = LimitedWriter::new(usize::MAX);
+        write_log_entry(&mut writer, &entry).unwrap();
+        assert_eq!(writer.data, entry.as_bytes());
+        assert_eq!(writer.writes, 1);
+        assert_eq!(writer.flushes, 1);
+    }
+
+    #[test]
+    fn test_write_log_entry_disk_full() {
+        let entry = format_log_entry(1, 1, "0badc0de", None, "output");
+        let mut writer = LimitedWriter::new(10);
+        let err = write_log_entry(&mut writer, &entry).unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(28));
+        assert_eq!(writer.flushes, 0);
+        assert_eq!(writer.data, &entry.as_bytes()[..10]);
+    }
+
     #[test]
     fn test_pause_action_equality() {
         assert_eq!(PauseAction::Continue, PauseAction::Continue);
@@ -1004,17 +3661,56 @@ Some educational content here.
     }
 
     #[test]
-    fn test_pause_action_clone() {
-        let action = PauseAction::Continue;
-        let cloned = action.clone();
-        assert_eq!(action, cloned);
+    fn test_pause_action_clone() {
+        let action = PauseAction::Continue;
+        let cloned = action.clone();
+        assert_eq!(action, cloned);
+    }
+
+    #[test]
+    fn test_pause_action_debug() {
+        let action = PauseAction::Stop;
+        let debug_str = format!("{:?}", action);
+        assert_eq!(debug_str, "Stop");
+    }
+
+    #[test]
+    fn test_pause_action_from_input() {
+        assert_eq!(pause_action(Some("\n")), PauseAction::Continue);
+        assert_eq!(pause_action(Some("Y\n")), PauseAction::Continue);
+        assert_eq!(pause_action(Some("n\n")), PauseAction::Stop);
+        assert_eq!(pause_action(None), PauseAction::Interrupted);
+    }
+
+    #[test]
+    fn test_blocked_action_from_input() {
+        assert_eq!(blocked_action(Some("r\n")), Some(BlockedAction::Retry));
+        assert_eq!(blocked_action(Some("Retry\n")), Some(BlockedAction::Retry));
+        assert_eq!(blocked_action(Some("s\n")), Some(BlockedAction::Shell));
+        assert_eq!(blocked_action(Some("a\n")), Some(BlockedAction::Abort));
+        // End of input aborts; an empty line or typo asks again
+        assert_eq!(blocked_action(Some("")), Some(BlockedAction::Abort));
+        assert_eq!(blocked_action(Some("\n")), None);
+        assert_eq!(blocked_action(Some("y\n")), None);
+        assert_eq!(blocked_action(None), Some(BlockedAction::Interrupted));
+    }
+
+    #[test]
+    fn test_no_signal_action_from_input() {
+        assert_eq!(no_signal_action(Some("\n")), NoSignalAction::Continue);
+        assert_eq!(
+            no_signal_action(Some("continue\n")),
+            NoSignalAction::Continue
+        );
+        assert_eq!(no_signal_action(Some("s\n")), NoSignalAction::Stop);
+        assert_eq!(no_signal_action(None), NoSignalAction::Interrupted);
     }
 
     #[test]
-    fn test_pause_action_debug() {
-        let action = PauseAction::Stop;
-        let debug_str = format!("{:?}", action);
-        assert_eq!(debug_str, "Stop");
+    fn test_read_line_interruptible_returns_none_when_interrupted() {
+        // The flag is checked before stdin is touched
+        let flag = AtomicBool::new(true);
+        assert_eq!(read_line_interruptible(&flag).unwrap(), None);
     }
 
     #[test]
@@ -1025,6 +3721,8 @@ Some educational content here.
             stdout: String::new(),
             stderr: String::new(),
             was_interrupted: true,
+            truncated: false,
+            stopped_early: false,
         };
         assert!(result.was_interrupted);
         assert!(!result.success);
@@ -1120,7 +3818,10 @@ Some educational content here.
         // Should print a warning to stderr but not panic.
         with_temp_dir(|_dir| {
             // No IMPLEMENTATION_PLAN.md exists - should handle gracefully
-            print_progress();
+            print_progress(
+                Path::new(files::IMPLEMENTATION_PLAN_FILE),
+                parser::PlanFormat::Markdown,
+            );
         });
     }
 
@@ -1132,7 +3833,711 @@ Some educational content here.
             fs::write(dir.path().join(files::IMPLEMENTATION_PLAN_FILE), content).unwrap();
 
             // Should not panic
-            print_progress();
+            print_progress(
+                Path::new(files::IMPLEMENTATION_PLAN_FILE),
+                parser::PlanFormat::Markdown,
+            );
+        });
+    }
+
+    #[test]
+    fn test_run_outcome_label() {
+        assert_eq!(RunOutcome::Done.label(), "done");
+        assert_eq!(
+            RunOutcome::Blocked("no key".to_string()).label(),
+            "blocked (no key)"
+        );
+        assert_eq!(RunOutcome::MaxIterations.label(), "max iterations reached");
+        assert_eq!(RunOutcome::Stopped.label(), "stopped by user");
+        assert_eq!(RunOutcome::Interrupted.label(), "interrupted");
+        assert_eq!(RunOutcome::NoSignal.label(), "no signal (strict)");
+        assert_eq!(RunOutcome::NoSignal.kind(), "no_signal");
+        assert_eq!(RunOutcome::OutputLimit.label(), "output limit exceeded");
+        assert_eq!(RunOutcome::OutputLimit.kind(), "output_limit");
+        assert_eq!(RunOutcome::BlankOutput.label(), "blank output");
+        assert_eq!(RunOutcome::BlankOutput.kind(), "blank_output");
+        let task = RunOutcome::TaskCompleted("Add parser".to_string());
+        assert_eq!(task.label(), "task completed (Add parser)");
+        assert_eq!(task.kind(), "task_completed");
+        assert_eq!(task.reason(), Some("Add parser"));
+        let failed = RunOutcome::Failed("claude exited with code 2".to_string());
+        assert_eq!(failed.label(), "failed (claude exited with code 2)");
+        assert_eq!(failed.kind(), "failed");
+        assert_eq!(failed.exit_code(), 1);
+        let modified = RunOutcome::PromptModified(3);
+        assert_eq!(modified.label(), "prompt modified (iteration 3)");
+        assert_eq!(modified.kind(), "prompt_modified");
+        assert_eq!(modified.exit_code(), 1);
+    }
+
+    #[test]
+    fn test_run_outcome_exit_code() {
+        assert_eq!(RunOutcome::Done.exit_code(), 0);
+        assert_eq!(RunOutcome::Blocked("x".to_string()).exit_code(), 3);
+        assert_eq!(RunOutcome::MaxIterations.exit_code(), 2);
+        assert_eq!(RunOutcome::Inconclusive("x".to_string()).exit_code(), 4);
+        assert_eq!(RunOutcome::BlankOutput.exit_code(), 8);
+        assert_eq!(RunOutcome::OutputLimit.exit_code(), 6);
+        assert_eq!(RunOutcome::Interrupted.exit_code(), 130);
+    }
+
+    #[test]
+    fn test_summary_json() {
+        let stats = RunStats {
+            task_percentage: Some(40),
+            ..RunStats::new(3, Duration::from_millis(1500)).with_field("Tasks", "2/5 (40%)")
+        };
+        let payload = summary_json(&RunOutcome::Blocked("need creds".to_string()), &stats);
+        assert_eq!(
+            payload,
+            serde_json::json!({
+                "outcome": "blocked",
+                "reason": "need creds",
+                "blocked_category": null,
+                "iterations": 3,
+                "duration_secs": 1.5,
+                "task_percentage": 40,
+                "fields": { "Tasks": "2/5 (40%)" },
+            })
+        );
+
+        let payload = summary_json(
+            &RunOutcome::Blocked("env:DATABASE_URL not set".to_string()),
+            &stats,
+        );
+        assert_eq!(payload["blocked_category"], "env");
+        assert_eq!(payload["reason"], "env:DATABASE_URL not set");
+
+        let payload = summary_json(&RunOutcome::Done, &RunStats::new(1, Duration::ZERO));
+        assert_eq!(payload["outcome"], "done");
+        assert!(payload["reason"].is_null());
+        assert!(payload["task_percentage"].is_null());
+    }
+
+    #[test]
+    fn test_format_run_summary_contains_all_lines() {
+        let stats = RunStats::new(3, Duration::from_secs(65)).with_field("Tasks", "2/4 (50%)");
+        let summary = format_run_summary(&RunOutcome::Done, &stats);
+
+        assert!(summary.starts_with("=== Run summary ==="));
+        assert!(summary.contains("Outcome:    done"));
+        assert!(summary.contains("Iterations: 3"));
+        assert!(summary.contains("Tasks:      2/4 (50%)"));
+        assert!(summary.contains("Elapsed:    1m 05s"));
+        assert!(summary.contains("Log:"));
+        assert!(summary.contains(files::LOG_FILE));
+    }
+
+    #[test]
+    fn test_forward_stats_reads_plan() {
+        with_temp_dir(|dir| {
+            let content = "- [x] Task 1\n- [ ] Task 2\n";
+            fs::write(dir.path().join(files::IMPLEMENTATION_PLAN_FILE), content).unwrap();
+
+            let stats = forward_stats(
+                Path::new(files::IMPLEMENTATION_PLAN_FILE),
+                parser::PlanFormat::Markdown,
+                1,
+                Duration::from_secs(1),
+            );
+            assert_eq!(stats.fields, vec![("Tasks", "1/2 (50%)".to_string())]);
+        });
+    }
+
+    #[test]
+    fn test_format_missing_files() {
+        let cwd = std::env::current_dir().unwrap();
+        assert_eq!(
+            format_missing_files(&[Path::new("SPEC.md"), Path::new("plans/a.md")]),
+            format!(
+                "missing required files: SPEC.md ({}), plans/a.md ({}); \
+                 run 'ralphctl init' to create them",
+                cwd.join("SPEC.md").display(),
+                cwd.join("plans/a.md").display()
+            )
+        );
+        // Already absolute paths are not repeated
+        assert_eq!(
+            format_missing_files(&[Path::new("/srv/app/PROMPT.md")]),
+            "missing required files: /srv/app/PROMPT.md; run 'ralphctl init' to create them"
+        );
+    }
+
+    #[test]
+    fn test_validate_required_files_custom_plan() {
+        with_temp_dir(|dir| {
+            fs::write(dir.path().join(files::PROMPT_FILE), "prompt").unwrap();
+            fs::write(dir.path().join(files::SPEC_FILE), "spec").unwrap();
+            fs::create_dir_all(dir.path().join("plans")).unwrap();
+            fs::write(dir.path().join("plans/auth.md"), "- [ ] Task").unwrap();
+
+            // Default IMPLEMENTATION_PLAN.md is not required when overridden
+            assert!(validate_required_files(
+                Path::new(files::SPEC_FILE),
+                Path::new("plans/auth.md"),
+                true
+            )
+            .is_ok());
+        });
+    }
+
+    #[test]
+    fn test_validate_required_files_without_prompt() {
+        with_temp_dir(|dir| {
+            fs::write(dir.path().join(files::SPEC_FILE), "spec").unwrap();
+            fs::write(
+                dir.path().join(files::IMPLEMENTATION_PLAN_FILE),
+                "- [ ] Task",
+            )
+            .unwrap();
+
+            // --prompt-from-url runs without a local PROMPT.md
+            let plan = Path::new(files::IMPLEMENTATION_PLAN_FILE);
+            assert!(validate_required_files(Path::new(files::SPEC_FILE), plan, false).is_ok());
+        });
+    }
+
+    #[test]
+    fn test_validate_required_files_custom_spec() {
+        with_temp_dir(|dir| {
+            fs::write(dir.path().join(files::PROMPT_FILE), "prompt").unwrap();
+            fs::create_dir_all(dir.path().join("docs")).unwrap();
+            fs::write(dir.path().join("docs/SPEC.md"), "spec").unwrap();
+            fs::write(dir.path().join(files::IMPLEMENTATION_PLAN_FILE), "- [ ] A").unwrap();
+
+            // Default SPEC.md is not required when overridden
+            let plan = Path::new(files::IMPLEMENTATION_PLAN_FILE);
+            assert!(validate_required_files(Path::new("docs/SPEC.md"), plan, true).is_ok());
+        });
+    }
+
+    #[test]
+    fn test_forward_stats_custom_plan() {
+        with_temp_dir(|dir| {
+            fs::write(dir.path().join("feature.md"), "- [x] A\n- [x] B\n- [ ] C\n").unwrap();
+
+            let stats = forward_stats(
+                Path::new("feature.md"),
+                parser::PlanFormat::Markdown,
+                2,
+                Duration::from_secs(1),
+            );
+            assert_eq!(stats.fields, vec![("Tasks", "2/3 (67%)".to_string())]);
+        });
+    }
+
+    #[test]
+    fn test_forward_stats_missing_plan() {
+        with_temp_dir(|_dir| {
+            let stats = forward_stats(
+                Path::new(files::IMPLEMENTATION_PLAN_FILE),
+                parser::PlanFormat::Markdown,
+                0,
+                Duration::from_secs(0),
+            );
+            assert_eq!(stats.fields, vec![("Tasks", "unknown".to_string())]);
+        });
+    }
+
+    #[test]
+    fn test_reset_log_truncates_existing_log() {
+        with_temp_dir(|_dir| {
+            log_iteration(1, 1, "0badc0de", None, "old output", false).unwrap();
+            reset_log().unwrap();
+            assert_eq!(fs::read_to_string(files::LOG_FILE).unwrap(), "");
+        });
+    }
+
+    #[test]
+    fn test_reset_log_without_log_is_ok() {
+        with_temp_dir(|_dir| {
+            reset_log().unwrap();
+            assert!(!Path::new(files::LOG_FILE).exists());
+        });
+    }
+
+    #[test]
+    fn test_format_heartbeat() {
+        assert_eq!(
+            format_heartbeat(Duration::from_millis(30_400), None),
+            "…still running (30s elapsed)"
+        );
+        assert_eq!(
+            format_heartbeat(Duration::from_secs(90), Some("myproject")),
+            "myproject \u{2014} …still running (1m 30s elapsed)"
+        );
+    }
+
+    #[test]
+    fn test_spawn_options_default() {
+        let opts = SpawnOptions::default();
+        assert!(opts.model.is_none());
+        assert!(opts.interrupt_flag.is_none());
+        assert!(opts.heartbeat.is_none());
+        assert!(opts.env.is_empty());
+        assert!(opts.env_allowlist.is_none());
+    }
+
+    #[test]
+    fn test_isolated_env_allowlist() {
+        assert_eq!(
+            isolated_env_allowlist(None),
+            vec!["PATH", "HOME", "TERM", "LANG"]
+        );
+        assert_eq!(
+            isolated_env_allowlist(Some("SSH_AUTH_SOCK, LC_ALL  PATH,,")),
+            vec!["PATH", "HOME", "TERM", "LANG", "SSH_AUTH_SOCK", "LC_ALL"]
+        );
+    }
+
+    #[test]
+    fn test_dropped_env_vars() {
+        let allow = isolated_env_allowlist(None);
+        let inherited = [
+            "PATH",
+            "ANTHROPIC_API_KEY",
+            "HOME",
+            "CLAUDE_CONFIG_DIR",
+            "EDITOR",
+        ]
+        .into_iter()
+        .map(String::from);
+        assert_eq!(
+            dropped_env_vars(&allow, inherited),
+            vec!["ANTHROPIC_API_KEY", "CLAUDE_CONFIG_DIR", "EDITOR"]
+        );
+        assert!(dropped_env_vars(&allow, std::iter::empty()).is_empty());
+    }
+
+    fn iteration_result(stdout: &str, was_interrupted: bool) -> IterationResult {
+        IterationResult {
+            success: !was_interrupted,
+            exit_code: Some(if was_interrupted { 130 } else { 0 }),
+            stdout: stdout.to_string(),
+            stderr: String::new(),
+            was_interrupted,
+            truncated: false,
+            stopped_early: false,
+        }
+    }
+
+    #[test]
+    fn test_iteration_record_detects_signal() {
+        let cases = [
+            ("work\n[[RALPH:DONE]]\n", false, "done"),
+            ("[[RALPH:CONTINUE]]\n", false, "continue"),
+            (
+                "[[RALPH:BLOCKED:no creds]]\n[[RALPH:DONE]]\n",
+                false,
+                "blocked",
+            ),
+            ("just text\n", false, "none"),
+            ("[[RALPH:DONE]]\n", true, "interrupted"),
+        ];
+        for (stdout, interrupted, expected) in cases {
+            let record = IterationRecord::new(
+                3,
+                &iteration_result(stdout, interrupted),
+                Duration::from_millis(1500),
+            );
+            assert_eq!(record.signal, expected, "stdout: {:?}", stdout);
+            assert_eq!(record.iteration, 3);
+            assert_eq!(record.output_bytes, stdout.len());
+            assert_eq!(record.duration_ms, 1500);
+            assert_eq!(record.blocked_category, None);
+        }
+
+        let record = IterationRecord::new(
+            1,
+            &iteration_result("[[RALPH:BLOCKED:access:need repo permissions]]\n", false),
+            Duration::ZERO,
+        );
+        assert_eq!(record.signal, "blocked");
+        assert_eq!(record.blocked_category, Some("access"));
+        let json = serde_json::to_value(&record).unwrap();
+        assert_eq!(json["blocked_category"], "access");
+        let json = serde_json::to_value(IterationRecord::new(
+            1,
+            &iteration_result("[[RALPH:DONE]]\n", false),
+            Duration::ZERO,
+        ))
+        .unwrap();
+        assert!(json.get("blocked_category").is_none());
+        assert!(json.get("tools").is_none());
+
+        let record = IterationRecord::new(
+            1,
+            &iteration_result(
+                "{\"type\":\"assistant\",\"message\":{\"content\":[{\"type\":\"tool_use\",\
+                 \"name\":\"Edit\",\"input\":{\"file_path\":\"a.rs\"}}]}}\n",
+                false,
+            ),
+            Duration::ZERO,
+        );
+        let json = serde_json::to_value(&record).unwrap();
+        assert_eq!(json["tools"]["counts"]["Edit"], 1);
+        assert_eq!(json["tools"]["files_written"][0], "a.rs");
+    }
+
+    #[test]
+    fn test_write_report_serializes_array() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("report.json");
+        let records = vec![
+            IterationRecord::new(
+                1,
+                &iteration_result("[[RALPH:CONTINUE]]\n", false),
+                Duration::from_millis(20),
+            ),
+            IterationRecord::new(
+                2,
+                &iteration_result("[[RALPH:DONE]]\n", false),
+                Duration::from_millis(35),
+            ),
+        ];
+
+        write_report(&path, &records).unwrap();
+
+        let json: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        let entries = json.as_array().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0]["iteration"], 1);
+        assert_eq!(entries[0]["signal"], "continue");
+        assert_eq!(entries[1]["output_bytes"], 15);
+        assert_eq!(entries[1]["duration_ms"], 35);
+    }
+
+    #[test]
+    fn test_write_report_empty_run() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("report.json");
+        write_report(&path, &[]).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "[]\n");
+    }
+
+    #[test]
+    fn test_newly_completed_tasks() {
+        let before = parser::parse_tasks("- [x] A\n- [ ] B\n- [ ] C\n- [ ] A\n");
+        let after = parser::parse_tasks("- [x] A\n- [x] B\n- [ ] C\n- [x] A\n");
+        assert_eq!(newly_completed_tasks(&before, &after), vec!["B", "A"]);
+        assert!(newly_completed_tasks(&after, &after).is_empty());
+        // Unchecking is not a completion
+        assert!(newly_completed_tasks(&after, &before).is_empty());
+    }
+
+    #[test]
+    fn test_format_session_delta() {
+        let start = parser::TaskCount::new(13, 30);
+        let end = parser::TaskCount::new(20, 30);
+        assert_eq!(
+            format_session_delta(&start, &end),
+            "completed 7 tasks this session (13→20)"
+        );
+    }
+
+    #[test]
+    fn test_format_session_delta_singular_and_none() {
+        let start = parser::TaskCount::new(2, 5);
+        assert_eq!(
+            format_session_delta(&start, &parser::TaskCount::new(3, 5)),
+            "completed 1 task this session (2→3)"
+        );
+        assert_eq!(
+            format_session_delta(&start, &start),
+            "completed 0 tasks this session (2→2)"
+        );
+    }
+
+    #[test]
+    fn test_format_session_delta_unchecked_tasks_never_negative() {
+        let start = parser::TaskCount::new(5, 5);
+        let end = parser::TaskCount::new(4, 5);
+        assert_eq!(
+            format_session_delta(&start, &end),
+            "completed 0 tasks this session (5→4)"
+        );
+    }
+
+    #[test]
+    fn test_plan_task_count() {
+        let dir = tempfile::tempdir().unwrap();
+        let plan = dir.path().join("plan.md");
+        fs::write(&plan, "- [x] A\n- [ ] B\n").unwrap();
+        let markdown = parser::PlanFormat::Markdown;
+        assert_eq!(
+            plan_task_count(&plan, markdown),
+            Some(parser::TaskCount::new(1, 2))
+        );
+        assert_eq!(
+            plan_task_count(&dir.path().join("missing.md"), markdown),
+            None
+        );
+
+        let org = dir.path().join("plan.org");
+        fs::write(
+            &org,
+            "* DONE A
+* TODO B
+* TODO C
+",
+        )
+        .unwrap();
+        assert_eq!(
+            plan_task_count(&org, parser::PlanFormat::Org),
+            Some(parser::TaskCount::new(1, 3))
+        );
+    }
+
+    /// Reader that returns at most `chunk` bytes per read, simulating a
+    /// child that flushes output in small pieces.
+    struct ChunkedReader {
+        data: Vec<u8>,
+        pos: usize,
+        chunk: usize,
+    }
+
+    impl std::io::Read for ChunkedReader {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let end = (self.pos + self.chunk)
+                .min(self.data.len())
+                .min(self.pos + buf.len());
+            let n = end - self.pos;
+            buf[..n].copy_from_slice(&self.data[self.pos..end]);
+            self.pos = end;
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn test_signal_split_across_reads_without_newlines() {
+        // "[[RALPH:" and "DONE]]" arrive in separate reads, no newline at all
+        let reader = ChunkedReader {
+            data: b"[[RALPH:DONE]]".to_vec(),
+            pos: 0,
+            chunk: 8,
+        };
+        let captured = stream_and_capture(Some(reader), Vec::new());
+        assert_eq!(captured, "[[RALPH:DONE]]\n");
+        assert_eq!(detect_signal(&captured), LoopSignal::Done);
+    }
+
+    #[test]
+    fn test_blocked_signal_split_across_reads() {
+        let reader = ChunkedReader {
+            data: b"working\n[[RALPH:BLOCK"
+                .iter()
+                .chain(b"ED:need key]]")
+                .copied()
+                .collect(),
+            pos: 0,
+            chunk: 3,
+        };
+        let captured = stream_and_capture(Some(reader), Vec::new());
+        assert_eq!(
+            detect_blocked_signal(&captured),
+            Some("need key".to_string())
+        );
+    }
+
+    #[test]
+    fn test_validate_plan_has_tasks_allows_empty_with_flag() {
+        let dir = tempfile::tempdir().unwrap();
+        let plan = dir.path().join("plan.md");
+        fs::write(&plan, "# Plan\n\nNothing yet.\n").unwrap();
+        // Returns (with a warning) instead of exiting
+        validate_plan_has_tasks(&plan, parser::PlanFormat::Markdown, true);
+    }
+
+    #[test]
+    fn test_validate_plan_has_tasks_with_tasks() {
+        let dir = tempfile::tempdir().unwrap();
+        let plan = dir.path().join("plan.md");
+        fs::write(&plan, "- [ ] One\n").unwrap();
+        validate_plan_has_tasks(&plan, parser::PlanFormat::Markdown, false);
+    }
+
+    #[test]
+    fn test_parse_phase_model() {
+        assert_eq!(
+            parse_phase_model("Phase 1 = haiku").unwrap(),
+            PhaseModel {
+                heading: "Phase 1".to_string(),
+                model: "haiku".to_string(),
+            }
+        );
+        assert!(parse_phase_model("Phase 1").is_err());
+        assert!(parse_phase_model("=opus").is_err());
+        assert!(parse_phase_model("Phase 2=").is_err());
+    }
+
+    #[test]
+    fn test_resolve_phase_model() {
+        let mappings = vec![
+            parse_phase_model("phase 1=haiku").unwrap(),
+            parse_phase_model("Phase 2=opus").unwrap(),
+        ];
+        let plan = "## Phase 1: Setup\n- [x] A\n## Phase 2: Core\n- [ ] B\n## Phase 3\n- [ ] C\n";
+        assert_eq!(
+            resolve_phase_model(plan, &mappings),
+            Some(("Phase 2: Core".to_string(), "opus"))
+        );
+
+        // Case-insensitive match on the active phase
+        let plan = "## PHASE 1: Setup\n- [ ] A\n";
+        assert_eq!(resolve_phase_model(plan, &mappings).unwrap().1, "haiku");
+    }
+
+    #[test]
+    fn test_resolve_phase_model_unmapped_or_complete() {
+        let mappings = vec![parse_phase_model("Phase 1=haiku").unwrap()];
+        assert_eq!(
+            resolve_phase_model("## Phase 2\n- [ ] B\n", &mappings),
+            None
+        );
+        assert_eq!(
+            resolve_phase_model("## Phase 1\n- [x] A\n", &mappings),
+            None
+        );
+    }
+
+    #[test]
+    fn test_preflight_agent_missing_binary() {
+        let err = preflight_agent("definitely_not_a_real_agent_abc123").unwrap_err();
+        assert!(err.to_string().contains("not found in PATH"));
+    }
+
+    #[test]
+    fn test_preflight_agent_success() {
+        // `true` accepts any arguments and exits 0
+        preflight_agent("true").unwrap();
+    }
+
+    #[test]
+    fn test_preflight_agent_failure_includes_login_hint() {
+        let err = preflight_agent("false").unwrap_err().to_string();
+        assert!(err.contains("false preflight failed (exit code 1)"));
+        assert!(err.contains("hint: run `false login`"));
+    }
+
+    #[test]
+    fn test_claude_argv() {
+        assert_eq!(
+            claude_argv(&SpawnOptions::default()),
+            vec!["claude", "-p", "--dangerously-skip-permissions"]
+        );
+        let opts = SpawnOptions {
+            model: Some("opus"),
+            ..Default::default()
+        };
+        assert_eq!(
+            claude_argv(&opts),
+            vec![
+                "claude",
+                "-p",
+                "--dangerously-skip-permissions",
+                "--model",
+                "opus"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_format_command_quotes() {
+        let argv: Vec<String> = ["claude", "--model", "my model", "it's", ""]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert_eq!(
+            format_command(&argv),
+            r"claude --model 'my model' 'it'\''s' ''"
+        );
+    }
+
+    #[test]
+    fn test_format_dry_run() {
+        let argv = claude_argv(&SpawnOptions {
+            model: Some("sonnet"),
+            ..Default::default()
         });
+        let report = format_dry_run(
+            &argv,
+            "PROMPT.md",
+            "hello",
+            7,
+            &[("SPEC.md".to_string(), "present")],
+        );
+        assert_eq!(
+            report,
+            "Dry run: claude will not be called.\n\
+             Command:        claude -p --dangerously-skip-permissions --model sonnet\n\
+             Prompt:         PROMPT.md (5 bytes)\n\
+             Max iterations: 7\n\
+             Files:\n  \
+             SPEC.md                  present\n"
+        );
+    }
+
+    #[test]
+    fn test_budget_env() {
+        let tasks = parser::TaskCount::new(3, 8);
+        assert_eq!(
+            budget_env(2, 10, Some(&tasks)),
+            vec![
+                ("RALPH_ITERATION", "2".to_string()),
+                ("RALPH_MAX_ITERATIONS", "10".to_string()),
+                ("RALPH_TASKS_COMPLETED", "3".to_string()),
+                ("RALPH_TASKS_TOTAL", "8".to_string()),
+            ]
+        );
+        assert_eq!(budget_env(1, 5, None).len(), 2);
+    }
+
+    #[test]
+    fn test_prepare_scratch_dir_creates_empty_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = dir.path().join("scratch");
+
+        let iter = prepare_scratch_dir(&base, 3).unwrap();
+        assert!(iter.is_absolute());
+        assert!(iter.ends_with("iter-3"));
+        fs::write(iter.join("notes.txt"), "leftover").unwrap();
+
+        // Re-preparing the same iteration starts empty
+        let iter = prepare_scratch_dir(&base, 3).unwrap();
+        assert_eq!(fs::read_dir(&iter).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_prune_scratch_dirs_keeps_newest() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = dir.path();
+        for n in [1, 2, 10, 3] {
+            fs::create_dir_all(base.join(format!("iter-{}", n))).unwrap();
+        }
+        fs::create_dir_all(base.join("keep-me")).unwrap();
+
+        assert_eq!(prune_scratch_dirs(base, 2).unwrap(), 2);
+        assert!(base.join("iter-10").exists());
+        assert!(base.join("iter-3").exists());
+        assert!(!base.join("iter-2").exists());
+        assert!(!base.join("iter-1").exists());
+        assert!(base.join("keep-me").exists());
+
+        assert_eq!(prune_scratch_dirs(base, 0).unwrap(), 2);
+        assert!(!base.join("iter-10").exists());
+    }
+
+    #[test]
+    fn test_prune_scratch_dirs_missing_base() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(prune_scratch_dirs(&dir.path().join("none"), 0).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_scratch_iteration() {
+        assert_eq!(scratch_iteration("iter-12"), Some(12));
+        assert_eq!(scratch_iteration("iter-"), None);
+        assert_eq!(scratch_iteration("notes"), None);
     }
 }
@@ -0,0 +1,242 @@
+//! `ralphctl self-test`: the loop end to end against a built-in mock agent.
+//!
+//! Separates "is it ralphctl or is it claude?" on the user's own machine. A
+//! scratch project gets minimal ralph files and a small `sh` script named
+//! `claude` that answers CONTINUE, then edits the plan, then says DONE. This
+//! binary is then re-invoked as `ralphctl run --porcelain` with the script
+//! first on PATH, so the real `run` command drives it, and its records, log,
+//! plan, and exit code are checked in turn. A second scratch project is then
+//! driven in-process through the event stream frontends use
+//! (`stream::start_loop`). No network and no claude binary are needed.
+
+use crate::run::{self, LoopSignal, RunOutcome};
+use crate::stream::{self, LoopEvent, LoopOptions};
+use crate::{error, files, parser};
+use anyhow::{Context, Result};
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// Iteration budget; the mock agent needs three.
+const MAX_ITERATIONS: u32 = 5;
+
+const SPEC: &str = "# Self-test\n\nGreet the world.\n";
+
+const PLAN: &str = "# Plan\n\n- [ ] Write the greeting\n- [ ] Check the greeting\n";
+
+const PROMPT: &str = "Work through IMPLEMENTATION_PLAN.md.\n";
+
+/// The mock agent: drains the prompt, then scripts one step per call.
+///
+/// Only shell builtins are used, so it runs anywhere `/bin/sh` does.
+const MOCK_AGENT_SCRIPT: &str = r#"#!/bin/sh
+while read -r line; do :; done
+calls=0
+[ -f .calls ] && read -r calls < .calls
+calls=$((calls + 1))
+echo "$calls" > .calls
+case $calls in
+1)
+    echo "Read the spec."
+    echo "[[RALPH:CONTINUE]]"
+    ;;
+2)
+    printf '# Plan\n\n- [x] Write the greeting\n- [ ] Check the greeting\n' > IMPLEMENTATION_PLAN.md
+    echo "Wrote the greeting."
+    echo "[[RALPH:CONTINUE]]"
+    ;;
+*)
+    printf '# Plan\n\n- [x] Write the greeting\n- [x] Check the greeting\n' > IMPLEMENTATION_PLAN.md
+    echo "Checked the greeting."
+    echo "[[RALPH:DONE]]"
+    ;;
+esac
+"#;
+
+/// Porcelain records the run must print, in order.
+const EXPECTED_RECORDS: &[&str] = &[
+    "porcelain-version 1",
+    "iteration-start 1",
+    "signal CONTINUE",
+    "iteration-start 2",
+    "signal CONTINUE",
+    "iteration-start 3",
+    "signal DONE",
+    "outcome done iterations=3 tasks=2/2",
+];
+
+/// Signals the event stream must report, in order.
+const EXPECTED_SIGNALS: &[LoopSignal] =
+    &[LoopSignal::Continue, LoopSignal::Continue, LoopSignal::Done];
+
+/// Print a passed check.
+fn pass(check: &str) {
+    println!("ok  {}", check);
+}
+
+/// Task count of the plan as `completed/total`, or an error if unreadable.
+fn plan_progress(plan: &Path) -> Result<(usize, usize)> {
+    let count = run::plan_task_count(plan, parser::PlanFormat::Markdown)
+        .with_context(|| format!("failed to read {}", plan.display()))?;
+    Ok((count.completed, count.total))
+}
+
+/// Write the ralph files and the mock agent into `dir`.
+///
+/// Returns the directory holding the mock `claude`.
+fn write_project(dir: &Path) -> Result<PathBuf> {
+    fs::write(dir.join(files::SPEC_FILE), SPEC)?;
+    fs::write(dir.join(files::IMPLEMENTATION_PLAN_FILE), PLAN)?;
+    fs::write(dir.join(files::PROMPT_FILE), PROMPT)?;
+    let bin = dir.join("bin");
+    fs::create_dir(&bin)?;
+    let agent = bin.join("claude");
+    fs::write(&agent, MOCK_AGENT_SCRIPT)?;
+    fs::set_permissions(&agent, fs::Permissions::from_mode(0o755))?;
+    Ok(bin)
+}
+
+/// PATH with `bin` first, so the mock shadows any real claude.
+fn path_with(bin: PathBuf) -> Result<std::ffi::OsString> {
+    Ok(match std::env::var_os("PATH") {
+        Some(path) => {
+            std::env::join_paths(std::iter::once(bin).chain(std::env::split_paths(&path)))?
+        }
+        None => bin.into_os_string(),
+    })
+}
+
+/// Drive a fresh scratch project through `stream::start_loop` and check
+/// its events.
+fn check_event_stream() -> Result<()> {
+    let scratch = tempfile::tempdir().context("failed to create a scratch project")?;
+    let dir = scratch.path();
+    let bin = write_project(dir).context("failed to write the scratch project")?;
+    let path = path_with(bin)?
+        .into_string()
+        .map_err(|_| anyhow::anyhow!("event stream: PATH is not UTF-8"))?;
+
+    let mut handle = stream::start_loop(LoopOptions {
+        prompt: PROMPT.to_string(),
+        max_iterations: MAX_ITERATIONS,
+        env: vec![("PATH", path)],
+        current_dir: Some(dir.to_path_buf()),
+        ..Default::default()
+    });
+    let mut started = 0;
+    let mut signals = Vec::new();
+    let mut ended = None;
+    for event in handle.by_ref() {
+        match event {
+            LoopEvent::IterationStarted(_) => started += 1,
+            LoopEvent::SignalDetected(signal) => signals.push(signal),
+            LoopEvent::LoopEnded(outcome) => ended = Some(outcome),
+            LoopEvent::OutputChunk(_) | LoopEvent::IterationFinished(_) => {}
+        }
+    }
+    let outcome = handle.join().context("event stream")?;
+    anyhow::ensure!(
+        started == 3 && signals == EXPECTED_SIGNALS && ended.as_ref() == Some(&outcome),
+        "event stream: expected 3 iterations signalling {:?}, got {} signalling {:?}",
+        EXPECTED_SIGNALS,
+        started,
+        signals
+    );
+    anyhow::ensure!(
+        outcome == RunOutcome::Done,
+        "event stream: expected the loop to end done, it ended {}",
+        outcome.kind()
+    );
+    Ok(())
+}
+
+/// Run every check, stopping at the first failure.
+///
+/// Returns the failing check's context as the error.
+pub fn run() -> Result<()> {
+    let scratch = tempfile::tempdir().context("failed to create a scratch project")?;
+    let dir = scratch.path();
+    let plan = dir.join(files::IMPLEMENTATION_PLAN_FILE);
+    let bin = write_project(dir).context("failed to write the scratch project")?;
+    pass(&format!("scratch project in {}", dir.display()));
+
+    let before = plan_progress(&plan).context("plan parsing")?;
+    anyhow::ensure!(
+        before == (0, 2),
+        "plan parsing: expected 0/2 tasks, counted {}/{}",
+        before.0,
+        before.1
+    );
+    pass("plan parsed: 0/2 tasks");
+
+    // The mock shadows any real claude; the rest of PATH stays as it was
+    let path = path_with(bin)?;
+    let exe = std::env::current_exe().context("failed to locate the ralphctl binary")?;
+    let output = Command::new(&exe)
+        .args([
+            "run",
+            "--porcelain",
+            "--max-iterations",
+            &MAX_ITERATIONS.to_string(),
+        ])
+        .current_dir(dir)
+        .env("PATH", path)
+        .stdin(Stdio::null())
+        .output()
+        .with_context(|| format!("failed to start {} run", exe.display()))?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let records: Vec<&str> = std::str::from_utf8(&output.stdout)
+        .context("porcelain records are not UTF-8")?
+        .lines()
+        .collect();
+    anyhow::ensure!(
+        records == EXPECTED_RECORDS,
+        "ralphctl run: expected records {:?}, got {:?}\n{}",
+        EXPECTED_RECORDS,
+        records,
+        stderr.trim_end()
+    );
+    pass("ralphctl run drove the mock agent for 3 iterations");
+    pass("signals detected: CONTINUE, CONTINUE, DONE");
+
+    let log_path = dir.join(files::LOG_FILE);
+    let log = fs::read_to_string(&log_path)
+        .with_context(|| format!("logging: failed to read {}", files::LOG_FILE))?;
+    let entries = log.matches("--- end iteration ").count();
+    anyhow::ensure!(
+        entries == 3 && log.contains(run::RALPH_DONE_MARKER),
+        "logging: expected 3 entries ending in DONE in {}, found {}",
+        files::LOG_FILE,
+        entries
+    );
+    pass(&format!("{} has 3 iteration entries", files::LOG_FILE));
+
+    let after = plan_progress(&plan).context("plan parsing")?;
+    anyhow::ensure!(
+        after == (2, 2),
+        "plan parsing: expected 2/2 tasks after the agent's edits, counted {}/{}",
+        after.0,
+        after.1
+    );
+    pass("plan edits parsed: 2/2 tasks");
+
+    anyhow::ensure!(
+        output.status.code() == Some(error::exit::SUCCESS),
+        "exit codes: expected done to exit {}, ralphctl run {}",
+        error::exit::SUCCESS,
+        match output.status.code() {
+            Some(code) => format!("exited {}", code),
+            None => "was killed by a signal".to_string(),
+        }
+    );
+    pass(&format!(
+        "outcome done maps to exit {}",
+        error::exit::SUCCESS
+    ));
+
+    check_event_stream()?;
+    pass("event stream drove a fresh project to done in 3 iterations");
+
+    Ok(())
+}
@@ -0,0 +1,174 @@
+//! Reference listing of the `[[RALPH:...]]` markers for `ralphctl signals`.
+//!
+//! Markers are built from the constants the detectors match on, so the
+//! listing cannot drift from what the loops actually recognize.
+
+use crate::reverse::{RALPH_FOUND_PREFIX, RALPH_INCONCLUSIVE_PREFIX};
+use crate::run::{
+    RALPH_BLOCKED_PREFIX, RALPH_CONTINUE_MARKER, RALPH_DONE_MARKER, RALPH_MARKER_SUFFIX,
+    RALPH_PROGRESS_PREFIX,
+};
+
+/// One recognized marker.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignalInfo {
+    /// The marker as printed, with a `<placeholder>` for its argument
+    pub marker: String,
+    /// Commands that act on it: `run`, `reverse`, or `run, reverse`
+    pub modes: &'static str,
+    /// One-line description
+    pub description: &'static str,
+}
+
+/// Every marker, in the order the README documents them.
+pub fn all() -> Vec<SignalInfo> {
+    let info = |marker: String, modes, description| SignalInfo {
+        marker,
+        modes,
+        description,
+    };
+    vec![
+        info(
+            RALPH_CONTINUE_MARKER.to_string(),
+            "run, reverse",
+            "Iteration finished, more work remains; the loop continues",
+        ),
+        info(
+            RALPH_DONE_MARKER.to_string(),
+            "run",
+            "All tasks complete; run exits 0",
+        ),
+        info(
+            format!("{}<reason>{}", RALPH_BLOCKED_PREFIX, RALPH_MARKER_SUFFIX),
+            "run, reverse",
+            "Cannot proceed without a human; exits 3",
+        ),
+        info(
+            format!("{}<pct>{}", RALPH_PROGRESS_PREFIX, RALPH_MARKER_SUFFIX),
+            "run",
+            "Optional self-reported progress (0-100); never ends the loop",
+        ),
+        info(
+            format!("{}<summary>{}", RALPH_FOUND_PREFIX, RALPH_MARKER_SUFFIX),
+            "reverse",
+            "Question answered, FINDINGS.md written; exits 0",
+        ),
+        info(
+            format!(
+                "{}<reason>{}",
+                RALPH_INCONCLUSIVE_PREFIX, RALPH_MARKER_SUFFIX
+            ),
+            "reverse",
+            "No answer found, FINDINGS.md records what was tried; exits 4",
+        ),
+    ]
+}
+
+/// The markers `command` (`run` or `reverse`) acts on, in listing order.
+pub fn for_command(command: &str) -> Vec<SignalInfo> {
+    all()
+        .into_iter()
+        .filter(|s| s.modes.split(", ").any(|mode| mode == command))
+        .collect()
+}
+
+/// Format the listing as aligned `marker  modes  description` lines.
+pub fn format_signals(signals: &[SignalInfo]) -> String {
+    let marker_width = signals
+        .iter()
+        .map(|s| s.marker.chars().count())
+        .max()
+        .unwrap_or(0);
+    let modes_width = signals.iter().map(|s| s.modes.len()).max().unwrap_or(0);
+    signals
+        .iter()
+        .map(|s| {
+            format!(
+                "{:marker_width$}  {:modes_width$}  {}\n",
+                s.marker, s.modes, s.description
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reverse::{detect_reverse_signal, ReverseSignal};
+    use crate::run::{detect_first_signal, detect_progress, LoopSignal};
+
+    fn marker(prefix: &str) -> String {
+        all()
+            .into_iter()
+            .find(|s| s.marker.starts_with(prefix))
+            .unwrap()
+            .marker
+    }
+
+    #[test]
+    fn test_listed_markers_are_detected() {
+        assert_eq!(
+            detect_first_signal(&marker(RALPH_DONE_MARKER)),
+            LoopSignal::Done
+        );
+        assert_eq!(
+            detect_first_signal(&marker(RALPH_CONTINUE_MARKER)),
+            LoopSignal::Continue
+        );
+        assert_eq!(
+            detect_first_signal(&marker(RALPH_BLOCKED_PREFIX)),
+            LoopSignal::Blocked("<reason>".to_string())
+        );
+        assert_eq!(
+            detect_reverse_signal(&marker(RALPH_FOUND_PREFIX)),
+            ReverseSignal::Found("<summary>".to_string())
+        );
+        assert_eq!(
+            detect_reverse_signal(&marker(RALPH_INCONCLUSIVE_PREFIX)),
+            ReverseSignal::Inconclusive("<reason>".to_string())
+        );
+        let progress = marker(RALPH_PROGRESS_PREFIX).replace("<pct>", "40");
+        assert_eq!(detect_progress(&progress), Some(40));
+    }
+
+    #[test]
+    fn test_for_command_filters_by_mode() {
+        let markers = |command| {
+            for_command(command)
+                .into_iter()
+                .map(|s| s.marker)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            markers("run"),
+            vec![
+                "[[RALPH:CONTINUE]]",
+                "[[RALPH:DONE]]",
+                "[[RALPH:BLOCKED:<reason>]]",
+                "[[RALPH:PROGRESS:<pct>]]",
+            ]
+        );
+        assert_eq!(
+            markers("reverse"),
+            vec![
+                "[[RALPH:CONTINUE]]",
+                "[[RALPH:BLOCKED:<reason>]]",
+                "[[RALPH:FOUND:<summary>]]",
+                "[[RALPH:INCONCLUSIVE:<reason>]]",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_format_signals_aligns_columns() {
+        let listing = format_signals(&all());
+        assert_eq!(listing.lines().count(), all().len());
+        let column = listing.lines().next().unwrap().find("run").unwrap();
+        for line in listing.lines() {
+            assert_eq!(
+                line.find(" run").or(line.find(" reverse")),
+                Some(column - 1)
+            );
+        }
+    }
+}
@@ -0,0 +1,225 @@
+//! Per-project run statistics in `.ralphctl/stats.toml`.
+//!
+//! Every `run` and `reverse` invocation adds its iterations, wall-clock time,
+//! and outcome to running totals when it exits. Nothing leaves the machine;
+//! `ralphctl stats` prints the totals and `stats --reset` zeroes them.
+
+use crate::{duration, files};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Running totals across every loop run in a project.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Stats {
+    /// Number of `run` and `reverse` invocations recorded
+    #[serde(default)]
+    pub runs: u64,
+    /// Iterations completed across all runs
+    #[serde(default)]
+    pub iterations: u64,
+    /// Wall-clock time across all runs, in seconds
+    #[serde(default)]
+    pub elapsed_secs: u64,
+    /// When the most recent run ended (RFC 3339)
+    #[serde(default)]
+    pub last_run: Option<String>,
+    /// Runs per outcome kind (`done`, `blocked`, `max_iterations`, ...)
+    #[serde(default)]
+    pub outcomes: BTreeMap<String, u64>,
+}
+
+impl Stats {
+    /// Add one finished run to the totals.
+    pub fn record(&mut self, outcome: &str, iterations: u32, elapsed: Duration, ended: &str) {
+        self.runs += 1;
+        self.iterations += u64::from(iterations);
+        self.elapsed_secs += elapsed.as_secs();
+        *self.outcomes.entry(outcome.to_string()).or_default() += 1;
+        self.last_run = Some(ended.to_string());
+    }
+}
+
+/// Load `.ralphctl/stats.toml` from `dir`, or empty totals if it is missing.
+///
+/// Fails if the file exists but cannot be read or parsed.
+pub fn load(dir: &Path) -> Result<Stats> {
+    let path = files::stats_file_path(dir);
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Stats::default()),
+        Err(e) => return Err(e).with_context(|| format!("failed to read {}", path.display())),
+    };
+    toml::from_str(&content).with_context(|| format!("failed to parse {}", path.display()))
+}
+
+/// Load the totals, moving an unparseable file aside to start over.
+///
+/// Returns the totals and, if the file was corrupt, where it was moved to.
+pub fn load_or_recover(dir: &Path) -> Result<(Stats, Option<PathBuf>)> {
+    let path = files::stats_file_path(dir);
+    match load(dir) {
+        Ok(stats) => Ok((stats, None)),
+        Err(_) if path.is_file() => {
+            let backup = path.with_extension("toml.corrupt");
+            fs::rename(&path, &backup).with_context(|| {
+                format!("failed to move {} to {}", path.display(), backup.display())
+            })?;
+            Ok((Stats::default(), Some(backup)))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Write `stats` to `.ralphctl/stats.toml` via a temp file and rename.
+pub fn save(dir: &Path, stats: &Stats) -> Result<()> {
+    let path = files::stats_file_path(dir);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("failed to create {}", parent.display()))?;
+    }
+    let tmp = path.with_extension("toml.tmp");
+    fs::write(&tmp, toml::to_string(stats)?)
+        .with_context(|| format!("failed to write {}", tmp.display()))?;
+    fs::rename(&tmp, &path).with_context(|| format!("failed to write {}", path.display()))
+}
+
+/// Add a finished run to `.ralphctl/stats.toml`.
+///
+/// Returns where a corrupt file was moved, if one had to be replaced.
+pub fn record_run(
+    dir: &Path,
+    outcome: &str,
+    iterations: u32,
+    elapsed: Duration,
+) -> Result<Option<PathBuf>> {
+    let (mut stats, backup) = load_or_recover(dir)?;
+    stats.record(
+        outcome,
+        iterations,
+        elapsed,
+        &chrono::Local::now().to_rfc3339(),
+    );
+    save(dir, &stats)?;
+    Ok(backup)
+}
+
+/// Format the totals for `ralphctl stats`.
+pub fn format_stats(stats: &Stats) -> String {
+    if stats.runs == 0 {
+        return "No runs recorded yet.\n".to_string();
+    }
+    let mut out = format!(
+        "Runs:        {}\nIterations:  {}\nTotal time:  {}\nLast run:    {}\n",
+        stats.runs,
+        stats.iterations,
+        duration::format_duration(Duration::from_secs(stats.elapsed_secs)),
+        stats.last_run.as_deref().unwrap_or("unknown"),
+    );
+    out.push_str("Outcomes:\n");
+    for (outcome, count) in &stats.outcomes {
+        out.push_str(&format!("  {:<15} {}\n", outcome, count));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_accumulates() {
+        let mut stats = Stats::default();
+        stats.record("done", 3, Duration::from_secs(40), "t1");
+        stats.record("blocked", 2, Duration::from_millis(20_900), "t2");
+        stats.record("done", 0, Duration::ZERO, "t3");
+
+        assert_eq!(stats.runs, 3);
+        assert_eq!(stats.iterations, 5);
+        assert_eq!(stats.elapsed_secs, 60);
+        assert_eq!(stats.last_run.as_deref(), Some("t3"));
+        assert_eq!(stats.outcomes["done"], 2);
+        assert_eq!(stats.outcomes["blocked"], 1);
+    }
+
+    #[test]
+    fn test_save_load_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(load(dir.path()).unwrap(), Stats::default());
+
+        let mut stats = Stats::default();
+        stats.record("max_iterations", 10, Duration::from_secs(600), "t1");
+        save(dir.path(), &stats).unwrap();
+        assert_eq!(load(dir.path()).unwrap(), stats);
+        assert!(!files::stats_file_path(dir.path())
+            .with_extension("toml.tmp")
+            .exists());
+    }
+
+    #[test]
+    fn test_record_run_merges_with_existing() {
+        let dir = tempfile::tempdir().unwrap();
+        record_run(dir.path(), "done", 2, Duration::from_secs(5)).unwrap();
+        record_run(dir.path(), "interrupted", 1, Duration::from_secs(5)).unwrap();
+
+        let stats = load(dir.path()).unwrap();
+        assert_eq!(stats.runs, 2);
+        assert_eq!(stats.iterations, 3);
+        assert_eq!(stats.elapsed_secs, 10);
+        assert_eq!(stats.outcomes.len(), 2);
+    }
+
+    #[test]
+    fn test_missing_fields_default() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join(files::RALPHCTL_DIR)).unwrap();
+        fs::write(files::stats_file_path(dir.path()), "runs = 4\n").unwrap();
+        let stats = load(dir.path()).unwrap();
+        assert_eq!(stats.runs, 4);
+        assert!(stats.outcomes.is_empty());
+    }
+
+    #[test]
+    fn test_corrupt_file_moved_aside() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join(files::RALPHCTL_DIR)).unwrap();
+        let path = files::stats_file_path(dir.path());
+        fs::write(&path, "runs = \"many\"\n[[[").unwrap();
+        assert!(load(dir.path()).is_err());
+
+        let backup = record_run(dir.path(), "done", 1, Duration::from_secs(1))
+            .unwrap()
+            .expect("corrupt file should be backed up");
+        assert_eq!(backup, path.with_extension("toml.corrupt"));
+        assert_eq!(fs::read_to_string(&backup).unwrap(), "runs = \"many\"\n[[[");
+        assert_eq!(load(dir.path()).unwrap().runs, 1);
+    }
+
+    #[test]
+    fn test_format_stats() {
+        assert_eq!(format_stats(&Stats::default()), "No runs recorded yet.\n");
+
+        let mut stats = Stats::default();
+        stats.record(
+            "done",
+            4,
+            Duration::from_secs(187),
+            "2026-01-02T03:04:05+00:00",
+        );
+        stats.record(
+            "blocked",
+            1,
+            Duration::from_secs(0),
+            "2026-01-02T03:04:05+00:00",
+        );
+        let out = format_stats(&stats);
+        assert!(out.contains("Runs:        2\n"));
+        assert!(out.contains("Iterations:  5\n"));
+        assert!(out.contains("Total time:  3m 07s\n"));
+        assert!(out.contains("Last run:    2026-01-02T03:04:05+00:00\n"));
+        assert!(out.contains("  blocked         1\n  done            1\n"));
+    }
+}
@@ -0,0 +1,474 @@
+//! Event stream over the forward iteration loop.
+//!
+//! `drive_loop` runs the iterations and reports every step to a consumer as
+//! a `LoopEvent`; the consumer's `Step` answer steers the loop (which prompt
+//! to send, whether to go on, retry, or stop). `ralphctl run` is one such
+//! consumer: it prints, logs, and prompts as the events arrive. `start_loop`
+//! runs a headless loop on a background thread and hands its events to a
+//! frontend (TUI, web) through a `LoopHandle`, so nothing has to scrape
+//! stdout.
+
+use crate::run::{self, IterationRecord, IterationResult, LoopSignal, RunOutcome, SpawnOptions};
+use anyhow::Result;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicBool;
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::thread;
+use std::time::Instant;
+
+/// Lifecycle event emitted by the loop.
+///
+/// Each iteration yields `IterationStarted`, its `OutputChunk`s, then
+/// `IterationFinished` and, unless claude was interrupted, cut off, or
+/// failed, `SignalDetected`. `LoopEnded` is always the last event.
+#[derive(Debug, Clone)]
+pub enum LoopEvent {
+    /// Iteration N is about to spawn claude
+    IterationStarted(u32),
+    /// One line of claude's stdout, including its trailing newline
+    OutputChunk(String),
+    /// Claude exited; its full result and the iteration's `--report` record
+    IterationFinished(FinishedIteration),
+    /// DONE/CONTINUE/BLOCKED/no-signal result of the iteration's output
+    SignalDetected(LoopSignal),
+    /// The loop stopped with this outcome
+    LoopEnded(RunOutcome),
+}
+
+/// A finished iteration, as carried by `LoopEvent::IterationFinished`.
+#[derive(Debug, Clone)]
+pub struct FinishedIteration {
+    /// What claude returned
+    pub result: IterationResult,
+    /// Per-iteration stats, in the same shape as `--report` entries
+    pub record: IterationRecord,
+}
+
+/// A consumer's answer to a `LoopEvent`.
+#[derive(Debug, Default)]
+pub enum Step<'a> {
+    /// Let the loop decide: after `SignalDetected`, DONE and BLOCKED end it
+    /// and anything else moves on to the next iteration
+    #[default]
+    Proceed,
+    /// Answer to `IterationStarted`: send this prompt to claude, spawned
+    /// with these options
+    Spawn(String, Box<SpawnOptions<'a>>),
+    /// Move on to the next iteration whatever the signal
+    Continue,
+    /// Run the same iteration number again; the budget is not consumed
+    Retry,
+    /// End the loop with this outcome
+    Stop(RunOutcome),
+}
+
+/// Run up to `max_iterations` iterations, passing each event to `consume`.
+///
+/// `IterationStarted` must be answered with `Step::Spawn` (or `Step::Stop`);
+/// any other answer ends the loop as failed. Answers to `OutputChunk` and
+/// `LoopEnded` are ignored, and `IterationFinished` only heeds `Step::Stop`.
+/// Errors spawning claude, and claude exiting non-zero, end the loop with
+/// `RunOutcome::Failed`. Returns the outcome `LoopEnded` reported.
+///
+/// # Example
+///
+/// A consumer that stops at the first iteration without a signal, where
+/// `run` would ask what to do:
+///
+/// ```
+/// use ralphctl::run::{LoopSignal, RunOutcome, SpawnOptions};
+/// use ralphctl::stream::{self, LoopEvent, Step};
+/// # use std::os::unix::fs::PermissionsExt;
+/// # let dir = tempfile::tempdir()?;
+/// # let claude = dir.path().join("claude");
+/// # std::fs::write(&claude, "#!/bin/sh\ncat > /dev/null\necho 'Still thinking.'\n")?;
+/// # std::fs::set_permissions(&claude, std::fs::Permissions::from_mode(0o755))?;
+/// # let path = format!("{}:/usr/bin:/bin", dir.path().display());
+///
+/// let opts = SpawnOptions {
+///     suppress_echo: true,
+/// #     env: vec![("PATH", path)],
+///     ..Default::default()
+/// };
+/// let mut lines = 0;
+/// let outcome = stream::drive_loop(5, |event| match event {
+///     LoopEvent::IterationStarted(_) => Step::Spawn("Keep going.\n".to_string(), Box::new(opts.clone())),
+///     LoopEvent::OutputChunk(_) => {
+///         lines += 1;
+///         Step::Proceed
+///     }
+///     LoopEvent::SignalDetected(LoopSignal::NoSignal) => Step::Stop(RunOutcome::NoSignal),
+///     _ => Step::Proceed,
+/// });
+/// assert_eq!(outcome, RunOutcome::NoSignal);
+/// assert_eq!(lines, 1);
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn drive_loop<'a>(
+    max_iterations: u32,
+    mut consume: impl FnMut(LoopEvent) -> Step<'a>,
+) -> RunOutcome {
+    let mut iterations = 1..=max_iterations;
+    let mut retry = None;
+
+    while let Some(iteration) = retry.take().or_else(|| iterations.next()) {
+        let (prompt, spawn_opts) = match consume(LoopEvent::IterationStarted(iteration)) {
+            Step::Spawn(prompt, spawn_opts) => (prompt, spawn_opts),
+            Step::Stop(outcome) => return end(&mut consume, outcome),
+            _ => {
+                let reason = format!("no prompt to send for iteration {}", iteration);
+                return end(&mut consume, RunOutcome::Failed(reason));
+            }
+        };
+
+        let started = Instant::now();
+        let result = match run_iteration(&prompt, *spawn_opts, &mut consume) {
+            Ok(result) => result,
+            Err(e) => return end(&mut consume, RunOutcome::Failed(format!("{:#}", e))),
+        };
+        let record = IterationRecord::new(iteration, &result, started.elapsed());
+        let signal = run::detect_first_signal(&result.stdout);
+        let (interrupted, truncated, success, exit_code) = (
+            result.was_interrupted,
+            result.truncated,
+            result.success,
+            result.exit_code,
+        );
+
+        let finished = FinishedIteration { result, record };
+        if let Step::Stop(outcome) = consume(LoopEvent::IterationFinished(finished)) {
+            return end(&mut consume, outcome);
+        }
+        if interrupted {
+            return end(&mut consume, RunOutcome::Interrupted);
+        }
+        if truncated {
+            return end(&mut consume, RunOutcome::OutputLimit);
+        }
+        if !success {
+            let reason = format!("claude exited with code {}", exit_code.unwrap_or(-1));
+            return end(&mut consume, RunOutcome::Failed(reason));
+        }
+
+        match consume(LoopEvent::SignalDetected(signal.clone())) {
+            Step::Proceed => match signal {
+                LoopSignal::Done => return end(&mut consume, RunOutcome::Done),
+                LoopSignal::Blocked(reason) => {
+                    return end(&mut consume, RunOutcome::Blocked(reason))
+                }
+                LoopSignal::Continue | LoopSignal::NoSignal => {}
+            },
+            Step::Retry => retry = Some(iteration),
+            Step::Stop(outcome) => return end(&mut consume, outcome),
+            Step::Continue | Step::Spawn(..) => {}
+        }
+    }
+
+    end(&mut consume, RunOutcome::MaxIterations)
+}
+
+/// Report `outcome` as the last event and return it.
+fn end<'a>(consume: &mut impl FnMut(LoopEvent) -> Step<'a>, outcome: RunOutcome) -> RunOutcome {
+    consume(LoopEvent::LoopEnded(outcome.clone()));
+    outcome
+}
+
+/// Spawn claude on its own thread, passing its stdout lines to `consume`
+/// as they arrive.
+fn run_iteration<'a>(
+    prompt: &str,
+    spawn_opts: SpawnOptions<'a>,
+    consume: &mut impl FnMut(LoopEvent) -> Step<'a>,
+) -> Result<IterationResult> {
+    let (line_tx, lines) = mpsc::channel();
+    let spawn_opts = SpawnOptions {
+        output_tx: Some(line_tx),
+        ..spawn_opts
+    };
+    thread::scope(|scope| {
+        // The options, and with them the sender, go away once claude is
+        // done, which ends the loop below
+        let claude = scope.spawn(move || run::spawn_claude(prompt, &spawn_opts));
+        for line in lines {
+            consume(LoopEvent::OutputChunk(line));
+        }
+        claude
+            .join()
+            .map_err(|_| anyhow::anyhow!("claude thread panicked"))?
+    })
+}
+
+/// Options for `start_loop`.
+#[derive(Debug, Clone, Default)]
+pub struct LoopOptions {
+    /// Prompt piped to claude on every iteration
+    pub prompt: String,
+    /// Maximum iterations before ending with `RunOutcome::MaxIterations`
+    pub max_iterations: u32,
+    /// Claude model to use (passed as `--model`)
+    pub model: Option<String>,
+    /// Flag that, once set, kills the running iteration and ends the loop
+    pub interrupt_flag: Option<Arc<AtomicBool>>,
+    /// Extra environment variables for claude
+    pub env: Vec<(&'static str, String)>,
+    /// Working directory for claude instead of ours
+    pub current_dir: Option<PathBuf>,
+}
+
+/// Handle to a loop started by `start_loop`.
+///
+/// Iterating the handle blocks for each event and ends after `LoopEnded`.
+pub struct LoopHandle {
+    events: Receiver<LoopEvent>,
+    thread: thread::JoinHandle<RunOutcome>,
+}
+
+impl Iterator for LoopHandle {
+    type Item = LoopEvent;
+
+    fn next(&mut self) -> Option<LoopEvent> {
+        self.events.recv().ok()
+    }
+}
+
+impl LoopHandle {
+    /// Wait for the loop to finish, returning its outcome.
+    ///
+    /// Events not yet received are discarded; the loop runs to its end
+    /// regardless.
+    pub fn join(self) -> Result<RunOutcome> {
+        drop(self.events);
+        self.thread
+            .join()
+            .map_err(|_| anyhow::anyhow!("loop thread panicked"))
+    }
+}
+
+/// Start a headless loop on a background thread.
+///
+/// Nothing is printed and nobody is asked: every iteration sends the same
+/// prompt, and an iteration without a signal simply moves on.
+///
+/// # Example
+///
+/// ```
+/// use ralphctl::run::RunOutcome;
+/// use ralphctl::stream::{self, LoopEvent, LoopOptions};
+/// # use std::os::unix::fs::PermissionsExt;
+/// # let dir = tempfile::tempdir()?;
+/// # let claude = dir.path().join("claude");
+/// # std::fs::write(&claude, "#!/bin/sh\ncat > /dev/null\necho 'All done.'\necho '[[RALPH:DONE]]'\n")?;
+/// # std::fs::set_permissions(&claude, std::fs::Permissions::from_mode(0o755))?;
+/// # let path = format!("{}:/usr/bin:/bin", dir.path().display());
+///
+/// let mut handle = stream::start_loop(LoopOptions {
+///     prompt: "Work through IMPLEMENTATION_PLAN.md.\n".to_string(),
+///     max_iterations: 10,
+/// #     env: vec![("PATH", path)],
+///     ..Default::default()
+/// });
+/// let mut transcript = String::new();
+/// for event in handle.by_ref() {
+///     match event {
+///         LoopEvent::IterationStarted(n) => transcript += &format!("-- iteration {} --\n", n),
+///         LoopEvent::OutputChunk(line) => transcript += &line,
+///         _ => {}
+///     }
+/// }
+/// assert_eq!(handle.join()?, RunOutcome::Done);
+/// assert_eq!(transcript, "-- iteration 1 --\nAll done.\n[[RALPH:DONE]]\n");
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn start_loop(opts: LoopOptions) -> LoopHandle {
+    let (tx, events) = mpsc::channel();
+    let thread = thread::spawn(move || {
+        let spawn_opts = SpawnOptions {
+            model: opts.model.as_deref(),
+            interrupt_flag: opts.interrupt_flag.clone(),
+            env: opts.env.clone(),
+            current_dir: opts.current_dir.clone(),
+            suppress_echo: true,
+            ..Default::default()
+        };
+        // A dropped handle stops nothing; its events are simply not delivered
+        drive_loop(opts.max_iterations, |event| {
+            let step = match event {
+                LoopEvent::IterationStarted(_) => {
+                    Step::Spawn(opts.prompt.clone(), Box::new(spawn_opts.clone()))
+                }
+                _ => Step::Proceed,
+            };
+            let _ = tx.send(event);
+            step
+        })
+    });
+    LoopHandle { events, thread }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+    use tempfile::TempDir;
+
+    /// Write an executable fake `claude` running `body` (stdin is discarded)
+    /// and return options that put it first on PATH.
+    fn loop_opts(dir: &TempDir, body: &str, max_iterations: u32) -> LoopOptions {
+        let path = dir.path().join("claude");
+        fs::write(&path, format!("#!/bin/sh\ncat > /dev/null\n{}\n", body)).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        LoopOptions {
+            prompt: "prompt".to_string(),
+            max_iterations,
+            env: vec![("PATH", format!("{}:/usr/bin:/bin", dir.path().display()))],
+            ..Default::default()
+        }
+    }
+
+    /// Events as short strings, so sequences compare exactly.
+    fn describe(events: &[LoopEvent]) -> Vec<String> {
+        events
+            .iter()
+            .map(|event| match event {
+                LoopEvent::IterationStarted(n) => format!("started {}", n),
+                LoopEvent::OutputChunk(line) => format!("output {}", line.trim_end()),
+                LoopEvent::IterationFinished(finished) => format!(
+                    "finished {} {}",
+                    finished.record.iteration, finished.record.signal
+                ),
+                LoopEvent::SignalDetected(signal) => format!("signal {:?}", signal),
+                LoopEvent::LoopEnded(outcome) => format!("ended {}", outcome.kind()),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_start_loop_emits_events_until_done() {
+        let dir = tempfile::tempdir().unwrap();
+        let marker = dir.path().join("called");
+        let opts = loop_opts(
+            &dir,
+            &format!(
+                "if [ -f {m} ]; then echo finished; echo '[[RALPH:DONE]]'; \
+                 else touch {m}; echo working; echo '[[RALPH:CONTINUE]]'; fi",
+                m = marker.display()
+            ),
+            5,
+        );
+
+        let mut handle = start_loop(opts);
+        let events: Vec<LoopEvent> = handle.by_ref().collect();
+        assert_eq!(handle.join().unwrap(), RunOutcome::Done);
+        assert_eq!(
+            describe(&events),
+            vec![
+                "started 1",
+                "output working",
+                "output [[RALPH:CONTINUE]]",
+                "finished 1 continue",
+                "signal Continue",
+                "started 2",
+                "output finished",
+                "output [[RALPH:DONE]]",
+                "finished 2 done",
+                "signal Done",
+                "ended done",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_start_loop_no_signal_runs_to_max_iterations() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut handle = start_loop(loop_opts(&dir, "echo nothing", 2));
+        let events = describe(&handle.by_ref().collect::<Vec<_>>());
+        assert_eq!(handle.join().unwrap(), RunOutcome::MaxIterations);
+
+        assert_eq!(events.iter().filter(|e| *e == "signal NoSignal").count(), 2);
+        assert_eq!(events.last().unwrap(), "ended max_iterations");
+    }
+
+    #[test]
+    fn test_start_loop_blocked() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut handle = start_loop(loop_opts(&dir, "echo '[[RALPH:BLOCKED:need creds]]'", 5));
+        let events = describe(&handle.by_ref().collect::<Vec<_>>());
+        assert_eq!(
+            handle.join().unwrap(),
+            RunOutcome::Blocked("need creds".to_string())
+        );
+        assert_eq!(
+            &events[events.len() - 2..],
+            ["signal Blocked(\"need creds\")", "ended blocked"]
+        );
+    }
+
+    #[test]
+    fn test_start_loop_failure_ends_the_loop() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut handle = start_loop(loop_opts(&dir, "echo partial; exit 3", 5));
+        let events = describe(&handle.by_ref().collect::<Vec<_>>());
+        assert_eq!(
+            handle.join().unwrap(),
+            RunOutcome::Failed("claude exited with code 3".to_string())
+        );
+        assert_eq!(
+            events,
+            vec![
+                "started 1",
+                "output partial",
+                "finished 1 none",
+                "ended failed"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_drive_loop_follows_consumer_steps() {
+        let dir = tempfile::tempdir().unwrap();
+        let opts = loop_opts(&dir, "echo '[[RALPH:BLOCKED:flaky]]'", 3);
+        let spawn_opts = SpawnOptions {
+            env: opts.env.clone(),
+            suppress_echo: true,
+            ..Default::default()
+        };
+
+        // Retry a blocked iteration once, then move on past the block
+        let mut started = Vec::new();
+        let mut blocked = 0;
+        let outcome = drive_loop(opts.max_iterations, |event| match event {
+            LoopEvent::IterationStarted(n) => {
+                started.push(n);
+                Step::Spawn(String::new(), Box::new(spawn_opts.clone()))
+            }
+            LoopEvent::SignalDetected(LoopSignal::Blocked(_)) => {
+                blocked += 1;
+                if blocked == 1 {
+                    Step::Retry
+                } else {
+                    Step::Continue
+                }
+            }
+            _ => Step::Proceed,
+        });
+
+        assert_eq!(outcome, RunOutcome::MaxIterations);
+        assert_eq!(started, vec![1, 1, 2, 3]);
+    }
+
+    #[test]
+    fn test_drive_loop_requires_a_prompt() {
+        let mut events = Vec::new();
+        let outcome = drive_loop(3, |event| {
+            events.push(event);
+            Step::Proceed
+        });
+        assert_eq!(
+            outcome,
+            RunOutcome::Failed("no prompt to send for iteration 1".to_string())
+        );
+        assert_eq!(describe(&events), vec!["started 1", "ended failed"]);
+    }
+}
@@ -7,15 +7,52 @@
 #![allow(dead_code)] // Used by init command (future task)
 
 use anyhow::{Context, Result};
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION, ETAG, IF_NONE_MATCH};
+use reqwest::StatusCode;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 /// Base URL for raw template content on GitHub.
-const TEMPLATE_BASE_URL: &str = "https://raw.githubusercontent.com/wcygan/ralphctl/main/templates";
+pub const TEMPLATE_BASE_URL: &str =
+    "https://raw.githubusercontent.com/wcygan/ralphctl/main/templates";
+
+/// Environment variable overriding `TEMPLATE_BASE_URL`.
+///
+/// Accepts an `http(s)://` base URL or a `file://` directory path.
+pub const TEMPLATE_URL_ENV: &str = "RALPHCTL_TEMPLATE_URL";
 
 /// Template file names for forward mode (init command).
 pub const TEMPLATE_FILES: &[&str] = &["SPEC.md", "IMPLEMENTATION_PLAN.md", "PROMPT.md"];
 
+/// Longest name accepted by `--plan-template`.
+const MAX_PLAN_TEMPLATE_NAME: usize = 64;
+
+/// File name of the specialized plan template `name`.
+///
+/// `cli` maps to `IMPLEMENTATION_PLAN-cli.md`, fetched from the same
+/// templates directory as the default plan.
+pub fn plan_template_filename(name: &str) -> String {
+    format!("IMPLEMENTATION_PLAN-{}.md", name)
+}
+
+/// clap value parser for `--plan-template`.
+///
+/// Names are lowercase letters, digits, and hyphens, so they cannot escape
+/// the templates directory or the cache.
+pub fn parse_plan_template_name(name: &str) -> Result<String, String> {
+    let valid = !name.is_empty()
+        && name.len() <= MAX_PLAN_TEMPLATE_NAME
+        && !name.starts_with('-')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+    if valid {
+        Ok(name.to_string())
+    } else {
+        Err("expected lowercase letters, digits, and hyphens (e.g. cli, web-service)".to_string())
+    }
+}
+
 /// Template file name for reverse mode.
 pub const REVERSE_PROMPT_TEMPLATE: &str = "REVERSE_PROMPT.md";
 
@@ -23,15 +60,46 @@ pub const REVERSE_PROMPT_TEMPLATE: &str = "REVERSE_PROMPT.md";
 /// This ensures the template is always available without network access.
 const EMBEDDED_REVERSE_PROMPT: &str = include_str!("../templates/REVERSE_PROMPT.md");
 
+/// Built-in system prompt for `interview`; `{cwd}` marks the working
+/// directory and `{spec}` the path the spec is written to.
+pub const INTERVIEW_SYSTEM_PROMPT: &str = include_str!("../templates/INTERVIEW_SYSTEM_PROMPT.md");
+
+/// Fill in an interview system prompt, replacing every `{cwd}` with `cwd`
+/// and every `{spec}` with `spec`.
+///
+/// Used for both the built-in prompt and `interview --system-prompt-file`.
+pub fn render_interview_system_prompt(template: &str, cwd: &str, spec: &str) -> String {
+    template
+        .replace("{cwd}", cwd)
+        .replace("{spec}", spec)
+        .trim_end()
+        .to_string()
+}
+
+/// First message `interview` sends to claude; `{spec}` marks the spec to
+/// create.
+const INTERVIEW_INITIAL_PROMPT: &str = "You are an assistant helping me set up a Ralph Loop. Interview me to create {spec} and IMPLEMENTATION_PLAN.md for my project. Tell me how to get started—I might paste a detailed project idea, describe something simple, or just have a rough concept.";
+
+/// Fill in the first `interview` message, replacing `{spec}` with `spec`.
+pub fn render_interview_initial_prompt(spec: &str) -> String {
+    INTERVIEW_INITIAL_PROMPT.replace("{spec}", spec)
+}
+
 /// Application name for cache directory.
 const APP_NAME: &str = "ralphctl";
 
 /// Subdirectory within app cache for templates.
 const TEMPLATES_SUBDIR: &str = "templates";
 
+/// Environment variable overriding the cache base directory.
+///
+/// Used verbatim; templates are cached in its `templates` subdirectory.
+pub const CACHE_DIR_ENV: &str = "RALPHCTL_CACHE_DIR";
+
 /// Get the XDG-compliant cache directory for ralphctl templates.
 ///
 /// Returns the path to the templates cache directory:
+/// - `$RALPHCTL_CACHE_DIR/templates/` when the variable is set
 /// - Linux: `~/.cache/ralphctl/templates/`
 /// - macOS: `~/Library/Caches/ralphctl/templates/`
 ///
@@ -39,6 +107,14 @@ const TEMPLATES_SUBDIR: &str = "templates";
 ///
 /// Returns an error if the cache directory cannot be determined (rare on Unix systems).
 pub fn get_cache_dir() -> Result<PathBuf> {
+    cache_dir_from(|var| std::env::var(var).ok())
+}
+
+/// `get_cache_dir` with environment variables read through `env`.
+pub fn cache_dir_from(env: impl Fn(&str) -> Option<String>) -> Result<PathBuf> {
+    if let Some(base) = env(CACHE_DIR_ENV).filter(|v| !v.is_empty()) {
+        return Ok(PathBuf::from(base).join(TEMPLATES_SUBDIR));
+    }
     let base = dirs::cache_dir().context("failed to determine cache directory")?;
     Ok(base.join(APP_NAME).join(TEMPLATES_SUBDIR))
 }
@@ -67,16 +143,18 @@ pub fn ensure_cache_dir() -> Result<PathBuf> {
 
 /// Save a template to the cache.
 ///
-/// Creates the cache directory if it doesn't exist.
+/// Creates the cache directory if it doesn't exist. A stored ETag belongs
+/// to the replaced content, so it is removed.
 ///
 /// # Errors
 ///
 /// Returns an error if the cache directory cannot be created or the file cannot be written.
 pub fn save_to_cache(filename: &str, content: &str) -> Result<()> {
-    ensure_cache_dir()?;
-    let path = get_cache_path(filename)?;
+    let cache_dir = ensure_cache_dir()?;
+    let path = cache_dir.join(filename);
     fs::write(&path, content)
         .with_context(|| format!("failed to write cache file: {}", path.display()))?;
+    let _ = fs::remove_file(etag_path(&cache_dir, filename));
     Ok(())
 }
 
@@ -91,32 +169,188 @@ pub fn load_from_cache(filename: &str) -> Result<String> {
         .with_context(|| format!("failed to read cache file: {}", path.display()))
 }
 
+/// Environment variables holding a token for private template repos,
+/// checked in order.
+pub const TOKEN_ENV_VARS: &[&str] = &["RALPHCTL_TOKEN", "GITHUB_TOKEN"];
+
+/// Get the template auth token from the environment, if any.
+///
+/// Empty values are treated as unset.
+pub fn auth_token() -> Option<String> {
+    auth_token_from(|var| std::env::var(var).ok())
+}
+
+/// `auth_token` with environment variables read through `env`.
+pub fn auth_token_from(env: impl Fn(&str) -> Option<String>) -> Option<String> {
+    TOKEN_ENV_VARS
+        .iter()
+        .filter_map(|var| env(var))
+        .find(|token| !token.is_empty())
+}
+
+/// Build an HTTP client, sending `Authorization: Bearer <token>` when given.
+fn build_client(token: Option<&str>) -> Result<reqwest::Client> {
+    let mut headers = HeaderMap::new();
+    if let Some(token) = token {
+        let mut value = HeaderValue::from_str(&format!("Bearer {}", token))
+            .context("template token contains invalid header characters")?;
+        value.set_sensitive(true);
+        headers.insert(AUTHORIZATION, value);
+    }
+    reqwest::Client::builder()
+        .default_headers(headers)
+        .build()
+        .context("failed to build HTTP client")
+}
+
 /// Fetch a single template file from GitHub.
 ///
+/// Uses the token from `RALPHCTL_TOKEN` or `GITHUB_TOKEN` when set, so
+/// templates can live in a private repository; otherwise fetches anonymously.
+/// When a cached copy has a stored ETag, the request is conditional and a
+/// `304 Not Modified` returns the cached copy without re-downloading.
+///
 /// Returns the template content as a string.
 ///
 /// # Errors
 ///
 /// Returns an error if the network request fails or the response is not successful.
 pub async fn fetch_template(filename: &str) -> Result<String> {
-    let url = format!("{}/{}", TEMPLATE_BASE_URL, filename);
+    let cache_dir = get_cache_dir().ok();
+    fetch_template_from(
+        &template_base_url(),
+        filename,
+        auth_token().as_deref(),
+        cache_dir.as_deref(),
+    )
+    .await
+}
+
+/// Get the template base URL, honoring `RALPHCTL_TEMPLATE_URL`.
+fn template_base_url() -> String {
+    std::env::var(TEMPLATE_URL_ENV)
+        .ok()
+        .filter(|url| !url.is_empty())
+        .unwrap_or_else(|| TEMPLATE_BASE_URL.to_string())
+}
 
-    let response = reqwest::get(&url)
+/// Path of the sidecar file holding the ETag of a cached template.
+fn etag_path(cache_dir: &Path, filename: &str) -> PathBuf {
+    cache_dir.join(format!("{}.etag", filename))
+}
+
+/// Cache `content` as `filename` in `cache_dir`, with its ETag if it has
+/// one. Any other stored ETag is removed, so it can never revalidate
+/// content it did not come with.
+///
+/// Caching is an optimization; write errors are ignored.
+fn write_cache(cache_dir: &Path, filename: &str, content: &str, etag: Option<&str>) {
+    if fs::create_dir_all(cache_dir).is_err()
+        || fs::write(cache_dir.join(filename), content).is_err()
+    {
+        return;
+    }
+    let etag_file = etag_path(cache_dir, filename);
+    match etag {
+        Some(etag) => {
+            let _ = fs::write(&etag_file, etag);
+        }
+        None => {
+            let _ = fs::remove_file(&etag_file);
+        }
+    }
+}
+
+/// Fetch `filename` from `base_url`, optionally authenticating with `token`.
+///
+/// A `file://` base URL reads from a local directory instead. With
+/// `cache_dir`, a stored ETag is sent as `If-None-Match`, and whatever is
+/// fetched is cached there together with its ETag, if any.
+pub async fn fetch_template_from(
+    base_url: &str,
+    filename: &str,
+    token: Option<&str>,
+    cache_dir: Option<&Path>,
+) -> Result<String> {
+    if let Some(dir) = base_url.strip_prefix("file://") {
+        let path = Path::new(dir).join(filename);
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        if let Some(cache_dir) = cache_dir {
+            write_cache(cache_dir, filename, &content, None);
+        }
+        return Ok(content);
+    }
+
+    let url = format!("{}/{}", base_url, filename);
+
+    // Only revalidate when both the content and its ETag are cached
+    let cached = cache_dir.and_then(|dir| {
+        let etag = fs::read_to_string(etag_path(dir, filename)).ok()?;
+        let content = fs::read_to_string(dir.join(filename)).ok()?;
+        Some((etag.trim().to_string(), content))
+    });
+
+    let mut request = build_client(token)?.get(&url);
+    if let Some((etag, _)) = &cached {
+        request = request.header(IF_NONE_MATCH, etag.as_str());
+    }
+    let response = request
+        .send()
         .await
-        .with_context(|| format!("failed to fetch {}", filename))?;
+        .with_context(|| format!("failed to fetch {}", url))?;
+
+    if response.status() == StatusCode::NOT_MODIFIED {
+        if let Some((_, content)) = cached {
+            return Ok(content);
+        }
+    }
 
     if !response.status().is_success() {
         anyhow::bail!(
             "failed to fetch {}: HTTP {}",
-            filename,
+            url,
             response.status().as_u16()
         );
     }
 
-    response
+    let etag = response
+        .headers()
+        .get(ETAG)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let content = response
         .text()
         .await
-        .with_context(|| format!("failed to read response for {}", filename))
+        .with_context(|| format!("failed to read response for {}", url))?;
+
+    if let Some(dir) = cache_dir {
+        write_cache(dir, filename, &content, etag.as_deref());
+    }
+
+    Ok(content)
+}
+
+/// Fetch the text at an arbitrary `http(s)://` URL.
+///
+/// Never sends the template token, since the URL may point anywhere.
+///
+/// # Errors
+///
+/// Returns an error if the URL is not `http://` or `https://` (so a
+/// `file://` URL cannot read local files), or if the request fails or the
+/// response is not successful.
+pub async fn fetch_url(url: &str) -> Result<String> {
+    if !(url.starts_with("http://") || url.starts_with("https://")) {
+        anyhow::bail!(
+            "unsupported URL {}: only http:// and https:// are allowed",
+            url
+        );
+    }
+    let (base_url, filename) = url
+        .rsplit_once('/')
+        .with_context(|| format!("invalid URL: {}", url))?;
+    fetch_template_from(base_url, filename, None, None).await
 }
 
 /// Fetch all template files from GitHub.
@@ -139,9 +373,9 @@ pub async fn fetch_all_templates() -> Result<Vec<(&'static str, String)>> {
 
 /// Fetch a template with network-first strategy and cache fallback.
 ///
-/// Tries to fetch the template from GitHub first. On success, the template is
-/// saved to the local cache for offline use. On network failure, falls back to
-/// the cached version if available.
+/// Tries to fetch the template from GitHub first; the fetch saves it to the
+/// local cache for offline use. On network failure, falls back to the cached
+/// version if available.
 ///
 /// # Arguments
 ///
@@ -157,12 +391,7 @@ pub async fn fetch_all_templates() -> Result<Vec<(&'static str, String)>> {
 pub async fn get_template(filename: &str) -> Result<String> {
     // Try network first
     match fetch_template(filename).await {
-        Ok(content) => {
-            // Cache the fetched content for offline use
-            // Ignore cache write errors - it's just an optimization
-            let _ = save_to_cache(filename, &content);
-            Ok(content)
-        }
+        Ok(content) => Ok(content),
         Err(network_err) => {
             // Fall back to cache
             load_from_cache(filename).with_context(|| {
@@ -226,6 +455,57 @@ mod tests {
         assert!(TEMPLATE_BASE_URL.ends_with("/templates"));
     }
 
+    #[test]
+    fn test_render_interview_system_prompt() {
+        assert_eq!(
+            render_interview_system_prompt(
+                "In `{cwd}`: write {spec}, then {cwd}/PLAN.md\n\n",
+                "/work",
+                "/work/docs/SPEC.md"
+            ),
+            "In `/work`: write /work/docs/SPEC.md, then /work/PLAN.md"
+        );
+
+        let builtin =
+            render_interview_system_prompt(INTERVIEW_SYSTEM_PROMPT, "/work", "/work/docs/SPEC.md");
+        assert!(builtin.starts_with("# Ralph Loop System Context"));
+        assert!(builtin.contains("Write `/work/docs/SPEC.md`"));
+        assert!(builtin.contains("`/work/IMPLEMENTATION_PLAN.md`"));
+        assert!(!builtin.contains("{cwd}"));
+        assert!(!builtin.contains("{spec}"));
+    }
+
+    #[test]
+    fn test_render_interview_initial_prompt() {
+        let prompt = render_interview_initial_prompt("docs/SPEC.md");
+        assert!(prompt.contains("Interview me to create docs/SPEC.md and IMPLEMENTATION_PLAN.md"));
+        assert!(!prompt.contains("{spec}"));
+    }
+
+    #[test]
+    fn test_plan_template_filename() {
+        assert_eq!(plan_template_filename("cli"), "IMPLEMENTATION_PLAN-cli.md");
+        assert_eq!(
+            plan_template_filename("web-service"),
+            "IMPLEMENTATION_PLAN-web-service.md"
+        );
+    }
+
+    #[test]
+    fn test_parse_plan_template_name() {
+        for name in ["cli", "library", "web-service", "v2"] {
+            assert_eq!(parse_plan_template_name(name), Ok(name.to_string()));
+        }
+        for name in ["", "CLI", "../cli", "cli.md", "a/b", "-cli", "web service"] {
+            assert!(
+                parse_plan_template_name(name).is_err(),
+                "accepted {:?}",
+                name
+            );
+        }
+        assert!(parse_plan_template_name(&"a".repeat(65)).is_err());
+    }
+
     #[test]
     fn test_template_files_list() {
         // Verify expected forward mode templates are listed
@@ -271,6 +551,20 @@ mod tests {
         assert!(path_str.ends_with("templates"));
     }
 
+    #[test]
+    fn test_get_cache_dir_env_override() {
+        let env = |var: &str| (var == CACHE_DIR_ENV).then(|| "/tmp/ralph-cache".to_string());
+        assert_eq!(
+            cache_dir_from(env).unwrap(),
+            PathBuf::from("/tmp/ralph-cache/templates")
+        );
+        let empty = |var: &str| (var == CACHE_DIR_ENV).then(String::new);
+        assert_eq!(
+            cache_dir_from(empty).unwrap(),
+            cache_dir_from(|_| None).unwrap()
+        );
+    }
+
     #[test]
     fn test_get_cache_path_includes_filename() {
         let path = get_cache_path("SPEC.md").unwrap();
@@ -295,8 +589,41 @@ mod tests {
         );
     }
 
-    // Note: Integration tests for actual HTTP fetching should use mock servers
-    // or be run as part of E2E testing to avoid flaky tests due to network issues.
+    #[tokio::test]
+    async fn test_fetch_template_from_file_url() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("PROMPT.md"), "# Local Prompt").unwrap();
+        let base_url = format!("file://{}", dir.path().display());
+
+        let content = fetch_template_from(&base_url, "PROMPT.md", None, None)
+            .await
+            .unwrap();
+        assert_eq!(content, "# Local Prompt");
+        assert!(fetch_template_from(&base_url, "SPEC.md", None, None)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_fetch_template_from_file_url_drops_stale_etag() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("PROMPT.md"), "# Local Prompt").unwrap();
+        let base_url = format!("file://{}", dir.path().display());
+        let cache = tempfile::tempdir().unwrap();
+        fs::write(cache.path().join("PROMPT.md"), "# Remote Prompt").unwrap();
+        fs::write(etag_path(cache.path(), "PROMPT.md"), "\"v1\"").unwrap();
+
+        let content = fetch_template_from(&base_url, "PROMPT.md", None, Some(cache.path()))
+            .await
+            .unwrap();
+
+        assert_eq!(content, "# Local Prompt");
+        assert_eq!(
+            fs::read_to_string(cache.path().join("PROMPT.md")).unwrap(),
+            "# Local Prompt"
+        );
+        assert!(!etag_path(cache.path(), "PROMPT.md").exists());
+    }
 
     #[test]
     fn test_cache_roundtrip() {
@@ -0,0 +1,193 @@
+//! Tool-use summaries from claude's `stream-json` output.
+//!
+//! With `--output-format stream-json`, claude prints one JSON event per line,
+//! and each `assistant` event's `message.content` lists the `tool_use` blocks
+//! it sent. Counting them shows whether an iteration actually edited files or
+//! only read and talked. Output that is not stream-json gets no summary.
+//! Within a stream, unknown block shapes and missing fields are skipped,
+//! never an error.
+
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// Tools that change files; an iteration that calls none of them is flagged.
+pub const WRITE_TOOLS: &[&str] = &["Edit", "MultiEdit", "Write", "NotebookEdit"];
+
+/// Input keys that name the file a write tool touched.
+const PATH_KEYS: &[&str] = &["file_path", "notebook_path"];
+
+/// Tool calls made during one iteration.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ToolSummary {
+    /// Calls per tool name
+    pub counts: BTreeMap<String, u64>,
+    /// Files written by write tools, in first-seen order
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub files_written: Vec<String>,
+}
+
+impl ToolSummary {
+    /// Whether the iteration made no Edit/Write-style calls.
+    pub fn is_suspicious(&self) -> bool {
+        !WRITE_TOOLS
+            .iter()
+            .any(|tool| self.counts.get(*tool).is_some_and(|&n| n > 0))
+    }
+
+    fn add_file(&mut self, path: &str) {
+        if !path.is_empty() && !self.files_written.iter().any(|f| f == path) {
+            self.files_written.push(path.to_string());
+        }
+    }
+
+    /// Fold the `tool_use` blocks of an `assistant` event into the summary.
+    fn collect(&mut self, event: &Value) {
+        let blocks = event.pointer("/message/content").and_then(Value::as_array);
+        for block in blocks.into_iter().flatten() {
+            if block.get("type").and_then(Value::as_str) != Some("tool_use") {
+                continue;
+            }
+            let Some(name) = block.get("name").and_then(Value::as_str) else {
+                continue;
+            };
+            *self.counts.entry(name.to_string()).or_default() += 1;
+            if WRITE_TOOLS.contains(&name) {
+                let input = block.get("input");
+                if let Some(path) = PATH_KEYS.iter().find_map(|key| input?.get(*key)?.as_str()) {
+                    self.add_file(path);
+                }
+            }
+        }
+    }
+}
+
+/// Summarize the tool calls in an iteration's stdout.
+///
+/// Returns `None` unless the output is a stream-json transcript: every
+/// non-blank line a JSON event with a `type`, at least one of them an
+/// `assistant` message. Plain text with a stray JSON line and single
+/// `--output-format json` result documents, which do not list tool calls,
+/// get no summary.
+pub fn summarize(stdout: &str) -> Option<ToolSummary> {
+    let mut summary = ToolSummary::default();
+    let mut assistant = false;
+    for line in stdout
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+    {
+        let event: Value = serde_json::from_str(line).ok()?;
+        if event.get("type")?.as_str()? == "assistant" {
+            assistant = true;
+            summary.collect(&event);
+        }
+    }
+    assistant.then_some(summary)
+}
+
+/// Format the counts, most-used tool first.
+///
+/// Format: `Edit×4, Bash×2, Read×9`, or `none` without tool calls
+pub fn format_counts(summary: &ToolSummary) -> String {
+    let mut counts: Vec<(&String, &u64)> = summary.counts.iter().collect();
+    counts.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+    if counts.is_empty() {
+        return "none".to_string();
+    }
+    counts
+        .iter()
+        .map(|(name, n)| format!("{}×{}", name, n))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Format the line printed after an iteration.
+///
+/// Format: `tools: Edit×4, Read×9`, with a note when no file was edited
+pub fn format_tool_line(summary: &ToolSummary) -> String {
+    let mut line = format!("tools: {}", format_counts(summary));
+    if summary.is_suspicious() {
+        line.push_str(" (no Edit/Write calls)");
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STREAM: &str = include_str!("../tests/fixtures/tools/stream.jsonl");
+    const READ_ONLY: &str = include_str!("../tests/fixtures/tools/read_only.jsonl");
+    const PARTIAL: &str = include_str!("../tests/fixtures/tools/partial.jsonl");
+
+    #[test]
+    fn test_summarize_stream_json() {
+        let summary = summarize(STREAM).unwrap();
+        assert_eq!(summary.counts["Edit"], 2);
+        assert_eq!(summary.counts["Write"], 1);
+        assert_eq!(summary.counts["Read"], 1);
+        assert_eq!(summary.counts["Bash"], 1);
+        assert_eq!(
+            summary.files_written,
+            vec![
+                "/home/dev/widget/src/lib.rs",
+                "/home/dev/widget/src/parser.rs",
+                "/home/dev/widget/IMPLEMENTATION_PLAN.md"
+            ]
+        );
+        assert!(!summary.is_suspicious());
+        assert_eq!(
+            format_tool_line(&summary),
+            "tools: Edit×2, Bash×1, Read×1, Write×1"
+        );
+    }
+
+    #[test]
+    fn test_summarize_flags_read_only_iteration() {
+        let summary = summarize(READ_ONLY).unwrap();
+        assert_eq!(format_counts(&summary), "Read×2, Grep×1");
+        assert!(summary.files_written.is_empty());
+        assert!(summary.is_suspicious());
+        assert_eq!(
+            format_tool_line(&summary),
+            "tools: Read×2, Grep×1 (no Edit/Write calls)"
+        );
+    }
+
+    #[test]
+    fn test_summarize_tolerates_schema_variations() {
+        let summary = summarize(PARTIAL).unwrap();
+        assert_eq!(format_counts(&summary), "Read×2, MultiEdit×1");
+        assert_eq!(summary.files_written, vec!["src/a.rs"]);
+        assert!(!summary.is_suspicious());
+    }
+
+    #[test]
+    fn test_summarize_needs_a_stream_json_transcript() {
+        assert_eq!(summarize("Did the task.\n[[RALPH:DONE]]\n"), None);
+        assert_eq!(summarize(""), None);
+        // Plain output that happens to print a JSON line
+        assert_eq!(
+            summarize("Wrote config:\n{\"type\":\"assistant\"}\n[[RALPH:DONE]]\n"),
+            None
+        );
+        assert_eq!(summarize("{\"unrelated\": true}\n"), None);
+        // --output-format json: one result document, no tool calls listed
+        assert_eq!(
+            summarize("{\"type\":\"result\",\"subtype\":\"success\",\"result\":\"hi\"}"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_format_without_tool_calls() {
+        let stream = "{\"type\":\"assistant\",\"message\":{\"content\":[{\"type\":\"text\",\"text\":\"hi\"}]}}\n\
+                      {\"type\":\"result\",\"result\":\"hi\"}\n";
+        let summary = summarize(stream).unwrap();
+        assert_eq!(
+            format_tool_line(&summary),
+            "tools: none (no Edit/Write calls)"
+        );
+    }
+}
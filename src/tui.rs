@@ -0,0 +1,359 @@
+//! Live terminal dashboard for `run --tui`.
+//!
+//! `DashboardState` holds what the dashboard shows (iteration, elapsed time,
+//! task progress, last signal, output tail) and is updated by the loop without
+//! touching the terminal, so it can be tested on its own. `Dashboard` owns the
+//! terminal: a background thread redraws the state on the alternate screen
+//! while claude runs. Raw mode is never enabled, so Ctrl+C still interrupts.
+
+use crate::duration;
+use crate::parser::TaskCount;
+use ratatui::backend::{Backend, CrosstermBackend};
+use ratatui::crossterm::cursor::{Hide, Show};
+use ratatui::crossterm::execute;
+use ratatui::crossterm::terminal::{EnterAlternateScreen, LeaveAlternateScreen};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Gauge, Paragraph};
+use ratatui::{Frame, Terminal};
+use std::collections::VecDeque;
+use std::io::{self, Stdout};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Output lines kept for the tail pane.
+const OUTPUT_TAIL_LINES: usize = 200;
+
+/// Time between redraws.
+const REDRAW_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Everything the dashboard displays.
+#[derive(Debug, Clone)]
+pub struct DashboardState {
+    /// Current iteration (0 before the first one starts)
+    pub iteration: u32,
+    /// Iteration limit for this run
+    pub max_iterations: u32,
+    /// When the run started
+    pub run_started: Instant,
+    /// When the current iteration started
+    pub iteration_started: Instant,
+    /// Plan progress, if the plan could be read
+    pub tasks: Option<TaskCount>,
+    /// Signal of the last finished iteration (`done`, `continue`, ...)
+    pub last_signal: Option<String>,
+    /// Most recent output lines, oldest first
+    pub output_tail: VecDeque<String>,
+}
+
+impl DashboardState {
+    /// Create the state for a run starting at `now`.
+    pub fn new(max_iterations: u32, tasks: Option<TaskCount>, now: Instant) -> Self {
+        Self {
+            iteration: 0,
+            max_iterations,
+            run_started: now,
+            iteration_started: now,
+            tasks,
+            last_signal: None,
+            output_tail: VecDeque::new(),
+        }
+    }
+
+    /// Mark `iteration` as started at `now` and clear the output tail.
+    pub fn start_iteration(&mut self, iteration: u32, now: Instant) {
+        self.iteration = iteration;
+        self.iteration_started = now;
+        self.output_tail.clear();
+    }
+
+    /// Append one line of output, dropping the oldest beyond the tail limit.
+    pub fn push_output(&mut self, line: &str) {
+        if self.output_tail.len() == OUTPUT_TAIL_LINES {
+            self.output_tail.pop_front();
+        }
+        self.output_tail
+            .push_back(line.trim_end_matches(['\n', '\r']).to_string());
+    }
+
+    /// Record the end of an iteration: its signal and the re-read plan progress.
+    pub fn finish_iteration(&mut self, signal: &str, tasks: Option<TaskCount>) {
+        self.last_signal = Some(signal.to_string());
+        if tasks.is_some() {
+            self.tasks = tasks;
+        }
+    }
+
+    /// Status lines: iteration, elapsed times, last signal.
+    ///
+    /// Format: `Iteration 3/11`, `Elapsed 2m 05s (iteration 12s)`, `Last signal: continue`
+    pub fn status_lines(&self, now: Instant) -> Vec<String> {
+        vec![
+            format!("Iteration {}/{}", self.iteration, self.max_iterations),
+            format!(
+                "Elapsed {} (iteration {})",
+                duration::format_duration(now.saturating_duration_since(self.run_started)),
+                duration::format_duration(now.saturating_duration_since(self.iteration_started))
+            ),
+            format!(
+                "Last signal: {}",
+                self.last_signal.as_deref().unwrap_or("-")
+            ),
+        ]
+    }
+
+    /// Task progress as a ratio in `0.0..=1.0` and its label.
+    pub fn progress(&self) -> (f64, String) {
+        match &self.tasks {
+            Some(tasks) if tasks.total > 0 => (
+                tasks.completed as f64 / tasks.total as f64,
+                format!(
+                    "{}% ({}/{} tasks)",
+                    tasks.percentage(),
+                    tasks.completed,
+                    tasks.total
+                ),
+            ),
+            _ => (0.0, "no tasks".to_string()),
+        }
+    }
+
+    /// The last `height` output lines, oldest first.
+    pub fn visible_output(&self, height: usize) -> impl Iterator<Item = &str> {
+        let skip = self.output_tail.len().saturating_sub(height);
+        self.output_tail.iter().skip(skip).map(String::as_str)
+    }
+}
+
+/// Render `state` into `frame`.
+pub fn draw(frame: &mut Frame, state: &DashboardState, now: Instant) {
+    let [status_area, progress_area, output_area] = Layout::vertical([
+        Constraint::Length(5),
+        Constraint::Length(3),
+        Constraint::Min(3),
+    ])
+    .areas(frame.area());
+
+    let status: Vec<Line> = state
+        .status_lines(now)
+        .into_iter()
+        .map(Line::from)
+        .collect();
+    frame.render_widget(
+        Paragraph::new(status).block(Block::bordered().title(" ralphctl run ")),
+        status_area,
+    );
+
+    let (ratio, label) = state.progress();
+    frame.render_widget(
+        Gauge::default()
+            .block(Block::bordered().title(" Tasks "))
+            .ratio(ratio)
+            .label(label),
+        progress_area,
+    );
+
+    let height = output_area.height.saturating_sub(2) as usize;
+    let output: Vec<Line> = state.visible_output(height).map(Line::from).collect();
+    frame.render_widget(
+        Paragraph::new(output).block(Block::bordered().title(" Output ")),
+        output_area,
+    );
+}
+
+struct Inner {
+    terminal: Terminal<CrosstermBackend<Stdout>>,
+    state: DashboardState,
+    /// Whether the alternate screen is shown and being redrawn
+    active: bool,
+}
+
+/// Dashboard drawn on the alternate screen between `resume` and `suspend`.
+pub struct Dashboard {
+    inner: Arc<Mutex<Inner>>,
+    stop: Arc<AtomicBool>,
+    renderer: Option<thread::JoinHandle<()>>,
+}
+
+impl Dashboard {
+    /// Start the redraw thread; nothing is shown until `resume`.
+    pub fn start(state: DashboardState) -> io::Result<Self> {
+        let terminal = Terminal::new(CrosstermBackend::new(io::stdout()))?;
+        let inner = Arc::new(Mutex::new(Inner {
+            terminal,
+            state,
+            active: false,
+        }));
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let dashboard_inner = inner.clone();
+        let dashboard_stop = stop.clone();
+        let renderer = thread::spawn(move || {
+            while !dashboard_stop.load(Ordering::SeqCst) {
+                if let Ok(mut inner) = dashboard_inner.lock() {
+                    if inner.active {
+                        let Inner {
+                            terminal, state, ..
+                        } = &mut *inner;
+                        // Full repaint so stray stderr output never lingers
+                        let _ = terminal.clear();
+                        let _ = terminal.draw(|frame| draw(frame, state, Instant::now()));
+                    }
+                }
+                thread::sleep(REDRAW_INTERVAL);
+            }
+        });
+
+        Ok(Self {
+            inner,
+            stop,
+            renderer: Some(renderer),
+        })
+    }
+
+    /// Apply `f` to the displayed state.
+    pub fn update(&self, f: impl FnOnce(&mut DashboardState)) {
+        if let Ok(mut inner) = self.inner.lock() {
+            f(&mut inner.state);
+        }
+    }
+
+    /// Show the dashboard (again) after `suspend`.
+    pub fn resume(&self) {
+        if let Ok(mut inner) = self.inner.lock() {
+            if !inner.active {
+                let _ = execute!(inner.terminal.backend_mut(), EnterAlternateScreen, Hide);
+                let _ = inner.terminal.clear();
+                inner.active = true;
+            }
+        }
+    }
+
+    /// Return to the normal screen so regular output and prompts are visible.
+    pub fn suspend(&self) {
+        if let Ok(mut inner) = self.inner.lock() {
+            if inner.active {
+                let backend = inner.terminal.backend_mut();
+                let _ = execute!(backend, Show, LeaveAlternateScreen);
+                let _ = backend.flush();
+                inner.active = false;
+            }
+        }
+    }
+}
+
+impl Drop for Dashboard {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(renderer) = self.renderer.take() {
+            let _ = renderer.join();
+        }
+        self.suspend();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::backend::TestBackend;
+
+    fn state_at(now: Instant) -> DashboardState {
+        DashboardState::new(10, Some(TaskCount::new(1, 4)), now)
+    }
+
+    /// Render `state` on a test backend and return the screen as text.
+    fn render(state: &DashboardState, now: Instant) -> String {
+        let mut terminal = Terminal::new(TestBackend::new(60, 16)).unwrap();
+        terminal.draw(|frame| draw(frame, state, now)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let width = buffer.area.width as usize;
+        buffer
+            .content
+            .chunks(width)
+            .map(|row| row.iter().map(|cell| cell.symbol()).collect::<String>() + "\n")
+            .collect()
+    }
+
+    #[test]
+    fn test_status_lines() {
+        let start = Instant::now();
+        let mut state = state_at(start);
+        state.start_iteration(3, start + Duration::from_secs(113));
+        let now = start + Duration::from_secs(125);
+
+        assert_eq!(
+            state.status_lines(now),
+            vec![
+                "Iteration 3/10".to_string(),
+                "Elapsed 2m 05s (iteration 12s)".to_string(),
+                "Last signal: -".to_string(),
+            ]
+        );
+
+        state.finish_iteration("continue", Some(TaskCount::new(2, 4)));
+        assert_eq!(state.status_lines(now)[2], "Last signal: continue");
+        assert_eq!(state.tasks, Some(TaskCount::new(2, 4)));
+    }
+
+    #[test]
+    fn test_finish_iteration_keeps_tasks_when_plan_unreadable() {
+        let mut state = state_at(Instant::now());
+        state.finish_iteration("no signal", None);
+        assert_eq!(state.tasks, Some(TaskCount::new(1, 4)));
+        assert_eq!(state.last_signal.as_deref(), Some("no signal"));
+    }
+
+    #[test]
+    fn test_progress() {
+        let mut state = state_at(Instant::now());
+        assert_eq!(state.progress(), (0.25, "25% (1/4 tasks)".to_string()));
+
+        state.tasks = Some(TaskCount::new(0, 0));
+        assert_eq!(state.progress(), (0.0, "no tasks".to_string()));
+        state.tasks = None;
+        assert_eq!(state.progress().1, "no tasks");
+    }
+
+    #[test]
+    fn test_output_tail_is_capped() {
+        let mut state = state_at(Instant::now());
+        for n in 0..OUTPUT_TAIL_LINES + 5 {
+            state.push_output(&format!("line {}\n", n));
+        }
+        assert_eq!(state.output_tail.len(), OUTPUT_TAIL_LINES);
+        assert_eq!(
+            state.output_tail.front().map(String::as_str),
+            Some("line 5")
+        );
+
+        let visible: Vec<&str> = state.visible_output(2).collect();
+        assert_eq!(
+            visible,
+            vec![
+                format!("line {}", OUTPUT_TAIL_LINES + 3),
+                format!("line {}", OUTPUT_TAIL_LINES + 4)
+            ]
+        );
+
+        state.start_iteration(2, Instant::now());
+        assert!(state.output_tail.is_empty());
+    }
+
+    #[test]
+    fn test_draw_renders_all_panes() {
+        let now = Instant::now();
+        let mut state = state_at(now);
+        state.start_iteration(2, now);
+        state.finish_iteration("continue", None);
+        state.push_output("Implementing parser\n");
+
+        let screen = render(&state, now);
+        assert!(screen.contains("ralphctl run"));
+        assert!(screen.contains("Iteration 2/10"));
+        assert!(screen.contains("Last signal: continue"));
+        assert!(screen.contains("25% (1/4 tasks)"));
+        assert!(screen.contains("Implementing parser"));
+    }
+}
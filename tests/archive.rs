@@ -1,6 +1,9 @@
 //! Integration tests for the `ralphctl archive` command.
 
+mod common;
+
 use assert_cmd::Command;
+use common::write_lock;
 use predicates::prelude::*;
 use std::fs;
 use tempfile::TempDir;
@@ -169,6 +172,24 @@ fn archive_does_not_duplicate_gitignore_entry() {
     assert_eq!(count, 1);
 }
 
+#[test]
+fn archive_without_force_fails_clearly_when_stdin_is_closed() {
+    let dir = temp_dir();
+    fs::write(dir.path().join("SPEC.md"), "# Spec").unwrap();
+
+    // No write_stdin: stdin is at end of input, as under a process manager
+    ralphctl()
+        .current_dir(dir.path())
+        .arg("archive")
+        .assert()
+        .code(7)
+        .stderr(predicate::str::contains(
+            "error: stdin is not interactive; pass --force to proceed",
+        ));
+
+    assert!(!dir.path().join(".ralphctl/archive").exists());
+}
+
 #[test]
 fn archive_without_force_prompts_user() {
     let dir = temp_dir();
@@ -462,3 +483,163 @@ fn archive_prompt_includes_reverse_file_count() {
         .code(1)
         .stderr(predicate::str::contains("Archive 3 files?"));
 }
+
+#[test]
+fn archive_refuses_while_run_is_active() {
+    let dir = temp_dir();
+    fs::write(dir.path().join("SPEC.md"), "# Spec").unwrap();
+    write_lock(&dir, std::process::id(), 3);
+
+    ralphctl()
+        .current_dir(dir.path())
+        .arg("archive")
+        .arg("--force")
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains(
+            "a ralph loop appears to be running",
+        ));
+
+    assert_eq!(
+        fs::read_to_string(dir.path().join("SPEC.md")).unwrap(),
+        "# Spec"
+    );
+    assert!(!dir.path().join(".ralphctl/archive").exists());
+}
+
+#[test]
+fn archive_ignore_active_overrides_lock() {
+    let dir = temp_dir();
+    fs::write(dir.path().join("SPEC.md"), "# Spec").unwrap();
+    write_lock(&dir, std::process::id(), 3);
+
+    ralphctl()
+        .current_dir(dir.path())
+        .arg("archive")
+        .arg("--force")
+        .arg("--ignore-active")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Archived 1 file"));
+}
+
+#[test]
+fn archive_ignores_stale_lock() {
+    let dir = temp_dir();
+    fs::write(dir.path().join("SPEC.md"), "# Spec").unwrap();
+    write_lock(&dir, i32::MAX as u32, 3);
+
+    ralphctl()
+        .current_dir(dir.path())
+        .arg("archive")
+        .arg("--force")
+        .assert()
+        .success();
+}
+
+#[test]
+fn archive_writes_manifest_with_last_run_id() {
+    let dir = temp_dir();
+    fs::write(dir.path().join("SPEC.md"), "# My Spec").unwrap();
+    fs::create_dir_all(dir.path().join(".ralphctl")).unwrap();
+    fs::write(
+        dir.path().join(".ralphctl/state.json"),
+        r#"{"last_run_id":"0badc0de"}"#,
+    )
+    .unwrap();
+
+    ralphctl()
+        .current_dir(dir.path())
+        .arg("archive")
+        .arg("--force")
+        .assert()
+        .success();
+
+    let archive_base = dir.path().join(".ralphctl").join("archive");
+    let timestamp_dir = fs::read_dir(&archive_base)
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap()
+        .path();
+    let manifest = fs::read_to_string(timestamp_dir.join("manifest.json")).unwrap();
+    assert!(manifest.contains(r#""run_id": "0badc0de""#));
+    assert!(manifest.contains(r#""project": "My Spec""#));
+    assert!(manifest.contains(r#""SPEC.md""#));
+}
+
+#[test]
+fn archive_include_log_and_prompt_copies_without_resetting() {
+    let dir = temp_dir();
+
+    fs::write(dir.path().join("SPEC.md"), "# Spec").unwrap();
+    fs::write(dir.path().join("IMPLEMENTATION_PLAN.md"), "- [x] Task").unwrap();
+    fs::write(dir.path().join("PROMPT.md"), "# The exact prompt").unwrap();
+    fs::write(dir.path().join("ralph.log"), "iteration output\n").unwrap();
+
+    ralphctl()
+        .current_dir(dir.path())
+        .args(["archive", "--force", "--include-log", "--include-prompt"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Archived 4 files"));
+
+    let archive_base = dir.path().join(".ralphctl").join("archive");
+    let timestamp_dir = fs::read_dir(&archive_base)
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap()
+        .path();
+    assert_eq!(
+        fs::read_to_string(timestamp_dir.join("PROMPT.md")).unwrap(),
+        "# The exact prompt"
+    );
+    assert_eq!(
+        fs::read_to_string(timestamp_dir.join("ralph.log")).unwrap(),
+        "iteration output\n"
+    );
+    let manifest = fs::read_to_string(timestamp_dir.join("manifest.json")).unwrap();
+    assert!(manifest.contains("\"ralph.log\""));
+    assert!(manifest.contains("\"PROMPT.md\""));
+
+    // Only the stateful files are reset
+    assert_eq!(
+        fs::read_to_string(dir.path().join("SPEC.md")).unwrap(),
+        "# Specification\n\n"
+    );
+    assert_eq!(
+        fs::read_to_string(dir.path().join("PROMPT.md")).unwrap(),
+        "# The exact prompt"
+    );
+    assert_eq!(
+        fs::read_to_string(dir.path().join("ralph.log")).unwrap(),
+        "iteration output\n"
+    );
+}
+
+#[test]
+fn archive_excludes_log_and_prompt_by_default() {
+    let dir = temp_dir();
+
+    fs::write(dir.path().join("SPEC.md"), "# Spec").unwrap();
+    fs::write(dir.path().join("PROMPT.md"), "# Prompt").unwrap();
+    fs::write(dir.path().join("ralph.log"), "log").unwrap();
+
+    ralphctl()
+        .current_dir(dir.path())
+        .args(["archive", "--force"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Archived 1 file "));
+
+    let archive_base = dir.path().join(".ralphctl").join("archive");
+    let timestamp_dir = fs::read_dir(&archive_base)
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap()
+        .path();
+    assert!(!timestamp_dir.join("PROMPT.md").exists());
+    assert!(!timestamp_dir.join("ralph.log").exists());
+}
@@ -1,6 +1,9 @@
 //! Integration tests for the `ralphctl clean` command.
 
+mod common;
+
 use assert_cmd::Command;
+use common::write_lock;
 use predicates::prelude::*;
 use std::fs;
 use tempfile::TempDir;
@@ -115,6 +118,24 @@ fn clean_preserves_non_ralph_files() {
     assert!(dir.path().join("src.rs").exists());
 }
 
+#[test]
+fn clean_without_force_fails_clearly_when_stdin_is_closed() {
+    let dir = temp_dir();
+    fs::write(dir.path().join("SPEC.md"), "# Spec").unwrap();
+
+    // No write_stdin: stdin is at end of input, as under a process manager
+    ralphctl()
+        .current_dir(dir.path())
+        .arg("clean")
+        .assert()
+        .code(7)
+        .stderr(predicate::str::contains(
+            "error: stdin is not interactive; pass --force to proceed",
+        ));
+
+    assert!(dir.path().join("SPEC.md").exists());
+}
+
 #[test]
 fn clean_without_force_declines_on_empty_input() {
     let dir = temp_dir();
@@ -329,3 +350,148 @@ fn clean_prompt_includes_reverse_file_count() {
         .code(1)
         .stderr(predicate::str::contains("Delete 3 ralph files?"));
 }
+
+#[test]
+fn clean_refuses_while_run_is_active() {
+    let dir = temp_dir();
+    fs::write(dir.path().join("PROMPT.md"), "# Prompt").unwrap();
+    // The test process itself stands in for a live loop
+    write_lock(&dir, std::process::id(), 7);
+
+    ralphctl()
+        .current_dir(dir.path())
+        .arg("clean")
+        .arg("--force")
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains(format!(
+            "a ralph loop appears to be running (pid {}, iteration 7); stop it first or pass --ignore-active",
+            std::process::id()
+        )));
+
+    assert!(dir.path().join("PROMPT.md").exists());
+}
+
+#[test]
+fn clean_ignore_active_overrides_lock() {
+    let dir = temp_dir();
+    fs::write(dir.path().join("PROMPT.md"), "# Prompt").unwrap();
+    write_lock(&dir, std::process::id(), 2);
+
+    ralphctl()
+        .current_dir(dir.path())
+        .arg("clean")
+        .arg("--force")
+        .arg("--ignore-active")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Deleted 1 file."));
+}
+
+#[test]
+fn clean_ignores_stale_lock() {
+    let dir = temp_dir();
+    fs::write(dir.path().join("PROMPT.md"), "# Prompt").unwrap();
+    // No process can have this PID
+    write_lock(&dir, i32::MAX as u32, 4);
+
+    ralphctl()
+        .current_dir(dir.path())
+        .arg("clean")
+        .arg("--force")
+        .assert()
+        .success();
+
+    assert!(!dir.path().join("PROMPT.md").exists());
+}
+
+#[test]
+fn clean_all_removes_scratch_tree() {
+    let dir = temp_dir();
+    fs::write(dir.path().join("PROMPT.md"), "# Prompt").unwrap();
+    fs::create_dir_all(dir.path().join(".ralphctl/scratch/iter-1")).unwrap();
+    fs::create_dir_all(dir.path().join(".ralphctl/archive")).unwrap();
+
+    ralphctl()
+        .current_dir(dir.path())
+        .arg("clean")
+        .arg("--force")
+        .arg("--all")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Deleted 1 file."))
+        .stdout(predicate::str::contains("Removed .ralphctl/scratch."));
+
+    assert!(!dir.path().join(".ralphctl/scratch").exists());
+    // Archives are not scratch
+    assert!(dir.path().join(".ralphctl/archive").exists());
+}
+
+#[test]
+fn clean_without_all_keeps_scratch_tree() {
+    let dir = temp_dir();
+    fs::write(dir.path().join("PROMPT.md"), "# Prompt").unwrap();
+    fs::create_dir_all(dir.path().join(".ralphctl/scratch/iter-1")).unwrap();
+
+    ralphctl()
+        .current_dir(dir.path())
+        .arg("clean")
+        .arg("--force")
+        .assert()
+        .success();
+
+    assert!(dir.path().join(".ralphctl/scratch/iter-1").exists());
+}
+
+#[test]
+fn clean_all_with_only_scratch() {
+    let dir = temp_dir();
+    fs::create_dir_all(dir.path().join(".ralphctl/scratch/iter-2")).unwrap();
+
+    ralphctl()
+        .current_dir(dir.path())
+        .arg("clean")
+        .arg("--force")
+        .arg("--all")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Removed .ralphctl/scratch."));
+
+    assert!(!dir.path().join(".ralphctl/scratch").exists());
+}
+
+#[test]
+fn clean_yes_deletes_without_stdin() {
+    let dir = temp_dir();
+    fs::write(dir.path().join("SPEC.md"), "# Spec").unwrap();
+    fs::write(dir.path().join("PROMPT.md"), "# Prompt").unwrap();
+
+    ralphctl()
+        .current_dir(dir.path())
+        .args(["clean", "-y"])
+        .write_stdin("")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "Delete 2 ralph files? [y/N] y (--yes)",
+        ))
+        .stdout(predicate::str::contains("Deleted 2 files."));
+
+    assert!(!dir.path().join("SPEC.md").exists());
+    assert!(!dir.path().join("PROMPT.md").exists());
+}
+
+#[test]
+fn clean_yes_flag_is_global() {
+    let dir = temp_dir();
+    fs::write(dir.path().join("SPEC.md"), "# Spec").unwrap();
+
+    ralphctl()
+        .current_dir(dir.path())
+        .args(["--yes", "clean"])
+        .write_stdin("")
+        .assert()
+        .success();
+
+    assert!(!dir.path().join("SPEC.md").exists());
+}
@@ -0,0 +1,103 @@
+//! Helpers shared by the integration tests.
+//!
+//! Each test binary compiles this module on its own and uses only some of
+//! it, hence the `dead_code` allowance.
+
+#![allow(dead_code)]
+
+use assert_cmd::Command;
+use std::fs;
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::thread::{self, JoinHandle};
+use tempfile::TempDir;
+
+/// Serve one HTTP request on a local port with a raw `response`.
+///
+/// Returns the base URL and a handle yielding the request as received,
+/// headers and body.
+pub fn serve_response(response: String) -> (String, JoinHandle<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let base_url = format!("http://{}", listener.local_addr().unwrap());
+    let handle = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut request = Vec::new();
+        let mut buf = [0u8; 4096];
+        loop {
+            let n = stream.read(&mut buf).unwrap();
+            request.extend_from_slice(&buf[..n]);
+            if n == 0 || request_complete(&request) {
+                break;
+            }
+        }
+        stream.write_all(response.as_bytes()).unwrap();
+        String::from_utf8_lossy(&request).to_string()
+    });
+    (base_url, handle)
+}
+
+/// Serve one response with the given status line and `body`.
+pub fn serve_once(status: &str, body: &str) -> (String, JoinHandle<String>) {
+    serve_response(format!(
+        "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    ))
+}
+
+/// Whether `request` holds its headers and the whole `Content-Length` body.
+fn request_complete(request: &[u8]) -> bool {
+    let text = String::from_utf8_lossy(request);
+    let Some(end) = text.find("\r\n\r\n") else {
+        return false;
+    };
+    let length = text[..end]
+        .lines()
+        .find_map(|line| {
+            line.to_lowercase()
+                .strip_prefix("content-length:")
+                .map(|v| v.trim().parse::<usize>().unwrap())
+        })
+        .unwrap_or(0);
+    request.len() >= end + 4 + length
+}
+
+/// Parse the EXIT CODES section of `ralphctl <command> --help`.
+///
+/// Returns `(code, description)` pairs in the order they are listed.
+pub fn help_exit_codes(command: &str) -> Vec<(i32, String)> {
+    let output = Command::new(assert_cmd::cargo::cargo_bin!("ralphctl"))
+        .args([command, "--help"])
+        .output()
+        .unwrap();
+    let help = String::from_utf8(output.stdout).unwrap();
+    help.lines()
+        .skip_while(|line| !line.starts_with("EXIT CODES:"))
+        .skip(1)
+        .take_while(|line| !line.trim().is_empty())
+        .map(|line| {
+            let (code, description) = line.trim().split_once(' ').unwrap();
+            (code.parse().unwrap(), description.trim().to_string())
+        })
+        .collect()
+}
+
+/// The code whose description in `codes` starts with `prefix`.
+pub fn exit_code_for(codes: &[(i32, String)], prefix: &str) -> i32 {
+    codes
+        .iter()
+        .find(|(_, description)| description.starts_with(prefix))
+        .map(|(code, _)| *code)
+        .unwrap_or_else(|| panic!("no '{}' exit code in {:?}", prefix, codes))
+}
+
+/// Write a run lock as an active loop would.
+pub fn write_lock(dir: &TempDir, pid: u32, iteration: u32) {
+    fs::create_dir_all(dir.path().join(".ralphctl")).unwrap();
+    fs::write(
+        dir.path().join(".ralphctl/run.lock"),
+        format!(r#"{{"pid":{},"iteration":{}}}"#, pid, iteration),
+    )
+    .unwrap();
+}
@@ -0,0 +1,91 @@
+//! Integration tests for the `ralphctl completions` command.
+
+use assert_cmd::Command;
+use predicates::prelude::*;
+
+/// Get a command for ralphctl.
+fn ralphctl() -> Command {
+    Command::new(assert_cmd::cargo::cargo_bin!("ralphctl"))
+}
+
+/// Run ralphctl with `args` and return its stdout.
+fn stdout_of(args: &[&str]) -> String {
+    let output = ralphctl().args(args).output().unwrap();
+    assert!(output.status.success(), "ralphctl {:?} failed", args);
+    String::from_utf8(output.stdout).unwrap()
+}
+
+/// Long flags (`--name`) mentioned in help text.
+fn long_flags(help: &str) -> Vec<String> {
+    help.split(|c: char| c.is_whitespace() || c == ',' || c == '[' || c == ']')
+        .map(|word| word.split('=').next().unwrap())
+        .map(|word| word.trim_end_matches(|c: char| !(c.is_alphanumeric() || c == '-')))
+        .filter(|word| word.starts_with("--") && word.len() > 2)
+        .map(str::to_string)
+        .collect()
+}
+
+#[test]
+fn completions_cover_every_subcommand_and_flag() {
+    let script = stdout_of(&["completions", "bash"]);
+
+    for subcommand in [
+        "init",
+        "interview",
+        "run",
+        "status",
+        "stats",
+        "plan",
+        "reorder",
+        "clean",
+        "rollback",
+        "archive",
+        "update",
+        "fetch-latest-prompt",
+        "reverse",
+        "completions",
+        "self-test",
+        "signals",
+        "man",
+    ] {
+        assert!(script.contains(subcommand), "missing {}", subcommand);
+    }
+
+    for subcommand in ["run", "reverse", "init", "clean"] {
+        let help = stdout_of(&[subcommand, "--help"]);
+        for flag in long_flags(&help) {
+            assert!(
+                script.contains(&flag),
+                "{} flag {} missing from completions",
+                subcommand,
+                flag
+            );
+        }
+    }
+}
+
+#[test]
+fn completions_support_zsh_and_fish() {
+    ralphctl()
+        .args(["completions", "zsh"])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("#compdef ralphctl"))
+        .stdout(predicate::str::contains("--max-iterations"));
+
+    ralphctl()
+        .args(["completions", "fish"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("complete -c ralphctl"))
+        .stdout(predicate::str::contains("fetch-latest-prompt"));
+}
+
+#[test]
+fn completions_reject_unknown_shell() {
+    ralphctl()
+        .args(["completions", "tcsh"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid value 'tcsh'"));
+}
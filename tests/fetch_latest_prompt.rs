@@ -1,7 +1,11 @@
 //! Integration tests for the `ralphctl fetch-latest-prompt` command.
 
+mod common;
+
 use assert_cmd::Command;
+use common::{serve_once, serve_response};
 use predicates::prelude::*;
+use ralphctl::templates::{auth_token_from, fetch_template_from, fetch_url};
 use std::fs;
 use tempfile::TempDir;
 
@@ -15,6 +19,25 @@ fn temp_dir() -> TempDir {
     tempfile::tempdir().expect("Failed to create temp dir")
 }
 
+/// Get a fetch-latest-prompt command that reads the template from a local
+/// `file://` source serving `latest` as PROMPT.md, with the cache in `dir`.
+fn fetch_from_local(dir: &TempDir, latest: &str) -> Command {
+    let source = dir.path().join("source");
+    fs::create_dir_all(&source).unwrap();
+    fs::write(source.join("PROMPT.md"), latest).unwrap();
+
+    let mut cmd = ralphctl();
+    cmd.current_dir(dir.path())
+        .env(
+            "RALPHCTL_TEMPLATE_URL",
+            format!("file://{}", source.display()),
+        )
+        .env("HOME", dir.path())
+        .env("XDG_CACHE_HOME", dir.path().join(".cache"))
+        .arg("fetch-latest-prompt");
+    cmd
+}
+
 #[test]
 fn fetch_latest_prompt_creates_file() {
     let dir = temp_dir();
@@ -39,6 +62,7 @@ fn fetch_latest_prompt_overwrites_existing() {
     ralphctl()
         .current_dir(dir.path())
         .arg("fetch-latest-prompt")
+        .arg("--force")
         .assert()
         .success();
 
@@ -90,3 +114,343 @@ fn fetch_latest_prompt_help_shows_description() {
         .stdout(predicate::str::contains("PROMPT.md"))
         .stdout(predicate::str::contains("GitHub"));
 }
+
+#[test]
+fn fetch_latest_prompt_up_to_date_touches_nothing() {
+    let dir = temp_dir();
+    fs::write(dir.path().join("PROMPT.md"), "same\n").unwrap();
+
+    fetch_from_local(&dir, "same\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("PROMPT.md is already up to date."))
+        .stdout(predicate::str::contains("Updated").not());
+
+    assert!(!dir.path().join("PROMPT.md.bak").exists());
+}
+
+#[test]
+fn fetch_latest_prompt_non_tty_without_force_refuses() {
+    let dir = temp_dir();
+    fs::write(dir.path().join("PROMPT.md"), "line one\nmy tweak\n").unwrap();
+
+    fetch_from_local(&dir, "line one\nupstream\n")
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains("-my tweak\n+upstream"))
+        .stderr(predicate::str::contains("pass --force to overwrite"));
+
+    let content = fs::read_to_string(dir.path().join("PROMPT.md")).unwrap();
+    assert_eq!(content, "line one\nmy tweak\n");
+}
+
+#[test]
+fn fetch_latest_prompt_yes_overwrites_without_tty() {
+    let dir = temp_dir();
+    fs::write(dir.path().join("PROMPT.md"), "line one\nmy tweak\n").unwrap();
+
+    // stdin is not a terminal; -y stands in for the confirmation
+    fetch_from_local(&dir, "line one\nupstream\n")
+        .arg("-y")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("-my tweak\n+upstream"))
+        .stdout(predicate::str::contains("Updated PROMPT.md"));
+
+    let content = fs::read_to_string(dir.path().join("PROMPT.md")).unwrap();
+    assert_eq!(content, "line one\nupstream\n");
+}
+
+#[test]
+fn fetch_latest_prompt_force_overwrites_and_shows_diff() {
+    let dir = temp_dir();
+    fs::write(dir.path().join("PROMPT.md"), "line one\nmy tweak\n").unwrap();
+
+    fetch_from_local(&dir, "line one\nupstream\n")
+        .arg("--force")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "--- PROMPT.md\n+++ PROMPT.md (latest)\n@@ -1,2 +1,2 @@\n line one\n-my tweak\n+upstream\n",
+        ))
+        .stdout(predicate::str::contains("Updated PROMPT.md"));
+
+    let content = fs::read_to_string(dir.path().join("PROMPT.md")).unwrap();
+    assert_eq!(content, "line one\nupstream\n");
+    assert!(!dir.path().join("PROMPT.md.bak").exists());
+}
+
+#[test]
+fn fetch_latest_prompt_backup_saves_previous_file() {
+    let dir = temp_dir();
+    fs::write(dir.path().join("PROMPT.md"), "my tweak\n").unwrap();
+
+    fetch_from_local(&dir, "upstream\n")
+        .arg("--force")
+        .arg("--backup")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Saved previous PROMPT.md to PROMPT.md.bak.",
+        ));
+
+    assert_eq!(
+        fs::read_to_string(dir.path().join("PROMPT.md.bak")).unwrap(),
+        "my tweak\n"
+    );
+    assert_eq!(
+        fs::read_to_string(dir.path().join("PROMPT.md")).unwrap(),
+        "upstream\n"
+    );
+}
+
+#[test]
+fn fetch_latest_prompt_missing_file_writes_without_prompt() {
+    let dir = temp_dir();
+
+    fetch_from_local(&dir, "upstream\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Updated PROMPT.md"));
+
+    assert_eq!(
+        fs::read_to_string(dir.path().join("PROMPT.md")).unwrap(),
+        "upstream\n"
+    );
+}
+
+#[test]
+fn fetch_latest_prompt_reverse_writes_reverse_prompt_only() {
+    let dir = temp_dir();
+    fs::write(dir.path().join("PROMPT.md"), "my prompt").unwrap();
+
+    fetch_from_local(&dir, "latest prompt")
+        .arg("--reverse")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Updated REVERSE_PROMPT.md to latest version.",
+        ));
+
+    let reverse = fs::read_to_string(dir.path().join("REVERSE_PROMPT.md")).unwrap();
+    assert!(reverse.contains("[[RALPH:FOUND:"));
+    assert!(reverse.contains("[[RALPH:INCONCLUSIVE:"));
+    assert_eq!(
+        fs::read_to_string(dir.path().join("PROMPT.md")).unwrap(),
+        "my prompt"
+    );
+
+    // A second run finds nothing to change
+    fetch_from_local(&dir, "latest prompt")
+        .arg("--reverse")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "REVERSE_PROMPT.md is already up to date.",
+        ));
+}
+
+#[test]
+fn fetch_latest_prompt_reverse_force_backs_up_modified_file() {
+    let dir = temp_dir();
+    fs::write(
+        dir.path().join("REVERSE_PROMPT.md"),
+        "edited reverse prompt\n",
+    )
+    .unwrap();
+
+    fetch_from_local(&dir, "latest prompt")
+        .args(["--reverse", "--force", "--backup"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("-edited reverse prompt"));
+
+    assert_eq!(
+        fs::read_to_string(dir.path().join("REVERSE_PROMPT.md.bak")).unwrap(),
+        "edited reverse prompt\n"
+    );
+    assert!(!dir.path().join("PROMPT.md").exists());
+}
+
+#[test]
+fn fetch_latest_prompt_diff_previews_without_writing() {
+    let dir = temp_dir();
+    fs::write(dir.path().join("PROMPT.md"), "line one\nmy tweak\n").unwrap();
+
+    fetch_from_local(&dir, "line one\nupstream\n")
+        .arg("--diff")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "--- PROMPT.md\n+++ PROMPT.md (latest)\n@@ -1,2 +1,2 @@\n line one\n-my tweak\n+upstream\n",
+        ))
+        .stdout(predicate::str::contains("Updated").not());
+
+    assert_eq!(
+        fs::read_to_string(dir.path().join("PROMPT.md")).unwrap(),
+        "line one\nmy tweak\n"
+    );
+    assert!(!dir.path().join("PROMPT.md.bak").exists());
+}
+
+#[test]
+fn fetch_latest_prompt_diff_missing_file_writes_nothing() {
+    let dir = temp_dir();
+
+    fetch_from_local(&dir, "upstream\n")
+        .arg("--diff")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "PROMPT.md does not exist; it would be created.",
+        ));
+
+    assert!(!dir.path().join("PROMPT.md").exists());
+}
+
+#[test]
+fn fetch_latest_prompt_diff_conflicts_with_force() {
+    ralphctl()
+        .args(["fetch-latest-prompt", "--diff", "--force"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+}
+
+// === Template downloads against a local server ===
+
+#[tokio::test]
+async fn fetch_template_sends_token_from_env() {
+    let token = auth_token_from(|var| match var {
+        "RALPHCTL_TOKEN" => Some("secret-token".to_string()),
+        "GITHUB_TOKEN" => Some("other-token".to_string()),
+        _ => None,
+    });
+    assert_eq!(token.as_deref(), Some("secret-token"));
+
+    let (base_url, handle) = serve_once("200 OK", "# Private Spec");
+    let content = fetch_template_from(&base_url, "SPEC.md", token.as_deref(), None)
+        .await
+        .unwrap();
+    let request = handle.join().unwrap().to_lowercase();
+
+    assert_eq!(content, "# Private Spec");
+    assert!(request.starts_with("get /spec.md "));
+    assert!(request.contains("authorization: bearer secret-token\r\n"));
+}
+
+#[tokio::test]
+async fn fetch_template_without_token_is_anonymous() {
+    let (base_url, handle) = serve_once("200 OK", "# Spec");
+    let content = fetch_template_from(&base_url, "SPEC.md", None, None)
+        .await
+        .unwrap();
+    let request = handle.join().unwrap().to_lowercase();
+
+    assert_eq!(content, "# Spec");
+    assert!(!request.contains("authorization:"));
+}
+
+#[tokio::test]
+async fn fetch_template_stores_etag() {
+    let cache = temp_dir();
+    let (base_url, handle) = serve_response(
+        "HTTP/1.1 200 OK\r\nETag: \"v1\"\r\nContent-Length: 6\r\nConnection: close\r\n\r\n# Spec"
+            .to_string(),
+    );
+    let content = fetch_template_from(&base_url, "SPEC.md", None, Some(cache.path()))
+        .await
+        .unwrap();
+    let request = handle.join().unwrap().to_lowercase();
+
+    assert_eq!(content, "# Spec");
+    assert!(!request.contains("if-none-match:"));
+    assert_eq!(
+        fs::read_to_string(cache.path().join("SPEC.md")).unwrap(),
+        "# Spec"
+    );
+    assert_eq!(
+        fs::read_to_string(cache.path().join("SPEC.md.etag")).unwrap(),
+        "\"v1\""
+    );
+}
+
+#[tokio::test]
+async fn fetch_template_not_modified_uses_cache() {
+    let cache = temp_dir();
+    fs::write(cache.path().join("SPEC.md"), "# Cached Spec").unwrap();
+    fs::write(cache.path().join("SPEC.md.etag"), "\"v1\"").unwrap();
+
+    let (base_url, handle) = serve_response(
+        "HTTP/1.1 304 Not Modified\r\nETag: \"v1\"\r\nConnection: close\r\n\r\n".to_string(),
+    );
+    let content = fetch_template_from(&base_url, "SPEC.md", None, Some(cache.path()))
+        .await
+        .unwrap();
+    let request = handle.join().unwrap().to_lowercase();
+
+    assert_eq!(content, "# Cached Spec");
+    assert!(request.contains("if-none-match: \"v1\"\r\n"));
+}
+
+#[tokio::test]
+async fn fetch_template_without_etag_does_full_fetch() {
+    let cache = temp_dir();
+    fs::write(cache.path().join("SPEC.md"), "# Old Spec").unwrap();
+
+    let (base_url, handle) = serve_once("200 OK", "# New Spec");
+    let content = fetch_template_from(&base_url, "SPEC.md", None, Some(cache.path()))
+        .await
+        .unwrap();
+    let request = handle.join().unwrap().to_lowercase();
+
+    assert_eq!(content, "# New Spec");
+    assert!(!request.contains("if-none-match:"));
+    assert_eq!(
+        fs::read_to_string(cache.path().join("SPEC.md")).unwrap(),
+        "# New Spec"
+    );
+    assert!(!cache.path().join("SPEC.md.etag").exists());
+}
+
+#[tokio::test]
+async fn fetch_url_downloads_without_token() {
+    let (base_url, handle) = serve_once("200 OK", "# Shared Prompt");
+    let content = fetch_url(&format!("{}/prompts/tdd.md", base_url))
+        .await
+        .unwrap();
+    let request = handle.join().unwrap().to_lowercase();
+
+    assert_eq!(content, "# Shared Prompt");
+    assert!(request.starts_with("get /prompts/tdd.md "));
+    assert!(!request.contains("authorization:"));
+}
+
+#[tokio::test]
+async fn fetch_url_non_success() {
+    let (base_url, handle) = serve_once("404 Not Found", "");
+    let url = format!("{}/missing.md", base_url);
+    let err = fetch_url(&url).await.unwrap_err();
+    handle.join().unwrap();
+
+    assert_eq!(
+        err.to_string(),
+        format!("failed to fetch {}: HTTP 404", url)
+    );
+}
+
+#[tokio::test]
+async fn fetch_url_rejects_non_http_urls() {
+    let dir = temp_dir();
+    fs::write(dir.path().join("secret.md"), "local only").unwrap();
+    let url = format!("file://{}/secret.md", dir.path().display());
+
+    let err = fetch_url(&url).await.unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "unsupported URL {}: only http:// and https:// are allowed",
+            url
+        )
+    );
+}
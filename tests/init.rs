@@ -7,6 +7,7 @@
 use assert_cmd::Command;
 use predicates::prelude::*;
 use std::fs;
+use std::os::unix::fs::PermissionsExt;
 use tempfile::TempDir;
 
 /// Get a command for ralphctl.
@@ -28,6 +29,37 @@ fn claude_available() -> bool {
         .unwrap_or(false)
 }
 
+/// Create a mock claude script that outputs the given content.
+///
+/// Returns the path to the directory containing the mock script.
+fn create_mock_claude(dir: &TempDir, output: &str) -> std::path::PathBuf {
+    let bin_dir = dir.path().join("bin");
+    fs::create_dir_all(&bin_dir).unwrap();
+
+    let script_path = bin_dir.join("claude");
+    let script_content = format!("#!/bin/sh\nprintf \"{}\"", output.replace('\n', "\\n"));
+    fs::write(&script_path, script_content).unwrap();
+
+    let mut perms = fs::metadata(&script_path).unwrap().permissions();
+    perms.set_mode(0o755);
+    fs::set_permissions(&script_path, perms).unwrap();
+
+    bin_dir
+}
+
+/// Seed the template cache so PROMPT.md is available without network access.
+///
+/// Callers must point HOME at `dir` for the cache to be picked up.
+fn setup_prompt_cache(dir: &TempDir) {
+    #[cfg(target_os = "macos")]
+    let cache_dir = dir.path().join("Library/Caches/ralphctl/templates");
+    #[cfg(not(target_os = "macos"))]
+    let cache_dir = dir.path().join(".cache/ralphctl/templates");
+
+    fs::create_dir_all(&cache_dir).unwrap();
+    fs::write(cache_dir.join("PROMPT.md"), "# Cached Prompt\n").unwrap();
+}
+
 #[test]
 fn init_fails_without_claude_cli() {
     let dir = temp_dir();
@@ -78,6 +110,192 @@ fn init_help_describes_force() {
         .stdout(predicate::str::contains("Overwrite existing files"));
 }
 
+#[test]
+fn init_help_shows_from_spec_flag() {
+    ralphctl()
+        .arg("init")
+        .arg("--help")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("--from-spec"))
+        .stdout(predicate::str::contains("--derive-plan"));
+}
+
+#[test]
+fn init_derive_plan_requires_from_spec() {
+    ralphctl()
+        .arg("init")
+        .arg("--derive-plan")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--from-spec"));
+}
+
+#[test]
+fn init_from_spec_copies_spec_and_writes_stub_plan() {
+    let dir = temp_dir();
+    setup_prompt_cache(&dir);
+    let bin_dir = create_mock_claude(&dir, "");
+    let path = format!("{}:/usr/bin", bin_dir.display());
+
+    fs::create_dir_all(dir.path().join("docs")).unwrap();
+    fs::write(dir.path().join("docs/spec.md"), "# My Existing Spec\n").unwrap();
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .env("HOME", dir.path())
+        .arg("init")
+        .arg("--from-spec")
+        .arg("docs/spec.md")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("docs/spec.md"));
+
+    let spec = fs::read_to_string(dir.path().join("SPEC.md")).unwrap();
+    assert_eq!(spec, "# My Existing Spec\n");
+
+    let plan = fs::read_to_string(dir.path().join("IMPLEMENTATION_PLAN.md")).unwrap();
+    assert!(plan.starts_with("# Implementation Plan"));
+
+    assert!(dir.path().join("PROMPT.md").exists());
+}
+
+#[test]
+fn init_from_spec_missing_file_fails() {
+    let dir = temp_dir();
+    let bin_dir = create_mock_claude(&dir, "");
+    let path = format!("{}:/usr/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .arg("init")
+        .arg("--from-spec")
+        .arg("nope.md")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot read spec: nope.md"));
+
+    assert!(!dir.path().join("SPEC.md").exists());
+}
+
+#[test]
+fn init_from_spec_respects_force() {
+    let dir = temp_dir();
+    setup_prompt_cache(&dir);
+    let bin_dir = create_mock_claude(&dir, "");
+    let path = format!("{}:/usr/bin", bin_dir.display());
+
+    fs::write(dir.path().join("spec.md"), "# Imported\n").unwrap();
+    fs::write(dir.path().join("SPEC.md"), "# Existing\n").unwrap();
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .env("HOME", dir.path())
+        .arg("init")
+        .arg("--from-spec")
+        .arg("spec.md")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("files already exist"));
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .env("HOME", dir.path())
+        .arg("init")
+        .arg("--from-spec")
+        .arg("spec.md")
+        .arg("--force")
+        .assert()
+        .success();
+
+    let spec = fs::read_to_string(dir.path().join("SPEC.md")).unwrap();
+    assert_eq!(spec, "# Imported\n");
+}
+
+/// Write a local templates directory with a `cli` plan template.
+///
+/// Returns the `file://` URL to pass as `RALPHCTL_TEMPLATE_URL`.
+fn setup_local_templates(dir: &TempDir) -> String {
+    let templates = dir.path().join("templates");
+    fs::create_dir_all(&templates).unwrap();
+    fs::write(templates.join("SPEC.md"), "# Spec\n").unwrap();
+    fs::write(templates.join("PROMPT.md"), "# Prompt\n").unwrap();
+    fs::write(
+        templates.join("IMPLEMENTATION_PLAN.md"),
+        "# Default Plan\n- [ ] Task\n",
+    )
+    .unwrap();
+    fs::write(
+        templates.join("IMPLEMENTATION_PLAN-cli.md"),
+        "# CLI Plan\n- [ ] Parse arguments\n",
+    )
+    .unwrap();
+    format!("file://{}", templates.display())
+}
+
+#[test]
+fn init_plan_template_uses_specialized_plan() {
+    let dir = temp_dir();
+    let url = setup_local_templates(&dir);
+    let bin_dir = create_mock_claude(&dir, "");
+    let path = format!("{}:/usr/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .env("RALPHCTL_TEMPLATE_URL", &url)
+        .env("RALPHCTL_CACHE_DIR", dir.path().join("cache"))
+        .args(["init", "--plan-template", "cli"])
+        .assert()
+        .success();
+
+    let plan = fs::read_to_string(dir.path().join("IMPLEMENTATION_PLAN.md")).unwrap();
+    assert_eq!(plan, "# CLI Plan\n- [ ] Parse arguments\n");
+    let spec = fs::read_to_string(dir.path().join("SPEC.md")).unwrap();
+    assert_eq!(spec, "# Spec\n");
+}
+
+#[test]
+fn init_unknown_plan_template_falls_back_to_default() {
+    let dir = temp_dir();
+    let url = setup_local_templates(&dir);
+    let bin_dir = create_mock_claude(&dir, "");
+    let path = format!("{}:/usr/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .env("RALPHCTL_TEMPLATE_URL", &url)
+        .env("RALPHCTL_CACHE_DIR", dir.path().join("cache"))
+        .args(["init", "--plan-template", "embedded"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "warning: unknown plan template 'embedded'",
+        ));
+
+    let plan = fs::read_to_string(dir.path().join("IMPLEMENTATION_PLAN.md")).unwrap();
+    assert_eq!(plan, "# Default Plan\n- [ ] Task\n");
+}
+
+#[test]
+fn init_plan_template_rejects_invalid_name() {
+    let dir = temp_dir();
+
+    ralphctl()
+        .current_dir(dir.path())
+        .args(["init", "--plan-template", "../cli"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid value '../cli'"));
+
+    assert!(!dir.path().join("IMPLEMENTATION_PLAN.md").exists());
+}
+
 // Tests that require claude to be installed
 // Run with: cargo test -- --ignored
 #[cfg(unix)]
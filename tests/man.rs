@@ -0,0 +1,64 @@
+//! Integration tests for the `ralphctl man` command.
+
+use assert_cmd::Command;
+use predicates::prelude::*;
+use std::fs;
+
+/// Get a command for ralphctl.
+fn ralphctl() -> Command {
+    Command::new(assert_cmd::cargo::cargo_bin!("ralphctl"))
+}
+
+#[test]
+fn man_prints_roff_page_with_subcommands() {
+    let output = ralphctl().arg("man").output().unwrap();
+    assert!(output.status.success());
+    let page = String::from_utf8(output.stdout).unwrap();
+
+    assert!(page.contains(".TH RALPHCTL 1"), "page: {}", page);
+    for subcommand in [
+        "init",
+        "interview",
+        "run",
+        "status",
+        "stats",
+        "plan",
+        "rollback",
+        "clean",
+        "archive",
+        "update",
+        "fetch\\-latest\\-prompt",
+        "reverse",
+        "completions",
+        "self\\-test",
+        "signals",
+        "man",
+    ] {
+        assert!(
+            page.contains(&format!("ralphctl\\-{}(1)", subcommand)),
+            "missing {}",
+            subcommand
+        );
+    }
+}
+
+#[test]
+fn man_output_writes_page_per_subcommand() {
+    let dir = tempfile::tempdir().unwrap();
+    let out = dir.path().join("man1");
+
+    ralphctl()
+        .args(["man", "--output"])
+        .arg(&out)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("man pages to"));
+
+    let top = fs::read_to_string(out.join("ralphctl.1")).unwrap();
+    assert!(top.contains(".TH RALPHCTL 1"));
+    let run = fs::read_to_string(out.join("ralphctl-run.1")).unwrap();
+    assert!(run.contains(".TH RALPHCTL-RUN 1"));
+    assert!(run.contains("max\\-iterations"));
+    assert!(out.join("ralphctl-plan-reorder.1").exists());
+    assert!(!out.join("ralphctl-help.1").exists());
+}
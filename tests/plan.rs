@@ -0,0 +1,310 @@
+//! Integration tests for the `ralphctl plan` command.
+
+use assert_cmd::Command;
+use predicates::prelude::*;
+use std::fs;
+use tempfile::TempDir;
+
+/// Get a command for ralphctl.
+fn ralphctl() -> Command {
+    Command::new(assert_cmd::cargo::cargo_bin!("ralphctl"))
+}
+
+/// Create a temporary directory for testing.
+fn temp_dir() -> TempDir {
+    tempfile::tempdir().expect("Failed to create temp dir")
+}
+
+#[test]
+fn plan_reorder_pending_first_rewrites_plan() {
+    let dir = temp_dir();
+    let plan = dir.path().join("IMPLEMENTATION_PLAN.md");
+    fs::write(
+        &plan,
+        "# Plan\n\n## Phase 1\n- [x] A\n- [ ] B\n\n## Phase 2\n- [x] C\n- [ ] D\n",
+    )
+    .unwrap();
+
+    ralphctl()
+        .current_dir(dir.path())
+        .args(["plan", "reorder", "--pending-first"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("pending tasks first"));
+
+    assert_eq!(
+        fs::read_to_string(&plan).unwrap(),
+        "# Plan\n\n## Phase 1\n- [ ] B\n- [x] A\n\n## Phase 2\n- [ ] D\n- [x] C\n"
+    );
+
+    // A second run is a no-op
+    ralphctl()
+        .current_dir(dir.path())
+        .args(["plan", "reorder", "--pending-first"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("already ordered"));
+}
+
+#[test]
+fn plan_reorder_custom_plan_file() {
+    let dir = temp_dir();
+    fs::create_dir(dir.path().join("plans")).unwrap();
+    let plan = dir.path().join("plans/auth.md");
+    fs::write(&plan, "- [x] A\n- [ ] B\n").unwrap();
+
+    ralphctl()
+        .current_dir(dir.path())
+        .args(["plan", "reorder", "--pending-first", "--plan-file"])
+        .arg("plans/auth.md")
+        .assert()
+        .success();
+
+    assert_eq!(fs::read_to_string(&plan).unwrap(), "- [ ] B\n- [x] A\n");
+}
+
+#[test]
+fn plan_reorder_fails_without_plan() {
+    let dir = temp_dir();
+
+    ralphctl()
+        .current_dir(dir.path())
+        .args(["plan", "reorder", "--pending-first"])
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("IMPLEMENTATION_PLAN.md not found"));
+}
+
+#[test]
+fn plan_reorder_requires_pending_first() {
+    ralphctl()
+        .args(["plan", "reorder"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--pending-first"));
+}
+
+#[test]
+fn plan_validate_reports_malformed_checkboxes() {
+    let dir = temp_dir();
+    fs::write(
+        dir.path().join("IMPLEMENTATION_PLAN.md"),
+        "# Plan\n\n- [ ] Good\n- [] Missing space\n- [y] Wrong mark\n",
+    )
+    .unwrap();
+
+    ralphctl()
+        .current_dir(dir.path())
+        .args(["plan", "validate"])
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains(
+            "warning: line 4: '- []' looks like a checkbox but won't be counted",
+        ))
+        .stderr(predicate::str::contains(
+            "warning: line 5: '- [y]' looks like a checkbox but won't be counted",
+        ))
+        .stderr(predicate::str::contains(
+            "error: IMPLEMENTATION_PLAN.md has 2 malformed checkboxes",
+        ));
+}
+
+#[test]
+fn plan_validate_passes_clean_custom_plan() {
+    let dir = temp_dir();
+    fs::create_dir(dir.path().join("plans")).unwrap();
+    fs::write(dir.path().join("plans/auth.md"), "- [x] A\n- [ ] B\n").unwrap();
+
+    ralphctl()
+        .current_dir(dir.path())
+        .args(["plan", "validate", "--plan-file", "plans/auth.md"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "plans/auth.md: no malformed checkboxes.",
+        ));
+}
+
+/// Write a billing spec and a plan with one task the spec never mentions.
+fn create_drifted_plan(dir: &TempDir) {
+    fs::write(
+        dir.path().join("SPEC.md"),
+        "# Billing\n\nSend invoices and overdue reminders.\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.path().join("IMPLEMENTATION_PLAN.md"),
+        "- [ ] Generate invoices\n- [ ] Implement Stripe webhooks\n",
+    )
+    .unwrap();
+}
+
+#[test]
+fn plan_validate_warns_about_tasks_missing_from_spec() {
+    let dir = temp_dir();
+    create_drifted_plan(&dir);
+
+    ralphctl()
+        .current_dir(dir.path())
+        .args(["plan", "validate"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "warning: task 'Implement Stripe webhooks' shares no terms with SPEC.md",
+        ))
+        .stderr(predicate::str::contains("Generate invoices").not());
+}
+
+#[test]
+fn plan_validate_strict_consistency_fails_on_drift() {
+    let dir = temp_dir();
+    create_drifted_plan(&dir);
+
+    ralphctl()
+        .current_dir(dir.path())
+        .args(["plan", "validate", "--strict-consistency"])
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains(
+            "error: 1 plan task shares no terms with SPEC.md (--strict-consistency)",
+        ));
+}
+
+/// Write `plan` as IMPLEMENTATION_PLAN.md inside archive `name`.
+fn create_archive(dir: &TempDir, name: &str, plan: &str) {
+    let archive = dir.path().join(".ralphctl/archive").join(name);
+    fs::create_dir_all(&archive).unwrap();
+    fs::write(archive.join("IMPLEMENTATION_PLAN.md"), plan).unwrap();
+}
+
+#[test]
+fn plan_diff_reports_task_changes_since_archive() {
+    let dir = temp_dir();
+    create_archive(
+        &dir,
+        "2026-01-15T18-30-00",
+        "# Plan\n- [x] Setup\n- [ ] Parser\n- [ ] Old idea\n",
+    );
+    fs::write(
+        dir.path().join("IMPLEMENTATION_PLAN.md"),
+        "# Plan\n- [x] Setup\n- [x] Parser\n- [ ] Deploy\n",
+    )
+    .unwrap();
+
+    ralphctl()
+        .current_dir(dir.path())
+        .args(["plan", "diff", "2026-01-15T18-30-00"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "IMPLEMENTATION_PLAN.md since archive 2026-01-15T18-30-00: 1/3 → 2/3 tasks",
+        ))
+        .stdout(predicate::str::contains("Completed (1):\n  [x] Parser\n"))
+        .stdout(predicate::str::contains("Added (1):\n  + Deploy\n"))
+        .stdout(predicate::str::contains("Removed (1):\n  - Old idea\n"))
+        .stdout(predicate::str::contains("Reopened").not());
+}
+
+#[test]
+fn plan_diff_without_changes() {
+    let dir = temp_dir();
+    let plan = "- [x] Setup\n- [ ] Parser\n";
+    create_archive(&dir, "2026-01-15T18-30-00", plan);
+    fs::write(dir.path().join("IMPLEMENTATION_PLAN.md"), plan).unwrap();
+
+    ralphctl()
+        .current_dir(dir.path())
+        .args(["plan", "diff", "2026-01-15T18-30-00"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No task changes."));
+}
+
+#[test]
+fn plan_diff_unknown_archive_lists_available() {
+    let dir = temp_dir();
+    create_archive(&dir, "2026-01-15T18-30-00", "- [ ] A\n");
+    create_archive(&dir, "2026-02-01T09-00-00", "- [ ] A\n");
+    fs::write(dir.path().join("IMPLEMENTATION_PLAN.md"), "- [ ] A\n").unwrap();
+
+    ralphctl()
+        .current_dir(dir.path())
+        .args(["plan", "diff", "2026-03-01T00-00-00"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "no archive '2026-03-01T00-00-00'; available: 2026-01-15T18-30-00, 2026-02-01T09-00-00",
+        ));
+}
+
+#[test]
+fn plan_diff_without_archives() {
+    let dir = temp_dir();
+    fs::write(dir.path().join("IMPLEMENTATION_PLAN.md"), "- [ ] A\n").unwrap();
+
+    ralphctl()
+        .current_dir(dir.path())
+        .args(["plan", "diff", "2026-03-01T00-00-00"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("no archives yet"));
+}
+
+#[test]
+fn plan_next_skips_tasks_waiting_on_a_dependency() {
+    let dir = temp_dir();
+    fs::write(
+        dir.path().join("IMPLEMENTATION_PLAN.md"),
+        "- [x] Setup\n- [ ] Deploy (after: Build image)\n- [ ] Build image\n",
+    )
+    .unwrap();
+
+    ralphctl()
+        .current_dir(dir.path())
+        .args(["plan", "next"])
+        .assert()
+        .success()
+        .stdout("Build image\n");
+}
+
+#[test]
+fn plan_next_warns_about_unknown_reference() {
+    let dir = temp_dir();
+    fs::create_dir(dir.path().join("plans")).unwrap();
+    fs::write(
+        dir.path().join("plans/auth.md"),
+        "- [ ] Login (after: Nothing like this)\n- [ ] Logout\n",
+    )
+    .unwrap();
+
+    ralphctl()
+        .current_dir(dir.path())
+        .args(["plan", "next", "--plan-file", "plans/auth.md"])
+        .assert()
+        .success()
+        .stdout("Login\n")
+        .stderr(predicate::str::contains(
+            "warning: no task matches (after: Nothing like this) on 'Login'; ignoring",
+        ));
+}
+
+#[test]
+fn plan_next_with_no_open_tasks() {
+    let dir = temp_dir();
+    fs::write(dir.path().join("IMPLEMENTATION_PLAN.md"), "- [x] Done\n").unwrap();
+
+    ralphctl()
+        .current_dir(dir.path())
+        .args(["plan", "next"])
+        .assert()
+        .success()
+        .stdout("No open tasks.\n");
+
+    fs::remove_file(dir.path().join("IMPLEMENTATION_PLAN.md")).unwrap();
+    ralphctl()
+        .current_dir(dir.path())
+        .args(["plan", "next"])
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("IMPLEMENTATION_PLAN.md not found"));
+}
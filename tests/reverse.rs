@@ -3,7 +3,10 @@
 //! These tests use mock scripts to simulate claude CLI output, allowing us to
 //! test the reverse command's behavior without requiring the actual claude binary.
 
+mod common;
+
 use assert_cmd::Command;
+use common::{exit_code_for, help_exit_codes};
 use predicates::prelude::*;
 use std::fs;
 use std::os::unix::fs::PermissionsExt;
@@ -48,27 +51,46 @@ fn create_mock_claude(dir: &TempDir, output: &str) -> std::path::PathBuf {
     bin_dir
 }
 
-/// Create a mock REVERSE_PROMPT.md in the cache directory.
+/// Create a mock REVERSE_PROMPT.md in the test's cache directory.
 ///
 /// This prevents the test from needing network access to fetch the template.
-/// On macOS, the cache is ~/Library/Caches/ralphctl/templates/
-/// On Linux, it would be ~/.cache/ralphctl/templates/
+/// Tests point `RALPHCTL_CACHE_DIR` at `cache_dir(dir)`.
 fn setup_reverse_prompt_cache(dir: &TempDir) {
-    // macOS uses ~/Library/Caches, Linux uses ~/.cache
-    // Since we're setting HOME to the temp dir, we need to create the right structure
-    #[cfg(target_os = "macos")]
-    let cache_dir = dir.path().join("Library/Caches/ralphctl/templates");
-    #[cfg(not(target_os = "macos"))]
-    let cache_dir = dir.path().join(".cache/ralphctl/templates");
-
-    fs::create_dir_all(&cache_dir).unwrap();
+    let templates = cache_dir(dir).join("templates");
+    fs::create_dir_all(&templates).unwrap();
     fs::write(
-        cache_dir.join("REVERSE_PROMPT.md"),
+        templates.join("REVERSE_PROMPT.md"),
         "# Reverse Prompt\n\nInvestigate the codebase.",
     )
     .unwrap();
 }
 
+/// Create a mock claude that saves its prompt to `prompt-seen.txt` and
+/// reports FOUND.
+fn create_prompt_capturing_claude(dir: &TempDir) -> std::path::PathBuf {
+    let bin_dir = dir.path().join("bin");
+    fs::create_dir_all(&bin_dir).unwrap();
+    let script_path = bin_dir.join("claude");
+    fs::write(
+        &script_path,
+        "#!/bin/sh\ncat > prompt-seen.txt\necho '[[RALPH:FOUND:answer]]'\n",
+    )
+    .unwrap();
+    let mut perms = fs::metadata(&script_path).unwrap().permissions();
+    perms.set_mode(0o755);
+    fs::set_permissions(&script_path, perms).unwrap();
+    bin_dir
+}
+
+/// A local REVERSE_PROMPT.md that mentions every reverse signal.
+const LOCAL_PROMPT: &str = "# Local prompt\n[[RALPH:FOUND:x]] [[RALPH:INCONCLUSIVE:x]] \
+                            [[RALPH:CONTINUE]] [[RALPH:BLOCKED:x]]\n";
+
+/// Cache base directory for a test, passed as `RALPHCTL_CACHE_DIR`.
+fn cache_dir(dir: &TempDir) -> std::path::PathBuf {
+    dir.path().join("cache")
+}
+
 // ==================== Happy Path Tests ====================
 
 #[test]
@@ -87,7 +109,7 @@ fn reverse_with_question_argument_creates_question_file_and_runs() {
     ralphctl()
         .current_dir(dir.path())
         .env("PATH", &path)
-        .env("HOME", dir.path())
+        .env("RALPHCTL_CACHE_DIR", cache_dir(&dir))
         .arg("reverse")
         .arg("Why does authentication fail?")
         .arg("--max-iterations")
@@ -116,7 +138,7 @@ fn reverse_with_question_prints_iteration_header() {
     ralphctl()
         .current_dir(dir.path())
         .env("PATH", &path)
-        .env("HOME", dir.path())
+        .env("RALPHCTL_CACHE_DIR", cache_dir(&dir))
         .arg("reverse")
         .arg("Test question")
         .arg("--max-iterations")
@@ -139,7 +161,7 @@ fn reverse_creates_ralph_log() {
     ralphctl()
         .current_dir(dir.path())
         .env("PATH", &path)
-        .env("HOME", dir.path())
+        .env("RALPHCTL_CACHE_DIR", cache_dir(&dir))
         .arg("reverse")
         .arg("Test question")
         .arg("--max-iterations")
@@ -163,7 +185,33 @@ fn reverse_creates_ralph_log() {
 }
 
 #[test]
-fn reverse_writes_reverse_prompt_file() {
+fn reverse_does_not_write_reverse_prompt_file_by_default() {
+    let dir = temp_dir();
+    setup_reverse_prompt_cache(&dir);
+
+    let bin_dir = create_mock_claude(&dir, "[[RALPH:FOUND:answer]]\n");
+    let path = format!("{}:/usr/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .env("RALPHCTL_CACHE_DIR", cache_dir(&dir))
+        .arg("reverse")
+        .arg("Test question")
+        .arg("--max-iterations")
+        .arg("1")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Investigation complete"));
+
+    assert!(
+        !dir.path().join("REVERSE_PROMPT.md").exists(),
+        "REVERSE_PROMPT.md should only be written with --write-prompt"
+    );
+}
+
+#[test]
+fn reverse_write_prompt_writes_reverse_prompt_file() {
     let dir = temp_dir();
     setup_reverse_prompt_cache(&dir);
 
@@ -175,9 +223,10 @@ fn reverse_writes_reverse_prompt_file() {
     ralphctl()
         .current_dir(dir.path())
         .env("PATH", &path)
-        .env("HOME", dir.path())
+        .env("RALPHCTL_CACHE_DIR", cache_dir(&dir))
         .arg("reverse")
         .arg("Test question")
+        .arg("--write-prompt")
         .arg("--max-iterations")
         .arg("1")
         .assert()
@@ -191,6 +240,191 @@ fn reverse_writes_reverse_prompt_file() {
     );
 }
 
+#[test]
+fn reverse_summary_only_hides_claude_output() {
+    let dir = temp_dir();
+    setup_reverse_prompt_cache(&dir);
+    let bin_dir = create_mock_claude(&dir, "Looking around\n[[RALPH:FOUND:answer]]\n");
+    let path = format!("{}:/usr/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .env("RALPHCTL_CACHE_DIR", cache_dir(&dir))
+        .args(["reverse", "Test question", "--summary-only"])
+        .args(["--max-iterations", "1"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("=== Iteration 1 starting ==="))
+        .stdout(predicate::str::contains("Found: answer"))
+        .stdout(predicate::str::contains("Looking around").not());
+
+    let log = fs::read_to_string(dir.path().join("ralph.log")).unwrap();
+    assert!(log.contains("Looking around"));
+}
+
+#[test]
+fn reverse_prefers_local_reverse_prompt() {
+    let dir = temp_dir();
+    fs::write(dir.path().join("REVERSE_PROMPT.md"), LOCAL_PROMPT).unwrap();
+    let bin_dir = create_prompt_capturing_claude(&dir);
+    let path = format!("{}:/usr/bin:/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .args(["reverse", "Test question", "--max-iterations", "1"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "warning: using local REVERSE_PROMPT.md instead of the built-in prompt \
+             (use --refresh-prompt to ignore it)",
+        ));
+
+    let seen = fs::read_to_string(dir.path().join("prompt-seen.txt")).unwrap();
+    assert_eq!(seen, LOCAL_PROMPT);
+}
+
+#[test]
+fn reverse_prompt_file_is_used_unchanged() {
+    let dir = temp_dir();
+    fs::write(dir.path().join("REVERSE_PROMPT.md"), "# Stale prompt\n").unwrap();
+    fs::create_dir_all(dir.path().join("prompts")).unwrap();
+    let custom = format!("{}# Draft\n", LOCAL_PROMPT);
+    fs::write(dir.path().join("prompts/draft.md"), &custom).unwrap();
+    let bin_dir = create_prompt_capturing_claude(&dir);
+    let path = format!("{}:/usr/bin:/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .args(["reverse", "Test question", "--max-iterations", "1"])
+        .args(["--reverse-prompt-file", "prompts/draft.md"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("using local prompts/draft.md"));
+
+    let seen = fs::read_to_string(dir.path().join("prompt-seen.txt")).unwrap();
+    assert_eq!(seen, custom);
+    // Neither file is rewritten
+    assert_eq!(
+        fs::read_to_string(dir.path().join("prompts/draft.md")).unwrap(),
+        custom
+    );
+    assert_eq!(
+        fs::read_to_string(dir.path().join("REVERSE_PROMPT.md")).unwrap(),
+        "# Stale prompt\n"
+    );
+}
+
+#[test]
+fn reverse_prompt_file_must_exist() {
+    let dir = temp_dir();
+    let bin_dir = create_prompt_capturing_claude(&dir);
+    let path = format!("{}:/usr/bin:/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .args([
+            "reverse",
+            "Test question",
+            "--reverse-prompt-file",
+            "nope.md",
+        ])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("failed to read nope.md"));
+
+    assert!(!dir.path().join("prompt-seen.txt").exists());
+}
+
+#[test]
+fn reverse_refresh_prompt_ignores_local_reverse_prompt() {
+    let dir = temp_dir();
+    fs::write(dir.path().join("REVERSE_PROMPT.md"), "# Broken prompt\n").unwrap();
+    let bin_dir = create_prompt_capturing_claude(&dir);
+    let path = format!("{}:/usr/bin:/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .args(["reverse", "Test question", "--max-iterations", "1"])
+        .arg("--refresh-prompt")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("using local").not());
+
+    let seen = fs::read_to_string(dir.path().join("prompt-seen.txt")).unwrap();
+    assert!(seen.contains("[[RALPH:INCONCLUSIVE:"));
+    assert_ne!(seen, "# Broken prompt\n");
+}
+
+#[test]
+fn reverse_rejects_local_prompt_without_signals() {
+    let dir = temp_dir();
+    fs::write(
+        dir.path().join("REVERSE_PROMPT.md"),
+        "# Prompt\n[[RALPH:FOUND:x]]\n",
+    )
+    .unwrap();
+    let bin_dir = create_prompt_capturing_claude(&dir);
+    let path = format!("{}:/usr/bin:/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .args(["reverse", "Test question", "--max-iterations", "1"])
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains(
+            "REVERSE_PROMPT.md: missing signal markers: [[RALPH:INCONCLUSIVE:",
+        ))
+        .stderr(predicate::str::contains("--refresh-prompt"));
+
+    assert!(!dir.path().join("prompt-seen.txt").exists());
+}
+
+#[test]
+fn reverse_write_investigation_appends_iteration_summary() {
+    let dir = temp_dir();
+    setup_reverse_prompt_cache(&dir);
+    let bin_dir = create_mock_claude(&dir, "Looked at the cache\n[[RALPH:CONTINUE]]\n");
+    let path = format!("{}:/usr/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .env("RALPHCTL_CACHE_DIR", cache_dir(&dir))
+        .args(["reverse", "Test question", "--write-investigation"])
+        .args(["--max-iterations", "2", "--tag", "t1"])
+        .assert()
+        .code(2);
+
+    let investigation = fs::read_to_string(dir.path().join("INVESTIGATION.md")).unwrap();
+    assert!(investigation.contains("## Iteration Log\n"));
+    assert!(investigation.contains("- Iteration 1 (run t1): continue, 39 bytes of output\n"));
+    assert!(investigation.contains("- Iteration 2 (run t1): continue, 39 bytes of output\n"));
+}
+
+#[test]
+fn reverse_without_write_investigation_leaves_investigation_alone() {
+    let dir = temp_dir();
+    setup_reverse_prompt_cache(&dir);
+    let bin_dir = create_mock_claude(&dir, "[[RALPH:FOUND:answer]]\n");
+    let path = format!("{}:/usr/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .env("RALPHCTL_CACHE_DIR", cache_dir(&dir))
+        .args(["reverse", "Test question", "--max-iterations", "1"])
+        .assert()
+        .success();
+
+    assert!(!dir.path().join("INVESTIGATION.md").exists());
+}
+
 #[test]
 fn reverse_with_long_question() {
     let dir = temp_dir();
@@ -206,7 +440,7 @@ fn reverse_with_long_question() {
     ralphctl()
         .current_dir(dir.path())
         .env("PATH", &path)
-        .env("HOME", dir.path())
+        .env("RALPHCTL_CACHE_DIR", cache_dir(&dir))
         .arg("reverse")
         .arg(long_question)
         .arg("--max-iterations")
@@ -233,7 +467,7 @@ fn reverse_with_special_characters_in_question() {
     ralphctl()
         .current_dir(dir.path())
         .env("PATH", &path)
-        .env("HOME", dir.path())
+        .env("RALPHCTL_CACHE_DIR", cache_dir(&dir))
         .arg("reverse")
         .arg(special_question)
         .arg("--max-iterations")
@@ -245,6 +479,68 @@ fn reverse_with_special_characters_in_question() {
     assert!(question_content.contains(special_question));
 }
 
+#[test]
+fn reverse_rejects_empty_question() {
+    let dir = temp_dir();
+    setup_reverse_prompt_cache(&dir);
+
+    let bin_dir = create_mock_claude(&dir, "[[RALPH:FOUND:answer]]\n");
+    let path = format!("{}:/usr/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .env("RALPHCTL_CACHE_DIR", cache_dir(&dir))
+        .arg("reverse")
+        .arg("   ")
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("error: question is empty"));
+
+    assert!(!dir.path().join("QUESTION.md").exists());
+}
+
+#[test]
+fn reverse_truncates_oversized_question() {
+    let dir = temp_dir();
+    setup_reverse_prompt_cache(&dir);
+
+    let bin_dir = create_mock_claude(&dir, "[[RALPH:FOUND:answer]]\n");
+    let path = format!("{}:/usr/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .env("RALPHCTL_CACHE_DIR", cache_dir(&dir))
+        .arg("reverse")
+        .arg("Why does it fail?")
+        .arg("--max-question-bytes")
+        .arg("8")
+        .arg("--max-iterations")
+        .arg("1")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("warning: question is 17 bytes"));
+
+    let question_content = fs::read_to_string(dir.path().join("QUESTION.md")).unwrap();
+    assert!(question_content.contains("\nWhy does\n"));
+    assert!(!question_content.contains("fail?"));
+}
+
+#[test]
+fn reverse_rejects_zero_max_question_bytes() {
+    let dir = temp_dir();
+
+    ralphctl()
+        .current_dir(dir.path())
+        .args(["reverse", "Why does it fail?", "--max-question-bytes", "0"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("--max-question-bytes"));
+
+    assert!(!dir.path().join("QUESTION.md").exists());
+}
+
 #[test]
 fn reverse_help_shows_all_flags() {
     ralphctl()
@@ -271,6 +567,21 @@ fn reverse_help_shows_exit_codes() {
         .stdout(predicate::str::contains("Inconclusive"));
 }
 
+#[test]
+fn reverse_help_exit_codes_match_constants() {
+    use ralphctl::error::exit;
+
+    let codes = help_exit_codes("reverse");
+    let code_for = |prefix| exit_code_for(&codes, prefix);
+    assert_eq!(code_for("Found"), exit::FOUND);
+    assert_eq!(code_for("Error"), exit::ERROR);
+    assert_eq!(code_for("Max iterations"), exit::MAX_ITERATIONS);
+    assert_eq!(code_for("Blocked"), exit::BLOCKED);
+    assert_eq!(code_for("Inconclusive"), exit::INCONCLUSIVE);
+    assert_eq!(code_for("Interrupted"), exit::INTERRUPTED);
+    assert_eq!(codes.len(), 6);
+}
+
 // ==================== No-Argument Behavior Tests ====================
 
 #[test]
@@ -300,7 +611,7 @@ The issue appears in production with high traffic.
     ralphctl()
         .current_dir(dir.path())
         .env("PATH", &path)
-        .env("HOME", dir.path())
+        .env("RALPHCTL_CACHE_DIR", cache_dir(&dir))
         .arg("reverse")
         .arg("--max-iterations")
         .arg("1")
@@ -347,7 +658,7 @@ Relevant files: src/payment.rs, src/stripe_client.rs
     ralphctl()
         .current_dir(dir.path())
         .env("PATH", &path)
-        .env("HOME", dir.path())
+        .env("RALPHCTL_CACHE_DIR", cache_dir(&dir))
         .arg("reverse")
         .arg("--max-iterations")
         .arg("1")
@@ -461,7 +772,7 @@ fn reverse_continue_signal_proceeds_to_next_iteration() {
     ralphctl()
         .current_dir(dir.path())
         .env("PATH", &path)
-        .env("HOME", dir.path())
+        .env("RALPHCTL_CACHE_DIR", cache_dir(&dir))
         .arg("reverse")
         .arg("Why does auth fail?")
         .arg("--max-iterations")
@@ -496,7 +807,7 @@ fn reverse_continue_signal_with_whitespace() {
     ralphctl()
         .current_dir(dir.path())
         .env("PATH", &path)
-        .env("HOME", dir.path())
+        .env("RALPHCTL_CACHE_DIR", cache_dir(&dir))
         .arg("reverse")
         .arg("Test question")
         .arg("--max-iterations")
@@ -518,7 +829,7 @@ fn reverse_continue_shows_iteration_headers_for_all_iterations() {
     let output = ralphctl()
         .current_dir(dir.path())
         .env("PATH", &path)
-        .env("HOME", dir.path())
+        .env("RALPHCTL_CACHE_DIR", cache_dir(&dir))
         .arg("reverse")
         .arg("Why does the test fail?")
         .arg("--max-iterations")
@@ -562,7 +873,7 @@ fn reverse_found_signal_exits_with_success() {
     ralphctl()
         .current_dir(dir.path())
         .env("PATH", &path)
-        .env("HOME", dir.path())
+        .env("RALPHCTL_CACHE_DIR", cache_dir(&dir))
         .arg("reverse")
         .arg("Why does authentication fail?")
         .arg("--max-iterations")
@@ -589,7 +900,7 @@ fn reverse_found_signal_stops_loop_immediately() {
     let output = ralphctl()
         .current_dir(dir.path())
         .env("PATH", &path)
-        .env("HOME", dir.path())
+        .env("RALPHCTL_CACHE_DIR", cache_dir(&dir))
         .arg("reverse")
         .arg("Quick question")
         .arg("--max-iterations")
@@ -627,7 +938,7 @@ fn reverse_found_signal_displays_summary_message() {
     ralphctl()
         .current_dir(dir.path())
         .env("PATH", &path)
-        .env("HOME", dir.path())
+        .env("RALPHCTL_CACHE_DIR", cache_dir(&dir))
         .arg("reverse")
         .arg("Why does the cache fail?")
         .arg("--max-iterations")
@@ -653,7 +964,7 @@ fn reverse_found_signal_with_special_characters_in_summary() {
     ralphctl()
         .current_dir(dir.path())
         .env("PATH", &path)
-        .env("HOME", dir.path())
+        .env("RALPHCTL_CACHE_DIR", cache_dir(&dir))
         .arg("reverse")
         .arg("Type error investigation")
         .arg("--max-iterations")
@@ -678,7 +989,7 @@ fn reverse_found_signal_with_whitespace() {
     ralphctl()
         .current_dir(dir.path())
         .env("PATH", &path)
-        .env("HOME", dir.path())
+        .env("RALPHCTL_CACHE_DIR", cache_dir(&dir))
         .arg("reverse")
         .arg("What is the answer?")
         .arg("--max-iterations")
@@ -701,7 +1012,7 @@ fn reverse_found_signal_logs_to_ralph_log() {
     ralphctl()
         .current_dir(dir.path())
         .env("PATH", &path)
-        .env("HOME", dir.path())
+        .env("RALPHCTL_CACHE_DIR", cache_dir(&dir))
         .arg("reverse")
         .arg("Log test question")
         .arg("--max-iterations")
@@ -739,7 +1050,7 @@ fn reverse_found_signal_takes_priority_over_continue() {
     ralphctl()
         .current_dir(dir.path())
         .env("PATH", &path)
-        .env("HOME", dir.path())
+        .env("RALPHCTL_CACHE_DIR", cache_dir(&dir))
         .arg("reverse")
         .arg("Priority test")
         .arg("--max-iterations")
@@ -767,7 +1078,7 @@ fn reverse_inconclusive_signal_exits_with_code_4() {
     ralphctl()
         .current_dir(dir.path())
         .env("PATH", &path)
-        .env("HOME", dir.path())
+        .env("RALPHCTL_CACHE_DIR", cache_dir(&dir))
         .arg("reverse")
         .arg("Why does authentication fail?")
         .arg("--max-iterations")
@@ -796,7 +1107,7 @@ fn reverse_inconclusive_signal_stops_loop_immediately() {
     let output = ralphctl()
         .current_dir(dir.path())
         .env("PATH", &path)
-        .env("HOME", dir.path())
+        .env("RALPHCTL_CACHE_DIR", cache_dir(&dir))
         .arg("reverse")
         .arg("Quick question")
         .arg("--max-iterations")
@@ -834,7 +1145,7 @@ fn reverse_inconclusive_signal_displays_reason() {
     ralphctl()
         .current_dir(dir.path())
         .env("PATH", &path)
-        .env("HOME", dir.path())
+        .env("RALPHCTL_CACHE_DIR", cache_dir(&dir))
         .arg("reverse")
         .arg("Why does the cache fail?")
         .arg("--max-iterations")
@@ -862,7 +1173,7 @@ fn reverse_inconclusive_signal_with_special_characters_in_reason() {
     ralphctl()
         .current_dir(dir.path())
         .env("PATH", &path)
-        .env("HOME", dir.path())
+        .env("RALPHCTL_CACHE_DIR", cache_dir(&dir))
         .arg("reverse")
         .arg("Async investigation")
         .arg("--max-iterations")
@@ -887,7 +1198,7 @@ fn reverse_inconclusive_signal_with_whitespace() {
     ralphctl()
         .current_dir(dir.path())
         .env("PATH", &path)
-        .env("HOME", dir.path())
+        .env("RALPHCTL_CACHE_DIR", cache_dir(&dir))
         .arg("reverse")
         .arg("Whitespace test")
         .arg("--max-iterations")
@@ -911,7 +1222,7 @@ fn reverse_inconclusive_signal_logs_to_ralph_log() {
     ralphctl()
         .current_dir(dir.path())
         .env("PATH", &path)
-        .env("HOME", dir.path())
+        .env("RALPHCTL_CACHE_DIR", cache_dir(&dir))
         .arg("reverse")
         .arg("Log test question")
         .arg("--max-iterations")
@@ -950,7 +1261,7 @@ fn reverse_inconclusive_signal_takes_priority_over_continue() {
     ralphctl()
         .current_dir(dir.path())
         .env("PATH", &path)
-        .env("HOME", dir.path())
+        .env("RALPHCTL_CACHE_DIR", cache_dir(&dir))
         .arg("reverse")
         .arg("Priority test")
         .arg("--max-iterations")
@@ -976,7 +1287,7 @@ fn reverse_found_signal_takes_priority_over_inconclusive() {
     ralphctl()
         .current_dir(dir.path())
         .env("PATH", &path)
-        .env("HOME", dir.path())
+        .env("RALPHCTL_CACHE_DIR", cache_dir(&dir))
         .arg("reverse")
         .arg("Priority test")
         .arg("--max-iterations")
@@ -1001,7 +1312,7 @@ fn reverse_inconclusive_signal_with_colon_in_reason() {
     ralphctl()
         .current_dir(dir.path())
         .env("PATH", &path)
-        .env("HOME", dir.path())
+        .env("RALPHCTL_CACHE_DIR", cache_dir(&dir))
         .arg("reverse")
         .arg("Colon test")
         .arg("--max-iterations")
@@ -1026,7 +1337,7 @@ fn reverse_found_signal_with_colon_in_summary() {
     ralphctl()
         .current_dir(dir.path())
         .env("PATH", &path)
-        .env("HOME", dir.path())
+        .env("RALPHCTL_CACHE_DIR", cache_dir(&dir))
         .arg("reverse")
         .arg("Colon test")
         .arg("--max-iterations")
@@ -1055,7 +1366,7 @@ fn reverse_blocked_signal_exits_with_code_3() {
     ralphctl()
         .current_dir(dir.path())
         .env("PATH", &path)
-        .env("HOME", dir.path())
+        .env("RALPHCTL_CACHE_DIR", cache_dir(&dir))
         .arg("reverse")
         .arg("Why does authentication fail?")
         .arg("--max-iterations")
@@ -1082,7 +1393,7 @@ fn reverse_blocked_signal_stops_loop_immediately() {
     let output = ralphctl()
         .current_dir(dir.path())
         .env("PATH", &path)
-        .env("HOME", dir.path())
+        .env("RALPHCTL_CACHE_DIR", cache_dir(&dir))
         .arg("reverse")
         .arg("Quick question")
         .arg("--max-iterations")
@@ -1120,7 +1431,7 @@ fn reverse_blocked_signal_displays_reason() {
     ralphctl()
         .current_dir(dir.path())
         .env("PATH", &path)
-        .env("HOME", dir.path())
+        .env("RALPHCTL_CACHE_DIR", cache_dir(&dir))
         .arg("reverse")
         .arg("Why does the API fail?")
         .arg("--max-iterations")
@@ -1145,7 +1456,7 @@ fn reverse_blocked_signal_with_special_characters_in_reason() {
     ralphctl()
         .current_dir(dir.path())
         .env("PATH", &path)
-        .env("HOME", dir.path())
+        .env("RALPHCTL_CACHE_DIR", cache_dir(&dir))
         .arg("reverse")
         .arg("Config investigation")
         .arg("--max-iterations")
@@ -1170,7 +1481,7 @@ fn reverse_blocked_signal_with_whitespace() {
     ralphctl()
         .current_dir(dir.path())
         .env("PATH", &path)
-        .env("HOME", dir.path())
+        .env("RALPHCTL_CACHE_DIR", cache_dir(&dir))
         .arg("reverse")
         .arg("Whitespace test")
         .arg("--max-iterations")
@@ -1194,7 +1505,7 @@ fn reverse_blocked_signal_with_empty_reason() {
     ralphctl()
         .current_dir(dir.path())
         .env("PATH", &path)
-        .env("HOME", dir.path())
+        .env("RALPHCTL_CACHE_DIR", cache_dir(&dir))
         .arg("reverse")
         .arg("Empty reason test")
         .arg("--max-iterations")
@@ -1217,7 +1528,7 @@ fn reverse_blocked_signal_logs_to_ralph_log() {
     ralphctl()
         .current_dir(dir.path())
         .env("PATH", &path)
-        .env("HOME", dir.path())
+        .env("RALPHCTL_CACHE_DIR", cache_dir(&dir))
         .arg("reverse")
         .arg("Log test question")
         .arg("--max-iterations")
@@ -1256,7 +1567,7 @@ fn reverse_blocked_signal_takes_priority_over_found() {
     ralphctl()
         .current_dir(dir.path())
         .env("PATH", &path)
-        .env("HOME", dir.path())
+        .env("RALPHCTL_CACHE_DIR", cache_dir(&dir))
         .arg("reverse")
         .arg("Priority test")
         .arg("--max-iterations")
@@ -1282,7 +1593,7 @@ fn reverse_blocked_signal_takes_priority_over_inconclusive() {
     ralphctl()
         .current_dir(dir.path())
         .env("PATH", &path)
-        .env("HOME", dir.path())
+        .env("RALPHCTL_CACHE_DIR", cache_dir(&dir))
         .arg("reverse")
         .arg("Priority test")
         .arg("--max-iterations")
@@ -1307,7 +1618,7 @@ fn reverse_blocked_signal_takes_priority_over_continue() {
     ralphctl()
         .current_dir(dir.path())
         .env("PATH", &path)
-        .env("HOME", dir.path())
+        .env("RALPHCTL_CACHE_DIR", cache_dir(&dir))
         .arg("reverse")
         .arg("Priority test")
         .arg("--max-iterations")
@@ -1331,7 +1642,7 @@ fn reverse_blocked_signal_takes_priority_over_all_signals() {
     ralphctl()
         .current_dir(dir.path())
         .env("PATH", &path)
-        .env("HOME", dir.path())
+        .env("RALPHCTL_CACHE_DIR", cache_dir(&dir))
         .arg("reverse")
         .arg("All signals test")
         .arg("--max-iterations")
@@ -1355,7 +1666,7 @@ fn reverse_blocked_signal_with_colon_in_reason() {
     ralphctl()
         .current_dir(dir.path())
         .env("PATH", &path)
-        .env("HOME", dir.path())
+        .env("RALPHCTL_CACHE_DIR", cache_dir(&dir))
         .arg("reverse")
         .arg("Colon test")
         .arg("--max-iterations")
@@ -1383,7 +1694,7 @@ fn reverse_max_iterations_reached_exits_with_code_2() {
     ralphctl()
         .current_dir(dir.path())
         .env("PATH", &path)
-        .env("HOME", dir.path())
+        .env("RALPHCTL_CACHE_DIR", cache_dir(&dir))
         .arg("reverse")
         .arg("Why does the test fail?")
         .arg("--max-iterations")
@@ -1407,7 +1718,7 @@ fn reverse_max_iterations_runs_exact_count() {
     ralphctl()
         .current_dir(dir.path())
         .env("PATH", &path)
-        .env("HOME", dir.path())
+        .env("RALPHCTL_CACHE_DIR", cache_dir(&dir))
         .arg("reverse")
         .arg("Test max iterations count")
         .arg("--max-iterations")
@@ -1468,7 +1779,7 @@ fn reverse_max_iterations_one_runs_single_iteration() {
     let output = ralphctl()
         .current_dir(dir.path())
         .env("PATH", &path)
-        .env("HOME", dir.path())
+        .env("RALPHCTL_CACHE_DIR", cache_dir(&dir))
         .arg("reverse")
         .arg("Single iteration test")
         .arg("--max-iterations")
@@ -1506,7 +1817,7 @@ fn reverse_max_iterations_with_no_signal_prompts_then_stops() {
     ralphctl()
         .current_dir(dir.path())
         .env("PATH", &path)
-        .env("HOME", dir.path())
+        .env("RALPHCTL_CACHE_DIR", cache_dir(&dir))
         .arg("reverse")
         .arg("No signal test")
         .arg("--max-iterations")
@@ -1535,7 +1846,7 @@ fn reverse_pause_flag_prompts_before_each_iteration() {
     ralphctl()
         .current_dir(dir.path())
         .env("PATH", &path)
-        .env("HOME", dir.path())
+        .env("RALPHCTL_CACHE_DIR", cache_dir(&dir))
         .arg("reverse")
         .arg("Test pause mode")
         .arg("--pause")
@@ -1575,7 +1886,7 @@ fn reverse_pause_flag_stops_when_user_declines() {
     ralphctl()
         .current_dir(dir.path())
         .env("PATH", &path)
-        .env("HOME", dir.path())
+        .env("RALPHCTL_CACHE_DIR", cache_dir(&dir))
         .arg("reverse")
         .arg("Test pause decline")
         .arg("--pause")
@@ -1602,7 +1913,7 @@ fn reverse_pause_flag_with_q_to_quit() {
     ralphctl()
         .current_dir(dir.path())
         .env("PATH", &path)
-        .env("HOME", dir.path())
+        .env("RALPHCTL_CACHE_DIR", cache_dir(&dir))
         .arg("reverse")
         .arg("Test q to quit")
         .arg("--pause")
@@ -1629,7 +1940,7 @@ fn reverse_pause_flag_empty_input_continues() {
     ralphctl()
         .current_dir(dir.path())
         .env("PATH", &path)
-        .env("HOME", dir.path())
+        .env("RALPHCTL_CACHE_DIR", cache_dir(&dir))
         .arg("reverse")
         .arg("Test empty input continues")
         .arg("--pause")
@@ -1662,7 +1973,7 @@ fn reverse_pause_flag_stops_before_found_signal_iteration() {
     ralphctl()
         .current_dir(dir.path())
         .env("PATH", &path)
-        .env("HOME", dir.path())
+        .env("RALPHCTL_CACHE_DIR", cache_dir(&dir))
         .arg("reverse")
         .arg("Test pause before FOUND")
         .arg("--pause")
@@ -1697,3 +2008,596 @@ fn reverse_pause_flag_shows_in_help() {
         .stdout(predicate::str::contains("--pause"))
         .stdout(predicate::str::contains("confirmation"));
 }
+
+#[test]
+fn reverse_prints_summary_on_found() {
+    let dir = temp_dir();
+
+    let mock_output = "Investigating...\n[[RALPH:FOUND:answer]]\n";
+    let bin_dir = create_mock_claude(&dir, mock_output);
+
+    let path = format!("{}:/usr/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .env("RALPHCTL_CACHE_DIR", cache_dir(&dir))
+        .arg("reverse")
+        .arg("What is the answer?")
+        .arg("--max-iterations")
+        .arg("1")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("=== Run summary ==="))
+        .stderr(predicate::str::contains("Outcome:    found (answer)"))
+        .stderr(predicate::str::contains("Hypotheses: 0 examined"))
+        .stderr(predicate::str::contains("Findings:   missing"));
+}
+
+#[test]
+fn reverse_fresh_truncates_previous_log() {
+    let dir = temp_dir();
+    fs::write(dir.path().join("ralph.log"), "Old investigation output\n").unwrap();
+
+    let mock_output = "New investigation.\n[[RALPH:FOUND:answer]]\n";
+    let bin_dir = create_mock_claude(&dir, mock_output);
+
+    let path = format!("{}:/usr/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .env("RALPHCTL_CACHE_DIR", cache_dir(&dir))
+        .arg("reverse")
+        .arg("--fresh")
+        .arg("What is the answer?")
+        .arg("--max-iterations")
+        .arg("1")
+        .assert()
+        .success();
+
+    let log_content = fs::read_to_string(dir.path().join("ralph.log")).unwrap();
+    assert!(!log_content.contains("Old investigation output"));
+    assert!(log_content.contains("New investigation"));
+}
+
+#[test]
+fn reverse_resume_continues_existing_investigation() {
+    let dir = temp_dir();
+    let question = "# Investigation Question\n\nWhy is the cache cold?\n";
+    let investigation = "# Investigation\n\n- [x] Hypothesis: TTL too short\n";
+    fs::write(dir.path().join("QUESTION.md"), question).unwrap();
+    fs::write(dir.path().join("INVESTIGATION.md"), investigation).unwrap();
+
+    let bin_dir = create_mock_claude(&dir, "[[RALPH:FOUND:eviction bug]]\n");
+    let path = format!("{}:/usr/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .env("RALPHCTL_CACHE_DIR", cache_dir(&dir))
+        .arg("reverse")
+        .arg("--resume")
+        .arg("--max-iterations")
+        .arg("1")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Resuming investigation from INVESTIGATION.md.",
+        ))
+        .stdout(predicate::str::contains("=== Iteration 1 starting ==="));
+
+    // Neither state file was rewritten
+    assert_eq!(
+        fs::read_to_string(dir.path().join("QUESTION.md")).unwrap(),
+        question
+    );
+    assert_eq!(
+        fs::read_to_string(dir.path().join("INVESTIGATION.md")).unwrap(),
+        investigation
+    );
+}
+
+#[test]
+fn reverse_resume_without_investigation_fails() {
+    let dir = temp_dir();
+    fs::write(dir.path().join("QUESTION.md"), "# Q\n\nWhy?\n").unwrap();
+
+    ralphctl()
+        .current_dir(dir.path())
+        .arg("reverse")
+        .arg("--resume")
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains(
+            "nothing to resume: missing INVESTIGATION.md",
+        ));
+}
+
+#[test]
+fn reverse_resume_without_question_does_not_create_template() {
+    let dir = temp_dir();
+
+    ralphctl()
+        .current_dir(dir.path())
+        .arg("reverse")
+        .arg("--resume")
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains(
+            "missing QUESTION.md, INVESTIGATION.md",
+        ));
+
+    assert!(!dir.path().join("QUESTION.md").exists());
+}
+
+#[test]
+fn reverse_resume_conflicts_with_question_and_fresh() {
+    ralphctl()
+        .arg("reverse")
+        .arg("--resume")
+        .arg("A new question")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+
+    ralphctl()
+        .arg("reverse")
+        .arg("--resume")
+        .arg("--fresh")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+}
+
+#[test]
+fn reverse_run_id_appears_in_stdout_and_log() {
+    let dir = temp_dir();
+    setup_reverse_prompt_cache(&dir);
+
+    let bin_dir = create_mock_claude(&dir, "[[RALPH:FOUND:answer]]\n");
+    let path = format!("{}:/usr/bin", bin_dir.display());
+
+    let output = ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .env("RALPHCTL_CACHE_DIR", cache_dir(&dir))
+        .arg("reverse")
+        .arg("Test question")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let start = stdout.find("(run ").expect("iteration header has a run ID") + "(run ".len();
+    let run_id = &stdout[start..start + 8];
+
+    let log = fs::read_to_string(dir.path().join("ralph.log")).unwrap();
+    assert!(log.contains(&format!("=== Run {} (reverse) started", run_id)));
+    assert!(log.contains(&format!("=== Iteration 1 starting === (run {})", run_id)));
+}
+
+#[test]
+fn reverse_split_logs_writes_iteration_file() {
+    let dir = temp_dir();
+    setup_reverse_prompt_cache(&dir);
+
+    let bin_dir = create_mock_claude(&dir, "Evidence.\n[[RALPH:FOUND:answer]]\n");
+    let path = format!("{}:/usr/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .env("RALPHCTL_CACHE_DIR", cache_dir(&dir))
+        .arg("reverse")
+        .arg("Test question")
+        .arg("--split-logs")
+        .assert()
+        .success();
+
+    assert_eq!(
+        fs::read_to_string(dir.path().join(".ralphctl/logs/iteration-1.log")).unwrap(),
+        "Evidence.\n[[RALPH:FOUND:answer]]\n"
+    );
+}
+
+#[test]
+fn reverse_dry_run_writes_nothing() {
+    let dir = temp_dir();
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("RALPHCTL_CACHE_DIR", cache_dir(&dir))
+        .arg("reverse")
+        .arg("--dry-run")
+        .arg("--model")
+        .arg("sonnet")
+        .arg("Why does it fail?")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Command:        claude -p --dangerously-skip-permissions --model sonnet",
+        ))
+        .stdout(predicate::str::contains("embedded REVERSE_PROMPT.md"))
+        .stdout(predicate::str::contains("Max iterations: 100"));
+
+    assert!(!dir.path().join("QUESTION.md").exists());
+    assert!(!dir.path().join("REVERSE_PROMPT.md").exists());
+}
+
+#[test]
+fn reverse_notes_revisited_hypothesis_as_dead_end() {
+    let dir = temp_dir();
+    fs::write(dir.path().join("QUESTION.md"), "# Q\n\nWhy is it slow?\n").unwrap();
+    fs::write(
+        dir.path().join("INVESTIGATION.md"),
+        "# Investigation Log\n\n## Hypothesis 1: Stale config cache\n\n## Dead Ends\n- DNS\n\n## Key Findings\n",
+    )
+    .unwrap();
+
+    // Iteration 1 adds a new hypothesis; iteration 2 repeats it
+    let bin_dir = dir.path().join("bin");
+    fs::create_dir_all(&bin_dir).unwrap();
+    let script = bin_dir.join("claude");
+    fs::write(
+        &script,
+        "#!/bin/sh\n\
+         cat > /dev/null\n\
+         if [ -f called ]; then\n\
+         printf '\\n## Hypothesis 3: race in  WRITER lock\\n' >> INVESTIGATION.md\n\
+         echo '[[RALPH:FOUND:lock contention]]'\n\
+         else\n\
+         touch called\n\
+         printf '\\n## Hypothesis 2: Race in writer lock\\n' >> INVESTIGATION.md\n\
+         echo '[[RALPH:CONTINUE]]'\n\
+         fi\n",
+    )
+    .unwrap();
+    fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+    let path = format!("{}:/usr/bin:/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .env("RALPHCTL_CACHE_DIR", cache_dir(&dir))
+        .arg("reverse")
+        .arg("--resume")
+        .arg("--max-iterations")
+        .arg("2")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "warning: hypothesis \"race in  WRITER lock\" was already explored in iteration 1",
+        ));
+
+    let investigation = fs::read_to_string(dir.path().join("INVESTIGATION.md")).unwrap();
+    assert!(investigation.contains(
+        "## Dead Ends\n- DNS\n- \"race in  WRITER lock\" was already explored in iteration 1;"
+    ));
+}
+
+#[test]
+fn reverse_early_stop_ends_iteration_at_found_signal() {
+    let dir = temp_dir();
+    setup_reverse_prompt_cache(&dir);
+    let bin_dir = dir.path().join("bin");
+    fs::create_dir_all(&bin_dir).unwrap();
+    let script_path = bin_dir.join("claude");
+    fs::write(
+        &script_path,
+        "#!/bin/sh\necho '[[RALPH:FOUND:the cache]]'\nexec sleep 30\n",
+    )
+    .unwrap();
+    let mut perms = fs::metadata(&script_path).unwrap().permissions();
+    perms.set_mode(0o755);
+    fs::set_permissions(&script_path, perms).unwrap();
+    let path = format!("{}:/usr/bin:/bin", bin_dir.display());
+
+    let started = std::time::Instant::now();
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .env("RALPHCTL_CACHE_DIR", cache_dir(&dir))
+        .args(["reverse", "Why is it slow?", "--early-stop"])
+        .timeout(std::time::Duration::from_secs(20))
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Found: the cache"));
+    assert!(started.elapsed() < std::time::Duration::from_secs(15));
+}
+
+#[test]
+fn reverse_format_json_reports_claude_failure() {
+    let dir = temp_dir();
+    setup_reverse_prompt_cache(&dir);
+    let bin_dir = dir.path().join("bin");
+    fs::create_dir_all(&bin_dir).unwrap();
+    let script_path = bin_dir.join("claude");
+    fs::write(&script_path, "#!/bin/sh\necho 'rate limited' >&2\nexit 2\n").unwrap();
+    let mut perms = fs::metadata(&script_path).unwrap().permissions();
+    perms.set_mode(0o755);
+    fs::set_permissions(&script_path, perms).unwrap();
+    let path = format!("{}:/usr/bin:/bin", bin_dir.display());
+
+    let output = ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .env("RALPHCTL_CACHE_DIR", cache_dir(&dir))
+        .args(["reverse", "Why is it slow?", "--format", "json"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("error: claude exited with code 2"));
+
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        json,
+        serde_json::json!({
+            "outcome": "failed",
+            "reason": "claude exited with code 2",
+            "iterations": 1
+        })
+    );
+}
+
+#[test]
+fn reverse_format_json_reports_found() {
+    let dir = temp_dir();
+    setup_reverse_prompt_cache(&dir);
+    let bin_dir = create_mock_claude(&dir, "Looking around\n[[RALPH:FOUND:a stale cache]]\n");
+    let path = format!("{}:/usr/bin", bin_dir.display());
+
+    let output = ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .env("RALPHCTL_CACHE_DIR", cache_dir(&dir))
+        .args(["reverse", "Why is it slow?", "--format", "json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        json,
+        serde_json::json!({"outcome": "found", "summary": "a stale cache", "iterations": 1})
+    );
+}
+
+#[test]
+fn reverse_format_json_reports_inconclusive_with_iteration_count() {
+    let dir = temp_dir();
+    setup_reverse_prompt_cache(&dir);
+    let bin_dir = dir.path().join("bin");
+    fs::create_dir_all(&bin_dir).unwrap();
+    let script_path = bin_dir.join("claude");
+    fs::write(
+        &script_path,
+        "#!/bin/sh\n\
+         if [ -f seen ]; then echo '[[RALPH:INCONCLUSIVE:no logs kept]]'; \
+         else touch seen; echo '[[RALPH:CONTINUE]]'; fi\n",
+    )
+    .unwrap();
+    let mut perms = fs::metadata(&script_path).unwrap().permissions();
+    perms.set_mode(0o755);
+    fs::set_permissions(&script_path, perms).unwrap();
+    let path = format!("{}:/usr/bin:/bin", bin_dir.display());
+
+    let output = ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .env("RALPHCTL_CACHE_DIR", cache_dir(&dir))
+        .args(["reverse", "Why is it slow?", "--format", "json"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(4));
+
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        json,
+        serde_json::json!({"outcome": "inconclusive", "reason": "no logs kept", "iterations": 2})
+    );
+}
+
+#[test]
+fn reverse_porcelain_reports_found() {
+    let dir = temp_dir();
+    setup_reverse_prompt_cache(&dir);
+    let bin_dir = create_mock_claude(&dir, "Looking around\n[[RALPH:FOUND:a stale cache]]\n");
+    let path = format!("{}:/usr/bin", bin_dir.display());
+
+    let output = ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .env("RALPHCTL_CACHE_DIR", cache_dir(&dir))
+        .args(["reverse", "Why is it slow?", "--porcelain"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        [
+            "porcelain-version 1",
+            "iteration-start 1",
+            "signal FOUND a stale cache",
+            "outcome found iterations=1",
+        ]
+    );
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Looking around"));
+    assert!(stderr.contains("=== Investigation complete ==="));
+}
+
+#[test]
+fn reverse_porcelain_reports_inconclusive() {
+    let dir = temp_dir();
+    setup_reverse_prompt_cache(&dir);
+    let bin_dir = create_mock_claude(&dir, "[[RALPH:INCONCLUSIVE:no logs kept]]\n");
+    let path = format!("{}:/usr/bin", bin_dir.display());
+
+    let output = ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .env("RALPHCTL_CACHE_DIR", cache_dir(&dir))
+        .args(["reverse", "Why is it slow?", "--porcelain"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(4));
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        [
+            "porcelain-version 1",
+            "iteration-start 1",
+            "signal INCONCLUSIVE no logs kept",
+            "outcome inconclusive iterations=1",
+        ]
+    );
+}
+
+#[test]
+fn reverse_porcelain_conflicts_with_format() {
+    let dir = temp_dir();
+
+    ralphctl()
+        .current_dir(dir.path())
+        .args(["reverse", "Why?", "--porcelain", "--format", "json"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+}
+
+#[test]
+fn reverse_max_time_stops_as_inconclusive() {
+    let dir = temp_dir();
+    setup_reverse_prompt_cache(&dir);
+    let bin_dir = dir.path().join("bin");
+    fs::create_dir_all(&bin_dir).unwrap();
+    let script_path = bin_dir.join("claude");
+    fs::write(
+        &script_path,
+        "#!/bin/sh\nsleep 1\necho '[[RALPH:CONTINUE]]'\n",
+    )
+    .unwrap();
+    let mut perms = fs::metadata(&script_path).unwrap().permissions();
+    perms.set_mode(0o755);
+    fs::set_permissions(&script_path, perms).unwrap();
+    let path = format!("{}:/usr/bin:/bin", bin_dir.display());
+
+    let output = ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .env("RALPHCTL_CACHE_DIR", cache_dir(&dir))
+        .args([
+            "reverse",
+            "Why is it slow?",
+            "--timeout",
+            "1s",
+            "--format",
+            "json",
+        ])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(4));
+
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        json,
+        serde_json::json!({
+            "outcome": "inconclusive",
+            "reason": "time budget of 1s exhausted after 1 iteration (--max-time)",
+            "iterations": 1
+        })
+    );
+}
+
+#[test]
+fn reverse_max_time_rejects_zero() {
+    let dir = temp_dir();
+
+    ralphctl()
+        .current_dir(dir.path())
+        .args(["reverse", "Why?", "--max-time", "0s"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn reverse_target_runs_claude_in_target_and_keeps_files_here() {
+    let home = temp_dir();
+    let target = temp_dir();
+    fs::write(target.path().join("lib.rs"), "fn main() {}\n").unwrap();
+    let home_abs = home.path().canonicalize().unwrap();
+    let target_abs = target.path().canonicalize().unwrap();
+
+    let bin_dir = home.path().join("bin");
+    fs::create_dir_all(&bin_dir).unwrap();
+    let script_path = bin_dir.join("claude");
+    fs::write(
+        &script_path,
+        format!(
+            "#!/bin/sh\ncat > '{}/prompt-seen.txt'\necho \"PWD=$(pwd)\"\necho '[[RALPH:FOUND:located]]'\n",
+            home_abs.display()
+        ),
+    )
+    .unwrap();
+    let mut perms = fs::metadata(&script_path).unwrap().permissions();
+    perms.set_mode(0o755);
+    fs::set_permissions(&script_path, perms).unwrap();
+    let path = format!("{}:/usr/bin:/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(home.path())
+        .env("PATH", &path)
+        .args(["reverse", "Where is main?", "--max-iterations", "1"])
+        .arg("--target")
+        .arg(target.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "PWD={}",
+            target_abs.display()
+        )));
+
+    // The ralph files and log land in the invoking directory only
+    assert!(home.path().join("QUESTION.md").exists());
+    let log = fs::read_to_string(home.path().join("ralph.log")).unwrap();
+    assert!(log.contains(&format!("PWD={}", target_abs.display())));
+    assert!(!target.path().join("QUESTION.md").exists());
+    assert!(!target.path().join("ralph.log").exists());
+    assert!(!target.path().join(".ralphctl").exists());
+
+    let prompt = fs::read_to_string(home.path().join("prompt-seen.txt")).unwrap();
+    assert!(prompt.contains(&format!(
+        "The codebase under investigation is at `{}`.",
+        target_abs.display()
+    )));
+    assert!(prompt.contains(&format!("`{}`", home_abs.join("FINDINGS.md").display())));
+}
+
+#[test]
+fn reverse_target_must_be_a_directory() {
+    let dir = temp_dir();
+    fs::write(dir.path().join("file.txt"), "").unwrap();
+
+    ralphctl()
+        .current_dir(dir.path())
+        .args(["reverse", "Why?", "--target", "file.txt"])
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains(
+            "--target file.txt: not a directory",
+        ));
+
+    ralphctl()
+        .current_dir(dir.path())
+        .args(["reverse", "Why?", "--target", "missing"])
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains(
+            "--target missing: no such directory",
+        ));
+    assert!(!dir.path().join("QUESTION.md").exists());
+}
@@ -0,0 +1,300 @@
+//! Integration tests for plan checkpoints and the `ralphctl rollback` command.
+
+use assert_cmd::Command;
+use predicates::prelude::*;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use tempfile::TempDir;
+
+/// Get a command for ralphctl.
+fn ralphctl() -> Command {
+    Command::new(assert_cmd::cargo::cargo_bin!("ralphctl"))
+}
+
+/// Create a temporary directory for testing.
+fn temp_dir() -> TempDir {
+    tempfile::tempdir().expect("Failed to create temp dir")
+}
+
+/// Set up ralph files and a mock claude that checks off one task per call,
+/// signalling DONE once none remain. Returns the PATH to run with.
+fn setup_project(dir: &TempDir) -> String {
+    fs::write(dir.path().join("PROMPT.md"), "# Test Prompt\n").unwrap();
+    fs::write(dir.path().join("SPEC.md"), "# Test Spec\n").unwrap();
+    fs::write(
+        dir.path().join("IMPLEMENTATION_PLAN.md"),
+        "# Plan\n\n- [ ] Task 1\n- [ ] Task 2\n",
+    )
+    .unwrap();
+
+    let bin_dir = dir.path().join("bin");
+    fs::create_dir_all(&bin_dir).unwrap();
+    let script = bin_dir.join("claude");
+    fs::write(
+        &script,
+        "#!/bin/sh\n\
+         sed -i '0,/- \\[ \\]/s//- [x]/' IMPLEMENTATION_PLAN.md\n\
+         if grep -q -- '- \\[ \\]' IMPLEMENTATION_PLAN.md; then\n\
+         printf '[[RALPH:CONTINUE]]\\n'\n\
+         else\n\
+         printf '[[RALPH:DONE]]\\n'\n\
+         fi\n",
+    )
+    .unwrap();
+    fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+    format!("{}:/usr/bin:/bin", bin_dir.display())
+}
+
+#[test]
+fn run_checkpoints_plan_and_rollback_restores_it() {
+    let dir = temp_dir();
+    let path = setup_project(&dir);
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .args(["run", "--max-iterations", "3", "--tag", "first"])
+        .assert()
+        .success();
+
+    let checkpoints = dir.path().join(".ralphctl/checkpoints/first");
+    let first = fs::read_to_string(checkpoints.join("iter-1/IMPLEMENTATION_PLAN.md")).unwrap();
+    let second = fs::read_to_string(checkpoints.join("iter-2/IMPLEMENTATION_PLAN.md")).unwrap();
+    assert_eq!(first, "# Plan\n\n- [ ] Task 1\n- [ ] Task 2\n");
+    assert_eq!(second, "# Plan\n\n- [x] Task 1\n- [ ] Task 2\n");
+    assert_eq!(
+        fs::read_to_string(checkpoints.join("iter-2/SPEC.md")).unwrap(),
+        "# Test Spec\n"
+    );
+
+    // An iteration "reorganized" the plan away
+    let plan = dir.path().join("IMPLEMENTATION_PLAN.md");
+    fs::write(&plan, "# Plan\n").unwrap();
+
+    ralphctl()
+        .current_dir(dir.path())
+        .args(["rollback", "--force"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Checkpoint before iteration 2:"))
+        .stdout(predicate::str::contains("Run:        first"))
+        .stdout(predicate::str::contains(
+            "Current:    [░░░░░░░░░░░░] 0% (0/0 tasks)",
+        ))
+        .stdout(
+            predicate::str::contains("Checkpoint: [").and(predicate::str::contains("(1/2 tasks)")),
+        )
+        .stdout(predicate::str::contains(
+            "Restored checkpoint from before iteration 2.",
+        ));
+    assert_eq!(fs::read_to_string(&plan).unwrap(), second);
+
+    ralphctl()
+        .current_dir(dir.path())
+        .args(["rollback", "--to", "1", "--force"])
+        .assert()
+        .success();
+    assert_eq!(fs::read_to_string(&plan).unwrap(), first);
+}
+
+#[test]
+fn rollback_asks_before_restoring() {
+    let dir = temp_dir();
+    let path = setup_project(&dir);
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .args(["run", "--max-iterations", "3"])
+        .assert()
+        .success();
+
+    let plan = dir.path().join("IMPLEMENTATION_PLAN.md");
+    fs::write(&plan, "- [x] Only\n").unwrap();
+
+    ralphctl()
+        .current_dir(dir.path())
+        .arg("rollback")
+        .write_stdin("n\n")
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains("(1/1 tasks)"))
+        .stderr(predicate::str::contains(
+            "Restore IMPLEMENTATION_PLAN.md from this checkpoint? [y/N]",
+        ));
+    assert_eq!(fs::read_to_string(&plan).unwrap(), "- [x] Only\n");
+
+    fs::write(dir.path().join("SPEC.md"), "# Rewritten\n").unwrap();
+    ralphctl()
+        .current_dir(dir.path())
+        .args(["rollback", "--spec"])
+        .write_stdin("y\n")
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(dir.path().join("SPEC.md")).unwrap(),
+        "# Test Spec\n"
+    );
+}
+
+#[test]
+fn run_keep_checkpoints_limits_and_zero_disables() {
+    let dir = temp_dir();
+    let path = setup_project(&dir);
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .args(["run", "--max-iterations", "3", "--keep-checkpoints", "1"])
+        .args(["--tag", "first"])
+        .assert()
+        .success();
+    let checkpoints = dir.path().join(".ralphctl/checkpoints");
+    assert!(!checkpoints.join("first/iter-1").exists());
+    assert!(checkpoints.join("first/iter-2").exists());
+
+    // With 0 a run takes none and leaves earlier runs' alone
+    fs::write(dir.path().join("IMPLEMENTATION_PLAN.md"), "- [ ] Task 1\n").unwrap();
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .args(["run", "--keep-checkpoints", "0", "--tag", "second"])
+        .assert()
+        .success();
+    assert!(!checkpoints.join("second").exists());
+    assert!(checkpoints.join("first/iter-2").exists());
+}
+
+#[test]
+fn run_keeps_checkpoints_of_earlier_runs() {
+    let dir = temp_dir();
+    let path = setup_project(&dir);
+    let plan = dir.path().join("IMPLEMENTATION_PLAN.md");
+
+    // The first run fails partway, leaving its checkpoints behind
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .args(["run", "--max-iterations", "1", "--tag", "failed"])
+        .assert()
+        .code(2);
+    let failed_plan = fs::read_to_string(&plan).unwrap();
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .args(["run", "--max-iterations", "3", "--tag", "retry"])
+        .assert()
+        .success();
+
+    let checkpoints = dir.path().join(".ralphctl/checkpoints");
+    assert!(checkpoints.join("failed/iter-1").exists());
+    assert!(checkpoints.join("retry/iter-1").exists());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .args(["rollback", "--run", "retry", "--force"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Run:        retry"));
+    assert_eq!(fs::read_to_string(&plan).unwrap(), failed_plan);
+
+    ralphctl()
+        .current_dir(dir.path())
+        .args(["rollback", "--run", "failed", "--force"])
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(&plan).unwrap(),
+        "# Plan\n\n- [ ] Task 1\n- [ ] Task 2\n"
+    );
+}
+
+#[test]
+fn rollback_counts_org_plans_with_plan_format() {
+    let dir = temp_dir();
+    let checkpoint = dir.path().join(".ralphctl/checkpoints/org/iter-1");
+    fs::create_dir_all(&checkpoint).unwrap();
+    fs::write(
+        checkpoint.join("IMPLEMENTATION_PLAN.md"),
+        "* DONE Task 1\n* TODO Task 2\n",
+    )
+    .unwrap();
+    let plan = dir.path().join("IMPLEMENTATION_PLAN.md");
+    fs::write(&plan, "* TODO Task 1\n* TODO Task 2\n* TODO Task 3\n").unwrap();
+
+    ralphctl()
+        .current_dir(dir.path())
+        .args(["rollback", "--plan-format", "org", "--force"])
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::contains("Current:    [").and(predicate::str::contains("(0/3 tasks)")),
+        )
+        .stdout(
+            predicate::str::contains("Checkpoint: [").and(predicate::str::contains("(1/2 tasks)")),
+        );
+    assert_eq!(
+        fs::read_to_string(&plan).unwrap(),
+        "* DONE Task 1\n* TODO Task 2\n"
+    );
+}
+
+#[test]
+fn rollback_without_checkpoints_fails() {
+    let dir = temp_dir();
+
+    ralphctl()
+        .current_dir(dir.path())
+        .args(["rollback", "--force"])
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("error: no checkpoints found"));
+
+    fs::create_dir_all(dir.path().join(".ralphctl/checkpoints/abc/iter-1")).unwrap();
+    ralphctl()
+        .current_dir(dir.path())
+        .args(["rollback", "--to", "4", "--force"])
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains(
+            "error: no checkpoint for iteration 4 in run abc",
+        ));
+}
+
+#[test]
+fn clean_all_removes_checkpoints_and_archive_ignores_them() {
+    let dir = temp_dir();
+    let path = setup_project(&dir);
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .args(["run", "--max-iterations", "3", "--tag", "first"])
+        .assert()
+        .success();
+
+    ralphctl()
+        .current_dir(dir.path())
+        .args(["archive", "--force"])
+        .assert()
+        .success();
+    assert!(dir
+        .path()
+        .join(".ralphctl/checkpoints/first/iter-1")
+        .exists());
+    let archives: Vec<_> = fs::read_dir(dir.path().join(".ralphctl/archive"))
+        .unwrap()
+        .collect();
+    assert_eq!(archives.len(), 1);
+    let archive = archives[0].as_ref().unwrap().path();
+    assert!(!archive.join("checkpoints").exists());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .args(["clean", "--force", "--all"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Removed .ralphctl/checkpoints."));
+    assert!(!dir.path().join(".ralphctl/checkpoints").exists());
+}
@@ -3,7 +3,10 @@
 //! These tests use mock scripts to simulate claude CLI output, allowing us to
 //! test the run command's behavior without requiring the actual claude binary.
 
+mod common;
+
 use assert_cmd::Command;
+use common::{exit_code_for, help_exit_codes, serve_once};
 use predicates::prelude::*;
 use std::fs;
 use std::os::unix::fs::PermissionsExt;
@@ -48,6 +51,24 @@ fn create_mock_claude(dir: &TempDir, output: &str) -> std::path::PathBuf {
     bin_dir
 }
 
+/// Create a mock claude from a raw shell script body.
+///
+/// Use this when the mock needs behavior beyond printing fixed output
+/// (sleeping, reading state, writing files).
+fn create_mock_claude_script(dir: &TempDir, body: &str) -> std::path::PathBuf {
+    let bin_dir = dir.path().join("bin");
+    fs::create_dir_all(&bin_dir).unwrap();
+
+    let script_path = bin_dir.join("claude");
+    fs::write(&script_path, format!("#!/bin/sh\n{}\n", body)).unwrap();
+
+    let mut perms = fs::metadata(&script_path).unwrap().permissions();
+    perms.set_mode(0o755);
+    fs::set_permissions(&script_path, perms).unwrap();
+
+    bin_dir
+}
+
 /// Create required ralph files in the given directory.
 fn create_ralph_files(dir: &TempDir) {
     fs::write(
@@ -80,6 +101,33 @@ fn run_fails_without_required_files() {
         .stderr(predicate::str::contains("missing required files"));
 }
 
+#[test]
+fn run_missing_files_error_names_absolute_paths() {
+    let dir = temp_dir();
+    fs::write(dir.path().join("PROMPT.md"), "# Prompt\n").unwrap();
+    let root = dir.path().canonicalize().unwrap();
+
+    let output = ralphctl()
+        .current_dir(dir.path())
+        .arg("run")
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    for name in ["SPEC.md", "IMPLEMENTATION_PLAN.md"] {
+        let abs = root.join(name);
+        assert!(abs.is_absolute());
+        assert!(
+            stderr.contains(&format!("{} ({})", name, abs.display())),
+            "stderr: {}",
+            stderr
+        );
+    }
+    assert!(!stderr.contains("PROMPT.md"));
+    assert!(stderr.contains("run 'ralphctl init' to create them"));
+}
+
 #[test]
 fn run_fails_without_prompt_md() {
     let dir = temp_dir();
@@ -228,6 +276,230 @@ fn run_creates_ralph_log() {
     );
 }
 
+#[test]
+fn run_durable_log_numbers_entries_across_runs() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+    let bin_dir = create_mock_claude(&dir, "Task output here.\n[[RALPH:DONE]]\n");
+    let path = format!("{}:/usr/bin", bin_dir.display());
+
+    for _ in 0..2 {
+        ralphctl()
+            .current_dir(dir.path())
+            .env("PATH", &path)
+            .args(["run", "--max-iterations", "1", "--durable-log"])
+            .assert()
+            .success();
+    }
+
+    let log = fs::read_to_string(dir.path().join("ralph.log")).unwrap();
+    assert!(log.contains("=== Iteration 1 starting ==="));
+    assert!(log.contains("[entry 1]"), "log: {}", log);
+    assert!(log.contains("[entry 2]"), "log: {}", log);
+}
+
+#[test]
+fn run_continues_when_ralph_log_cannot_be_written() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+    // A directory in place of the log makes every append fail
+    fs::create_dir(dir.path().join("ralph.log")).unwrap();
+    let bin_dir = create_mock_claude(&dir, "Task output here.\n[[RALPH:DONE]]\n");
+    let path = format!("{}:/usr/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .args(["run", "--max-iterations", "1"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "warning: failed to open ralph.log",
+        ));
+}
+
+#[test]
+fn run_summary_only_hides_claude_output_but_logs_it() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+    let bin_dir = create_mock_claude_script(
+        &dir,
+        "echo 'claude says hello'\necho 'claude warning' >&2\necho '[[RALPH:DONE]]'",
+    );
+    let path = format!("{}:/usr/bin:/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .args(["run", "--max-iterations", "1", "--summary-only"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("=== Iteration 1 starting ==="))
+        .stdout(predicate::str::contains("=== Loop complete ==="))
+        .stdout(predicate::str::contains("claude says hello").not())
+        .stdout(predicate::str::contains("[[RALPH:DONE]]").not())
+        .stdout(predicate::str::contains("░").not())
+        .stderr(predicate::str::contains("claude warning").not());
+
+    let log = fs::read_to_string(dir.path().join("ralph.log")).unwrap();
+    assert!(log.contains("claude says hello"));
+    assert!(log.contains("[[RALPH:DONE]]"));
+}
+
+#[test]
+fn run_trace_prints_and_logs_claude_command() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+    let bin_dir = create_mock_claude(&dir, "[[RALPH:DONE]]\n");
+    let path = format!("{}:/usr/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .args(["run", "--max-iterations", "1", "--trace", "--model", "opus"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "+ claude -p --dangerously-skip-permissions --model opus\n",
+        ));
+
+    let log = fs::read_to_string(dir.path().join("ralph.log")).unwrap();
+    assert!(log.contains("+ claude -p --dangerously-skip-permissions --model opus\n"));
+}
+
+#[test]
+fn run_short_model_flag_reaches_claude() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+    let bin_dir = create_mock_claude_script(&dir, "echo \"$@\" > args.txt\necho '[[RALPH:DONE]]'");
+    let path = format!("{}:/usr/bin:/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .args(["run", "-m", "opus", "--max-iterations", "1"])
+        .assert()
+        .success();
+
+    let args = fs::read_to_string(dir.path().join("args.txt")).unwrap();
+    assert_eq!(args, "-p --dangerously-skip-permissions --model opus\n");
+}
+
+#[test]
+fn interview_system_prompt_file_replaces_builtin_prompt() {
+    let dir = temp_dir();
+    let bin_dir = create_mock_claude_script(&dir, "printf '%s\\n' \"$@\" > args.txt");
+    let path = format!("{}:/usr/bin:/bin", bin_dir.display());
+    fs::write(dir.path().join("custom.md"), "Team prompt for {cwd}\n").unwrap();
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .args(["interview", "--system-prompt-file", "custom.md"])
+        .assert()
+        .success();
+
+    let args = fs::read_to_string(dir.path().join("args.txt")).unwrap();
+    let cwd = dir.path().canonicalize().unwrap();
+    assert!(args.contains(&format!("Team prompt for {}\n", cwd.display())));
+    assert!(!args.contains("Ralph Loop System Context"));
+}
+
+#[test]
+fn interview_system_prompt_file_must_exist() {
+    let dir = temp_dir();
+    let bin_dir = create_mock_claude_script(&dir, "exit 0");
+    let path = format!("{}:/usr/bin:/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .args(["interview", "--system-prompt-file", "missing.md"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "failed to read system prompt file missing.md",
+        ));
+}
+
+#[test]
+fn interview_spec_file_sets_spec_write_path() {
+    let dir = temp_dir();
+    let bin_dir = create_mock_claude_script(&dir, "printf '%s\\n' \"$@\" > args.txt");
+    let path = format!("{}:/usr/bin:/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .args(["interview", "--spec-file", "docs/SPEC.md"])
+        .assert()
+        .success();
+
+    let args = fs::read_to_string(dir.path().join("args.txt")).unwrap();
+    let cwd = dir.path().canonicalize().unwrap();
+    assert!(args.contains(&format!("Write `{}/docs/SPEC.md`", cwd.display())));
+    assert!(args.contains("Interview me to create docs/SPEC.md and IMPLEMENTATION_PLAN.md"));
+}
+
+#[test]
+fn run_spec_file_validates_against_that_path() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+    fs::create_dir_all(dir.path().join("docs")).unwrap();
+    fs::rename(dir.path().join("SPEC.md"), dir.path().join("docs/SPEC.md")).unwrap();
+    let bin_dir = create_mock_claude(&dir, "[[RALPH:DONE]]\n");
+    let path = format!("{}:/usr/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .args(["run", "--max-iterations", "1"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("missing required files: SPEC.md"));
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .args([
+            "run",
+            "--spec-file",
+            "docs/SPEC.md",
+            "--max-iterations",
+            "1",
+        ])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Project:    Test Spec"));
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .args([
+            "run",
+            "--spec-file",
+            "docs/MISSING.md",
+            "--max-iterations",
+            "1",
+        ])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "missing required files: docs/MISSING.md",
+        ));
+}
+
+#[test]
+fn short_model_flag_parses_on_run_reverse_and_interview() {
+    for command in ["run", "reverse", "interview"] {
+        ralphctl()
+            .args([command, "-m", "opus", "--help"])
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("-m, --model <MODEL>"));
+    }
+}
+
 #[test]
 fn run_respects_max_iterations() {
     let dir = temp_dir();
@@ -760,3 +1032,3076 @@ fn run_progress_shows_correct_count() {
         // Should show 0/2 tasks (0%)
         .stdout(predicate::str::contains("0/2 tasks"));
 }
+
+#[test]
+fn run_help_shows_pause_every_flag() {
+    ralphctl()
+        .arg("run")
+        .arg("--help")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("--pause-every"));
+}
+
+#[test]
+fn run_pause_every_rejects_zero() {
+    ralphctl()
+        .arg("run")
+        .arg("--pause-every")
+        .arg("0")
+        .assert()
+        .failure();
+}
+
+#[test]
+fn run_pause_every_prompts_only_on_nth_iteration() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+
+    let mock_output = "Task completed.\n[[RALPH:CONTINUE]]\n";
+    let bin_dir = create_mock_claude(&dir, mock_output);
+
+    let path = format!("{}:/usr/bin", bin_dir.display());
+
+    // 4 iterations with --pause-every 2 should prompt after iterations 2 and 4 only
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .arg("run")
+        .arg("--max-iterations")
+        .arg("4")
+        .arg("--pause-every")
+        .arg("2")
+        .write_stdin("y\ny\n")
+        .assert()
+        .code(2)
+        .stderr(predicate::function(|stderr: &str| {
+            stderr.matches("Continue? [Y/n]").count() == 2
+        }));
+}
+
+#[test]
+fn run_prompts_each_take_one_line_of_typed_input() {
+    use std::io::{Read, Write};
+    use std::process::Stdio;
+    use std::time::{Duration, Instant};
+
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+
+    let bin_dir = create_mock_claude(&dir, "Working without a marker.\n");
+    let path = format!("{}:/usr/bin", bin_dir.display());
+
+    // Both answers arrive at once and stdin stays open, as when pasted into
+    // a terminal; the second must still reach the second prompt
+    let mut child = std::process::Command::new(assert_cmd::cargo::cargo_bin!("ralphctl"))
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .args(["run", "--max-iterations", "5"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    stdin.write_all(b"c\ns\n").unwrap();
+
+    let deadline = Instant::now() + Duration::from_secs(10);
+    let status = loop {
+        if let Some(status) = child.try_wait().unwrap() {
+            break Some(status);
+        }
+        if Instant::now() > deadline {
+            child.kill().unwrap();
+            break None;
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    };
+    drop(stdin);
+    let mut stdout = String::new();
+    child
+        .stdout
+        .take()
+        .unwrap()
+        .read_to_string(&mut stdout)
+        .unwrap();
+
+    assert!(status.is_some(), "second prompt never saw its answer");
+    assert!(stdout.contains("=== Iteration 2 starting ==="));
+    assert!(!stdout.contains("=== Iteration 3 starting ==="));
+    assert!(stdout.contains("Stopped by user"));
+}
+
+#[test]
+fn run_pause_every_stop_halts_on_even_iteration() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+
+    let mock_output = "Task completed.\n[[RALPH:CONTINUE]]\n";
+    let bin_dir = create_mock_claude(&dir, mock_output);
+
+    let path = format!("{}:/usr/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .arg("run")
+        .arg("--max-iterations")
+        .arg("4")
+        .arg("--pause-every")
+        .arg("2")
+        .write_stdin("n\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("=== Iteration 2 starting ==="))
+        .stdout(predicate::str::contains("=== Iteration 3 starting ===").not())
+        .stdout(predicate::str::contains("Stopped by user"));
+}
+
+#[test]
+fn run_prints_summary_on_done() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+
+    let mock_output = "Task completed.\n[[RALPH:DONE]]\n";
+    let bin_dir = create_mock_claude(&dir, mock_output);
+
+    let path = format!("{}:/usr/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .arg("run")
+        .arg("--max-iterations")
+        .arg("3")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("=== Run summary ==="))
+        .stderr(predicate::str::contains("Outcome:    done"))
+        .stderr(predicate::str::contains("Iterations: 1"))
+        .stderr(predicate::str::contains("Tasks:      0/2 (0%)"))
+        .stderr(predicate::str::contains("Elapsed:"))
+        .stderr(predicate::str::contains("ralph.log"));
+}
+
+#[test]
+fn run_prints_summary_on_blocked() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+
+    let mock_output = "[[RALPH:BLOCKED:missing API key]]\n";
+    let bin_dir = create_mock_claude(&dir, mock_output);
+
+    let path = format!("{}:/usr/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .arg("run")
+        .arg("--max-iterations")
+        .arg("3")
+        .assert()
+        .code(3)
+        .stderr(predicate::str::contains("=== Run summary ==="))
+        .stderr(predicate::str::contains(
+            "Outcome:    blocked (missing API key)",
+        ))
+        .stderr(predicate::str::contains("Iterations: 1"));
+}
+
+#[test]
+fn run_prints_summary_on_max_iterations() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+
+    let mock_output = "Task completed.\n[[RALPH:CONTINUE]]\n";
+    let bin_dir = create_mock_claude(&dir, mock_output);
+
+    let path = format!("{}:/usr/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .arg("run")
+        .arg("--max-iterations")
+        .arg("2")
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("=== Run summary ==="))
+        .stderr(predicate::str::contains(
+            "Outcome:    max iterations reached",
+        ))
+        .stderr(predicate::str::contains("Iterations: 2"));
+}
+
+#[test]
+fn run_prints_summary_when_stopped_by_user() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+
+    let mock_output = "No signal here.\n";
+    let bin_dir = create_mock_claude(&dir, mock_output);
+
+    let path = format!("{}:/usr/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .arg("run")
+        .arg("--max-iterations")
+        .arg("3")
+        .write_stdin("s\n")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Outcome:    stopped by user"));
+}
+
+#[test]
+fn run_fresh_log_rotates_previous_log() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+    fs::write(dir.path().join("ralph.log"), "Old attempt output\n").unwrap();
+
+    let bin_dir = create_mock_claude(&dir, "New attempt output.\n[[RALPH:DONE]]\n");
+    let path = format!("{}:/usr/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .args(["run", "--fresh-log", "--max-iterations", "1"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Rotated ralph.log to ralph.log."));
+
+    let log = fs::read_to_string(dir.path().join("ralph.log")).unwrap();
+    assert!(!log.contains("Old attempt output"));
+    assert!(log.contains("New attempt output"));
+
+    let rotated: Vec<_> = fs::read_dir(dir.path())
+        .unwrap()
+        .map(|e| e.unwrap().file_name().into_string().unwrap())
+        .filter(|name| name.starts_with("ralph.log."))
+        .collect();
+    assert_eq!(rotated.len(), 1, "rotated logs: {:?}", rotated);
+    assert_eq!(
+        fs::read_to_string(dir.path().join(&rotated[0])).unwrap(),
+        "Old attempt output\n"
+    );
+}
+
+#[test]
+fn run_fresh_log_conflicts_with_fresh() {
+    ralphctl()
+        .args(["run", "--fresh", "--fresh-log"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+}
+
+#[test]
+fn run_fresh_truncates_previous_log() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+    fs::write(
+        dir.path().join("ralph.log"),
+        "=== Iteration 1 starting ===\nOld attempt output\n--- end iteration 1 ---\n",
+    )
+    .unwrap();
+
+    let mock_output = "New attempt output.\n[[RALPH:DONE]]\n";
+    let bin_dir = create_mock_claude(&dir, mock_output);
+
+    let path = format!("{}:/usr/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .arg("run")
+        .arg("--fresh")
+        .arg("--max-iterations")
+        .arg("1")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Reset ralph.log"));
+
+    let log_content = fs::read_to_string(dir.path().join("ralph.log")).unwrap();
+    assert!(!log_content.contains("Old attempt output"));
+    assert!(log_content.contains("New attempt output"));
+    assert_eq!(
+        log_content.matches("=== Iteration 1 starting ===").count(),
+        1
+    );
+
+    // Spec and plan are never touched
+    let plan = fs::read_to_string(dir.path().join("IMPLEMENTATION_PLAN.md")).unwrap();
+    assert_eq!(plan, "# Plan\n\n- [ ] Task 1\n- [ ] Task 2\n");
+    let spec = fs::read_to_string(dir.path().join("SPEC.md")).unwrap();
+    assert_eq!(spec, "# Test Spec\n\nProject specification.");
+}
+
+#[test]
+fn run_without_fresh_appends_to_log() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+    fs::write(dir.path().join("ralph.log"), "Old attempt output\n").unwrap();
+
+    let bin_dir = create_mock_claude(&dir, "[[RALPH:DONE]]\n");
+    let path = format!("{}:/usr/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .arg("run")
+        .arg("--max-iterations")
+        .arg("1")
+        .assert()
+        .success();
+
+    let log_content = fs::read_to_string(dir.path().join("ralph.log")).unwrap();
+    assert!(log_content.contains("Old attempt output"));
+}
+
+#[test]
+fn run_heartbeat_prints_status_during_long_iteration() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+
+    let bin_dir = create_mock_claude_script(&dir, "sleep 2\nprintf '[[RALPH:DONE]]\\n'");
+    let path = format!("{}:/usr/bin:/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .arg("run")
+        .arg("--heartbeat")
+        .arg("1")
+        .arg("--max-iterations")
+        .arg("1")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("still running (1s elapsed)"));
+}
+
+#[test]
+fn run_pre_cmd_runs_before_each_iteration_and_is_logged() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+
+    // Claude sees the marker the pre-command left behind
+    let bin_dir = create_mock_claude_script(
+        &dir,
+        "if [ -f pre-ran ]; then printf 'saw pre\\n[[RALPH:DONE]]\\n'; fi",
+    );
+    let path = format!("{}:/usr/bin:/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .args(["run", "--pre-cmd", "echo setting up; touch pre-ran"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("saw pre"));
+
+    let log = fs::read_to_string(dir.path().join("ralph.log")).unwrap();
+    let entry =
+        "--- pre-cmd before iteration 1: echo setting up; touch pre-ran (exit code 0) ---\n\
+                 setting up\n";
+    assert!(log.contains(entry), "log: {}", log);
+    assert!(log.find(entry).unwrap() < log.find("saw pre").unwrap());
+}
+
+#[test]
+fn run_pre_cmd_failure_stops_before_spawning_claude() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+
+    let bin_dir = create_mock_claude_script(&dir, "touch claude-ran\nprintf '[[RALPH:DONE]]\\n'");
+    let path = format!("{}:/usr/bin:/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .args(["run", "--pre-cmd", "echo db unreachable >&2; exit 4"])
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("db unreachable"))
+        .stderr(predicate::str::contains(
+            "error: --pre-cmd failed before iteration 1 (exit code 4); claude was not started",
+        ))
+        .stderr(predicate::str::contains("failed (--pre-cmd failed"));
+
+    assert!(!dir.path().join("claude-ran").exists());
+    let stats = fs::read_to_string(dir.path().join(".ralphctl/stats.toml")).unwrap();
+    assert!(stats.contains("failed = 1"));
+    let log = fs::read_to_string(dir.path().join("ralph.log")).unwrap();
+    assert!(log.contains("(exit code 4) ---\ndb unreachable\n"));
+}
+
+#[test]
+fn run_on_blocked_retry_resumes_loop_when_command_succeeds() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+
+    // BLOCKED until the retry command creates the credentials file
+    let bin_dir = create_mock_claude_script(
+        &dir,
+        "if [ -f creds ]; then printf '[[RALPH:DONE]]\\n'; \
+         else printf '[[RALPH:BLOCKED:env:no creds]]\\n'; fi",
+    );
+    let path = format!("{}:/usr/bin:/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .args([
+            "run",
+            "--max-iterations",
+            "3",
+            "--on-blocked-retry",
+            "echo fixing; touch creds",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Running --on-blocked-retry (1/1): echo fixing; touch creds",
+        ))
+        .stdout(predicate::str::contains(
+            "Retry command succeeded; resuming the loop.",
+        ))
+        .stdout(predicate::str::contains("=== Loop complete ==="))
+        .stderr(predicate::str::contains("blocked [env]: no creds"));
+
+    let log = fs::read_to_string(dir.path().join("ralph.log")).unwrap();
+    assert!(log.contains(
+        "--- on-blocked-retry after iteration 1: echo fixing; touch creds (exit code 0) ---\nfixing\n"
+    ));
+    assert!(log.contains("Iteration 2 starting"));
+}
+
+#[test]
+fn run_on_blocked_retry_failure_exits_blocked() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+
+    let bin_dir = create_mock_claude(&dir, "[[RALPH:BLOCKED:no creds]]\n");
+    let path = format!("{}:/usr/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .args(["run", "--on-blocked-retry", "exit 2"])
+        .assert()
+        .code(3)
+        .stderr(predicate::str::contains(
+            "warning: --on-blocked-retry failed (exit code 2); stopping",
+        ));
+}
+
+#[test]
+fn run_max_blocked_retries_caps_retries() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+
+    let bin_dir = create_mock_claude_script(
+        &dir,
+        "echo x >> calls\nprintf '[[RALPH:BLOCKED:still stuck]]\\n'",
+    );
+    let path = format!("{}:/usr/bin:/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .args([
+            "run",
+            "--max-iterations",
+            "5",
+            "--on-blocked-retry",
+            "true",
+            "--max-blocked-retries",
+            "2",
+        ])
+        .assert()
+        .code(3)
+        .stdout(predicate::str::contains(
+            "Running --on-blocked-retry (2/2): true",
+        ))
+        .stderr(predicate::str::contains(
+            "warning: --max-blocked-retries (2) reached; stopping",
+        ));
+
+    let calls = fs::read_to_string(dir.path().join("calls")).unwrap();
+    assert_eq!(calls.lines().count(), 3);
+}
+
+#[test]
+fn run_max_blocked_retries_requires_on_blocked_retry() {
+    ralphctl()
+        .args(["run", "--max-blocked-retries", "2"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--on-blocked-retry"));
+}
+
+#[test]
+fn run_stops_when_iteration_edits_prompt() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+
+    let bin_dir = create_mock_claude_script(
+        &dir,
+        "echo 'Improved instructions.' > PROMPT.md\nprintf '[[RALPH:CONTINUE]]\\n'",
+    );
+    let path = format!("{}:/usr/bin:/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .args(["run", "--max-iterations", "3"])
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains(
+            "error: PROMPT.md was modified during iteration 1 \u{2014} refusing to continue; \
+             restore it or rerun with --allow-prompt-edits",
+        ))
+        .stderr(predicate::str::contains("prompt modified (iteration 1)"));
+
+    let log = fs::read_to_string(dir.path().join("ralph.log")).unwrap();
+    assert!(log.contains("--- PROMPT.md modified during iteration 1; run stopped ---"));
+    assert!(!log.contains("Iteration 2 starting"));
+}
+
+#[test]
+fn run_allow_prompt_edits_with_reload_uses_new_prompt() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+
+    // The first call rewrites the prompt; the next one sees the new text
+    let bin_dir = create_mock_claude_script(
+        &dir,
+        "if grep -q Rewritten; then printf '[[RALPH:DONE]]\\n'; \
+         else echo 'Rewritten prompt.' > PROMPT.md; printf '[[RALPH:CONTINUE]]\\n'; fi",
+    );
+    let path = format!("{}:/usr/bin:/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .args([
+            "run",
+            "--max-iterations",
+            "3",
+            "--allow-prompt-edits",
+            "--reload-prompt",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("=== Loop complete ==="));
+}
+
+#[test]
+fn run_allow_prompt_edits_keeps_original_prompt() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+
+    let bin_dir = create_mock_claude_script(
+        &dir,
+        "if grep -q Rewritten; then printf '[[RALPH:DONE]]\\n'; \
+         else echo 'Rewritten prompt.' > PROMPT.md; printf '[[RALPH:CONTINUE]]\\n'; fi",
+    );
+    let path = format!("{}:/usr/bin:/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .args(["run", "--max-iterations", "2", "--allow-prompt-edits"])
+        .assert()
+        .code(2);
+}
+
+#[test]
+fn run_save_prompt_writes_exact_prompt_sent() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+    fs::write(
+        dir.path().join("PROMPT.md"),
+        "Keep notes in {{SCRATCH_DIR}}.\n",
+    )
+    .unwrap();
+
+    let bin_dir = create_mock_claude_script(&dir, "cat > received.txt\nprintf '[[RALPH:DONE]]\\n'");
+    let path = format!("{}:/usr/bin:/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .args(["run", "--save-prompt", "sent.md"])
+        .assert()
+        .success();
+
+    let saved = fs::read_to_string(dir.path().join("sent.md")).unwrap();
+    let received = fs::read_to_string(dir.path().join("received.txt")).unwrap();
+    assert_eq!(saved, received);
+    assert!(!saved.contains("{{SCRATCH_DIR}}"));
+    assert!(saved.contains(".ralphctl/scratch/iter-1"));
+}
+
+#[test]
+fn run_save_prompt_fails_on_unwritable_path() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+
+    let bin_dir = create_mock_claude(&dir, "[[RALPH:DONE]]\n");
+    let path = format!("{}:/usr/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .args(["run", "--save-prompt", "missing/dir/sent.md"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "failed to write prompt to missing/dir/sent.md",
+        ));
+}
+
+#[test]
+fn run_prompt_max_bytes_refuses_long_prompt_before_spawning() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+
+    let bin_dir = create_mock_claude_script(&dir, "cat > received.txt\nprintf '[[RALPH:DONE]]\\n'");
+    let path = format!("{}:/usr/bin:/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .args(["run", "--prompt-max-bytes", "10"])
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains(
+            "prompt is 27 bytes, over --prompt-max-bytes 10",
+        ));
+
+    assert!(!dir.path().join("received.txt").exists());
+}
+
+#[test]
+fn run_reload_prompt_over_limit_fails_with_outcome() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+
+    // The first iteration grows PROMPT.md past the limit
+    let bin_dir = create_mock_claude_script(
+        &dir,
+        "cat > /dev/null\n\
+         echo 'A much longer prompt than before.' >> PROMPT.md\n\
+         printf '[[RALPH:CONTINUE]]\\n'",
+    );
+    let path = format!("{}:/usr/bin:/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .args(["run", "--prompt-max-bytes", "40", "--max-iterations", "3"])
+        .args(["--reload-prompt", "--allow-prompt-edits"])
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("over --prompt-max-bytes 40"))
+        .stderr(predicate::str::contains("Outcome:    failed"))
+        .stderr(predicate::str::contains("Iterations: 1"));
+}
+
+#[test]
+fn run_prompt_overflow_truncate_sends_first_bytes() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+
+    let bin_dir = create_mock_claude_script(&dir, "cat > received.txt\nprintf '[[RALPH:DONE]]\\n'");
+    let path = format!("{}:/usr/bin:/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .args([
+            "run",
+            "--prompt-max-bytes",
+            "13",
+            "--prompt-overflow",
+            "truncate",
+        ])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "warning: prompt is 27 bytes; sending only the first 13",
+        ));
+
+    let received = fs::read_to_string(dir.path().join("received.txt")).unwrap();
+    assert_eq!(received, "# Test Prompt");
+}
+
+#[test]
+fn run_prompt_overflow_requires_prompt_max_bytes() {
+    ralphctl()
+        .args(["run", "--prompt-overflow", "truncate"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("--prompt-max-bytes"));
+}
+
+#[test]
+fn run_check_consistency_warns_but_still_runs() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+    fs::write(
+        dir.path().join("IMPLEMENTATION_PLAN.md"),
+        "- [ ] Project specification\n- [ ] Implement Stripe webhooks\n",
+    )
+    .unwrap();
+
+    let bin_dir = create_mock_claude(&dir, "[[RALPH:DONE]]\n");
+    let path = format!("{}:/usr/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .args(["run", "--check-consistency"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "warning: task 'Implement Stripe webhooks' shares no terms with SPEC.md",
+        ));
+}
+
+#[test]
+fn run_strict_consistency_refuses_to_start() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+    fs::write(
+        dir.path().join("IMPLEMENTATION_PLAN.md"),
+        "- [ ] Implement Stripe webhooks\n",
+    )
+    .unwrap();
+
+    let bin_dir = create_mock_claude(&dir, "[[RALPH:DONE]]\n");
+    let path = format!("{}:/usr/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .args(["run", "--strict-consistency"])
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains(
+            "error: 1 plan task shares no terms with SPEC.md (--strict-consistency)",
+        ));
+
+    assert!(!dir.path().join("ralph.log").exists());
+}
+
+#[test]
+fn run_live_progress_reports_tasks_checked_mid_iteration() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+
+    let bin_dir = create_mock_claude_script(
+        &dir,
+        "printf 'Working\\n'\n\
+         sed -i 's/- \\[ \\] Task 1/- [x] Task 1/' IMPLEMENTATION_PLAN.md\n\
+         sleep 2\n\
+         printf '[[RALPH:DONE]]\\n'",
+    );
+    let path = format!("{}:/usr/bin:/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .args(["run", "--live-progress", "--max-iterations", "1"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Working"))
+        .stderr(predicate::str::contains("…plan updated:").count(1))
+        .stderr(predicate::str::contains("(1/2 tasks)"));
+}
+
+#[test]
+fn run_live_progress_quiet_when_plan_unchanged() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+
+    let bin_dir = create_mock_claude(&dir, "Working\n[[RALPH:DONE]]\n");
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", format!("{}:/usr/bin", bin_dir.display()))
+        .args(["run", "--live-progress", "--max-iterations", "1"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Working"))
+        .stderr(predicate::str::contains("plan updated").not());
+}
+
+#[test]
+fn run_color_flag_controls_dimmed_status_lines() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+
+    let bin_dir = create_mock_claude_script(&dir, "sleep 1.5\nprintf '[[RALPH:DONE]]\\n'");
+    let path = format!("{}:/usr/bin:/bin", bin_dir.display());
+    let run = |color: Option<&str>, no_color: Option<&str>| {
+        let mut cmd = ralphctl();
+        cmd.current_dir(dir.path())
+            .env("PATH", &path)
+            .env_remove("NO_COLOR")
+            .args(["run", "--heartbeat", "1", "--max-iterations", "1"]);
+        if let Some(color) = color {
+            cmd.args(["--color", color]);
+        }
+        if let Some(no_color) = no_color {
+            cmd.env("NO_COLOR", no_color);
+        }
+        cmd.assert().success()
+    };
+
+    run(Some("always"), None).stderr(predicate::str::contains(
+        "\x1b[2m…still running (1s elapsed)\x1b[0m",
+    ));
+    // Output is piped, so auto (the default) means no color
+    run(None, None).stderr(predicate::str::contains("\x1b[").not());
+    run(Some("never"), None).stderr(predicate::str::contains("\x1b[").not());
+    // --color always wins over NO_COLOR
+    run(Some("always"), Some("1")).stderr(predicate::str::contains("\x1b[2m"));
+}
+
+#[test]
+fn color_flag_rejects_unknown_value() {
+    ralphctl()
+        .args(["status", "--color", "sometimes"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid value 'sometimes'"));
+}
+
+#[test]
+fn run_without_heartbeat_prints_no_status() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+
+    let bin_dir = create_mock_claude_script(&dir, "sleep 1\nprintf '[[RALPH:DONE]]\\n'");
+    let path = format!("{}:/usr/bin:/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .arg("run")
+        .arg("--max-iterations")
+        .arg("1")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("still running").not());
+}
+
+#[test]
+fn run_with_plan_file_uses_custom_plan_for_progress() {
+    let dir = temp_dir();
+    fs::write(dir.path().join("PROMPT.md"), "# Prompt").unwrap();
+    fs::write(dir.path().join("SPEC.md"), "# Spec").unwrap();
+    fs::create_dir_all(dir.path().join("plans")).unwrap();
+    fs::write(
+        dir.path().join("plans/auth.md"),
+        "# Auth\n\n- [x] Login\n- [ ] Logout\n- [ ] Refresh\n",
+    )
+    .unwrap();
+
+    let bin_dir = create_mock_claude(&dir, "[[RALPH:DONE]]\n");
+    let path = format!("{}:/usr/bin", bin_dir.display());
+
+    // No IMPLEMENTATION_PLAN.md exists; the custom plan satisfies validation
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .arg("run")
+        .arg("--plan-file")
+        .arg("plans/auth.md")
+        .arg("--max-iterations")
+        .arg("1")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("1/3 tasks"))
+        .stderr(predicate::str::contains("Tasks:      1/3 (33%)"));
+}
+
+#[test]
+fn run_with_missing_plan_file_fails() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+
+    ralphctl()
+        .current_dir(dir.path())
+        .arg("run")
+        .arg("--plan-file")
+        .arg("plans/missing.md")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "missing required files: plans/missing.md",
+        ));
+}
+
+#[test]
+fn run_with_report_writes_iteration_records() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+
+    // First call continues, second completes
+    let bin_dir = create_mock_claude_script(
+        &dir,
+        "if [ -f called ]; then printf '[[RALPH:DONE]]\\n'; else touch called; printf 'work\\n[[RALPH:CONTINUE]]\\n'; fi",
+    );
+    let path = format!("{}:/usr/bin:/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .arg("run")
+        .arg("--report")
+        .arg("report.json")
+        .arg("--max-iterations")
+        .arg("5")
+        .assert()
+        .success();
+
+    let report = fs::read_to_string(dir.path().join("report.json")).unwrap();
+    let json: serde_json::Value = serde_json::from_str(&report).unwrap();
+    let records = json.as_array().unwrap();
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0]["iteration"], 1);
+    assert_eq!(records[0]["signal"], "continue");
+    assert_eq!(records[0]["output_bytes"], 24);
+    assert_eq!(records[1]["iteration"], 2);
+    assert_eq!(records[1]["signal"], "done");
+    assert!(records[1]["duration_ms"].is_u64());
+}
+
+#[test]
+fn run_without_report_writes_no_report() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+
+    let bin_dir = create_mock_claude(&dir, "[[RALPH:DONE]]\n");
+    let path = format!("{}:/usr/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .arg("run")
+        .arg("--max-iterations")
+        .arg("1")
+        .assert()
+        .success();
+
+    let entries: Vec<_> = fs::read_dir(dir.path())
+        .unwrap()
+        .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+        .filter(|name| name.ends_with(".json"))
+        .collect();
+    assert!(entries.is_empty());
+}
+
+#[test]
+fn run_with_report_written_on_blocked() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+
+    let bin_dir = create_mock_claude(&dir, "[[RALPH:BLOCKED:need input]]\n");
+    let path = format!("{}:/usr/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .arg("run")
+        .arg("--report")
+        .arg("report.json")
+        .assert()
+        .code(3);
+
+    let report = fs::read_to_string(dir.path().join("report.json")).unwrap();
+    let json: serde_json::Value = serde_json::from_str(&report).unwrap();
+    assert_eq!(json[0]["signal"], "blocked");
+}
+
+#[test]
+fn run_blocked_category_is_printed_and_reported() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+
+    let bin_dir = create_mock_claude(&dir, "[[RALPH:BLOCKED:env:DATABASE_URL not set]]\n");
+    let path = format!("{}:/usr/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .args(["run", "--report", "report.json"])
+        .assert()
+        .code(3)
+        .stderr(predicate::str::contains(
+            "blocked [env]: DATABASE_URL not set",
+        ));
+
+    let report = fs::read_to_string(dir.path().join("report.json")).unwrap();
+    let json: serde_json::Value = serde_json::from_str(&report).unwrap();
+    assert_eq!(json[0]["signal"], "blocked");
+    assert_eq!(json[0]["blocked_category"], "env");
+}
+
+#[test]
+fn run_prints_and_records_tool_summary_from_json_output() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+
+    let events = fs::read_to_string(
+        std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/tools/stream.jsonl"),
+    )
+    .unwrap();
+    let bin_dir = create_mock_claude_script(&dir, &format!("cat <<'EOF'\n{}EOF", events));
+    let path = format!("{}:/usr/bin:/bin", bin_dir.display());
+
+    // The signal sits inside a JSON string, so the iteration has none
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .args([
+            "run",
+            "--max-iterations",
+            "1",
+            "--yes",
+            "--report",
+            "report.json",
+        ])
+        .assert()
+        .code(2)
+        .stdout(predicate::str::contains(
+            "tools: Edit×2, Bash×1, Read×1, Write×1\n",
+        ));
+
+    let log = fs::read_to_string(dir.path().join("ralph.log")).unwrap();
+    assert!(log.contains("--- tools in iteration 1: Edit×2, Bash×1, Read×1, Write×1 ---"));
+
+    let report = fs::read_to_string(dir.path().join("report.json")).unwrap();
+    let json: serde_json::Value = serde_json::from_str(&report).unwrap();
+    assert_eq!(json[0]["tools"]["counts"]["Edit"], 2);
+    assert_eq!(
+        json[0]["tools"]["files_written"][1],
+        "/home/dev/widget/src/parser.rs"
+    );
+}
+
+#[test]
+fn run_flags_json_iteration_without_edits() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+
+    let events = fs::read_to_string(
+        std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/tools/read_only.jsonl"),
+    )
+    .unwrap();
+    let bin_dir = create_mock_claude_script(&dir, &format!("cat <<'EOF'\n{}EOF", events));
+    let path = format!("{}:/usr/bin:/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .args(["run", "--max-iterations", "1", "--yes"])
+        .assert()
+        .code(2)
+        .stdout(predicate::str::contains(
+            "tools: Read×2, Grep×1 (no Edit/Write calls)",
+        ));
+}
+
+#[test]
+fn run_plain_output_with_a_json_line_prints_no_tool_summary() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+
+    let bin_dir = create_mock_claude(
+        &dir,
+        "Wrote the fixture:\n{\"type\":\"assistant\",\"message\":{\"content\":[]}}\n[[RALPH:DONE]]\n",
+    );
+    let path = format!("{}:/usr/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .arg("run")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("tools:").not());
+}
+
+#[test]
+fn run_plain_text_output_prints_no_tool_summary() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+
+    let bin_dir = create_mock_claude(&dir, "Did it.\n[[RALPH:DONE]]\n");
+    let path = format!("{}:/usr/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .args(["run", "--report", "report.json"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("tools:").not());
+
+    let log = fs::read_to_string(dir.path().join("ralph.log")).unwrap();
+    assert!(!log.contains("--- tools"));
+    let report = fs::read_to_string(dir.path().join("report.json")).unwrap();
+    let json: serde_json::Value = serde_json::from_str(&report).unwrap();
+    assert!(json[0].get("tools").is_none());
+}
+
+#[test]
+fn run_reports_tasks_completed_this_session_on_done() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+    fs::write(
+        dir.path().join("IMPLEMENTATION_PLAN.md"),
+        "# Plan\n\n- [x] Setup\n- [ ] Task 1\n- [ ] Task 2\n",
+    )
+    .unwrap();
+
+    // Each call checks off the first open task; DONE once none remain
+    let bin_dir = create_mock_claude_script(
+        &dir,
+        "sed -i '0,/- \\[ \\]/s//- [x]/' IMPLEMENTATION_PLAN.md\n\
+         if grep -q -- '- \\[ \\]' IMPLEMENTATION_PLAN.md; then printf '[[RALPH:CONTINUE]]\\n'; else printf '[[RALPH:DONE]]\\n'; fi",
+    );
+    let path = format!("{}:/usr/bin:/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .arg("run")
+        .arg("--max-iterations")
+        .arg("5")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "completed 2 tasks this session (1→3)",
+        ));
+}
+
+#[test]
+fn run_reports_tasks_completed_this_session_at_max_iterations() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+
+    let bin_dir = create_mock_claude_script(
+        &dir,
+        "sed -i '0,/- \\[ \\]/s//- [x]/' IMPLEMENTATION_PLAN.md\nprintf '[[RALPH:CONTINUE]]\\n'",
+    );
+    let path = format!("{}:/usr/bin:/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .arg("run")
+        .arg("--max-iterations")
+        .arg("1")
+        .assert()
+        .code(2)
+        .stdout(predicate::str::contains(
+            "completed 1 task this session (0→1)",
+        ));
+}
+
+#[test]
+fn run_detects_signal_split_across_writes_without_newline() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+
+    // The marker is flushed in two pieces with no newline anywhere
+    let bin_dir = create_mock_claude_script(&dir, "printf '[[RALPH:'\nsleep 0.2\nprintf 'DONE]]'");
+    let path = format!("{}:/usr/bin:/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .arg("run")
+        .arg("--max-iterations")
+        .arg("1")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("=== Loop complete ==="));
+}
+
+#[test]
+fn run_fails_when_plan_has_no_tasks() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+    fs::write(
+        dir.path().join("IMPLEMENTATION_PLAN.md"),
+        "# Plan\n\nTODO: fill this in\n",
+    )
+    .unwrap();
+
+    // claude is never invoked, so no mock is needed
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", "/usr/bin")
+        .arg("run")
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains(
+            "error: IMPLEMENTATION_PLAN.md has no tasks",
+        ))
+        .stdout(predicate::str::contains("Iteration").not());
+}
+
+#[test]
+fn run_with_allow_empty_plan_proceeds() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+    fs::write(dir.path().join("IMPLEMENTATION_PLAN.md"), "# Plan\n").unwrap();
+
+    let bin_dir = create_mock_claude(&dir, "[[RALPH:DONE]]\n");
+    let path = format!("{}:/usr/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .arg("run")
+        .arg("--allow-empty-plan")
+        .arg("--max-iterations")
+        .arg("1")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "warning: IMPLEMENTATION_PLAN.md has no tasks",
+        ))
+        .stdout(predicate::str::contains("=== Loop complete ==="));
+}
+
+#[test]
+fn run_removes_lock_on_exit() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+
+    // The lock exists while claude runs
+    let bin_dir = create_mock_claude_script(
+        &dir,
+        "test -f .ralphctl/run.lock && printf '[[RALPH:BLOCKED:lock present]]\\n'",
+    );
+    let path = format!("{}:/usr/bin:/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .arg("run")
+        .assert()
+        .code(3)
+        .stderr(predicate::str::contains("blocked: lock present"));
+
+    assert!(!dir.path().join(".ralphctl/run.lock").exists());
+}
+
+#[test]
+fn run_model_per_phase_selects_model_for_active_phase() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+    fs::write(
+        dir.path().join("IMPLEMENTATION_PLAN.md"),
+        "# Plan\n\n## Phase 1: Setup\n- [x] Scaffold\n\n## Phase 2: Core\n- [ ] Engine\n",
+    )
+    .unwrap();
+
+    let bin_dir = create_mock_claude_script(&dir, "echo \"args: $*\"\nprintf '[[RALPH:DONE]]\\n'");
+    let path = format!("{}:/usr/bin:/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .arg("run")
+        .arg("--model")
+        .arg("sonnet")
+        .arg("--model-per-phase")
+        .arg("Phase 1=haiku")
+        .arg("--model-per-phase")
+        .arg("Phase 2=opus")
+        .arg("--max-iterations")
+        .arg("1")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Using model opus for phase: Phase 2: Core",
+        ))
+        .stdout(predicate::str::contains("--model opus"));
+}
+
+#[test]
+fn run_model_per_phase_falls_back_to_model_when_unmapped() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+    fs::write(
+        dir.path().join("IMPLEMENTATION_PLAN.md"),
+        "# Plan\n\n## Phase 3: Polish\n- [ ] Docs\n",
+    )
+    .unwrap();
+
+    let bin_dir = create_mock_claude_script(&dir, "echo \"args: $*\"\nprintf '[[RALPH:DONE]]\\n'");
+    let path = format!("{}:/usr/bin:/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .arg("run")
+        .arg("--model")
+        .arg("sonnet")
+        .arg("--model-per-phase")
+        .arg("Phase 1=haiku")
+        .arg("--max-iterations")
+        .arg("1")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("--model sonnet"))
+        .stdout(predicate::str::contains("Using model").not());
+}
+
+#[test]
+fn run_model_per_phase_rejects_malformed_mapping() {
+    ralphctl()
+        .arg("run")
+        .arg("--model-per-phase")
+        .arg("opus")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("expected HEADING=MODEL"));
+}
+
+#[test]
+fn run_preflight_fails_with_login_guidance() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+
+    // Simulates an installed but unauthenticated claude
+    let bin_dir = create_mock_claude_script(
+        &dir,
+        "echo 'Invalid API key · Please run /login' >&2\nexit 1",
+    );
+    let path = format!("{}:/usr/bin:/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .arg("run")
+        .arg("--preflight")
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains(
+            "error: claude preflight failed (exit code 1): Invalid API key",
+        ))
+        .stderr(predicate::str::contains("claude login"))
+        .stdout(predicate::str::contains("Iteration").not());
+}
+
+#[test]
+fn run_preflight_passes_then_runs_loop() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+
+    let bin_dir = create_mock_claude(&dir, "[[RALPH:DONE]]\n");
+    let path = format!("{}:/usr/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .arg("run")
+        .arg("--preflight")
+        .arg("--max-iterations")
+        .arg("1")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("=== Loop complete ==="));
+}
+
+#[test]
+fn run_exports_scratch_dir_per_iteration() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+    fs::write(
+        dir.path().join("PROMPT.md"),
+        "Keep notes in {{SCRATCH_DIR}}.",
+    )
+    .unwrap();
+
+    // Echo the env var and prompt, leave a scratch file, finish on the second call
+    let bin_dir = create_mock_claude_script(
+        &dir,
+        "echo \"scratch=$RALPH_SCRATCH_DIR\"\n\
+         echo \"prompt=$(cat)\"\n\
+         touch \"$RALPH_SCRATCH_DIR/notes.txt\"\n\
+         if [ -f called ]; then printf '[[RALPH:DONE]]\\n'; else touch called; printf '[[RALPH:CONTINUE]]\\n'; fi",
+    );
+    let path = format!("{}:/usr/bin:/bin", bin_dir.display());
+
+    let scratch = dir.path().canonicalize().unwrap().join(".ralphctl/scratch");
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .arg("run")
+        .arg("--max-iterations")
+        .arg("5")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "scratch={}",
+            scratch.join("iter-1").display()
+        )))
+        .stdout(predicate::str::contains(format!(
+            "prompt=Keep notes in {}.",
+            scratch.join("iter-2").display()
+        )));
+
+    assert!(scratch.join("iter-1/notes.txt").exists());
+    assert!(scratch.join("iter-2/notes.txt").exists());
+}
+
+#[test]
+fn run_prunes_scratch_from_previous_runs() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+    let scratch = dir.path().join(".ralphctl/scratch");
+    fs::create_dir_all(scratch.join("iter-7")).unwrap();
+    fs::create_dir_all(scratch.join("iter-1")).unwrap();
+    fs::write(scratch.join("iter-1/old.txt"), "stale").unwrap();
+
+    let bin_dir = create_mock_claude(&dir, "[[RALPH:DONE]]\n");
+    let path = format!("{}:/usr/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .arg("run")
+        .arg("--max-iterations")
+        .arg("1")
+        .assert()
+        .success();
+
+    assert!(!scratch.join("iter-7").exists());
+    assert!(scratch.join("iter-1").exists());
+    assert!(!scratch.join("iter-1/old.txt").exists());
+}
+
+#[test]
+fn run_keep_scratch_limits_directories() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+
+    let bin_dir = create_mock_claude(&dir, "[[RALPH:CONTINUE]]\n");
+    let path = format!("{}:/usr/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .arg("run")
+        .arg("--keep-scratch")
+        .arg("2")
+        .arg("--max-iterations")
+        .arg("4")
+        .assert()
+        .code(2);
+
+    let scratch = dir.path().join(".ralphctl/scratch");
+    assert!(!scratch.join("iter-1").exists());
+    assert!(!scratch.join("iter-2").exists());
+    assert!(scratch.join("iter-3").exists());
+    assert!(scratch.join("iter-4").exists());
+}
+
+#[test]
+fn run_stop_file_stops_before_next_iteration() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+
+    // The first iteration creates the stop file; the loop must not start a second
+    let bin_dir = create_mock_claude_script(
+        &dir,
+        "echo call >> calls.txt\ntouch STOP\nprintf '[[RALPH:CONTINUE]]\\n'",
+    );
+    let path = format!("{}:/usr/bin:/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .arg("run")
+        .arg("--stop-file")
+        .arg("STOP")
+        .arg("--max-iterations")
+        .arg("5")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Stopped via stop-file (STOP)."))
+        .stdout(predicate::str::contains("=== Iteration 2 starting ===").not());
+
+    assert_eq!(
+        fs::read_to_string(dir.path().join("calls.txt")).unwrap(),
+        "call\n"
+    );
+    assert!(!dir.path().join("STOP").exists());
+}
+
+#[test]
+fn run_stop_file_present_at_start_runs_nothing() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+    fs::write(dir.path().join("STOP"), "").unwrap();
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", "/usr/bin")
+        .arg("run")
+        .arg("--stop-file")
+        .arg("STOP")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Stopped via stop-file"))
+        .stdout(predicate::str::contains("Iteration").not());
+}
+
+/// Extract the run ID from an iteration header like `=== ... === (run 1a2b3c4d)`.
+fn run_id_from_stdout(stdout: &str) -> String {
+    let start = stdout.find("(run ").expect("iteration header has a run ID") + "(run ".len();
+    stdout[start..start + 8].to_string()
+}
+
+#[test]
+fn run_id_appears_in_stdout_log_and_child_env() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+
+    let bin_dir = create_mock_claude_script(
+        &dir,
+        "echo \"child-run-id=$RALPH_RUN_ID\"\nprintf '[[RALPH:DONE]]\\n'",
+    );
+    let path = format!("{}:/usr/bin:/bin", bin_dir.display());
+
+    let output = ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .arg("run")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let run_id = run_id_from_stdout(&stdout);
+    assert!(run_id.chars().all(|c| c.is_ascii_hexdigit()));
+    assert!(stdout.contains(&format!("child-run-id={}", run_id)));
+
+    let log = fs::read_to_string(dir.path().join("ralph.log")).unwrap();
+    assert!(log.contains(&format!("=== Run {} (run) started", run_id)));
+    assert!(log.contains(&format!("=== Iteration 1 starting === (run {})", run_id)));
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains(&format!("Run ID:     {}", run_id)));
+
+    let state = fs::read_to_string(dir.path().join(".ralphctl/state.json")).unwrap();
+    assert!(state.contains(&run_id));
+}
+
+#[test]
+fn run_split_logs_writes_file_per_iteration() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+
+    let bin_dir = create_mock_claude_script(
+        &dir,
+        "if [ -f called ]; then printf 'second output\\n[[RALPH:DONE]]\\n'; \
+         else touch called; printf 'first output\\n[[RALPH:CONTINUE]]\\n'; fi",
+    );
+    let path = format!("{}:/usr/bin:/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .arg("run")
+        .arg("--split-logs")
+        .assert()
+        .success();
+
+    let logs = dir.path().join(".ralphctl/logs");
+    assert_eq!(
+        fs::read_to_string(logs.join("iteration-1.log")).unwrap(),
+        "first output\n[[RALPH:CONTINUE]]\n"
+    );
+    assert_eq!(
+        fs::read_to_string(logs.join("iteration-2.log")).unwrap(),
+        "second output\n[[RALPH:DONE]]\n"
+    );
+    assert!(!logs.join("iteration-3.log").exists());
+
+    // ralph.log still gets the combined output
+    let log = fs::read_to_string(dir.path().join("ralph.log")).unwrap();
+    assert!(log.contains("first output") && log.contains("second output"));
+}
+
+#[test]
+fn run_without_split_logs_writes_no_iteration_files() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+
+    let bin_dir = create_mock_claude(&dir, "[[RALPH:DONE]]\n");
+    let path = format!("{}:/usr/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .arg("run")
+        .assert()
+        .success();
+
+    assert!(!dir.path().join(".ralphctl/logs").exists());
+}
+
+#[test]
+fn run_interactive_signals_non_tty_keeps_strict_detection() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+
+    let bin_dir = create_mock_claude(&dir, "All done [[RALPH:DONE]] for now\n");
+    let path = format!("{}:/usr/bin", bin_dir.display());
+
+    // stdin is not a terminal, so the inline marker is not offered
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .arg("run")
+        .arg("--interactive-signals")
+        .arg("--max-iterations")
+        .arg("1")
+        .write_stdin("\n")
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("mentioned inline").not())
+        .stderr(predicate::str::contains("no [[RALPH:DONE]]"))
+        .stdout(predicate::str::contains("Loop complete").not());
+}
+
+#[test]
+fn run_tag_labels_log_state_and_summary() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+
+    let bin_dir = create_mock_claude(&dir, "[[RALPH:DONE]]\n");
+    let path = format!("{}:/usr/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .arg("run")
+        .arg("--tag")
+        .arg("nightly-1")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "=== Iteration 1 starting === (run nightly-1)",
+        ))
+        .stderr(predicate::str::contains("Run ID:     nightly-1"));
+
+    let log = fs::read_to_string(dir.path().join("ralph.log")).unwrap();
+    assert!(log.contains("=== Iteration 1 starting === (run nightly-1)"));
+    let state = fs::read_to_string(dir.path().join(".ralphctl/state.json")).unwrap();
+    assert!(state.contains(r#""last_run_id": "nightly-1""#));
+}
+
+#[test]
+fn run_session_id_alias_and_invalid_tag() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+
+    let bin_dir = create_mock_claude(&dir, "[[RALPH:DONE]]\n");
+    let path = format!("{}:/usr/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .arg("run")
+        .arg("--session-id")
+        .arg("abc123")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("(run abc123)"));
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .arg("run")
+        .arg("--tag")
+        .arg("has space")
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("invalid character"));
+}
+
+#[test]
+fn run_dump_config_prints_sources_and_exits() {
+    let dir = temp_dir();
+
+    // No ralph files and no claude: nothing beyond printing should happen
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", "/usr/bin")
+        .env("RALPHCTL_TEMPLATE_URL", "file:///tmp/templates")
+        .arg("run")
+        .arg("--model")
+        .arg("opus")
+        .arg("--dump-config")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "model           opus  (flag --model)",
+        ))
+        .stdout(predicate::str::contains("max-iterations  50  (auto)"))
+        .stdout(predicate::str::contains(
+            "template-base   file:///tmp/templates  (env RALPHCTL_TEMPLATE_URL)",
+        ))
+        .stdout(predicate::str::contains("Iteration").not());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .arg("--dump-config")
+        .arg("run")
+        .arg("--max-iterations")
+        .arg("7")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "max-iterations  7  (flag --max-iterations)",
+        ));
+}
+
+#[test]
+fn run_derives_max_iterations_from_open_tasks() {
+    let dir = temp_dir();
+    create_ralph_files(&dir); // 2 open tasks → 2 * 2 + 5 = 9
+
+    let bin_dir = create_mock_claude(&dir, "[[RALPH:CONTINUE]]\n");
+    let path = format!("{}:/usr/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .arg("run")
+        .assert()
+        .code(2)
+        .stdout(predicate::str::contains(
+            "Max iterations: 9 (auto-derived from open tasks)",
+        ))
+        .stdout(predicate::str::contains("=== Iteration 9 starting ==="))
+        .stdout(predicate::str::contains("=== Iteration 10 starting ===").not());
+}
+
+#[test]
+fn run_explicit_max_iterations_suppresses_auto_note() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+
+    let bin_dir = create_mock_claude(&dir, "[[RALPH:CONTINUE]]\n");
+    let path = format!("{}:/usr/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .arg("run")
+        .arg("--max-iterations")
+        .arg("1")
+        .assert()
+        .code(2)
+        .stdout(predicate::str::contains("auto-derived").not());
+}
+
+#[test]
+fn run_help_shows_auto_max_iterations_default() {
+    ralphctl()
+        .arg("run")
+        .arg("--help")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("default: auto (≤50)"));
+}
+
+#[test]
+fn run_webhook_failure_does_not_fail_run() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+
+    let bin_dir = create_mock_claude(&dir, "[[RALPH:DONE]]\n");
+    let path = format!("{}:/usr/bin", bin_dir.display());
+
+    // Nothing listens on a port we just released
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .arg("run")
+        .arg("--webhook")
+        .arg(format!("http://127.0.0.1:{}/hook", port))
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("warning: webhook POST to"));
+}
+
+#[test]
+fn post_webhook_sends_json() {
+    let (base_url, handle) = serve_once("200 OK", "");
+    let payload = serde_json::json!({ "outcome": "done", "iterations": 2 });
+    ralphctl::run::post_webhook(&format!("{}/hook", base_url), &payload).unwrap();
+
+    let request = handle.join().unwrap();
+    assert!(request.starts_with("POST /hook "));
+    assert!(request
+        .to_lowercase()
+        .contains("content-type: application/json"));
+    let body = request.split("\r\n\r\n").nth(1).unwrap();
+    assert_eq!(
+        serde_json::from_str::<serde_json::Value>(body).unwrap(),
+        payload
+    );
+}
+
+#[test]
+fn post_webhook_reports_http_error() {
+    let (base_url, handle) = serve_once("500 Internal Server Error", "");
+    let err = ralphctl::run::post_webhook(&format!("{}/hook", base_url), &serde_json::json!({}))
+        .unwrap_err();
+    handle.join().unwrap();
+    assert!(format!("{:#}", err).contains("HTTP 500"));
+}
+
+#[test]
+fn run_tui_without_tty_falls_back_to_plain_output() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+
+    let bin_dir = create_mock_claude(&dir, "working on it\n[[RALPH:DONE]]\n");
+    let path = format!("{}:/usr/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .arg("run")
+        .arg("--tui")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("=== Iteration 1 starting ==="))
+        .stdout(predicate::str::contains("working on it"))
+        .stdout(predicate::str::contains("\x1b[?1049h").not());
+
+    let log = fs::read_to_string(dir.path().join("ralph.log")).unwrap();
+    assert!(log.contains("working on it"));
+}
+
+#[test]
+fn run_tui_conflicts_with_heartbeat() {
+    ralphctl()
+        .arg("run")
+        .arg("--tui")
+        .arg("--heartbeat")
+        .arg("5")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+}
+
+#[test]
+fn run_passes_iteration_budget_env_to_claude() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+
+    // The first iteration checks off a task, so the second sees new counts
+    let bin_dir = create_mock_claude_script(
+        &dir,
+        "echo \"budget $RALPH_ITERATION/$RALPH_MAX_ITERATIONS tasks $RALPH_TASKS_COMPLETED/$RALPH_TASKS_TOTAL\"; \
+         if [ -f called ]; then echo '[[RALPH:DONE]]'; \
+         else touch called; sed -i 's/- \\[ \\] Task 1/- [x] Task 1/' IMPLEMENTATION_PLAN.md; \
+         echo '[[RALPH:CONTINUE]]'; fi",
+    );
+    let path = format!("{}:/usr/bin:/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .arg("run")
+        .arg("--max-iterations")
+        .arg("5")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("budget 1/5 tasks 0/2"))
+        .stdout(predicate::str::contains("budget 2/5 tasks 1/2"));
+}
+
+#[test]
+fn run_help_documents_budget_env() {
+    ralphctl()
+        .arg("run")
+        .arg("--help")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("RALPH_ITERATION"))
+        .stdout(predicate::str::contains("RALPH_TASKS_TOTAL"));
+}
+
+#[test]
+fn run_ctrl_c_at_no_signal_prompt_exits_130() {
+    use std::process::Stdio;
+
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+
+    // Interrupt ralphctl (the script's parent) once it is waiting at the prompt
+    let bin_dir = create_mock_claude_script(
+        &dir,
+        "cat > /dev/null; (sleep 1; kill -INT $PPID) > /dev/null 2>&1 & echo 'no signal here'",
+    );
+    let path = format!("{}:/usr/bin:/bin", bin_dir.display());
+
+    // Keep stdin open so the prompt would block forever without the fix
+    let mut child = std::process::Command::new(assert_cmd::cargo::cargo_bin!("ralphctl"))
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .arg("run")
+        .arg("--max-iterations")
+        .arg("3")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let _stdin = child.stdin.take();
+
+    let output = child.wait_with_output().unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert_eq!(output.status.code(), Some(130), "stderr: {}", stderr);
+    assert!(stderr.contains("Continue or stop? [C/s]"));
+    assert!(stderr.contains("Interrupted after 1 iteration."));
+    assert!(stderr.contains("Outcome:    interrupted"));
+}
+
+#[test]
+fn run_prompt_from_url_pipes_downloaded_prompt() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+    fs::remove_file(dir.path().join("PROMPT.md")).unwrap();
+
+    // Echo the prompt claude receives, then finish
+    let bin_dir = create_mock_claude_script(&dir, "cat; echo; echo '[[RALPH:DONE]]'");
+    let path = format!("{}:/usr/bin:/bin", bin_dir.display());
+    let (base_url, _) = serve_once("200 OK", "Remote prompt: do the task.");
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .arg("run")
+        .arg("--prompt-from-url")
+        .arg(format!("{}/prompts/tdd.md", base_url))
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Remote prompt: do the task."));
+
+    assert!(!dir.path().join("PROMPT.md").exists());
+}
+
+#[test]
+fn run_prompt_from_url_fails_on_http_error() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+
+    let bin_dir = create_mock_claude(&dir, "[[RALPH:DONE]]\n");
+    let path = format!("{}:/usr/bin", bin_dir.display());
+    let (base_url, _) = serve_once("404 Not Found", "");
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .arg("run")
+        .arg("--prompt-from-url")
+        .arg(format!("{}/missing.md", base_url))
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("HTTP 404"));
+}
+
+#[test]
+fn run_prompt_from_url_rejects_file_url() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+
+    let bin_dir = create_mock_claude(&dir, "[[RALPH:DONE]]\n");
+    let path = format!("{}:/usr/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .arg("run")
+        .arg("--prompt-from-url")
+        .arg(format!("file://{}/SPEC.md", dir.path().display()))
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains(
+            "only http:// and https:// are allowed",
+        ));
+}
+
+#[test]
+fn run_prompt_from_url_still_requires_spec() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+    fs::remove_file(dir.path().join("SPEC.md")).unwrap();
+
+    ralphctl()
+        .current_dir(dir.path())
+        .arg("run")
+        .arg("--prompt-from-url")
+        .arg("http://127.0.0.1:9/prompt.md")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("missing required files: SPEC.md"));
+}
+
+#[test]
+fn run_strict_signals_fails_fast_without_signal() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+
+    let bin_dir = create_mock_claude(
+        &dir,
+        "Did some work on the parser and the tests, but forgot the marker.\n",
+    );
+    let path = format!("{}:/usr/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .arg("run")
+        .arg("--strict-signals")
+        .arg("--max-iterations")
+        .arg("5")
+        .assert()
+        .code(5)
+        .stderr(predicate::str::contains(
+            "error: iteration 1 produced 66 bytes of output but no [[RALPH:DONE]]",
+        ))
+        .stderr(predicate::str::contains("Outcome:    no signal (strict)"))
+        .stderr(predicate::str::contains("Continue or stop?").not())
+        .stdout(predicate::str::contains("=== Iteration 2 starting ===").not());
+}
+
+#[test]
+fn run_strict_signals_ignores_short_output() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+
+    let bin_dir = create_mock_claude(&dir, "Thinking...\n");
+    let path = format!("{}:/usr/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .args(["run", "--strict-signals", "--yes", "--max-iterations", "2"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("(--strict-signals)").not())
+        .stdout(predicate::str::contains("=== Iteration 2 starting ==="));
+}
+
+#[test]
+fn run_strict_signals_allows_continue() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+
+    let bin_dir = create_mock_claude(&dir, "[[RALPH:CONTINUE]]\n");
+    let path = format!("{}:/usr/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .arg("run")
+        .arg("--strict-signals")
+        .arg("--max-iterations")
+        .arg("2")
+        .assert()
+        .code(2);
+}
+
+#[test]
+fn run_dry_run_prints_command_without_calling_claude() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+
+    // A claude that leaves a trace if it is ever called
+    let bin_dir = create_mock_claude_script(&dir, "touch called; echo '[[RALPH:DONE]]'");
+    let path = format!("{}:/usr/bin:/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .arg("run")
+        .arg("--dry-run")
+        .arg("--model")
+        .arg("opus")
+        .arg("--max-iterations")
+        .arg("4")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Command:        claude -p --dangerously-skip-permissions --model opus",
+        ))
+        .stdout(predicate::str::contains(
+            "Prompt:         PROMPT.md (27 bytes)",
+        ))
+        .stdout(predicate::str::contains("Max iterations: 4"))
+        .stdout(predicate::str::contains("IMPLEMENTATION_PLAN.md"));
+
+    assert!(!dir.path().join("called").exists());
+    assert!(!dir.path().join("ralph.log").exists());
+    assert!(!dir.path().join(".ralphctl").exists());
+}
+
+#[test]
+fn run_show_diff_lists_changed_files() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+    let init = std::process::Command::new("git")
+        .args(["init", "-q"])
+        .current_dir(dir.path())
+        .status();
+    if !init.is_ok_and(|s| s.success()) {
+        return; // git not installed
+    }
+
+    let bin_dir = create_mock_claude_script(
+        &dir,
+        "cat > /dev/null\nprintf 'a\\nb\\n' > feature.rs\necho '[[RALPH:DONE]]'",
+    );
+    let path = format!("{}:/usr/bin:/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .arg("run")
+        .arg("--show-diff")
+        .arg("--max-iterations")
+        .arg("1")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Changed 1 file:\n      +2     -0  feature.rs\n",
+        ));
+
+    let log = fs::read_to_string(dir.path().join("ralph.log")).unwrap();
+    assert!(log.contains("--- changes in iteration 1 ---\nChanged 1 file:"));
+}
+
+#[test]
+fn run_show_diff_is_a_no_op_outside_git() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+
+    let bin_dir = create_mock_claude_script(&dir, "touch feature.rs\necho '[[RALPH:DONE]]'");
+    let path = format!("{}:/usr/bin:/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .env("GIT_CEILING_DIRECTORIES", dir.path().parent().unwrap())
+        .arg("run")
+        .arg("--show-diff")
+        .arg("--max-iterations")
+        .arg("1")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Changed").not())
+        .stdout(predicate::str::contains("No files changed").not());
+}
+
+#[test]
+fn run_max_output_bytes_stops_runaway_output() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+
+    let bin_dir = create_mock_claude_script(&dir, "while :; do echo 'lots of output'; done");
+    let path = format!("{}:/usr/bin:/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .arg("run")
+        .arg("--max-output-bytes")
+        .arg("4096")
+        .arg("--max-iterations")
+        .arg("3")
+        .timeout(std::time::Duration::from_secs(30))
+        .assert()
+        .code(6)
+        .stderr(predicate::str::contains(
+            "error: iteration 1 exceeded --max-output-bytes (4096 bytes); claude was stopped",
+        ));
+
+    let log = fs::read_to_string(dir.path().join("ralph.log")).unwrap();
+    assert!(log.len() < 8192);
+    assert!(!log.contains("=== Iteration 2"));
+}
+
+#[test]
+fn run_max_output_lines_elides_terminal_echo_only() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+
+    let bin_dir = create_mock_claude_script(
+        &dir,
+        "i=1; while [ $i -le 200 ]; do echo \"line $i\"; i=$((i + 1)); done\necho '[[RALPH:DONE]]'",
+    );
+    let path = format!("{}:/usr/bin:/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .args(["run", "--max-output-lines", "10", "--max-iterations", "1"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "line 5\n... (191 lines elided) ...\nline 197\n",
+        ))
+        .stdout(predicate::str::contains("line 100\n").not())
+        .stdout(predicate::str::contains("[[RALPH:DONE]]"));
+
+    let log = fs::read_to_string(dir.path().join("ralph.log")).unwrap();
+    assert!(log.contains("line 100\n"));
+    assert!(!log.contains("elided"));
+}
+
+#[test]
+fn run_stops_after_max_blank_iterations() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+    let bin_dir = create_mock_claude(&dir, "\n");
+    let path = format!("{}:/usr/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .args([
+            "run",
+            "--max-blank-iterations",
+            "2",
+            "--max-iterations",
+            "5",
+        ])
+        .assert()
+        .code(8)
+        .stderr(predicate::str::contains(
+            "error: claude produced no output for 2 consecutive iterations",
+        ))
+        .stderr(predicate::str::contains("blank output"))
+        .stdout(predicate::str::contains("Iteration 3").not());
+}
+
+#[test]
+fn run_blank_iteration_count_resets_on_output() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+
+    // Blank, then real output, then blank: never two blanks in a row
+    let bin_dir = create_mock_claude_script(
+        &dir,
+        "n=$(cat count 2>/dev/null || echo 0); n=$((n + 1)); echo $n > count\n\
+         [ $n -eq 2 ] && echo 'Made some progress'\n\
+         [ $n -eq 3 ] && echo '[[RALPH:DONE]]'\n\
+         exit 0",
+    );
+    let path = format!("{}:/usr/bin:/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .args([
+            "--yes",
+            "run",
+            "--max-blank-iterations",
+            "2",
+            "--max-iterations",
+            "5",
+        ])
+        .assert()
+        .success();
+}
+
+#[test]
+fn run_early_stop_ends_iteration_at_done_signal() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+
+    // The mock lingers long after signalling; without --early-stop the run
+    // would wait for it
+    let bin_dir = create_mock_claude_script(
+        &dir,
+        "echo 'Finished everything'\necho '[[RALPH:DONE]]'\nexec sleep 30",
+    );
+    let path = format!("{}:/usr/bin:/bin", bin_dir.display());
+
+    let started = std::time::Instant::now();
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .args(["run", "--early-stop", "--max-iterations", "3"])
+        .timeout(std::time::Duration::from_secs(20))
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("[[RALPH:DONE]]"))
+        .stdout(predicate::str::contains("Iteration 2").not());
+    assert!(started.elapsed() < std::time::Duration::from_secs(15));
+
+    let log = fs::read_to_string(dir.path().join("ralph.log")).unwrap();
+    assert!(log.contains("Finished everything\n[[RALPH:DONE]]\n"));
+}
+
+#[test]
+fn run_early_stop_exits_blocked_with_blocked_code() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+
+    let bin_dir = create_mock_claude_script(
+        &dir,
+        "echo '[[RALPH:BLOCKED:need credentials]]'\nexec sleep 30",
+    );
+    let path = format!("{}:/usr/bin:/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .args(["run", "--early-stop", "--max-iterations", "3"])
+        .timeout(std::time::Duration::from_secs(20))
+        .assert()
+        .code(3)
+        .stderr(predicate::str::contains("need credentials"));
+}
+
+#[test]
+fn run_once_per_task_stops_after_one_checked_task() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+
+    // Iteration 1 works without checking anything; iteration 2 checks Task 1;
+    // a third iteration would leave a trace
+    let bin_dir = create_mock_claude_script(
+        &dir,
+        "cat > /dev/null\n\
+         if [ -f second ]; then touch third; \
+         elif [ -f first ]; then touch second; \
+         printf '# Plan\\n\\n- [x] Task 1\\n- [ ] Task 2\\n' > IMPLEMENTATION_PLAN.md; \
+         else touch first; fi\n\
+         echo '[[RALPH:CONTINUE]]'",
+    );
+    let path = format!("{}:/usr/bin:/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .arg("run")
+        .arg("--once-per-task")
+        .arg("--max-iterations")
+        .arg("5")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Task completed: Task 1"))
+        .stdout(predicate::str::contains(
+            "Stopping after one task (--once-per-task).",
+        ))
+        .stdout(predicate::str::contains("=== Iteration 2 starting ==="));
+
+    assert!(dir.path().join("second").exists());
+    assert!(!dir.path().join("third").exists());
+}
+
+#[test]
+fn run_heartbeat_accepts_duration_strings_and_rejects_zero() {
+    ralphctl()
+        .arg("run")
+        .arg("--heartbeat")
+        .arg("0s")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--heartbeat"))
+        .stderr(predicate::str::contains("must be greater than zero"));
+
+    ralphctl()
+        .arg("run")
+        .arg("--heartbeat")
+        .arg("5x")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("unknown unit 'x'"));
+
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+    let bin_dir = create_mock_claude_script(&dir, "sleep 2\nprintf '[[RALPH:DONE]]\\n'");
+    let path = format!("{}:/usr/bin:/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .arg("run")
+        .arg("--heartbeat")
+        .arg("1S")
+        .arg("--max-iterations")
+        .arg("1")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("…still running (1s elapsed)"));
+}
+
+#[test]
+fn run_accumulates_stats_across_runs() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+    let bin_dir = create_mock_claude(&dir, "Done.\n[[RALPH:DONE]]\n");
+    let path = format!("{}:/usr/bin", bin_dir.display());
+
+    for _ in 0..2 {
+        ralphctl()
+            .current_dir(dir.path())
+            .env("PATH", &path)
+            .args(["run", "--max-iterations", "1"])
+            .assert()
+            .success();
+    }
+
+    let stats = fs::read_to_string(dir.path().join(".ralphctl/stats.toml")).unwrap();
+    assert!(stats.contains("runs = 2"), "stats.toml: {}", stats);
+    assert!(stats.contains("iterations = 2"), "stats.toml: {}", stats);
+
+    ralphctl()
+        .current_dir(dir.path())
+        .arg("stats")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Runs:        2"))
+        .stdout(predicate::str::contains("done            2"));
+
+    ralphctl()
+        .current_dir(dir.path())
+        .args(["stats", "--reset", "--force"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Reset run statistics."));
+
+    ralphctl()
+        .current_dir(dir.path())
+        .arg("stats")
+        .assert()
+        .success()
+        .stdout("No runs recorded yet.\n");
+}
+
+#[test]
+fn stats_reset_refuses_without_terminal() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+    let bin_dir = create_mock_claude(&dir, "Done.\n[[RALPH:DONE]]\n");
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", format!("{}:/usr/bin", bin_dir.display()))
+        .args(["run", "--max-iterations", "1"])
+        .assert()
+        .success();
+
+    ralphctl()
+        .current_dir(dir.path())
+        .args(["stats", "--reset"])
+        .write_stdin("")
+        .assert()
+        .code(7);
+    let stats = fs::read_to_string(dir.path().join(".ralphctl/stats.toml")).unwrap();
+    assert!(stats.contains("runs = 1"));
+}
+
+#[test]
+fn run_prints_reported_progress() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+    let bin_dir = create_mock_claude(&dir, "Working.\n[[RALPH:PROGRESS:50]]\n[[RALPH:DONE]]\n");
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", format!("{}:/usr/bin", bin_dir.display()))
+        .args(["run", "--max-iterations", "1"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("[██████░░░░░░] 50% (reported)"));
+}
+
+#[test]
+fn run_isolate_env_drops_inherited_variables() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+    let bin_dir = create_mock_claude_script(&dir, "env | sort > env.txt\necho '[[RALPH:DONE]]'");
+    let path = format!("{}:/usr/bin:/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .env("RALPH_TEST_CANARY", "tweet")
+        .args(["run", "--max-iterations", "1"])
+        .assert()
+        .success();
+    let env = fs::read_to_string(dir.path().join("env.txt")).unwrap();
+    assert!(env.contains("RALPH_TEST_CANARY=tweet"));
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .env("RALPH_TEST_CANARY", "tweet")
+        .args(["run", "--max-iterations", "1", "--isolate-env"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("RALPH_TEST_CANARY"))
+        .stderr(predicate::str::contains("tweet").not());
+    let env = fs::read_to_string(dir.path().join("env.txt")).unwrap();
+    assert!(!env.contains("RALPH_TEST_CANARY"), "env: {}", env);
+    assert!(env.contains(&format!("PATH={}", path)));
+    // Variables ralphctl sets for the child still get through
+    assert!(env.contains("RALPH_RUN_ID="));
+}
+
+#[test]
+fn run_isolate_env_allowlist_extends_from_env() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+    let bin_dir = create_mock_claude_script(&dir, "env | sort > env.txt\necho '[[RALPH:DONE]]'");
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", format!("{}:/usr/bin:/bin", bin_dir.display()))
+        .env("RALPH_TEST_CANARY", "tweet")
+        .env("RALPHCTL_ENV_ALLOW", "RALPH_TEST_CANARY")
+        .args(["run", "--max-iterations", "1", "--isolate-env"])
+        .assert()
+        .success();
+    let env = fs::read_to_string(dir.path().join("env.txt")).unwrap();
+    assert!(env.contains("RALPH_TEST_CANARY=tweet"));
+}
+
+#[test]
+fn run_yes_continues_through_no_signal_iteration() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+    let bin_dir = create_mock_claude(&dir, "Worked a bit, forgot the marker.\n");
+    let path = format!("{}:/usr/bin", bin_dir.display());
+
+    // Without --yes, answering "s" stops after the first iteration
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .args(["run", "--max-iterations", "2"])
+        .write_stdin("s\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Stopped by user."));
+
+    // With -y the prompt answers itself and stdin is never read
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .args(["run", "-y", "--max-iterations", "2"])
+        .write_stdin("s\n")
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains(
+            "Continue or stop? [C/s] c (--yes)",
+        ))
+        .stdout(predicate::str::contains("=== Iteration 2 starting ==="));
+}
+
+#[test]
+fn run_project_name_labels_headers_summary_and_lock() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+    let bin_dir = create_mock_claude_script(
+        &dir,
+        "cat .ralphctl/run.lock > lock.json\necho '[[RALPH:DONE]]'",
+    );
+    let path = format!("{}:/usr/bin:/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .args([
+            "run",
+            "--max-iterations",
+            "1",
+            "--project-name",
+            "myproject",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "=== myproject — Iteration 1 starting ===",
+        ))
+        .stderr(predicate::str::contains("Project:    myproject"));
+
+    let log = fs::read_to_string(dir.path().join("ralph.log")).unwrap();
+    assert!(log.contains("=== myproject — Run "));
+    let lock = fs::read_to_string(dir.path().join("lock.json")).unwrap();
+    assert!(lock.contains(r#""project":"myproject""#), "lock: {}", lock);
+
+    // The environment variable overrides the SPEC.md heading too
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .env("RALPHCTL_PROJECT_NAME", "from-env")
+        .args(["run", "--max-iterations", "1"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "=== from-env — Iteration 1 starting ===",
+        ));
+}
+
+#[test]
+fn run_without_configured_name_keeps_plain_header() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+    let bin_dir = create_mock_claude(&dir, "[[RALPH:DONE]]\n");
+
+    // The SPEC.md heading names the run, but only a configured name labels headers
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", format!("{}:/usr/bin", bin_dir.display()))
+        .env_remove("RALPHCTL_PROJECT_NAME")
+        .args(["run", "--max-iterations", "1"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("=== Iteration 1 starting ==="))
+        .stdout(predicate::str::contains("Test Spec —").not())
+        .stderr(predicate::str::contains("Project:    Test Spec"));
+
+    let log = fs::read_to_string(dir.path().join("ralph.log")).unwrap();
+    assert!(log.contains("=== Run "));
+    let state = fs::read_to_string(dir.path().join(".ralphctl/state.json")).unwrap();
+    assert!(
+        state.contains(r#""project": "Test Spec""#),
+        "state: {}",
+        state
+    );
+}
+
+#[test]
+fn run_log_similarity_records_ratio_between_iterations() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+    let bin_dir = create_mock_claude(&dir, "Trying the same fix again.\n[[RALPH:CONTINUE]]\n");
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", format!("{}:/usr/bin", bin_dir.display()))
+        .args(["run", "--max-iterations", "2", "--log-similarity"])
+        .assert()
+        .code(2);
+
+    let log = fs::read_to_string(dir.path().join("ralph.log")).unwrap();
+    // Only the second iteration has a predecessor to compare with
+    assert_eq!(log.matches("--- similarity to previous:").count(), 1);
+    assert!(log.contains("--- similarity to previous: 1.00 ---"));
+}
+
+#[test]
+fn run_ctrl_c_at_pause_prompt_exits_130_despite_later_input() {
+    use std::io::{BufRead, BufReader, Write};
+    use std::process::Stdio;
+
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+    let bin_dir = create_mock_claude(&dir, "Working\n[[RALPH:CONTINUE]]\n");
+    let path = format!("{}:/usr/bin", bin_dir.display());
+
+    let mut child = std::process::Command::new(assert_cmd::cargo::cargo_bin!("ralphctl"))
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .args(["run", "--pause", "--max-iterations", "3"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    // Wait until ralphctl is sitting at the first prompt
+    let mut stderr = BufReader::new(child.stderr.take().unwrap());
+    let mut seen = Vec::new();
+    while !String::from_utf8_lossy(&seen).contains("Continue? [Y/n] ") {
+        let buf = stderr.fill_buf().unwrap();
+        assert!(!buf.is_empty(), "ralphctl exited before prompting");
+        seen.extend_from_slice(buf);
+        let n = buf.len();
+        stderr.consume(n);
+    }
+
+    nix::sys::signal::kill(
+        nix::unistd::Pid::from_raw(child.id() as i32),
+        nix::sys::signal::Signal::SIGINT,
+    )
+    .unwrap();
+    // A stale answer typed after Ctrl+C must not continue the run
+    let _ = child.stdin.take().unwrap().write_all(b"y\n");
+
+    let mut rest = String::new();
+    std::io::Read::read_to_string(&mut stderr, &mut rest).unwrap();
+    let status = child.wait().unwrap();
+    assert_eq!(status.code(), Some(130), "stderr: {}", rest);
+    assert!(rest.contains("Interrupted after 1 iteration."));
+    let log = fs::read_to_string(dir.path().join("ralph.log")).unwrap();
+    assert_eq!(log.matches("Working").count(), 1);
+}
+
+/// Run with `--porcelain` and return (exit code, stdout records, stderr).
+fn run_porcelain(dir: &TempDir, path: &str, extra: &[&str]) -> (Option<i32>, Vec<String>, String) {
+    let output = ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", path)
+        .args(["run", "--porcelain"])
+        .args(extra)
+        .output()
+        .unwrap();
+    let records = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::to_string)
+        .collect();
+    let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+    (output.status.code(), records, stderr)
+}
+
+#[test]
+fn run_porcelain_reports_claude_failure_with_summary() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+    let bin_dir = create_mock_claude_script(
+        &dir,
+        "if [ -f seen ]; then exit 2; fi\ntouch seen\necho '[[RALPH:CONTINUE]]'",
+    );
+    let path = format!("{}:/usr/bin:/bin", bin_dir.display());
+
+    let (code, records, stderr) = run_porcelain(&dir, &path, &["--max-iterations", "5"]);
+    assert_eq!(code, Some(1), "stderr: {}", stderr);
+    assert_eq!(
+        records.last().map(String::as_str),
+        Some("outcome failed iterations=2 tasks=0/2")
+    );
+    assert!(stderr.contains("error: claude exited with code 2"));
+    // The summary block still reports the iterations that ran
+    assert!(stderr.contains("failed (claude exited with code 2)"));
+    let stats = fs::read_to_string(dir.path().join(".ralphctl/stats.toml")).unwrap();
+    assert!(stats.contains("failed = 1"), "stats: {}", stats);
+}
+
+#[test]
+fn run_porcelain_reports_done() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+    let bin_dir = create_mock_claude_script(
+        &dir,
+        "printf '# Plan\\n\\n- [x] Task 1\\n- [x] Task 2\\n' > IMPLEMENTATION_PLAN.md\n\
+         echo 'All finished.'\necho '[[RALPH:DONE]]'",
+    );
+    let path = format!("{}:/usr/bin:/bin", bin_dir.display());
+
+    let (code, records, stderr) = run_porcelain(&dir, &path, &["--max-iterations", "5"]);
+    assert_eq!(code, Some(0), "stderr: {}", stderr);
+    assert_eq!(
+        records,
+        [
+            "porcelain-version 1",
+            "iteration-start 1",
+            "signal DONE",
+            "outcome done iterations=1 tasks=2/2",
+        ]
+    );
+    // Human output, including claude's, moves to stderr
+    assert!(stderr.contains("All finished."));
+    assert!(stderr.contains("=== Loop complete ==="));
+}
+
+#[test]
+fn run_porcelain_reports_blocked_reason_last() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+    let bin_dir = create_mock_claude(&dir, "Cannot proceed.\n[[RALPH:BLOCKED:missing API key]]\n");
+    let path = format!("{}:/usr/bin", bin_dir.display());
+
+    let (code, records, stderr) = run_porcelain(&dir, &path, &["--max-iterations", "5"]);
+    assert_eq!(code, Some(3), "stderr: {}", stderr);
+    assert_eq!(
+        records,
+        [
+            "porcelain-version 1",
+            "iteration-start 1",
+            "signal BLOCKED missing API key",
+            "outcome blocked iterations=1 tasks=0/2",
+        ]
+    );
+    let fields: Vec<&str> = records[2].split_whitespace().collect();
+    assert_eq!(&fields[..2], ["signal", "BLOCKED"]);
+    assert_eq!(fields[2..].join(" "), "missing API key");
+}
+
+#[test]
+fn run_porcelain_reports_max_iterations() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+    let bin_dir = create_mock_claude(&dir, "Working.\n[[RALPH:CONTINUE]]\n");
+    let path = format!("{}:/usr/bin", bin_dir.display());
+
+    let (code, records, stderr) = run_porcelain(&dir, &path, &["--max-iterations", "2"]);
+    assert_eq!(code, Some(2), "stderr: {}", stderr);
+    assert_eq!(
+        records,
+        [
+            "porcelain-version 1",
+            "iteration-start 1",
+            "signal CONTINUE",
+            "iteration-start 2",
+            "signal CONTINUE",
+            "outcome max_iterations iterations=2 tasks=0/2",
+        ]
+    );
+}
+
+#[test]
+fn run_porcelain_conflicts_with_tui() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+
+    ralphctl()
+        .current_dir(dir.path())
+        .args(["run", "--porcelain", "--tui"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+}
+
+#[test]
+fn run_plan_progress_in_log_records_each_iteration() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+    let bin_dir = create_mock_claude(&dir, "Working.\n[[RALPH:CONTINUE]]\n");
+    let path = format!("{}:/usr/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .args([
+            "run",
+            "--max-iterations",
+            "2",
+            "--plan-progress-in-log",
+            "--progress-file",
+            "PROGRESS.md",
+        ])
+        .assert()
+        .code(2);
+
+    let log = fs::read_to_string(dir.path().join("ralph.log")).unwrap();
+    assert!(log.contains("--- progress after iteration 1 ("));
+    assert!(log.contains("--- progress after iteration 2 ("));
+    assert!(log.contains("[░░░░░░░░░░░░] 0% (0/2 tasks) ---"));
+
+    let progress = fs::read_to_string(dir.path().join("PROGRESS.md")).unwrap();
+    assert!(progress.starts_with("# Progress\n\n- "));
+    assert_eq!(progress.matches("(0/2 tasks)").count(), 2);
+    assert!(progress.contains(" — iteration 2: "));
+}
+
+#[test]
+fn run_plan_progress_in_log_counts_org_plans() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+    fs::write(
+        dir.path().join("PLAN.org"),
+        "* DONE Parse input\n* TODO Write output\n* TODO Add tests\n",
+    )
+    .unwrap();
+    let bin_dir = create_mock_claude(&dir, "Working.\n[[RALPH:CONTINUE]]\n");
+    let path = format!("{}:/usr/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .args([
+            "run",
+            "--max-iterations",
+            "1",
+            "--plan-file",
+            "PLAN.org",
+            "--plan-format",
+            "org",
+            "--plan-progress-in-log",
+        ])
+        .assert()
+        .code(2)
+        .stdout(predicate::str::contains("(1/3 tasks)"));
+
+    let log = fs::read_to_string(dir.path().join("ralph.log")).unwrap();
+    assert!(log.contains("] 33% (1/3 tasks) ---\n\n"));
+}
+
+#[test]
+fn run_without_plan_progress_in_log_keeps_log_unchanged() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+    let bin_dir = create_mock_claude(&dir, "Working.\n[[RALPH:CONTINUE]]\n");
+    let path = format!("{}:/usr/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .args(["run", "--max-iterations", "1"])
+        .assert()
+        .code(2);
+
+    let log = fs::read_to_string(dir.path().join("ralph.log")).unwrap();
+    assert!(!log.contains("--- progress after"));
+}
+
+/// Mock claude that is BLOCKED on its first call and DONE afterwards.
+fn create_block_once_claude(dir: &TempDir) -> std::path::PathBuf {
+    create_mock_claude_script(
+        dir,
+        "if [ -f blocked-once ]; then\n\
+         printf '# Plan\\n\\n- [x] Task 1\\n- [x] Task 2\\n' > IMPLEMENTATION_PLAN.md\n\
+         echo '[[RALPH:DONE]]'\n\
+         else\n\
+         touch blocked-once\n\
+         echo '[[RALPH:BLOCKED:missing API key]]'\n\
+         fi",
+    )
+}
+
+#[test]
+fn run_interactive_blocked_retry_reruns_same_iteration() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+    let bin_dir = create_block_once_claude(&dir);
+    let path = format!("{}:/usr/bin:/bin", bin_dir.display());
+
+    // One iteration of budget: the retry must not consume it
+    let output = ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .args(["run", "--max-iterations", "1", "--interactive-blocked"])
+        .write_stdin("r\n")
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(0), "stderr: {}", stderr);
+    assert!(stderr.contains("missing API key"));
+    assert!(stderr.contains("[r]etry after fixing, [s]hell, [a]bort?"));
+    assert!(stdout.contains("Retrying iteration 1."));
+    assert_eq!(stdout.matches("Iteration 1 starting").count(), 2);
+    assert!(!stdout.contains("Iteration 2 starting"));
+    assert!(stdout.contains("=== Loop complete ==="));
+}
+
+#[test]
+fn run_interactive_blocked_shell_returns_to_prompt() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+    let bin_dir = create_block_once_claude(&dir);
+    let path = format!("{}:/usr/bin:/bin", bin_dir.display());
+    let shell = dir.path().join("fake-shell");
+    fs::write(&shell, "#!/bin/sh\ntouch shell-ran\n").unwrap();
+    let mut perms = fs::metadata(&shell).unwrap().permissions();
+    perms.set_mode(0o755);
+    fs::set_permissions(&shell, perms).unwrap();
+
+    let output = ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .env("SHELL", &shell)
+        .args(["run", "--max-iterations", "3", "--interactive-blocked"])
+        .write_stdin("s\nr\n")
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(0), "stderr: {}", stderr);
+    assert!(dir.path().join("shell-ran").exists());
+    assert!(stderr.contains("exit it to return to this prompt"));
+    assert_eq!(stderr.matches("[r]etry after fixing").count(), 2);
+}
+
+#[test]
+fn run_interactive_blocked_abort_exits_blocked() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+    let bin_dir = create_block_once_claude(&dir);
+    let path = format!("{}:/usr/bin:/bin", bin_dir.display());
+
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .args(["run", "--max-iterations", "3", "--interactive-blocked"])
+        .write_stdin("a\n")
+        .assert()
+        .code(3);
+}
+
+#[test]
+fn run_interactive_blocked_ignored_under_yes() {
+    let dir = temp_dir();
+    create_ralph_files(&dir);
+    let bin_dir = create_block_once_claude(&dir);
+    let path = format!("{}:/usr/bin:/bin", bin_dir.display());
+
+    // Under --yes nobody answers prompts
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", &path)
+        .args([
+            "-y",
+            "run",
+            "--max-iterations",
+            "3",
+            "--interactive-blocked",
+        ])
+        .assert()
+        .code(3)
+        .stderr(predicate::str::contains(
+            "warning: --interactive-blocked ignored",
+        ))
+        .stderr(predicate::str::contains("[r]etry").not());
+}
+
+#[test]
+fn run_help_exit_codes_match_constants() {
+    use ralphctl::error::exit;
+
+    let codes = help_exit_codes("run");
+    let code_for = |prefix| exit_code_for(&codes, prefix);
+    assert_eq!(code_for("Success"), exit::SUCCESS);
+    assert_eq!(code_for("Error"), exit::ERROR);
+    assert_eq!(code_for("Max iterations"), exit::MAX_ITERATIONS);
+    assert_eq!(code_for("Blocked"), exit::BLOCKED);
+    assert_eq!(code_for("No signal"), exit::NO_SIGNAL);
+    assert_eq!(code_for("Iteration output exceeded"), exit::OUTPUT_LIMIT);
+    assert_eq!(code_for("Blank output"), exit::BLANK_OUTPUT);
+    assert_eq!(code_for("Interrupted"), exit::INTERRUPTED);
+    assert_eq!(codes.len(), 8);
+}
@@ -0,0 +1,32 @@
+//! Integration tests for the `ralphctl self-test` command.
+
+use assert_cmd::Command;
+use predicates::prelude::*;
+
+/// Get a command for ralphctl.
+fn ralphctl() -> Command {
+    Command::new(assert_cmd::cargo::cargo_bin!("ralphctl"))
+}
+
+#[test]
+fn test_self_test_passes_without_claude() {
+    let dir = tempfile::tempdir().unwrap();
+
+    // No claude on PATH: the built-in mock agent must be all it needs
+    ralphctl()
+        .current_dir(dir.path())
+        .env("PATH", "/nonexistent")
+        .arg("self-test")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "ok  signals detected: CONTINUE, CONTINUE, DONE",
+        ))
+        .stdout(predicate::str::contains(
+            "ok  event stream drove a fresh project to done in 3 iterations",
+        ))
+        .stdout(predicate::str::ends_with("self-test passed\n"));
+
+    // The scratch project lives elsewhere and is cleaned up
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+}
@@ -0,0 +1,44 @@
+//! Integration tests for the `ralphctl signals` command.
+
+use assert_cmd::Command;
+
+/// Get a command for ralphctl.
+fn ralphctl() -> Command {
+    Command::new(assert_cmd::cargo::cargo_bin!("ralphctl"))
+}
+
+#[test]
+fn test_signals_lists_every_marker() {
+    let output = ralphctl().arg("signals").output().unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+
+    for marker in [
+        "[[RALPH:CONTINUE]]",
+        "[[RALPH:DONE]]",
+        "[[RALPH:BLOCKED:<reason>]]",
+        "[[RALPH:PROGRESS:<pct>]]",
+        "[[RALPH:FOUND:<summary>]]",
+        "[[RALPH:INCONCLUSIVE:<reason>]]",
+    ] {
+        assert!(stdout.contains(marker), "missing {}:\n{}", marker, stdout);
+    }
+    assert_eq!(stdout.lines().count(), 6);
+}
+
+#[test]
+fn test_signals_names_the_commands_that_act_on_each_marker() {
+    let output = ralphctl().arg("signals").output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+
+    let line = |marker: &str| {
+        stdout
+            .lines()
+            .find(|l| l.starts_with(marker))
+            .unwrap()
+            .to_string()
+    };
+    assert!(line("[[RALPH:DONE]]").contains("  run  "));
+    assert!(line("[[RALPH:FOUND:").contains("  reverse  "));
+    assert!(line("[[RALPH:BLOCKED:").contains("run, reverse"));
+}
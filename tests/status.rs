@@ -0,0 +1,252 @@
+//! Integration tests for the `ralphctl status` command.
+
+use assert_cmd::Command;
+use predicates::prelude::*;
+use std::fs;
+use tempfile::TempDir;
+
+/// Get a command for ralphctl.
+fn ralphctl() -> Command {
+    Command::new(assert_cmd::cargo::cargo_bin!("ralphctl"))
+}
+
+/// Create a temporary directory for testing.
+fn temp_dir() -> TempDir {
+    tempfile::tempdir().expect("Failed to create temp dir")
+}
+
+#[test]
+fn status_shows_progress_bar() {
+    let dir = temp_dir();
+    fs::write(
+        dir.path().join("IMPLEMENTATION_PLAN.md"),
+        "# Plan\n\n- [x] Task 1\n- [ ] Task 2\n",
+    )
+    .unwrap();
+
+    ralphctl()
+        .current_dir(dir.path())
+        .arg("status")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("50% (1/2 tasks)"));
+}
+
+#[test]
+fn status_fails_without_plan() {
+    let dir = temp_dir();
+
+    ralphctl()
+        .current_dir(dir.path())
+        .arg("status")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("IMPLEMENTATION_PLAN.md not found"));
+}
+
+#[test]
+fn status_with_plan_file_reads_custom_path() {
+    let dir = temp_dir();
+    fs::create_dir_all(dir.path().join("plans")).unwrap();
+    fs::write(
+        dir.path().join("plans/auth.md"),
+        "# Auth\n\n- [x] Login\n- [x] Logout\n- [ ] Refresh\n",
+    )
+    .unwrap();
+
+    ralphctl()
+        .current_dir(dir.path())
+        .arg("status")
+        .arg("--plan-file")
+        .arg("plans/auth.md")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("67% (2/3 tasks)"));
+}
+
+#[test]
+fn status_with_positional_plan_file_reads_it() {
+    let dir = temp_dir();
+    fs::write(
+        dir.path().join("IMPLEMENTATION_PLAN.backend.md"),
+        "- [x] Schema\n- [ ] Handlers\n- [ ] Tests\n- [ ] Deploy\n",
+    )
+    .unwrap();
+
+    ralphctl()
+        .current_dir(dir.path())
+        .args(["status", "IMPLEMENTATION_PLAN.backend.md"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("25% (1/4 tasks)"));
+}
+
+#[test]
+fn status_with_missing_positional_plan_file_names_it() {
+    let dir = temp_dir();
+
+    ralphctl()
+        .current_dir(dir.path())
+        .args(["status", "IMPLEMENTATION_PLAN.frontend.md"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "IMPLEMENTATION_PLAN.frontend.md not found",
+        ));
+}
+
+#[test]
+fn status_glob_alias_sums_component_plans() {
+    let dir = temp_dir();
+    fs::write(
+        dir.path().join("IMPLEMENTATION_PLAN.backend.md"),
+        "- [x] Schema\n- [ ] Handlers\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.path().join("IMPLEMENTATION_PLAN.frontend.md"),
+        "- [x] Layout\n- [x] Forms\n",
+    )
+    .unwrap();
+
+    ralphctl()
+        .current_dir(dir.path())
+        .args(["status", "--glob", "IMPLEMENTATION_PLAN.*.md"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "IMPLEMENTATION_PLAN.backend.md   [██████░░░░░░] 50% (1/2 tasks)",
+        ))
+        .stdout(predicate::str::contains(
+            "IMPLEMENTATION_PLAN.frontend.md  [████████████] 100% (2/2 tasks)",
+        ))
+        .stdout(predicate::str::contains(
+            "Total                            [█████████░░░] 75% (3/4 tasks)",
+        ));
+}
+
+#[test]
+fn status_positional_plan_file_conflicts_with_glob() {
+    let dir = temp_dir();
+
+    ralphctl()
+        .current_dir(dir.path())
+        .args(["status", "a.md", "--glob", "*.md"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+}
+
+#[test]
+fn status_with_missing_plan_file_names_it() {
+    let dir = temp_dir();
+
+    ralphctl()
+        .current_dir(dir.path())
+        .arg("status")
+        .arg("--plan-file")
+        .arg("plans/missing.md")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("plans/missing.md not found"));
+}
+
+#[test]
+fn status_plan_glob_sums_matching_plans() {
+    let dir = temp_dir();
+    fs::create_dir_all(dir.path().join("plans")).unwrap();
+    fs::write(
+        dir.path().join("plans/api.md"),
+        "- [x] Routes\n- [x] Auth\n- [ ] Docs\n",
+    )
+    .unwrap();
+    fs::write(dir.path().join("plans/ui.md"), "- [ ] Layout\n").unwrap();
+    fs::write(dir.path().join("plans/notes.txt"), "- [x] Not a plan\n").unwrap();
+
+    ralphctl()
+        .current_dir(dir.path())
+        .args(["status", "--plan-glob", "plans/*.md"])
+        .assert()
+        .success()
+        .stdout(
+            "plans/api.md  [████████░░░░] 67% (2/3 tasks)\n\
+             plans/ui.md   [░░░░░░░░░░░░] 0% (0/1 tasks)\n\
+             Total         [██████░░░░░░] 50% (2/4 tasks)\n",
+        );
+}
+
+#[test]
+fn status_plan_glob_fails_without_matches() {
+    let dir = temp_dir();
+
+    ralphctl()
+        .current_dir(dir.path())
+        .args(["status", "--plan-file-glob", "plans/*.md"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("no plan files match 'plans/*.md'"));
+}
+
+#[test]
+fn status_plan_glob_conflicts_with_plan_file() {
+    let dir = temp_dir();
+
+    ralphctl()
+        .current_dir(dir.path())
+        .args(["status", "--plan-glob", "*.md", "--plan-file", "a.md"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+}
+
+#[test]
+fn status_plan_format_org_counts_todo_headlines() {
+    let dir = temp_dir();
+    fs::write(
+        dir.path().join("PLAN.org"),
+        "* Plan\n** DONE Task 1\n** TODO Task 2\n** TODO Task 3\n",
+    )
+    .unwrap();
+
+    ralphctl()
+        .current_dir(dir.path())
+        .args(["status", "--plan-file", "PLAN.org", "--plan-format", "org"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("33% (1/3 tasks)"));
+}
+
+#[test]
+fn status_badge_prints_shields_endpoint_json() {
+    let dir = temp_dir();
+    fs::write(
+        dir.path().join("IMPLEMENTATION_PLAN.md"),
+        "# Plan\n\n- [x] Task 1\n- [ ] Task 2\n",
+    )
+    .unwrap();
+
+    ralphctl()
+        .current_dir(dir.path())
+        .args(["status", "--badge"])
+        .assert()
+        .success()
+        .stdout(
+            "{\"schemaVersion\":1,\"label\":\"tasks\",\"message\":\"1/2 (50%)\",\"color\":\"yellow\"}\n",
+        );
+}
+
+#[test]
+fn status_badge_with_glob_sums_plans_without_breakdown() {
+    let dir = temp_dir();
+    fs::write(dir.path().join("a.md"), "- [x] One\n- [x] Two\n").unwrap();
+    fs::write(dir.path().join("b.md"), "- [x] Three\n").unwrap();
+
+    ralphctl()
+        .current_dir(dir.path())
+        .args(["status", "--glob", "*.md", "--badge"])
+        .assert()
+        .success()
+        .stdout(
+            "{\"schemaVersion\":1,\"label\":\"tasks\",\"message\":\"3/3 (100%)\",\"color\":\"green\"}\n",
+        );
+}